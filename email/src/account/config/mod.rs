@@ -34,13 +34,15 @@
 use super::sync::config::SyncConfig;
 #[doc(inline)]
 pub use super::{Error, Result};
+#[cfg(feature = "watch")]
+use crate::envelope::watch::event::WatchEnvelopeEvent;
 use crate::{
     date::from_mail_parser_to_chrono_datetime,
     debug,
     email::config::EmailTextPlainFormat,
     envelope::{config::EnvelopeConfig, Envelope},
     flag::config::FlagConfig,
-    folder::{config::FolderConfig, FolderKind, DRAFTS, INBOX, SENT, TRASH},
+    folder::{config::FolderConfig, filter::FolderFilter, FolderKind, DRAFTS, INBOX, SENT, TRASH},
     message::config::MessageConfig,
     template::{
         config::TemplateConfig,
@@ -214,6 +216,10 @@ pub fn does_sync_dir_exist(&self) -> bool {
 
     /// Execute the envelope received hook.
     #[cfg(feature = "watch")]
+    #[deprecated(
+        since = "0.26.0",
+        note = "use AccountConfig::exec_envelope_event_hook instead"
+    )]
     pub async fn exec_received_envelope_hook(&self, envelope: &Envelope) {
         let hook = self
             .envelope
@@ -228,6 +234,10 @@ pub async fn exec_received_envelope_hook(&self, envelope: &Envelope) {
 
     /// Execute the envelope any hook.
     #[cfg(feature = "watch")]
+    #[deprecated(
+        since = "0.26.0",
+        note = "use AccountConfig::exec_envelope_event_hook instead"
+    )]
     pub async fn exec_any_envelope_hook(&self, envelope: &Envelope) {
         let hook = self
             .envelope
@@ -240,16 +250,90 @@ pub async fn exec_any_envelope_hook(&self, envelope: &Envelope) {
         }
     }
 
+    /// Execute the hook matching the given envelope event.
+    ///
+    /// On top of the envelope placeholders, the event accepts the
+    /// "{event}", "{flags.added}" and "{flags.removed}" placeholders.
+    #[cfg(feature = "watch")]
+    pub async fn exec_envelope_event_hook(&self, event: &WatchEnvelopeEvent) {
+        let hook = self
+            .envelope
+            .as_ref()
+            .and_then(|c| c.watch.as_ref())
+            .and_then(|c| c.find_hook(event));
+
+        if let Some(hook) = hook {
+            let (added, removed) = match event.flags_diff() {
+                Some(diff) => (diff.added.to_string(), diff.removed.to_string()),
+                None => Default::default(),
+            };
+
+            let placeholders = [
+                ("{event}", event.to_string()),
+                ("{flags.added}", added),
+                ("{flags.removed}", removed),
+            ];
+
+            self.exec_envelope_hook_with(hook, event.envelope(), &placeholders)
+                .await;
+
+            self.send_envelope_event(hook, event);
+        }
+    }
+
+    /// Send the given envelope event to the JSON outputs of the given
+    /// hook.
+    #[cfg(feature = "watch")]
+    fn send_envelope_event(&self, hook: &WatchHook, event: &WatchEnvelopeEvent) {
+        if hook.json_lines.is_none() && hook.webhook.is_none() {
+            return;
+        }
+
+        let event = event.to_json(&self.name);
+
+        if let Some(json_lines) = hook.json_lines.as_ref() {
+            json_lines.send(event.clone());
+        }
+
+        #[cfg(feature = "webhook")]
+        if let Some(webhook) = hook.webhook.as_ref() {
+            webhook.send(event);
+        }
+
+        #[cfg(not(feature = "webhook"))]
+        if let Some(_webhook) = hook.webhook.as_ref() {
+            crate::warn!(
+                "cannot send envelope event to webhook {}: cargo feature `webhook` is not enabled",
+                _webhook.url
+            );
+        }
+    }
+
     /// Execute the given envelope hook.
     pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
+        self.exec_envelope_hook_with(hook, envelope, &[]).await
+    }
+
+    /// Execute the given envelope hook, replacing the given extra
+    /// placeholders.
+    async fn exec_envelope_hook_with(
+        &self,
+        hook: &WatchHook,
+        envelope: &Envelope,
+        placeholders: &[(&str, String)],
+    ) {
+        let flags = envelope.flags.to_string();
         let sender = envelope.from.name.as_deref().unwrap_or(&envelope.from.addr);
         let sender_name = envelope.from.name.as_deref().unwrap_or("unknown");
         let recipient = envelope.to.name.as_deref().unwrap_or(&envelope.to.addr);
         let recipient_name = envelope.to.name.as_deref().unwrap_or("unknown");
 
         if let Some(cmd) = hook.cmd.as_ref() {
+            let cmd = placeholders
+                .iter()
+                .fold(cmd.clone(), |cmd, (from, to)| cmd.replace(from, to));
+
             let res = cmd
-                .clone()
                 .replace("{id}", &envelope.id)
                 .replace("{subject}", &envelope.subject)
                 .replace("{sender}", sender)
@@ -258,6 +342,7 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
                 .replace("{recipient}", recipient)
                 .replace("{recipient.name}", recipient_name)
                 .replace("{recipient.address}", &envelope.to.addr)
+                .replace("{flags}", &flags)
                 .run()
                 .await;
 
@@ -268,7 +353,11 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
         }
 
         #[allow(unused_variables)]
-        let replace = move |fmt: &str, envelope: &Envelope| -> String {
+        let replace = |fmt: &str, envelope: &Envelope| -> String {
+            let fmt = placeholders
+                .iter()
+                .fold(fmt.to_owned(), |fmt, (from, to)| fmt.replace(from, to));
+
             fmt.replace("{id}", &envelope.id)
                 .replace("{subject}", &envelope.subject)
                 .replace("{sender}", sender)
@@ -277,6 +366,7 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
                 .replace("{recipient}", recipient)
                 .replace("{recipient.name}", recipient_name)
                 .replace("{recipient.address}", &envelope.to.addr)
+                .replace("{flags}", &flags)
         };
 
         #[cfg(all(feature = "notify", target_os = "linux"))]
@@ -410,6 +500,14 @@ pub fn find_folder_kind_from_alias(&self, alias: &str) -> Option<FolderKind> {
             })
     }
 
+    /// Get the folder listing filter, if defined.
+    pub fn get_folder_list_filter(&self) -> Option<&FolderFilter> {
+        self.folder
+            .as_ref()
+            .and_then(|c| c.list.as_ref())
+            .and_then(|c| c.filter.as_ref())
+    }
+
     /// Get the envelope listing page size if defined, otherwise
     /// return the default one.
     pub fn get_envelope_list_page_size(&self) -> usize {
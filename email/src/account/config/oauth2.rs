@@ -3,14 +3,25 @@
 //! This module contains everything related to OAuth 2.0
 //! configuration.
 
-use std::{fmt, io, net::TcpListener, vec};
-
-use oauth::v2_0::{AuthorizationCodeGrant, Client, RefreshAccessToken};
+use std::{
+    borrow::Cow,
+    fmt, io,
+    net::TcpListener,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+    vec,
+};
+
+use oauth::v2_0::{
+    AuthorizationCodeGrant, Client, DeviceAuthorizationGrant, RefreshAccessToken, RevokeToken,
+    ServerMetadata, Tokens,
+};
 use secret::Secret;
+use tokio::sync::Mutex;
 
 #[doc(inline)]
 pub use super::{Error, Result};
-use crate::debug;
+use crate::{debug, warn};
 
 /// The OAuth 2.0 configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -24,6 +35,10 @@ pub struct OAuth2Config {
     /// for authentication.
     pub method: OAuth2Method,
 
+    /// Flow used to obtain the first access token.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub flow: OAuth2Flow,
+
     /// Client identifier issued to the client during the registration process described by
     /// [Section 2.2](https://datatracker.ietf.org/doc/html/rfc6749#section-2.2).
     pub client_id: String,
@@ -36,12 +51,39 @@ pub struct OAuth2Config {
     )]
     pub client_secret: Secret,
 
+    /// Issuer identifier of the authorization server, either an URL
+    /// or a bare host name.
+    ///
+    /// When defined, missing endpoints are discovered from the
+    /// authorization server metadata, see [`OAuth2Config::discover`].
+    pub issuer: Option<String>,
+
     /// URL of the authorization server's authorization endpoint.
+    ///
+    /// It can be omitted when the issuer is defined.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
     pub auth_url: String,
 
     /// URL of the authorization server's token endpoint.
+    ///
+    /// It can be omitted when the issuer is defined.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
     pub token_url: String,
 
+    /// URL of the authorization server's device authorization
+    /// endpoint, required by the device authorization flow.
+    pub device_auth_url: Option<String>,
+
+    /// URL of the authorization server's revocation endpoint, used to
+    /// revoke tokens when removing the account.
+    pub revocation_url: Option<String>,
+
     /// Access token returned by the token endpoint and used to access
     /// protected resources.
     #[cfg_attr(
@@ -69,11 +111,19 @@ pub struct OAuth2Config {
     /// Access token scope(s), as defined by the authorization server.
     #[cfg_attr(feature = "derive", serde(flatten))]
     pub scopes: OAuth2Scopes,
+
+    /// The in-memory state of the tokens.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub tokens: OAuth2Tokens,
 }
 
 impl OAuth2Config {
     pub const LOCALHOST: &'static str = "localhost";
 
+    /// Duration before the expiry of the access token from which the
+    /// access token is refreshed proactively.
+    pub const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
     /// Return the first available port on [`LOCALHOST`].
     pub fn get_first_available_port() -> Result<u16> {
         (49_152..65_535)
@@ -81,8 +131,10 @@ pub fn get_first_available_port() -> Result<u16> {
             .ok_or(Error::GetAvailablePortError)
     }
 
-    /// Resets the three secrets of the OAuth 2.0 configuration.
+    /// Resets the secrets of the OAuth 2.0 configuration.
     pub async fn reset(&self) -> Result<()> {
+        *self.tokens.0.lock().await = Default::default();
+
         self.client_secret
             .delete_only_keyring()
             .await
@@ -98,9 +150,129 @@ pub async fn reset(&self) -> Result<()> {
         Ok(())
     }
 
-    /// If the access token is not defined, runs the authorization
-    /// code grant OAuth 2.0 flow in order to save the acces token and
-    /// the refresh token if present.
+    /// Revokes the tokens, then resets the secrets.
+    ///
+    /// Revoking the refresh token should also revoke the access
+    /// tokens issued from it, so the access token is only revoked when
+    /// there is no refresh token. Tokens are not revoked when the
+    /// revocation URL is not defined.
+    pub async fn revoke(&self) -> Result<()> {
+        let config = match self.revocation_url {
+            Some(_) => self.resolve().await?,
+            None => {
+                let mut config = self.clone();
+                config.discover().await?;
+                Cow::Owned(config)
+            }
+        };
+
+        if config.revocation_url.is_none() {
+            debug!("no oauth2 revocation url defined, skipping revocation");
+            return self.reset().await;
+        }
+
+        let client = config
+            .build_client()
+            .await?
+            .build()
+            .map_err(Error::BuildOauthClientError)?;
+
+        let state = self.tokens.0.lock().await;
+
+        let refresh_token = match &state.refresh_token {
+            Some(refresh_token) => Some(refresh_token.clone()),
+            None => self.refresh_token.find().await.ok().flatten(),
+        };
+
+        let access_token = match &state.access_token {
+            Some(access_token) => Some(access_token.clone()),
+            None => self.access_token.find().await.ok().flatten(),
+        };
+
+        drop(state);
+
+        if let Some(refresh_token) = refresh_token {
+            RevokeToken::new()
+                .revoke_refresh_token(&client, refresh_token)
+                .await
+                .map_err(Error::RevokeTokenOauthError)?;
+        } else if let Some(access_token) = access_token {
+            RevokeToken::new()
+                .revoke_access_token(&client, access_token)
+                .await
+                .map_err(Error::RevokeTokenOauthError)?;
+        }
+
+        self.reset().await
+    }
+
+    /// Fills the missing endpoints using the metadata of the
+    /// authorization server, discovered from the issuer.
+    ///
+    /// Endpoints already defined are kept as they are, and the PKCE
+    /// protection is enabled if the authorization server supports it.
+    /// Scopes are not discovered, since they depend on the service
+    /// being accessed. This function does nothing if the issuer is
+    /// not defined.
+    pub async fn discover(&mut self) -> Result<()> {
+        let Some(issuer) = &self.issuer else {
+            return Ok(());
+        };
+
+        debug!("discovering oauth2 authorization server metadata of {issuer}");
+
+        let metadata = ServerMetadata::discover(issuer)
+            .await
+            .map_err(Error::DiscoverOauthServerMetadataError)?;
+
+        if self.auth_url.is_empty() {
+            if let Some(url) = &metadata.authorization_endpoint {
+                self.auth_url = url.clone();
+            }
+        }
+
+        if self.token_url.is_empty() {
+            if let Some(url) = &metadata.token_endpoint {
+                self.token_url = url.clone();
+            }
+        }
+
+        if self.device_auth_url.is_none() {
+            self.device_auth_url = metadata.device_authorization_endpoint.clone();
+        }
+
+        if self.revocation_url.is_none() {
+            self.revocation_url = metadata.revocation_endpoint.clone();
+        }
+
+        if !self.pkce && metadata.supports_pkce() {
+            debug!("oauth2 authorization server supports pkce, enabling it");
+            self.pkce = true;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configuration with the missing endpoints
+    /// discovered, or the configuration itself if no endpoint is
+    /// missing.
+    async fn resolve(&self) -> Result<Cow<'_, Self>> {
+        let is_missing_endpoints = self.auth_url.is_empty()
+            || self.token_url.is_empty()
+            || (self.flow.is_device_authorization() && self.device_auth_url.is_none());
+
+        if self.issuer.is_none() || !is_missing_endpoints {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let mut config = self.clone();
+        config.discover().await?;
+        Ok(Cow::Owned(config))
+    }
+
+    /// If the access token is not defined, runs the OAuth 2.0 flow
+    /// in order to save the acces token and the refresh token if
+    /// present.
     pub async fn configure(
         &self,
         get_client_secret: impl Fn() -> io::Result<String>,
@@ -109,17 +281,29 @@ pub async fn configure(
             return Ok(());
         }
 
-        let redirect_host = match self.redirect_host.as_ref() {
-            Some(host) => host.clone(),
-            None => OAuth2Config::LOCALHOST.to_owned(),
+        let tokens = match self.flow {
+            OAuth2Flow::AuthorizationCode => {
+                self.run_authorization_code_grant(get_client_secret).await?
+            }
+            OAuth2Flow::DeviceAuthorization => {
+                self.run_device_authorization_grant(get_client_secret)
+                    .await?
+            }
         };
 
-        let redirect_port = match self.redirect_port {
-            Some(port) => port,
-            None => OAuth2Config::get_first_available_port()?,
-        };
+        let mut state = self.tokens.0.lock().await;
+        self.save_tokens(&mut state, tokens).await?;
 
-        let client_secret = match self.client_secret.find().await {
+        Ok(())
+    }
+
+    /// Get the client secret from the keyring, otherwise ask the user
+    /// for it and save it into the keyring.
+    async fn get_or_set_client_secret(
+        &self,
+        get_client_secret: impl Fn() -> io::Result<String>,
+    ) -> Result<String> {
+        match self.client_secret.find().await {
             Ok(None) => {
                 debug!("cannot find oauth2 client secret from keyring, setting it");
                 self.client_secret
@@ -131,13 +315,34 @@ pub async fn configure(
             }
             Ok(Some(client_secret)) => Ok(client_secret),
             Err(err) => Err(Error::GetClientSecretFromKeyringOauthError(err)),
-        }?;
+        }
+    }
+
+    /// Run the authorization code grant flow, which needs a browser
+    /// and a local redirect server.
+    async fn run_authorization_code_grant(
+        &self,
+        get_client_secret: impl Fn() -> io::Result<String>,
+    ) -> Result<Tokens> {
+        let config = self.resolve().await?;
+
+        let redirect_host = match self.redirect_host.as_ref() {
+            Some(host) => host.clone(),
+            None => OAuth2Config::LOCALHOST.to_owned(),
+        };
+
+        let redirect_port = match self.redirect_port {
+            Some(port) => port,
+            None => OAuth2Config::get_first_available_port()?,
+        };
+
+        let client_secret = self.get_or_set_client_secret(get_client_secret).await?;
 
         let client = Client::new(
             self.client_id.clone(),
             client_secret,
-            self.auth_url.clone(),
-            self.token_url.clone(),
+            config.auth_url.clone(),
+            config.token_url.clone(),
         )
         .map_err(Error::InitOauthClientError)?
         .with_redirect_host(redirect_host)
@@ -149,7 +354,7 @@ pub async fn configure(
             .with_redirect_host(OAuth2Config::LOCALHOST.to_owned())
             .with_redirect_port(redirect_port);
 
-        if self.pkce {
+        if config.pkce {
             auth_code_grant = auth_code_grant.with_pkce();
         }
 
@@ -163,82 +368,340 @@ pub async fn configure(
         println!();
         println!("{}", redirect_url);
 
-        let (access_token, refresh_token) = auth_code_grant
+        auth_code_grant
             .wait_for_redirection(&client, csrf_token)
             .await
-            .map_err(Error::WaitForOauthRedirectionError)?;
+            .map_err(Error::WaitForOauthRedirectionError)
+    }
 
-        self.access_token
-            .set_only_keyring(access_token)
-            .await
-            .map_err(Error::SetAccessTokenOauthError)?;
+    /// Run the device authorization grant flow, which only needs the
+    /// user to open an URL and to enter a code from any device.
+    ///
+    /// Public clients do not need any client secret, so the user is
+    /// not asked for it when the client secret is undefined.
+    async fn run_device_authorization_grant(
+        &self,
+        get_client_secret: impl Fn() -> io::Result<String>,
+    ) -> Result<Tokens> {
+        let config = self.resolve().await?;
+
+        let device_auth_url = config
+            .device_auth_url
+            .as_ref()
+            .ok_or(Error::MissingDeviceAuthUrlOauthError)?;
+
+        let client_secret = if self.client_secret.is_undefined() {
+            String::new()
+        } else {
+            self.get_or_set_client_secret(get_client_secret).await?
+        };
 
-        if let Some(refresh_token) = &refresh_token {
-            self.refresh_token
-                .set_only_keyring(refresh_token)
-                .await
-                .map_err(Error::SetRefreshTokenOauthError)?;
+        let client = Client::new(
+            self.client_id.clone(),
+            client_secret,
+            config.auth_url.clone(),
+            config.token_url.clone(),
+        )
+        .map_err(Error::InitOauthClientError)?
+        .with_device_auth_url(device_auth_url)
+        .map_err(Error::InitOauthClientError)?
+        .build()
+        .map_err(Error::BuildOauthClientError)?;
+
+        let mut device_grant = DeviceAuthorizationGrant::new();
+
+        for scope in self.scopes.clone() {
+            device_grant = device_grant.with_scope(scope);
         }
 
-        Ok(())
-    }
+        let details = device_grant
+            .request_device_code(&client)
+            .await
+            .map_err(Error::RequestDeviceCodeOauthError)?;
 
-    /// Runs the refresh access token OAuth 2.0 flow by exchanging a
-    /// refresh token with a new pair of access/refresh token.
-    pub async fn refresh_access_token(&self) -> Result<String> {
-        let redirect_port = OAuth2Config::get_first_available_port()?;
+        println!("To complete your OAuth 2.0 setup, open the following link from any device:");
+        println!();
 
-        let client_secret = self
-            .client_secret
-            .get()
+        match details.verification_uri_complete() {
+            Some(uri) => println!("{}", uri.secret()),
+            None => {
+                println!("{}", details.verification_uri().url());
+                println!();
+                println!("then enter the code {}", details.user_code().secret());
+            }
+        }
+
+        device_grant
+            .wait_for_authorization(&client, &details)
             .await
-            .map_err(Error::GetClientSecretFromKeyringOauthError)?;
+            .map_err(Error::WaitForOauthDeviceAuthorizationError)
+    }
 
-        let client = Client::new(
+    /// Builds the OAuth 2.0 client used to refresh and to revoke
+    /// tokens.
+    ///
+    /// Missing endpoints are not discovered, see
+    /// [`OAuth2Config::resolve`].
+    async fn build_client(&self) -> Result<Client> {
+        let client_secret = if self.client_secret.is_undefined() {
+            String::new()
+        } else {
+            self.client_secret
+                .get()
+                .await
+                .map_err(Error::GetClientSecretFromKeyringOauthError)?
+        };
+
+        let mut client = Client::new(
             self.client_id.clone(),
             client_secret,
             self.auth_url.clone(),
             self.token_url.clone(),
         )
-        .map_err(Error::InitOauthClientError)?
-        .with_redirect_host(OAuth2Config::LOCALHOST.to_owned())
-        .with_redirect_port(redirect_port)
-        .build()
-        .map_err(Error::BuildOauthClientError)?;
+        .map_err(Error::InitOauthClientError)?;
+
+        if let Some(url) = &self.revocation_url {
+            client = client
+                .with_revocation_url(url)
+                .map_err(Error::InitOauthClientError)?;
+        }
 
-        let refresh_token = self
-            .refresh_token
+        Ok(client)
+    }
+
+    /// Loads the access token from the secrets, unless it is already
+    /// loaded.
+    ///
+    /// The expiry of a loaded access token is unknown, so it is only
+    /// refreshed once rejected by the server, see
+    /// [`OAuth2Config::renew_access_token`].
+    async fn load_tokens(&self, state: &mut OAuth2TokensState) -> Result<()> {
+        if state.access_token.is_some() {
+            return Ok(());
+        }
+
+        let access_token = self
+            .access_token
             .get()
             .await
-            .map_err(Error::GetRefreshTokenOauthError)?;
+            .map_err(Error::GetAccessTokenOauthError)?;
 
-        let (access_token, refresh_token) = RefreshAccessToken::new()
-            .refresh_access_token(&client, refresh_token)
-            .await
-            .map_err(Error::RefreshAccessTokenOauthError)?;
+        state.access_token = Some(access_token);
+        state.expires_at = None;
 
-        self.access_token
-            .set_only_keyring(&access_token)
-            .await
-            .map_err(Error::SetAccessTokenOauthError)?;
+        Ok(())
+    }
+
+    /// Saves the given tokens in memory, then in the secrets.
+    ///
+    /// A rotated refresh token is saved first, since the previous one
+    /// may not be valid anymore. Only keyring-based secrets can be
+    /// updated: other ones are kept in memory only. The expiry of
+    /// the access token is not a secret, so it is only kept in
+    /// memory.
+    async fn save_tokens(&self, state: &mut OAuth2TokensState, tokens: Tokens) -> Result<String> {
+        let expires_at = tokens.expires_in.map(|ttl| SystemTime::now() + ttl);
+
+        state.access_token = Some(tokens.access_token.clone());
+        state.expires_at = expires_at;
+
+        if let Some(refresh_token) = tokens.refresh_token {
+            let rotated = state
+                .refresh_token
+                .as_ref()
+                .is_some_and(|prev| *prev != refresh_token);
+
+            if rotated && !matches!(self.refresh_token, Secret::KeyringEntry(_)) {
+                warn!("oauth2 refresh token rotated but cannot be saved outside of the keyring");
+            }
 
-        if let Some(refresh_token) = &refresh_token {
             self.refresh_token
-                .set_only_keyring(refresh_token)
+                .set_only_keyring(&refresh_token)
                 .await
                 .map_err(Error::SetRefreshTokenOauthError)?;
+
+            state.refresh_token = Some(refresh_token);
         }
 
-        Ok(access_token)
+        self.access_token
+            .set_only_keyring(&tokens.access_token)
+            .await
+            .map_err(Error::SetAccessTokenOauthError)?;
+
+        Ok(tokens.access_token)
+    }
+
+    /// Exchanges the refresh token for new tokens, then saves them.
+    ///
+    /// If the refresh token in memory is rejected, the saved one is
+    /// tried as well: it may have been rotated by another process
+    /// sharing the same secrets.
+    async fn refresh(&self, state: &mut OAuth2TokensState) -> Result<String> {
+        let client = self
+            .resolve()
+            .await?
+            .build_client()
+            .await?
+            .build()
+            .map_err(Error::BuildOauthClientError)?;
+
+        let mut refresh_token = match &state.refresh_token {
+            Some(refresh_token) => refresh_token.clone(),
+            None => self
+                .refresh_token
+                .get()
+                .await
+                .map_err(Error::GetRefreshTokenOauthError)?,
+        };
+
+        let res = RefreshAccessToken::new()
+            .refresh_access_token(&client, &refresh_token)
+            .await;
+
+        let res = match res {
+            Err(err) if state.refresh_token.is_some() => match self.refresh_token.find().await {
+                Ok(Some(saved)) if saved != refresh_token => {
+                    debug!("oauth2 refresh token rejected, retrying with the saved one");
+                    refresh_token = saved;
+                    RefreshAccessToken::new()
+                        .refresh_access_token(&client, &refresh_token)
+                        .await
+                }
+                _ => Err(err),
+            },
+            res => res,
+        };
+
+        let tokens = res.map_err(Error::RefreshAccessTokenOauthError)?;
+        state.refresh_token = Some(refresh_token);
+
+        self.save_tokens(state, tokens).await
+    }
+
+    /// Runs the refresh access token OAuth 2.0 flow by exchanging a
+    /// refresh token with a new pair of access/refresh token.
+    ///
+    /// Refreshes are serialized between all the clones of the
+    /// configuration: concurrent callers wait for the current refresh
+    /// to complete.
+    pub async fn refresh_access_token(&self) -> Result<String> {
+        let mut state = self.tokens.0.lock().await;
+        self.refresh(&mut state).await
+    }
+
+    /// Renews the given access token, after it has been rejected by
+    /// the server.
+    ///
+    /// If the access token has already been renewed in the meantime,
+    /// for example by another pooled client, the renewed one is
+    /// returned without refreshing it again.
+    pub async fn renew_access_token(&self, rejected: &str) -> Result<String> {
+        let mut state = self.tokens.0.lock().await;
+
+        match &state.access_token {
+            Some(access_token) if access_token != rejected => {
+                debug!("oauth2 access token already renewed");
+                Ok(access_token.clone())
+            }
+            _ => self.refresh(&mut state).await,
+        }
     }
 
     /// Returns the access token if existing, otherwise returns an
     /// error.
+    ///
+    /// The access token is refreshed first when it is about to
+    /// expire, see [`OAuth2Config::REFRESH_MARGIN`]. If this refresh
+    /// fails while the access token did not expire yet, the current
+    /// access token is returned.
     pub async fn access_token(&self) -> Result<String> {
-        self.access_token
-            .get()
-            .await
-            .map_err(Error::GetAccessTokenOauthError)
+        let mut state = self.tokens.0.lock().await;
+        self.load_tokens(&mut state).await?;
+
+        if state.renews_in() == Some(Duration::ZERO) {
+            debug!("oauth2 access token about to expire, refreshing it");
+
+            match self.refresh(&mut state).await {
+                Ok(access_token) => return Ok(access_token),
+                Err(err) if state.is_expired() => return Err(err),
+                Err(_err) => {
+                    warn!("cannot refresh oauth2 access token before expiry: {_err}");
+                }
+            }
+        }
+
+        Ok(state.access_token.clone().unwrap_or_default())
+    }
+
+    /// Returns the duration left before the access token needs to be
+    /// refreshed, if its expiry is known.
+    pub async fn access_token_renews_in(&self) -> Option<Duration> {
+        let mut state = self.tokens.0.lock().await;
+        self.load_tokens(&mut state).await.ok()?;
+        state.renews_in()
+    }
+}
+
+/// The in-memory state of the OAuth 2.0 tokens.
+///
+/// The state is shared between all the clones of a configuration, so
+/// that pooled clients share the same tokens and refresh them one at
+/// a time. It is ignored when comparing configurations.
+#[derive(Clone, Debug, Default)]
+pub struct OAuth2Tokens(Arc<Mutex<OAuth2TokensState>>);
+
+/// The tokens are runtime state, not configuration: any two states
+/// are considered equal, so that comparing two [`OAuth2Config`]
+/// only compares their configuration.
+impl PartialEq for OAuth2Tokens {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// The equality above is trivially reflexive, symmetric and
+/// transitive.
+impl Eq for OAuth2Tokens {}
+
+#[derive(Default)]
+struct OAuth2TokensState {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_at: Option<SystemTime>,
+}
+
+/// Tokens are redacted, so that they do not leak in logs.
+impl fmt::Debug for OAuth2TokensState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redact = |token: &Option<String>| token.as_ref().map(|_| "<redacted>");
+
+        f.debug_struct("OAuth2TokensState")
+            .field("access_token", &redact(&self.access_token))
+            .field("refresh_token", &redact(&self.refresh_token))
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl OAuth2TokensState {
+    /// Returns the duration left before the access token needs to be
+    /// refreshed, if its expiry is known.
+    fn renews_in(&self) -> Option<Duration> {
+        let renews_at = self
+            .expires_at?
+            .checked_sub(OAuth2Config::REFRESH_MARGIN)
+            .unwrap_or(UNIX_EPOCH);
+
+        Some(
+            renews_at
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        )
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= SystemTime::now())
     }
 }
 
@@ -267,6 +730,41 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Flow used to obtain the first access token.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum OAuth2Flow {
+    /// The authorization code grant, which needs a browser and a
+    /// local redirect server, see
+    /// [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-4.1).
+    #[default]
+    AuthorizationCode,
+
+    /// The device authorization grant, which suits headless devices
+    /// and SSH sessions, see
+    /// [RFC8628](https://datatracker.ietf.org/doc/html/rfc8628).
+    DeviceAuthorization,
+}
+
+impl OAuth2Flow {
+    pub fn is_device_authorization(&self) -> bool {
+        matches!(self, Self::DeviceAuthorization)
+    }
+}
+
+impl fmt::Display for OAuth2Flow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AuthorizationCode => write!(f, "authorization code"),
+            Self::DeviceAuthorization => write!(f, "device authorization"),
+        }
+    }
+}
+
 /// Access token scope(s), as defined by the authorization server.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
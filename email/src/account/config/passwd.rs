@@ -3,7 +3,7 @@
 //! This module contains everything related to password configuration.
 
 use std::{
-    io,
+    fmt, io,
     ops::{Deref, DerefMut},
 };
 
@@ -13,6 +13,10 @@
 pub use super::{Error, Result};
 
 /// The password configuration.
+///
+/// The SASL mechanism used to authenticate with the password is
+/// defined next to the authentication configuration, see
+/// `ImapConfig::sasl_mechanism` and `SmtpConfig::sasl_mechanism`.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
@@ -27,6 +31,12 @@ pub struct PasswdConfig(
     pub Secret,
 );
 
+impl From<Secret> for PasswdConfig {
+    fn from(secret: Secret) -> Self {
+        Self(secret)
+    }
+}
+
 impl Deref for PasswdConfig {
     type Target = Secret;
 
@@ -78,3 +88,98 @@ pub async fn configure<F>(
         }
     }
 }
+
+/// The SASL mechanism used to authenticate with a password.
+///
+/// See the [`crate::sasl`] module for the implementation of every
+/// mechanism.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum SaslMechanism {
+    /// The SCRAM-SHA-256 mechanism, as defined in the
+    /// [RFC7677](https://datatracker.ietf.org/doc/html/rfc7677).
+    ///
+    /// The password is never sent to the server, and the server has
+    /// to prove that it knows the password as well. Channel binding
+    /// (SCRAM-SHA-256-PLUS) is used when the connection is encrypted
+    /// with TLS 1.3 and the server supports it.
+    #[cfg_attr(feature = "derive", serde(rename = "scram-sha-256"))]
+    ScramSha256,
+
+    /// The SCRAM-SHA-1 mechanism, as defined in the
+    /// [RFC5802](https://datatracker.ietf.org/doc/html/rfc5802).
+    #[cfg_attr(feature = "derive", serde(rename = "scram-sha-1"))]
+    ScramSha1,
+
+    /// The CRAM-MD5 mechanism, as defined in the
+    /// [RFC2195](https://datatracker.ietf.org/doc/html/rfc2195).
+    ///
+    /// The password is not sent in clear, but the mechanism relies
+    /// on MD5 and does not authenticate the server.
+    CramMd5,
+
+    /// The PLAIN mechanism, as defined in the
+    /// [RFC4616](https://datatracker.ietf.org/doc/html/rfc4616).
+    ///
+    /// The password is sent in clear.
+    Plain,
+
+    /// The LOGIN mechanism.
+    ///
+    /// The password is sent in clear.
+    Login,
+
+    /// The EXTERNAL mechanism, as defined in the
+    /// [RFC4422](https://datatracker.ietf.org/doc/html/rfc4422#appendix-A).
+    ///
+    /// The client is authenticated by other means, usually a TLS
+    /// client certificate, the password is not used.
+    External,
+}
+
+impl SaslMechanism {
+    /// The mechanisms negotiated when none is defined in the
+    /// configuration, from the most secure to the least secure one.
+    ///
+    /// The EXTERNAL mechanism needs to be explicitly defined.
+    pub const NEGOTIABLE: [SaslMechanism; 5] = [
+        SaslMechanism::ScramSha256,
+        SaslMechanism::ScramSha1,
+        SaslMechanism::CramMd5,
+        SaslMechanism::Plain,
+        SaslMechanism::Login,
+    ];
+
+    /// Return the name of the mechanism, as registered at the
+    /// [IANA](https://www.iana.org/assignments/sasl-mechanisms/sasl-mechanisms.xhtml).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ScramSha256 => "SCRAM-SHA-256",
+            Self::ScramSha1 => "SCRAM-SHA-1",
+            Self::CramMd5 => "CRAM-MD5",
+            Self::Plain => "PLAIN",
+            Self::Login => "LOGIN",
+            Self::External => "EXTERNAL",
+        }
+    }
+
+    /// Return `true` if the mechanism supports channel binding.
+    pub fn supports_channel_binding(&self) -> bool {
+        matches!(self, Self::ScramSha256 | Self::ScramSha1)
+    }
+
+    /// Return `true` if the mechanism needs the password.
+    pub fn needs_passwd(&self) -> bool {
+        !matches!(self, Self::External)
+    }
+}
+
+impl fmt::Display for SaslMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
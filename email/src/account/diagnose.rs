@@ -0,0 +1,1136 @@
+//! # Account diagnostics
+//!
+//! This module contains everything needed to diagnose an account
+//! configuration, which is the first thing to do when an account
+//! does not work as expected.
+//!
+//! The [`DiagnosticBuilder`] first validates the account
+//! configuration statically: paths, signature, PGP keys and folder
+//! aliases. It then checks every given backend live: DNS resolution,
+//! TCP connection, TLS handshake, authentication, capabilities and
+//! folder aliases resolution. Every check adds a [`Diagnostic`] to
+//! the final [`DiagnosticReport`], with a remediation hint when the
+//! check does not pass.
+
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use shellexpand_utils::shellexpand_path;
+use tokio::{net::TcpStream, time};
+
+#[cfg(feature = "pgp")]
+use super::config::pgp::PgpConfig;
+use super::config::AccountConfig;
+#[cfg(feature = "maildir")]
+use crate::maildir::config::MaildirConfig;
+#[cfg(feature = "smtp")]
+use crate::smtp::{config::SmtpConfig, SmtpContextBuilder};
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use crate::{backend::context::BackendContextBuilder, AnyBoxedError};
+use crate::{
+    debug,
+    folder::{DRAFTS, INBOX, SENT, TRASH},
+};
+#[cfg(feature = "imap")]
+use crate::{
+    folder::{FolderKind, Folders},
+    imap::{config::ImapConfig, ImapContextBuilder},
+};
+
+/// The default timeout of every live check.
+pub const DEFAULT_DIAGNOSTIC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The special folders every account should resolve.
+const SPECIAL_FOLDERS: [&str; 4] = [INBOX, SENT, DRAFTS, TRASH];
+
+/// The status of a diagnostic, from the best to the worst.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum DiagnosticStatus {
+    /// The check passed.
+    #[default]
+    Pass,
+
+    /// The check passed, but something may not work as expected.
+    Warn,
+
+    /// The check failed.
+    Fail,
+}
+
+impl fmt::Display for DiagnosticStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "pass"),
+            Self::Warn => write!(f, "warn"),
+            Self::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// The result of a single check.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct Diagnostic {
+    /// The identifier of the check, for example `imap.tls` or
+    /// `signature`.
+    pub check: String,
+
+    /// The status of the check.
+    pub status: DiagnosticStatus,
+
+    /// The human-readable result of the check.
+    pub message: String,
+
+    /// How to fix the configuration, if the check did not pass.
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Create a new passing diagnostic.
+    pub fn pass(check: impl ToString, message: impl ToString) -> Self {
+        Self {
+            check: check.to_string(),
+            status: DiagnosticStatus::Pass,
+            message: message.to_string(),
+            hint: None,
+        }
+    }
+
+    /// Create a new warning diagnostic.
+    pub fn warn(check: impl ToString, message: impl ToString, hint: impl ToString) -> Self {
+        Self {
+            check: check.to_string(),
+            status: DiagnosticStatus::Warn,
+            message: message.to_string(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    /// Create a new failing diagnostic.
+    pub fn fail(check: impl ToString, message: impl ToString, hint: impl ToString) -> Self {
+        Self {
+            check: check.to_string(),
+            status: DiagnosticStatus::Fail,
+            message: message.to_string(),
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.check, self.message)?;
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The report of every check, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "derive", derive(serde::Serialize), serde(transparent))]
+pub struct DiagnosticReport {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticReport {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        debug!("{diagnostic}");
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Get the worst status of the report.
+    pub fn status(&self) -> DiagnosticStatus {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.status)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if no check failed.
+    pub fn is_ok(&self) -> bool {
+        self.status() != DiagnosticStatus::Fail
+    }
+
+    /// Get the diagnostics that did not pass.
+    pub fn problems(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.status != DiagnosticStatus::Pass)
+    }
+
+    /// Find the diagnostic of the given check.
+    pub fn find(&self, check: &str) -> Option<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.check == check)
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{diagnostic}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The stage a backend connection failed at.
+#[cfg(any(feature = "imap", feature = "smtp"))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stage {
+    Tls,
+    Credentials,
+    Auth,
+    Other,
+}
+
+/// The account diagnostic builder.
+///
+/// Backends are only checked live when their configuration is given.
+#[derive(Clone, Debug)]
+pub struct DiagnosticBuilder {
+    account_config: Arc<AccountConfig>,
+
+    #[cfg(feature = "imap")]
+    imap_config: Option<Arc<ImapConfig>>,
+
+    #[cfg(feature = "smtp")]
+    smtp_config: Option<Arc<SmtpConfig>>,
+
+    #[cfg(feature = "maildir")]
+    maildir_config: Option<Arc<MaildirConfig>>,
+
+    timeout: Duration,
+}
+
+impl DiagnosticBuilder {
+    pub fn new(account_config: Arc<AccountConfig>) -> Self {
+        Self {
+            account_config,
+            #[cfg(feature = "imap")]
+            imap_config: None,
+            #[cfg(feature = "smtp")]
+            smtp_config: None,
+            #[cfg(feature = "maildir")]
+            maildir_config: None,
+            timeout: DEFAULT_DIAGNOSTIC_TIMEOUT,
+        }
+    }
+
+    #[cfg(feature = "imap")]
+    pub fn with_imap_config(mut self, config: Arc<ImapConfig>) -> Self {
+        self.imap_config = Some(config);
+        self
+    }
+
+    #[cfg(feature = "smtp")]
+    pub fn with_smtp_config(mut self, config: Arc<SmtpConfig>) -> Self {
+        self.smtp_config = Some(config);
+        self
+    }
+
+    #[cfg(feature = "maildir")]
+    pub fn with_maildir_config(mut self, config: Arc<MaildirConfig>) -> Self {
+        self.maildir_config = Some(config);
+        self
+    }
+
+    /// Set the timeout of every live check.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Validate the configuration without any network access.
+    pub async fn diagnose_config(&self) -> DiagnosticReport {
+        let mut report = DiagnosticReport::default();
+
+        self.check_email(&mut report);
+        self.check_downloads_dir(&mut report);
+        self.check_signature(&mut report);
+        self.check_folder_aliases(&mut report);
+        #[cfg(feature = "sync")]
+        self.check_sync_dir(&mut report);
+        #[cfg(feature = "maildir")]
+        self.check_maildir(&mut report);
+        #[cfg(feature = "pgp")]
+        self.check_pgp(&mut report).await;
+
+        report
+    }
+
+    /// Validate the configuration, then check every backend live.
+    pub async fn diagnose(&self) -> DiagnosticReport {
+        #[allow(unused_mut)]
+        let mut report = self.diagnose_config().await;
+
+        #[cfg(feature = "imap")]
+        if let Some(config) = &self.imap_config {
+            self.check_imap(config, &mut report).await;
+        }
+
+        #[cfg(feature = "smtp")]
+        if let Some(config) = &self.smtp_config {
+            self.check_smtp(config, &mut report).await;
+        }
+
+        report
+    }
+
+    fn check_email(&self, report: &mut DiagnosticReport) {
+        let email = self.account_config.email.trim();
+
+        let valid = email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && !domain.is_empty());
+
+        if valid {
+            report.push(Diagnostic::pass("email", format!("{email} is valid")));
+        } else if email.is_empty() {
+            let hint = "set the email address of the account";
+            report.push(Diagnostic::fail("email", "email address is missing", hint));
+        } else {
+            let message = format!("{email} is not a valid email address");
+            let hint = "set an email address like user@example.org";
+            report.push(Diagnostic::fail("email", message, hint));
+        }
+    }
+
+    fn check_downloads_dir(&self, report: &mut DiagnosticReport) {
+        let Some(dir) = &self.account_config.downloads_dir else {
+            return;
+        };
+
+        let path = shellexpand_path(dir);
+
+        if path.is_dir() {
+            let message = format!("{} exists", path.display());
+            report.push(Diagnostic::pass("downloads-dir", message));
+        } else if path.exists() {
+            let message = format!("{} is not a directory", path.display());
+            let hint = "point downloads-dir to a directory";
+            report.push(Diagnostic::fail("downloads-dir", message, hint));
+        } else {
+            let message = format!("{} does not exist", path.display());
+            let hint = "create the directory, or point downloads-dir to an existing one";
+            report.push(Diagnostic::fail("downloads-dir", message, hint));
+        }
+    }
+
+    /// Check the signature.
+    ///
+    /// Since the signature can be either a path or a raw string, a
+    /// signature looking like a path that cannot be read is only
+    /// reported as warning: it would be used as raw string.
+    fn check_signature(&self, report: &mut DiagnosticReport) {
+        let Some(signature) = &self.account_config.signature else {
+            return;
+        };
+
+        let is_path = !signature.contains('\n') && signature.starts_with(['/', '~', '.', '$']);
+
+        if !is_path {
+            report.push(Diagnostic::pass("signature", "raw signature"));
+            return;
+        }
+
+        let path = shellexpand_path(signature);
+
+        match std::fs::read_to_string(&path) {
+            Ok(_) => {
+                let message = format!("{} is readable", path.display());
+                report.push(Diagnostic::pass("signature", message));
+            }
+            Err(err) => {
+                let message = format!("cannot read {}: {err}", path.display());
+                let hint = "fix the signature path, otherwise the path itself is used as signature";
+                report.push(Diagnostic::warn("signature", message, hint));
+            }
+        }
+    }
+
+    /// Check that folder aliases are neither empty, nor ambiguous,
+    /// nor shared between special folders.
+    fn check_folder_aliases(&self, report: &mut DiagnosticReport) {
+        let Some(aliases) = self.account_config.get_folder_aliases() else {
+            return;
+        };
+
+        let mut ok = true;
+        let mut keys: HashMap<String, Vec<&str>> = HashMap::new();
+
+        for (key, folder) in aliases {
+            keys.entry(key.to_lowercase()).or_default().push(key);
+
+            if folder.trim().is_empty() {
+                ok = false;
+                let message = format!("alias {key} is empty");
+                let hint = format!("set folder.aliases.{key} to a folder name, or remove it");
+                report.push(Diagnostic::fail("folder.aliases", message, hint));
+            }
+        }
+
+        for (_, mut keys) in keys.into_iter().filter(|(_, keys)| keys.len() > 1) {
+            ok = false;
+            keys.sort_unstable();
+            let message = format!("aliases {} are ambiguous", keys.join(", "));
+            let hint = "aliases are case-insensitive, keep only one of them";
+            report.push(Diagnostic::fail("folder.aliases", message, hint));
+        }
+
+        let mut specials: HashMap<String, Vec<&str>> = HashMap::new();
+
+        for kind in SPECIAL_FOLDERS {
+            let folder = self.account_config.get_folder_alias(kind);
+            specials.entry(folder).or_default().push(kind);
+        }
+
+        for (folder, kinds) in specials.into_iter().filter(|(_, kinds)| kinds.len() > 1) {
+            ok = false;
+            let message = format!("{} all resolve to folder {folder}", kinds.join(", "));
+            let hint = "special folders must resolve to distinct folders, \
+                        otherwise moving a message to one of them may alter another one";
+            report.push(Diagnostic::fail("folder.aliases", message, hint));
+        }
+
+        if ok {
+            let message = format!("{} aliases are consistent", aliases.len());
+            report.push(Diagnostic::pass("folder.aliases", message));
+        }
+    }
+
+    #[cfg(feature = "sync")]
+    fn check_sync_dir(&self, report: &mut DiagnosticReport) {
+        if !self.account_config.is_sync_enabled() {
+            return;
+        }
+
+        let Some(dir) = self
+            .account_config
+            .sync
+            .as_ref()
+            .and_then(|c| c.dir.as_ref())
+        else {
+            return;
+        };
+
+        let path = shellexpand_path(dir);
+
+        if path.is_dir() {
+            let message = format!("{} exists", path.display());
+            report.push(Diagnostic::pass("sync.dir", message));
+        } else if path.exists() {
+            let message = format!("{} is not a directory", path.display());
+            let hint = "point sync.dir to a directory";
+            report.push(Diagnostic::fail("sync.dir", message, hint));
+        } else {
+            let message = format!("{} does not exist yet", path.display());
+            let hint = "the directory will be created by the first synchronization, \
+                        check the path if it should already exist";
+            report.push(Diagnostic::warn("sync.dir", message, hint));
+        }
+    }
+
+    #[cfg(feature = "maildir")]
+    fn check_maildir(&self, report: &mut DiagnosticReport) {
+        let Some(config) = &self.maildir_config else {
+            return;
+        };
+
+        let path = shellexpand_path(&config.root_dir);
+
+        if !path.is_dir() {
+            let message = format!("{} is not a directory", path.display());
+            let hint = "create the Maildir, or fix maildir.root-dir";
+            report.push(Diagnostic::fail("maildir.root-dir", message, hint));
+            return;
+        }
+
+        let message = format!("{} exists", path.display());
+        report.push(Diagnostic::pass("maildir.root-dir", message));
+
+        let has_inbox = ["cur", "new", "tmp"]
+            .into_iter()
+            .all(|dir| path.join(dir).is_dir());
+
+        if has_inbox {
+            report.push(Diagnostic::pass("maildir.inbox", "inbox found at the root"));
+        } else {
+            let message = format!("{} does not contain cur, new and tmp", path.display());
+            let hint = "maildir.root-dir should point to the root level of the Maildir, \
+                        the one containing the inbox";
+            report.push(Diagnostic::warn("maildir.inbox", message, hint));
+        }
+    }
+
+    #[cfg(feature = "pgp")]
+    async fn check_pgp(&self, report: &mut DiagnosticReport) {
+        let Some(pgp) = &self.account_config.pgp else {
+            return;
+        };
+
+        match pgp {
+            #[cfg(feature = "pgp-native")]
+            PgpConfig::Native(config) => {
+                use mml::pgp::NativePgpSecretKey;
+
+                if let NativePgpSecretKey::None = config.secret_key {
+                    let message = "no secret key configured";
+                    let hint = "set pgp.secret-key to be able to sign and decrypt messages";
+                    report.push(Diagnostic::warn("pgp.secret-key", message, hint));
+                    return;
+                }
+
+                match config.secret_key.get(&self.account_config.email).await {
+                    Ok(_) => {
+                        report.push(Diagnostic::pass("pgp.secret-key", "secret key loaded"));
+                    }
+                    Err(err) => {
+                        let message = format!("cannot load secret key: {err}");
+                        let hint = "check that pgp.secret-key points to an armored secret key";
+                        report.push(Diagnostic::fail("pgp.secret-key", message, hint));
+                    }
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => debug!("skipping PGP check: only native keys can be loaded"),
+        }
+    }
+
+    /// Check that the given host resolves and accepts TCP
+    /// connections on the given port.
+    ///
+    /// Return `true` if both checks passed.
+    #[cfg_attr(not(any(feature = "imap", feature = "smtp")), allow(dead_code))]
+    async fn check_network(
+        &self,
+        prefix: &str,
+        host: &str,
+        port: u16,
+        report: &mut DiagnosticReport,
+    ) -> bool {
+        let check = format!("{prefix}.dns");
+
+        let addrs = match time::timeout(self.timeout, tokio::net::lookup_host((host, port))).await {
+            Ok(Ok(addrs)) => addrs.collect::<Vec<_>>(),
+            Ok(Err(err)) => {
+                let message = format!("cannot resolve {host}: {err}");
+                let hint = format!("check {prefix}.host and the network connection");
+                report.push(Diagnostic::fail(check, message, hint));
+                return false;
+            }
+            Err(_) => {
+                let message = format!("cannot resolve {host}: timed out");
+                let hint = "check the DNS configuration and the network connection";
+                report.push(Diagnostic::fail(check, message, hint));
+                return false;
+            }
+        };
+
+        let message = format!("{host} resolves to {} address(es)", addrs.len());
+        report.push(Diagnostic::pass(check, message));
+
+        let check = format!("{prefix}.tcp");
+        let mut errors = Vec::new();
+
+        for addr in &addrs {
+            match time::timeout(self.timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(_)) => {
+                    let message = format!("connected to {addr}");
+                    report.push(Diagnostic::pass(check, message));
+                    return true;
+                }
+                Ok(Err(err)) => errors.push(format!("{addr}: {err}")),
+                Err(_) => errors.push(format!("{addr}: timed out")),
+            }
+        }
+
+        let message = format!("cannot connect to {host}:{port}: {}", errors.join(", "));
+        let hint = format!("check {prefix}.port, and that no firewall blocks the connection");
+        report.push(Diagnostic::fail(check, message, hint));
+        false
+    }
+
+    /// Report the outcome of a backend connection, which covers TLS
+    /// handshake, credentials and authentication.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    fn report_connection(
+        &self,
+        prefix: &str,
+        encrypted: bool,
+        login: &str,
+        res: Result<(), (Stage, AnyBoxedError)>,
+        report: &mut DiagnosticReport,
+    ) -> bool {
+        let tls_check = format!("{prefix}.tls");
+
+        if !encrypted {
+            let message = "connection is not encrypted";
+            let hint = format!("set {prefix}.encryption to tls or start-tls");
+            report.push(Diagnostic::warn(&tls_check, message, hint));
+        }
+
+        let (stage, err) = match res {
+            Ok(()) => {
+                if encrypted {
+                    report.push(Diagnostic::pass(tls_check, "TLS handshake succeeded"));
+                }
+
+                let message = format!("authenticated as {login}");
+                report.push(Diagnostic::pass(format!("{prefix}.auth"), message));
+                return true;
+            }
+            Err(err) => err,
+        };
+
+        if stage == Stage::Auth && encrypted {
+            report.push(Diagnostic::pass(&tls_check, "TLS handshake succeeded"));
+        }
+
+        let mut errors = Vec::new();
+        let mut source = Some(&err as &dyn std::error::Error);
+
+        while let Some(err) = source {
+            errors.push(err.to_string());
+            source = err.source();
+        }
+
+        let message = errors.join(": ");
+
+        let diagnostic = match stage {
+            Stage::Tls => {
+                let hint = format!(
+                    "check that {prefix}.encryption matches {prefix}.port \
+                     (SSL/TLS usually on 993 for IMAP and 465 for SMTP, \
+                     STARTTLS on 143 and 587), and that the server certificate is valid \
+                     or trusted by {prefix}.tls"
+                );
+                Diagnostic::fail(tls_check, message, hint)
+            }
+            Stage::Credentials => {
+                let hint = format!(
+                    "check {prefix}.auth: the password command must succeed and print \
+                     the password on its first line, the keyring entry must exist"
+                );
+                Diagnostic::fail(format!("{prefix}.credentials"), message, hint)
+            }
+            Stage::Auth => {
+                let hint = format!(
+                    "check {prefix}.login and the password: some providers require an app \
+                     password or OAuth 2.0"
+                );
+                Diagnostic::fail(format!("{prefix}.auth"), message, hint)
+            }
+            Stage::Other => {
+                let hint = "enable tracing logs for more details";
+                Diagnostic::fail(format!("{prefix}.connection"), message, hint)
+            }
+        };
+
+        report.push(diagnostic);
+        false
+    }
+
+    #[cfg(feature = "imap")]
+    async fn check_imap(&self, config: &Arc<ImapConfig>, report: &mut DiagnosticReport) {
+        use crate::imap::Error;
+
+        if !self
+            .check_network("imap", &config.host, config.port, report)
+            .await
+        {
+            return;
+        }
+
+        let ctx_builder =
+            ImapContextBuilder::new(self.account_config.clone(), config.clone()).with_pool_size(1);
+
+        let encrypted = config.is_encryption_enabled();
+
+        let res = match time::timeout(self.timeout, ctx_builder.build()).await {
+            Ok(Ok(ctx)) => Ok(ctx),
+            Ok(Err(err)) => {
+                let stage = match err.as_any().downcast_ref::<Error>() {
+                    Some(
+                        Error::StartTlsError(..)
+                        | Error::RejectStartTlsError(..)
+                        | Error::BuildTlsConfigError(_)
+                        | Error::ParseServerNameError(..)
+                        | Error::TlsHandshakeError(..),
+                    ) => Stage::Tls,
+                    Some(
+                        Error::GetPasswdImapError(_)
+                        | Error::GetPasswdEmptyImapError
+                        | Error::RefreshAccessTokenError(_),
+                    ) => Stage::Credentials,
+                    Some(
+                        Error::LoginError(_)
+                        | Error::LoginNotSupportedError
+                        | Error::AuthenticatePlainError(_)
+                        | Error::AuthenticateXOauth2Error(_)
+                        | Error::AuthenticateOAuthBearerError(_)
+                        | Error::AuthenticatePlainNotSupportedError(_)
+                        | Error::AuthenticateXOAuth2NotSupportedError(_)
+                        | Error::AuthenticateOAuthBearerNotSupportedError(_)
+                        | Error::AuthenticateSaslError(..)
+                        | Error::AuthenticateSaslExchangeError(..)
+                        | Error::AuthenticateSaslNotSupportedError(..),
+                    ) => Stage::Auth,
+                    _ => Stage::Other,
+                };
+
+                Err((stage, err))
+            }
+            Err(_) => {
+                let secs = self.timeout.as_secs();
+                let message = format!("connection timed out after {secs}s");
+                let hint = "check imap.encryption and imap.port, or increase the timeout";
+                report.push(Diagnostic::fail("imap.connection", message, hint));
+                return;
+            }
+        };
+
+        let (ctx, res) = match res {
+            Ok(ctx) => (Some(ctx), Ok(())),
+            Err(err) => (None, Err(err)),
+        };
+
+        if !self.report_connection("imap", encrypted, &config.login, res, report) {
+            return;
+        }
+
+        let Some(ctx) = ctx else {
+            return;
+        };
+
+        let mut client = ctx.client().await;
+
+        if client.ext_sort_supported() {
+            report.push(Diagnostic::pass("imap.capabilities", "SORT supported"));
+        } else {
+            let message = "SORT not supported";
+            let hint = "envelopes are sorted by the client, listing may be slower";
+            report.push(Diagnostic::warn("imap.capabilities", message, hint));
+        }
+
+        let list = client.list_all_mailboxes(&self.account_config);
+
+        match time::timeout(self.timeout, list).await {
+            Ok(Ok(folders)) => self.check_remote_folder_aliases("imap", &folders, report),
+            Ok(Err(err)) => {
+                let message = format!("cannot list folders: {err}");
+                let hint = "check the permissions of the account";
+                report.push(Diagnostic::fail("imap.folders", message, hint));
+            }
+            Err(_) => {
+                let message = "cannot list folders: timed out";
+                let hint = "increase the timeout";
+                report.push(Diagnostic::fail("imap.folders", message, hint));
+            }
+        }
+    }
+
+    /// Check that special folders and folder aliases resolve to
+    /// existing folders.
+    #[cfg(feature = "imap")]
+    fn check_remote_folder_aliases(
+        &self,
+        prefix: &str,
+        folders: &Folders,
+        report: &mut DiagnosticReport,
+    ) {
+        let mut kinds: Vec<String> = SPECIAL_FOLDERS.into_iter().map(String::from).collect();
+
+        if let Some(aliases) = self.account_config.get_folder_aliases() {
+            let custom = aliases.keys().filter(|key| {
+                !SPECIAL_FOLDERS
+                    .iter()
+                    .any(|kind| FolderKind::from(kind) == FolderKind::from(key.as_str()))
+            });
+
+            kinds.extend(custom.cloned());
+            kinds.sort_unstable();
+            kinds.dedup();
+        }
+
+        let available = || {
+            folders
+                .iter()
+                .map(|folder| folder.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        for kind in kinds {
+            let folder = self.account_config.get_folder_alias(&kind);
+            let check = format!("{prefix}.folder.{}", kind.to_lowercase());
+            let is_inbox = kind.eq_ignore_ascii_case(INBOX);
+
+            let exists = folders
+                .iter()
+                .any(|f| f.name == folder || (is_inbox && f.name.eq_ignore_ascii_case(&folder)));
+
+            if exists {
+                let message = format!("{kind} resolves to {folder}");
+                report.push(Diagnostic::pass(check, message));
+                continue;
+            }
+
+            let key = kind.to_lowercase();
+            let message = format!("{kind} resolves to {folder}, which does not exist");
+
+            // special-use attributes may reveal the actual folder
+            let candidate = folders
+                .iter()
+                .find(|f| f.kind.as_ref() == Some(&FolderKind::from(&kind)))
+                .map(|f| f.name.as_str());
+
+            let hint = match candidate {
+                Some(name) => format!("set folder.aliases.{key} to {name:?}"),
+                None => format!("set folder.aliases.{key} to one of: {}", available()),
+            };
+
+            match self.account_config.find_folder_alias(&kind) {
+                Some(_) => report.push(Diagnostic::fail(check, message, hint)),
+                None => report.push(Diagnostic::warn(check, message, hint)),
+            }
+        }
+    }
+
+    #[cfg(feature = "smtp")]
+    async fn check_smtp(&self, config: &Arc<SmtpConfig>, report: &mut DiagnosticReport) {
+        use crate::smtp::Error;
+
+        if !self
+            .check_network("smtp", &config.host, config.port, report)
+            .await
+        {
+            return;
+        }
+
+        let ctx_builder = SmtpContextBuilder::new(self.account_config.clone(), config.clone());
+        let encrypted = config.is_encryption_enabled();
+
+        let res = match time::timeout(self.timeout, ctx_builder.build()).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(err)) => {
+                let stage = match err.as_any().downcast_ref::<Error>() {
+                    Some(err) => smtp_stage(err),
+                    None => Stage::Other,
+                };
+
+                Err((stage, err))
+            }
+            Err(_) => {
+                let secs = self.timeout.as_secs();
+                let message = format!("connection timed out after {secs}s");
+                let hint = "check smtp.encryption and smtp.port, or increase the timeout";
+                report.push(Diagnostic::fail("smtp.connection", message, hint));
+                return;
+            }
+        };
+
+        // capabilities can still be fetched when only the
+        // authentication failed, which helps to fix it
+        let connected = !matches!(res, Err((Stage::Tls | Stage::Other, _)));
+
+        self.report_connection("smtp", encrypted, &config.login, res, report);
+
+        if connected {
+            self.check_smtp_capabilities(config, report).await;
+        }
+    }
+
+    /// Check the extensions and the authentication mechanisms
+    /// advertised by the SMTP server in its `EHLO` response.
+    #[cfg(feature = "smtp")]
+    async fn check_smtp_capabilities(&self, config: &SmtpConfig, report: &mut DiagnosticReport) {
+        use smtp_proto::{EXT_8BIT_MIME, EXT_SMTP_UTF8};
+
+        use crate::{account::config::passwd::SaslMechanism, smtp::config::SmtpAuthConfig};
+
+        let ehlo = match time::timeout(self.timeout, crate::smtp::ehlo(config)).await {
+            Ok(Ok(ehlo)) => ehlo,
+            Ok(Err(err)) => {
+                let message = format!("cannot get capabilities: {err}");
+                let hint = "enable tracing logs for more details";
+                report.push(Diagnostic::fail("smtp.capabilities", message, hint));
+                return;
+            }
+            Err(_) => {
+                let message = "cannot get capabilities: timed out";
+                let hint = "increase the timeout";
+                report.push(Diagnostic::fail("smtp.capabilities", message, hint));
+                return;
+            }
+        };
+
+        let extensions = SMTP_EXTENSIONS
+            .into_iter()
+            .filter(|(ext, _)| ehlo.has_capability(*ext))
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+
+        if extensions.is_empty() {
+            report.push(Diagnostic::pass(
+                "smtp.capabilities",
+                "no extension supported",
+            ));
+        } else {
+            let message = format!("{} supported", extensions.join(", "));
+            report.push(Diagnostic::pass("smtp.capabilities", message));
+        }
+
+        if !ehlo.has_capability(EXT_8BIT_MIME) {
+            let message = "8BITMIME not supported";
+            let hint = "messages with 8-bit bodies may be rejected,                         prefer quoted-printable or base64 encodings";
+            report.push(Diagnostic::warn("smtp.capabilities", message, hint));
+        }
+
+        if !ehlo.has_capability(EXT_SMTP_UTF8) {
+            let message = "SMTPUTF8 not supported";
+            let hint = "messages to or from non-ASCII email addresses cannot be sent";
+            report.push(Diagnostic::warn("smtp.capabilities", message, hint));
+        }
+
+        let check = "smtp.auth-mechanisms";
+
+        let advertised = SMTP_AUTH_MECHANISMS
+            .into_iter()
+            .filter(|(mechanism, _)| ehlo.auth_mechanisms & mechanism != 0)
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>();
+
+        if advertised.is_empty() {
+            let message = "no authentication mechanism advertised";
+            let hint = "the server may only advertise them over encrypted connections, \
+                        check smtp.encryption";
+            report.push(Diagnostic::warn(check, message, hint));
+            return;
+        }
+
+        let advertised = advertised.join(", ");
+        let supports = |mechanism: u64| ehlo.auth_mechanisms & mechanism != 0;
+
+        let missing = match &config.auth {
+            SmtpAuthConfig::Passwd(_) => match config.sasl_mechanism {
+                Some(mechanism) => {
+                    let plain = crate::smtp::auth_mechanism(mechanism, false);
+                    let plus = crate::smtp::auth_mechanism(mechanism, true);
+                    (!supports(plain | plus)).then(|| mechanism.to_string())
+                }
+                None => {
+                    let negotiable = SaslMechanism::NEGOTIABLE
+                        .into_iter()
+                        .map(|m| crate::smtp::auth_mechanism(m, false))
+                        .fold(0, |flags, flag| flags | flag);
+                    (!supports(negotiable)).then(|| String::from("password mechanisms"))
+                }
+            },
+            #[cfg(feature = "oauth2")]
+            SmtpAuthConfig::OAuth2(oauth2) => {
+                use crate::account::config::oauth2::OAuth2Method;
+                use smtp_proto::{AUTH_OAUTHBEARER, AUTH_XOAUTH2};
+
+                let flag = match oauth2.method {
+                    OAuth2Method::XOAuth2 => AUTH_XOAUTH2,
+                    OAuth2Method::OAuthBearer => AUTH_OAUTHBEARER,
+                };
+
+                (!supports(flag)).then(|| oauth2.method.to_string())
+            }
+        };
+
+        match missing {
+            Some(mechanism) => {
+                let message = format!("{mechanism} not advertised, only {advertised}");
+                let hint = "set smtp.auth to use one of the advertised mechanisms";
+                report.push(Diagnostic::fail(check, message, hint));
+            }
+            None => {
+                let message = format!("{advertised} advertised");
+                report.push(Diagnostic::pass(check, message));
+            }
+        }
+    }
+}
+
+/// The SMTP extensions reported by the diagnostic, with their name.
+#[cfg(feature = "smtp")]
+const SMTP_EXTENSIONS: [(u32, &str); 6] = [
+    (smtp_proto::EXT_PIPELINING, "PIPELINING"),
+    (smtp_proto::EXT_8BIT_MIME, "8BITMIME"),
+    (smtp_proto::EXT_SMTP_UTF8, "SMTPUTF8"),
+    (smtp_proto::EXT_SIZE, "SIZE"),
+    (smtp_proto::EXT_CHUNKING, "CHUNKING"),
+    (smtp_proto::EXT_DSN, "DSN"),
+];
+
+/// The SMTP authentication mechanisms reported by the diagnostic,
+/// with their name.
+#[cfg(feature = "smtp")]
+const SMTP_AUTH_MECHANISMS: [(u64, &str); 10] = [
+    (smtp_proto::AUTH_SCRAM_SHA_256_PLUS, "SCRAM-SHA-256-PLUS"),
+    (smtp_proto::AUTH_SCRAM_SHA_256, "SCRAM-SHA-256"),
+    (smtp_proto::AUTH_SCRAM_SHA_1_PLUS, "SCRAM-SHA-1-PLUS"),
+    (smtp_proto::AUTH_SCRAM_SHA_1, "SCRAM-SHA-1"),
+    (smtp_proto::AUTH_CRAM_MD5, "CRAM-MD5"),
+    (smtp_proto::AUTH_PLAIN, "PLAIN"),
+    (smtp_proto::AUTH_LOGIN, "LOGIN"),
+    (smtp_proto::AUTH_EXTERNAL, "EXTERNAL"),
+    (smtp_proto::AUTH_XOAUTH2, "XOAUTH2"),
+    (smtp_proto::AUTH_OAUTHBEARER, "OAUTHBEARER"),
+];
+
+/// Return the stage the SMTP connection failed at, given its error.
+#[cfg(feature = "smtp")]
+fn smtp_stage(err: &crate::smtp::Error) -> Stage {
+    use crate::smtp::Error;
+
+    match err {
+        Error::GetPasswdSmtpError(_)
+        | Error::GetPasswdEmptySmtpError
+        | Error::AccessTokenWasNotAvailable
+        | Error::RefreshingAccessTokenFailed => Stage::Credentials,
+        Error::BuildTlsConfigSmtpError(_) => Stage::Tls,
+        Error::AuthenticateSmtpError(_)
+        | Error::AuthenticateSaslSmtpError(..)
+        | Error::AuthenticateSaslNotSupportedSmtpError(_)
+        | Error::AuthenticateSaslMechanismNotFoundSmtpError => Stage::Auth,
+        Error::ConnectTcpSmtpError(err) | Error::ConnectTlsSmtpError(err) => match err {
+            mail_send::Error::AuthenticationFailed(_)
+            | mail_send::Error::Auth(_)
+            | mail_send::Error::MissingCredentials
+            | mail_send::Error::UnsupportedAuthMechanism => Stage::Auth,
+            mail_send::Error::Tls(_)
+            | mail_send::Error::InvalidTLSName
+            | mail_send::Error::MissingStartTls => Stage::Tls,
+            _ => Stage::Other,
+        },
+        // the connection has been retried, the last error matters
+        Error::ConnectSmtpRetryError(err, _) => smtp_stage(err),
+        _ => Stage::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use tempfile::tempdir;
+    use tokio::net::TcpListener;
+
+    use super::{DiagnosticBuilder, DiagnosticReport, DiagnosticStatus};
+    use crate::{account::config::AccountConfig, folder::config::FolderConfig};
+
+    #[tokio::test]
+    async fn diagnose_config() {
+        let dir = tempdir().unwrap();
+        let signature = dir.path().join("signature");
+
+        let aliases = HashMap::from_iter([
+            (String::from("sent"), String::from("INBOX")),
+            (String::from("Trash"), String::from("Deleted")),
+            (String::from("trash"), String::from("Bin")),
+            (String::from("archives"), String::from("")),
+        ]);
+
+        let config = AccountConfig {
+            email: String::from("test"),
+            signature: Some(signature.to_string_lossy().to_string()),
+            downloads_dir: Some(dir.path().to_owned()),
+            folder: Some(FolderConfig {
+                aliases: Some(aliases),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let report = DiagnosticBuilder::new(Arc::new(config))
+            .diagnose_config()
+            .await;
+
+        assert_eq!(report.status(), DiagnosticStatus::Fail);
+
+        let email = report.find("email").unwrap();
+        assert_eq!(email.status, DiagnosticStatus::Fail);
+        assert!(email.hint.is_some());
+
+        let downloads_dir = report.find("downloads-dir").unwrap();
+        assert_eq!(downloads_dir.status, DiagnosticStatus::Pass);
+
+        let signature = report.find("signature").unwrap();
+        assert_eq!(signature.status, DiagnosticStatus::Warn);
+
+        let aliases: Vec<_> = report
+            .problems()
+            .filter(|diagnostic| diagnostic.check == "folder.aliases")
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+
+        assert_eq!(aliases.len(), 3, "{report}");
+        assert!(aliases.contains(&"alias archives is empty"));
+        assert!(aliases.contains(&"aliases Trash, trash are ambiguous"));
+        assert!(aliases.contains(&"INBOX, Sent all resolve to folder INBOX"));
+    }
+
+    #[tokio::test]
+    async fn check_network() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let diagnostic = DiagnosticBuilder::new(Default::default());
+
+        let mut report = DiagnosticReport::default();
+        assert!(
+            diagnostic
+                .check_network("imap", "127.0.0.1", port, &mut report)
+                .await
+        );
+        assert!(report.is_ok());
+        assert_eq!(report.len(), 2);
+
+        drop(listener);
+
+        let mut report = DiagnosticReport::default();
+        assert!(
+            !diagnostic
+                .check_network("imap", "127.0.0.1", port, &mut report)
+                .await
+        );
+        assert_eq!(
+            report.find("imap.dns").unwrap().status,
+            DiagnosticStatus::Pass
+        );
+        assert_eq!(
+            report.find("imap.tcp").unwrap().status,
+            DiagnosticStatus::Fail
+        );
+    }
+}
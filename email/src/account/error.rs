@@ -33,6 +33,20 @@ pub enum Error {
     #[cfg(feature = "oauth2")]
     #[error("cannot wait for oauth2 redirection error")]
     WaitForOauthRedirectionError(#[source] oauth::v2_0::Error),
+    #[error("cannot run oauth2 device authorization grant: missing device authorization url")]
+    MissingDeviceAuthUrlOauthError,
+    #[cfg(feature = "oauth2")]
+    #[error("cannot request oauth2 device code")]
+    RequestDeviceCodeOauthError(#[source] oauth::v2_0::Error),
+    #[cfg(feature = "oauth2")]
+    #[error("cannot wait for oauth2 device authorization")]
+    WaitForOauthDeviceAuthorizationError(#[source] oauth::v2_0::Error),
+    #[cfg(feature = "oauth2")]
+    #[error("cannot revoke oauth2 tokens")]
+    RevokeTokenOauthError(#[source] oauth::v2_0::Error),
+    #[cfg(feature = "oauth2")]
+    #[error("cannot discover oauth2 authorization server metadata")]
+    DiscoverOauthServerMetadataError(#[source] oauth::v2_0::Error),
 
     #[error("cannot get oauth2 access token from global keyring")]
     GetAccessTokenOauthError(#[source] secret::Error),
@@ -6,6 +6,7 @@
 //! PGP.
 
 pub mod config;
+pub mod diagnose;
 mod error;
 #[cfg(feature = "sync")]
 pub mod sync;
@@ -0,0 +1,828 @@
+//! # Account configuration builder
+//!
+//! This module contains the [`AutoConfigBuilder`], which turns
+//! discovered servers into ready IMAP, SMTP and OAuth 2.0
+//! configuration candidates, then probes them in order to only keep
+//! the ones that actually work.
+
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use std::{error, sync::Arc};
+use std::{fmt, time::Duration};
+
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use tokio::time::{self, Instant};
+
+use super::config::AutoConfig;
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use super::config::{AuthenticationType, SecurityType, Server, ServerType};
+#[cfg(feature = "oauth2")]
+use crate::account::config::oauth2::{OAuth2Config, OAuth2Scopes};
+use crate::account::config::{passwd::PasswdConfig, AccountConfig};
+#[cfg(feature = "imap")]
+use crate::imap::{
+    config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind},
+    ImapContextBuilder,
+};
+#[cfg(feature = "smtp")]
+use crate::smtp::{
+    config::{SmtpAuthConfig, SmtpConfig, SmtpEncryptionKind},
+    SmtpContextBuilder,
+};
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use crate::{
+    backend::{context::BackendContextBuilder, BackendBuilder},
+    debug,
+};
+
+/// The default maximum duration of a candidate check up.
+pub const DEFAULT_CHECK_UP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The account configuration builder.
+///
+/// The builder takes the discovered [`AutoConfig`] and generates
+/// configuration candidates, ordered by preference: SSL/TLS first,
+/// then STARTTLS, then plain text (only if allowed). Every
+/// encryption is tried for every discovered host, whatever the
+/// security type it advertises. Within the same encryption, the
+/// order of the discovered servers and of their authentication types
+/// is kept.
+pub struct AutoConfigBuilder {
+    account_config: AccountConfig,
+    autoconfig: AutoConfig,
+    login: Option<String>,
+    passwd: PasswdConfig,
+    #[cfg(feature = "oauth2")]
+    oauth2: Option<OAuth2Config>,
+    allow_plaintext: bool,
+    timeout: Duration,
+}
+
+impl AutoConfigBuilder {
+    /// Create a new builder from the given account configuration and
+    /// the configuration discovered for its email address.
+    pub fn new(account_config: AccountConfig, autoconfig: AutoConfig) -> Self {
+        Self {
+            account_config,
+            autoconfig,
+            login: None,
+            passwd: PasswdConfig::default(),
+            #[cfg(feature = "oauth2")]
+            oauth2: None,
+            allow_plaintext: false,
+            timeout: DEFAULT_CHECK_UP_TIMEOUT,
+        }
+    }
+
+    /// Set the login used by candidates.
+    ///
+    /// Defaults to the user name of the discovered server, or to the
+    /// email address of the account.
+    pub fn with_login(mut self, login: impl ToString) -> Self {
+        self.login = Some(login.to_string());
+        self
+    }
+
+    /// Set the password configuration used by candidates relying on
+    /// password authentication.
+    pub fn with_passwd(mut self, passwd: PasswdConfig) -> Self {
+        self.passwd = passwd;
+        self
+    }
+
+    /// Set the OAuth 2.0 configuration used by candidates relying on
+    /// OAuth 2.0 authentication.
+    ///
+    /// Empty URLs and scopes are completed with the discovered ones.
+    #[cfg(feature = "oauth2")]
+    pub fn with_oauth2(mut self, oauth2: OAuth2Config) -> Self {
+        self.oauth2 = Some(oauth2);
+        self
+    }
+
+    /// Allow candidates without encryption.
+    pub fn with_plaintext(mut self, allow: bool) -> Self {
+        self.allow_plaintext = allow;
+        self
+    }
+
+    /// Set the maximum duration of a candidate check up.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build the OAuth 2.0 configuration candidate.
+    ///
+    /// The candidate is made of the given OAuth 2.0 configuration,
+    /// completed with the discovered one. The discovered issuer lets
+    /// the remaining endpoints be discovered from the authorization
+    /// server metadata, see [`OAuth2Config::discover`].
+    #[cfg(feature = "oauth2")]
+    pub fn oauth2_candidate(&self) -> Option<OAuth2Config> {
+        let discovered = self.autoconfig.oauth2();
+
+        if self.oauth2.is_none() && discovered.is_none() {
+            return None;
+        }
+
+        let mut config = self.oauth2.clone().unwrap_or_default();
+
+        if let Some(discovered) = discovered {
+            if config.issuer.is_none() {
+                config.issuer = Some(discovered.issuer().to_owned());
+            }
+
+            if config.auth_url.is_empty() {
+                config.auth_url = discovered.auth_url().to_owned();
+            }
+
+            if config.token_url.is_empty() {
+                config.token_url = discovered.token_url().to_owned();
+            }
+
+            if config.scopes.clone().into_iter().next().is_none() {
+                let scopes = discovered.scope().into_iter().map(ToOwned::to_owned);
+                config.scopes = OAuth2Scopes::Scopes(scopes.collect());
+            }
+        }
+
+        Some(config)
+    }
+
+    /// Build the IMAP configuration candidates, ordered by
+    /// preference.
+    #[cfg(feature = "imap")]
+    pub fn imap_candidates(&self) -> Vec<ImapConfig> {
+        let mut candidates: Vec<ImapConfig> = Vec::new();
+
+        let servers: Vec<_> = self
+            .autoconfig
+            .email_provider()
+            .incoming_servers()
+            .into_iter()
+            .filter(|server| matches!(server.server_type(), ServerType::Imap))
+            .collect();
+
+        for server in &servers {
+            let Some(host) = server.hostname() else {
+                continue;
+            };
+
+            let mut encryptions = vec![ImapEncryptionKind::Tls, ImapEncryptionKind::StartTls];
+
+            if self.allow_plaintext {
+                encryptions.push(ImapEncryptionKind::None);
+            }
+
+            for encryption in encryptions {
+                let implicit_tls = matches!(encryption, ImapEncryptionKind::Tls);
+
+                let port = match advertised_port(&servers, server, implicit_tls) {
+                    Some(port) => port,
+                    None if implicit_tls => 993,
+                    None => 143,
+                };
+
+                for auth in self.auth_kinds(server) {
+                    let config = ImapConfig {
+                        host: self.replace_placeholders(host),
+                        port,
+                        encryption: Some(encryption.clone()),
+                        login: self.login(server),
+                        auth: match auth {
+                            AuthKind::Passwd => ImapAuthConfig::Passwd(self.passwd.clone()),
+                            #[cfg(feature = "oauth2")]
+                            AuthKind::OAuth2(config) => ImapAuthConfig::OAuth2(config),
+                        },
+                        ..Default::default()
+                    };
+
+                    if !candidates.contains(&config) {
+                        candidates.push(config);
+                    }
+                }
+            }
+        }
+
+        candidates.sort_by_key(|config| match config.encryption {
+            Some(ImapEncryptionKind::Tls) => 0,
+            Some(ImapEncryptionKind::StartTls) => 1,
+            _ => 2,
+        });
+
+        candidates
+    }
+
+    /// Build the SMTP configuration candidates, ordered by
+    /// preference.
+    #[cfg(feature = "smtp")]
+    pub fn smtp_candidates(&self) -> Vec<SmtpConfig> {
+        let mut candidates: Vec<SmtpConfig> = Vec::new();
+
+        let servers: Vec<_> = self
+            .autoconfig
+            .email_provider()
+            .outgoing_servers()
+            .into_iter()
+            .filter(|server| matches!(server.server_type(), ServerType::Smtp))
+            .collect();
+
+        for server in &servers {
+            let Some(host) = server.hostname() else {
+                continue;
+            };
+
+            let mut encryptions = vec![SmtpEncryptionKind::Tls, SmtpEncryptionKind::StartTls];
+
+            if self.allow_plaintext {
+                encryptions.push(SmtpEncryptionKind::None);
+            }
+
+            for encryption in encryptions {
+                let implicit_tls = matches!(encryption, SmtpEncryptionKind::Tls);
+
+                let port = match (advertised_port(&servers, server, implicit_tls), &encryption) {
+                    (Some(port), _) => port,
+                    (None, SmtpEncryptionKind::Tls) => 465,
+                    (None, SmtpEncryptionKind::StartTls) => 587,
+                    (None, SmtpEncryptionKind::None) => 25,
+                };
+
+                for auth in self.auth_kinds(server) {
+                    let config = SmtpConfig {
+                        host: self.replace_placeholders(host),
+                        port,
+                        encryption: Some(encryption.clone()),
+                        login: self.login(server),
+                        auth: match auth {
+                            AuthKind::Passwd => SmtpAuthConfig::Passwd(self.passwd.clone()),
+                            #[cfg(feature = "oauth2")]
+                            AuthKind::OAuth2(config) => SmtpAuthConfig::OAuth2(config),
+                        },
+                        ..Default::default()
+                    };
+
+                    if !candidates.contains(&config) {
+                        candidates.push(config);
+                    }
+                }
+            }
+        }
+
+        candidates.sort_by_key(|config| match config.encryption {
+            Some(SmtpEncryptionKind::Tls) => 0,
+            Some(SmtpEncryptionKind::StartTls) => 1,
+            _ => 2,
+        });
+
+        candidates
+    }
+
+    /// Probe IMAP candidates in order, and return the first one
+    /// passing the check up.
+    ///
+    /// Every attempt is added to the given report.
+    #[cfg(feature = "imap")]
+    pub async fn check_up_imap(&self, report: &mut AutoConfigReport) -> Option<ImapConfig> {
+        let account_config = Arc::new(self.account_config.clone());
+
+        for config in self.imap_candidates() {
+            let auth = match &config.auth {
+                ImapAuthConfig::Passwd(_) => String::from("password"),
+                #[cfg(feature = "oauth2")]
+                ImapAuthConfig::OAuth2(config) => format!("OAuth 2.0 ({})", config.method),
+            };
+
+            let ctx_builder =
+                ImapContextBuilder::new(account_config.clone(), Arc::new(config.clone()))
+                    .with_pool_size(1);
+
+            let attempt = AutoConfigAttempt {
+                protocol: AutoConfigProtocol::Imap,
+                host: config.host.clone(),
+                port: config.port,
+                encryption: config.encryption.clone().unwrap_or_default().to_string(),
+                auth,
+                ..Default::default()
+            };
+
+            if report.push(self.check_up(attempt, ctx_builder).await) {
+                return Some(config);
+            }
+        }
+
+        None
+    }
+
+    /// Probe SMTP candidates in order, and return the first one
+    /// passing the check up.
+    ///
+    /// Every attempt is added to the given report.
+    #[cfg(feature = "smtp")]
+    pub async fn check_up_smtp(&self, report: &mut AutoConfigReport) -> Option<SmtpConfig> {
+        let account_config = Arc::new(self.account_config.clone());
+
+        for config in self.smtp_candidates() {
+            let auth = match &config.auth {
+                SmtpAuthConfig::Passwd(_) => String::from("password"),
+                #[cfg(feature = "oauth2")]
+                SmtpAuthConfig::OAuth2(config) => format!("OAuth 2.0 ({})", config.method),
+            };
+
+            let ctx_builder =
+                SmtpContextBuilder::new(account_config.clone(), Arc::new(config.clone()));
+
+            let attempt = AutoConfigAttempt {
+                protocol: AutoConfigProtocol::Smtp,
+                host: config.host.clone(),
+                port: config.port,
+                encryption: config.encryption.clone().unwrap_or_default().to_string(),
+                auth,
+                ..Default::default()
+            };
+
+            if report.push(self.check_up(attempt, ctx_builder).await) {
+                return Some(config);
+            }
+        }
+
+        None
+    }
+
+    /// Probe all candidates, and build the verified account
+    /// configuration.
+    pub async fn build(self) -> AutoAccountConfig {
+        #[allow(unused_mut)]
+        let mut report = AutoConfigReport::default();
+
+        #[cfg(feature = "imap")]
+        let imap = self.check_up_imap(&mut report).await;
+        #[cfg(feature = "smtp")]
+        let smtp = self.check_up_smtp(&mut report).await;
+        #[cfg(feature = "oauth2")]
+        let oauth2 = self.oauth2_candidate();
+
+        AutoAccountConfig {
+            account_config: self.account_config,
+            #[cfg(feature = "imap")]
+            imap,
+            #[cfg(feature = "smtp")]
+            smtp,
+            #[cfg(feature = "oauth2")]
+            oauth2,
+            report,
+        }
+    }
+
+    /// Check up the backend built from the given context builder,
+    /// and complete the given attempt with the result.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    async fn check_up<CB: BackendContextBuilder>(
+        &self,
+        mut attempt: AutoConfigAttempt,
+        ctx_builder: CB,
+    ) -> AutoConfigAttempt {
+        let account_config = Arc::new(self.account_config.clone());
+        let check_up = BackendBuilder::new(account_config, ctx_builder).check_up();
+
+        let start = Instant::now();
+        let res = time::timeout(self.timeout, check_up).await;
+        attempt.duration = start.elapsed();
+
+        match res {
+            Ok(Ok(())) => (),
+            Ok(Err(err)) => {
+                let mut source = Some(&err as &dyn error::Error);
+
+                while let Some(err) = source {
+                    attempt.errors.push(err.to_string());
+                    source = err.source();
+                }
+            }
+            Err(_) => {
+                let secs = self.timeout.as_secs();
+                attempt
+                    .errors
+                    .push(format!("check up timed out after {secs}s"));
+            }
+        }
+
+        debug!("{attempt}");
+        attempt
+    }
+
+    /// Get the authentication kinds supported by both the given
+    /// server and the library, in order.
+    ///
+    /// Password authentication is used when the server does not
+    /// specify any authentication type.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    fn auth_kinds(&self, server: &Server) -> Vec<AuthKind> {
+        let types = server.authentication_type();
+
+        if types.is_empty() {
+            return vec![AuthKind::Passwd];
+        }
+
+        let mut kinds = Vec::new();
+
+        for auth in types {
+            match auth {
+                AuthenticationType::PasswordCleartext | AuthenticationType::PasswordEncrypted => {
+                    if !kinds.iter().any(|kind| matches!(kind, AuthKind::Passwd)) {
+                        kinds.push(AuthKind::Passwd);
+                    }
+                }
+                #[cfg(feature = "oauth2")]
+                AuthenticationType::OAuth2 => {
+                    if let Some(config) = self.oauth2_candidate() {
+                        kinds.push(AuthKind::OAuth2(config));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        kinds
+    }
+
+    /// Get the login of the given server.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    fn login(&self, server: &Server) -> String {
+        match (&self.login, server.username()) {
+            (Some(login), _) => login.clone(),
+            (None, Some(username)) => self.replace_placeholders(username),
+            (None, None) => self.account_config.email.clone(),
+        }
+    }
+
+    /// Replace the email placeholders of the Mozilla Autoconfiguration
+    /// in the given string.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    fn replace_placeholders(&self, s: &str) -> String {
+        let email = &self.account_config.email;
+        let (local_part, domain) = email.rsplit_once('@').unwrap_or((email, ""));
+
+        s.replace("%EMAILADDRESS%", email)
+            .replace("%EMAILLOCALPART%", local_part)
+            .replace("%EMAILDOMAIN%", domain)
+    }
+}
+
+/// Find the port advertised for the host of the given server, either
+/// for implicit TLS or for a clear connection (STARTTLS or plain
+/// text).
+///
+/// The port of the given server is preferred over the ports of the
+/// other servers of the same host.
+#[cfg(any(feature = "imap", feature = "smtp"))]
+fn advertised_port(servers: &[&Server], server: &Server, implicit_tls: bool) -> Option<u16> {
+    let same_host = servers
+        .iter()
+        .copied()
+        .filter(|other| other.hostname() == server.hostname());
+
+    std::iter::once(server)
+        .chain(same_host)
+        .filter(|server| {
+            let tls = matches!(server.security_type(), Some(SecurityType::Tls) | None);
+            tls == implicit_tls
+        })
+        .find_map(|server| server.port().copied())
+}
+
+/// The authentication kind of a candidate.
+#[cfg(any(feature = "imap", feature = "smtp"))]
+enum AuthKind {
+    Passwd,
+    #[cfg(feature = "oauth2")]
+    OAuth2(OAuth2Config),
+}
+
+/// The account configuration discovered then verified by the
+/// [`AutoConfigBuilder`].
+#[derive(Clone, Debug)]
+pub struct AutoAccountConfig {
+    /// The account configuration.
+    pub account_config: AccountConfig,
+
+    /// The first IMAP candidate passing the check up.
+    #[cfg(feature = "imap")]
+    pub imap: Option<ImapConfig>,
+
+    /// The first SMTP candidate passing the check up.
+    #[cfg(feature = "smtp")]
+    pub smtp: Option<SmtpConfig>,
+
+    /// The OAuth 2.0 candidate, if any.
+    #[cfg(feature = "oauth2")]
+    pub oauth2: Option<OAuth2Config>,
+
+    /// The report of every attempt.
+    pub report: AutoConfigReport,
+}
+
+/// The protocol of a candidate.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AutoConfigProtocol {
+    #[default]
+    Imap,
+    Smtp,
+}
+
+impl fmt::Display for AutoConfigProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Imap => write!(f, "IMAP"),
+            Self::Smtp => write!(f, "SMTP"),
+        }
+    }
+}
+
+/// The check up attempt of a candidate.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AutoConfigAttempt {
+    /// The protocol of the candidate.
+    pub protocol: AutoConfigProtocol,
+
+    /// The host name of the candidate.
+    pub host: String,
+
+    /// The port of the candidate.
+    pub port: u16,
+
+    /// The encryption of the candidate.
+    pub encryption: String,
+
+    /// The authentication of the candidate.
+    pub auth: String,
+
+    /// The time spent checking up the candidate.
+    pub duration: Duration,
+
+    /// The error chain, from the outermost error to its root cause.
+    ///
+    /// The chain is empty if the check up succeeded.
+    pub errors: Vec<String>,
+}
+
+impl AutoConfigAttempt {
+    /// Return `true` if the check up succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for AutoConfigAttempt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            protocol,
+            host,
+            port,
+            encryption,
+            auth,
+            duration,
+            errors,
+        } = self;
+
+        write!(f, "{protocol} {host}:{port} ({encryption}, {auth}): ")?;
+
+        if errors.is_empty() {
+            write!(f, "ok in {duration:?}")
+        } else {
+            write!(f, "failed in {duration:?}: {}", errors.join(": "))
+        }
+    }
+}
+
+/// The report of every candidate check up attempt, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AutoConfigReport {
+    attempts: Vec<AutoConfigAttempt>,
+}
+
+impl AutoConfigReport {
+    /// Add the given attempt to the report, and return `true` if it
+    /// succeeded.
+    pub fn push(&mut self, attempt: AutoConfigAttempt) -> bool {
+        let ok = attempt.is_ok();
+        self.attempts.push(attempt);
+        ok
+    }
+
+    pub fn len(&self) -> usize {
+        self.attempts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attempts.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AutoConfigAttempt> {
+        self.attempts.iter()
+    }
+}
+
+impl fmt::Display for AutoConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for attempt in &self.attempts {
+            writeln!(f, "{attempt}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "imap", feature = "smtp"))]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+
+    use super::{AutoConfigBuilder, AutoConfigProtocol};
+    use crate::{
+        account::config::AccountConfig,
+        autoconfig::config::{
+            AuthenticationType, AutoConfig, EmailProvider, EmailProviderProperty, SecurityType,
+            Server, ServerProperty, ServerType,
+        },
+        imap::config::{ImapAuthConfig, ImapEncryptionKind},
+        smtp::config::SmtpEncryptionKind,
+    };
+
+    fn server(r#type: ServerType, host: &str, port: Option<u16>, security: SecurityType) -> Server {
+        let mut properties = vec![ServerProperty::Hostname(host.to_owned())];
+
+        if let Some(port) = port {
+            properties.push(ServerProperty::Port(port));
+        }
+
+        properties.push(ServerProperty::SocketType(security));
+        properties.push(ServerProperty::Authentication(
+            AuthenticationType::PasswordCleartext,
+        ));
+        properties.push(ServerProperty::Username(String::from("%EMAILLOCALPART%")));
+
+        Server { r#type, properties }
+    }
+
+    fn builder(properties: Vec<EmailProviderProperty>) -> AutoConfigBuilder {
+        let account_config = AccountConfig {
+            email: String::from("test@localhost"),
+            ..Default::default()
+        };
+
+        let autoconfig = AutoConfig {
+            version: String::from("1.1"),
+            email_provider: EmailProvider {
+                id: String::from("localhost"),
+                properties,
+            },
+            oauth2: None,
+        };
+
+        AutoConfigBuilder::new(account_config, autoconfig)
+    }
+
+    /// Spawn a local stub closing every connection straight away, and
+    /// return its port.
+    async fn spawn_stub() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                drop(stream);
+            }
+        });
+
+        port
+    }
+
+    #[test]
+    fn candidates() {
+        let builder = || {
+            builder(vec![
+                EmailProviderProperty::IncomingServer(server(
+                    ServerType::Imap,
+                    "imap.%EMAILDOMAIN%",
+                    Some(143),
+                    SecurityType::Plain,
+                )),
+                EmailProviderProperty::IncomingServer(server(
+                    ServerType::Imap,
+                    "localhost",
+                    Some(1143),
+                    SecurityType::Starttls,
+                )),
+                EmailProviderProperty::IncomingServer(server(
+                    ServerType::Imap,
+                    "localhost",
+                    None,
+                    SecurityType::Tls,
+                )),
+                EmailProviderProperty::OutgoingServer(server(
+                    ServerType::Smtp,
+                    "localhost",
+                    None,
+                    SecurityType::Starttls,
+                )),
+            ])
+        };
+
+        let candidates = builder().imap_candidates();
+        let candidates: Vec<_> = candidates
+            .iter()
+            .map(|c| (c.host.as_str(), c.port, c.encryption.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            candidates,
+            [
+                ("imap.localhost", 993, ImapEncryptionKind::Tls),
+                ("localhost", 993, ImapEncryptionKind::Tls),
+                ("imap.localhost", 143, ImapEncryptionKind::StartTls),
+                ("localhost", 1143, ImapEncryptionKind::StartTls),
+            ]
+        );
+
+        let candidates = builder().imap_candidates();
+        assert_eq!(candidates[0].login, "test");
+        assert!(matches!(candidates[0].auth, ImapAuthConfig::Passwd(_)));
+
+        let candidates = builder().with_plaintext(true).imap_candidates();
+        assert_eq!(candidates.len(), 6);
+        assert_eq!(candidates[4].host, "imap.localhost");
+        assert_eq!(candidates[4].port, 143);
+        assert_eq!(candidates[4].encryption, Some(ImapEncryptionKind::None));
+        assert_eq!(candidates[5].host, "localhost");
+        assert_eq!(candidates[5].port, 1143);
+        assert_eq!(candidates[5].encryption, Some(ImapEncryptionKind::None));
+
+        let candidates = builder().with_login("login").smtp_candidates();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].port, 465);
+        assert_eq!(candidates[0].encryption, Some(SmtpEncryptionKind::Tls));
+        assert_eq!(candidates[1].port, 587);
+        assert_eq!(candidates[1].encryption, Some(SmtpEncryptionKind::StartTls));
+        assert_eq!(candidates[1].login, "login");
+    }
+
+    #[tokio::test]
+    async fn report() {
+        let (imap_tls, imap_starttls) = (spawn_stub().await, spawn_stub().await);
+        let (smtp_tls, smtp_starttls) = (spawn_stub().await, spawn_stub().await);
+
+        let account = builder(vec![
+            EmailProviderProperty::IncomingServer(server(
+                ServerType::Imap,
+                "127.0.0.1",
+                Some(imap_starttls),
+                SecurityType::Starttls,
+            )),
+            EmailProviderProperty::IncomingServer(server(
+                ServerType::Imap,
+                "127.0.0.1",
+                Some(imap_tls),
+                SecurityType::Tls,
+            )),
+            EmailProviderProperty::OutgoingServer(server(
+                ServerType::Smtp,
+                "127.0.0.1",
+                Some(smtp_tls),
+                SecurityType::Tls,
+            )),
+            EmailProviderProperty::OutgoingServer(server(
+                ServerType::Smtp,
+                "127.0.0.1",
+                Some(smtp_starttls),
+                SecurityType::Plain,
+            )),
+        ])
+        .with_plaintext(true)
+        .with_timeout(Duration::from_secs(5))
+        .build()
+        .await;
+
+        assert_eq!(account.imap, None);
+        assert_eq!(account.smtp, None);
+
+        let attempts: Vec<_> = account
+            .report
+            .iter()
+            .map(|attempt| (attempt.protocol, attempt.port, attempt.encryption.as_str()))
+            .collect();
+        assert_eq!(
+            attempts,
+            [
+                (AutoConfigProtocol::Imap, imap_tls, "SSL/TLS"),
+                (AutoConfigProtocol::Imap, imap_starttls, "StartTLS"),
+                (AutoConfigProtocol::Imap, imap_starttls, "None"),
+                (AutoConfigProtocol::Smtp, smtp_tls, "SSL/TLS"),
+                (AutoConfigProtocol::Smtp, smtp_starttls, "StartTLS"),
+                (AutoConfigProtocol::Smtp, smtp_starttls, "None"),
+            ]
+        );
+        assert!(account.report.iter().all(|attempt| !attempt.is_ok()));
+    }
+}
@@ -1,24 +1,56 @@
 //! # Account HTTP discovery
 //!
 //! This module contains everything needed to discover account using
-//! HTTP requests.
+//! HTTP requests: Mozilla autoconfig files, and Microsoft
+//! [Autodiscover] (POX XML and JSON v2 endpoints).
+//!
+//! [Autodiscover]: https://learn.microsoft.com/en-us/exchange/client-developer/exchange-web-services/autodiscover-for-exchange
+
+use std::str::FromStr;
 
+use email_address::EmailAddress;
 use http_body_util::{BodyExt, Full};
-use hyper::{body::Bytes, Uri};
+use hyper::{
+    body::Bytes,
+    header::{ACCEPT, CONTENT_TYPE, LOCATION},
+    http::uri::Scheme,
+    Request, Response, Uri,
+};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use hyper_util::{
     client::legacy::{connect::HttpConnector, Client},
     rt::TokioExecutor,
 };
+use serde::Deserialize;
 
-use super::config::AutoConfig;
+use super::config::{
+    AuthenticationType, AutoConfig, EmailProvider, EmailProviderProperty, SecurityType, Server,
+    ServerProperty, ServerType,
+};
 #[doc(inline)]
 pub use super::{Error, Result};
-use crate::trace;
+use crate::{debug, trace};
+
+/// The maximum number of redirections followed by the Autodiscover
+/// client, as recommended by Microsoft.
+pub const AUTODISCOVER_MAX_REDIRECTS: usize = 10;
+
+/// The Autodiscover request schema.
+const AUTODISCOVER_REQUEST_SCHEMA: &str =
+    "http://schemas.microsoft.com/exchange/autodiscover/outlook/requestschema/2006";
+
+/// The Autodiscover response schema accepted by the client.
+const AUTODISCOVER_RESPONSE_SCHEMA: &str =
+    "http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a";
 
 /// Simple HTTP client using rustls connector.
 pub struct HttpClient {
     client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+
+    /// Follow Autodiscover redirections to plain HTTP locations.
+    ///
+    /// Only enabled by tests, which cannot serve HTTPS.
+    insecure_redirects: bool,
 }
 
 impl HttpClient {
@@ -33,6 +65,7 @@ pub fn new() -> Result<Self> {
 
         let client = Self {
             client: Client::builder(TokioExecutor::new()).build(conn),
+            insecure_redirects: false,
         };
 
         Ok(client)
@@ -65,4 +98,651 @@ pub async fn get_config(&self, uri: Uri) -> Result<AutoConfig> {
             .map_err(|e| Error::SerdeXmlFailedForAutoConfig(uri, e))?;
         Ok(config)
     }
+
+    /// Send a POST request to the given Autodiscover (POX) URI and
+    /// try to map the response into autoconfig.
+    ///
+    /// HTTP redirections as well as Autodiscover redirections
+    /// (`redirectAddr` and `redirectUrl` actions) are followed, up to
+    /// [`AUTODISCOVER_MAX_REDIRECTS`] times. Redirections are only
+    /// followed to HTTPS locations, since the response dictates the
+    /// servers the credentials are later sent to.
+    pub async fn get_autodiscover_config(
+        &self,
+        uri: Uri,
+        addr: &EmailAddress,
+    ) -> Result<AutoConfig> {
+        let mut uri = uri;
+        let mut addr = addr.clone();
+
+        for _ in 0..=AUTODISCOVER_MAX_REDIRECTS {
+            let body = format!(
+                concat!(
+                    r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                    r#"<Autodiscover xmlns="{}">"#,
+                    "<Request>",
+                    "<EMailAddress>{}</EMailAddress>",
+                    "<AcceptableResponseSchema>{}</AcceptableResponseSchema>",
+                    "</Request>",
+                    "</Autodiscover>",
+                ),
+                AUTODISCOVER_REQUEST_SCHEMA,
+                escape_xml(addr.as_str()),
+                AUTODISCOVER_RESPONSE_SCHEMA,
+            );
+
+            let req = Request::post(uri.clone())
+                .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+                .body(Full::new(Bytes::from(body)))
+                .map_err(|e| Error::BuildAutodiscoverRequestError(uri.clone(), e))?;
+
+            let res = self.send(req).await?;
+            let status = res.status();
+
+            if status.is_redirection() {
+                uri = self.check_redirect(get_location(&uri, &res)?)?;
+                debug!("following Autodiscover HTTP redirection to {uri}");
+                continue;
+            }
+
+            if !status.is_success() {
+                trace!("{}", String::from_utf8_lossy(res.body()));
+                return Err(Error::GetAutoConfigError(uri, status));
+            }
+
+            let autodiscover: Autodiscover = serde_xml_rs::from_reader(res.body().as_ref())
+                .map_err(|e| Error::SerdeXmlFailedForAutoConfig(uri.clone(), e))?;
+
+            let account = autodiscover.response.into_account(&uri)?;
+
+            match account.action.as_deref() {
+                Some(action) if action.eq_ignore_ascii_case("redirectAddr") => {
+                    let redirect = account
+                        .redirect_addr
+                        .ok_or_else(|| Error::GetAutodiscoverRedirectError(uri.clone()))?;
+                    addr = EmailAddress::from_str(&redirect)
+                        .map_err(|e| Error::ParsingEmailAddress(redirect, e))?;
+                    uri = self.check_redirect(uri)?;
+                    debug!("following Autodiscover redirection to address {addr}");
+                }
+                Some(action) if action.eq_ignore_ascii_case("redirectUrl") => {
+                    let redirect = account
+                        .redirect_url
+                        .ok_or_else(|| Error::GetAutodiscoverRedirectError(uri.clone()))?;
+                    let redirect = Uri::from_str(&redirect)
+                        .map_err(|e| Error::ParseAutodiscoverUriError(redirect, e))?;
+                    uri = self.check_redirect(redirect)?;
+                    debug!("following Autodiscover redirection to {uri}");
+                }
+                _ => {
+                    let domain = addr.domain().trim_matches('.');
+                    return account.into_config(&uri, domain);
+                }
+            }
+        }
+
+        Err(Error::GetAutodiscoverTooManyRedirectsError(uri))
+    }
+
+    /// Send a GET request to the given Autodiscover v2 (JSON) URI and
+    /// return the Autodiscover (POX) URI it points to.
+    ///
+    /// The given URI is expected to request the `AutodiscoverV1`
+    /// protocol. The returned URI is rejected if it does not use
+    /// HTTPS.
+    pub async fn get_autodiscover_v2_uri(&self, uri: Uri) -> Result<Uri> {
+        let req = Request::get(uri.clone())
+            .header(ACCEPT, "application/json")
+            .body(Full::default())
+            .map_err(|e| Error::BuildAutodiscoverRequestError(uri.clone(), e))?;
+
+        let res = self.send(req).await?;
+        let status = res.status();
+
+        if !status.is_success() {
+            trace!("{}", String::from_utf8_lossy(res.body()));
+            return Err(Error::GetAutoConfigError(uri, status));
+        }
+
+        let res: AutodiscoverV2 = serde_json::from_slice(res.body())
+            .map_err(|e| Error::SerdeJsonFailedForAutodiscover(uri.clone(), e))?;
+
+        match res.url {
+            Some(url) => {
+                let url =
+                    Uri::from_str(&url).map_err(|e| Error::ParseAutodiscoverUriError(url, e))?;
+                self.check_redirect(url)
+            }
+            None => {
+                let err = res.error_message.or(res.error_code).unwrap_or_default();
+                Err(Error::GetAutodiscoverError(uri, err))
+            }
+        }
+    }
+
+    /// Return the given redirection URI if it uses HTTPS, otherwise
+    /// fail.
+    fn check_redirect(&self, uri: Uri) -> Result<Uri> {
+        if self.insecure_redirects || uri.scheme() == Some(&Scheme::HTTPS) {
+            Ok(uri)
+        } else {
+            Err(Error::GetAutodiscoverInsecureRedirectError(uri))
+        }
+    }
+
+    /// Send the given request and collect the body of its response.
+    async fn send(&self, req: Request<Full<Bytes>>) -> Result<Response<Bytes>> {
+        let uri = req.uri().clone();
+
+        let res = self
+            .client
+            .request(req)
+            .await
+            .map_err(|e| Error::GetConnectionAutoConfigError(uri.clone(), e))?;
+
+        let (parts, body) = res.into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(|e| Error::GetBodyAutoConfigError(uri, e))?
+            .to_bytes();
+
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+/// Escape the given string so that it can be used as XML text.
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Get the URI of the given HTTP redirection response, resolved
+/// against the URI of the request.
+fn get_location(uri: &Uri, res: &Response<Bytes>) -> Result<Uri> {
+    let location = res
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .ok_or_else(|| Error::GetAutodiscoverRedirectError(uri.clone()))?;
+
+    let location = Uri::from_str(location)
+        .map_err(|e| Error::ParseAutodiscoverUriError(location.to_owned(), e))?;
+
+    if location.scheme().is_some() {
+        return Ok(location);
+    }
+
+    let mut parts = location.into_parts();
+    parts.scheme = uri.scheme().cloned();
+    parts.authority = uri.authority().cloned();
+
+    Uri::from_parts(parts).map_err(|_| Error::GetAutodiscoverRedirectError(uri.clone()))
+}
+
+/// The root level of an Autodiscover (POX) response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Autodiscover {
+    response: AutodiscoverResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverResponse {
+    error: Option<AutodiscoverError>,
+    account: Option<AutodiscoverAccount>,
+}
+
+impl AutodiscoverResponse {
+    /// Extract the account from the response, or fail with the
+    /// Autodiscover error if any.
+    fn into_account(self, uri: &Uri) -> Result<AutodiscoverAccount> {
+        match (self.account, self.error) {
+            (Some(account), _) => Ok(account),
+            (None, Some(err)) => {
+                let err = err.message.or(err.error_code).unwrap_or_default();
+                Err(Error::GetAutodiscoverError(uri.clone(), err))
+            }
+            (None, None) => Err(Error::GetAutodiscoverError(uri.clone(), String::new())),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverError {
+    error_code: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverAccount {
+    action: Option<String>,
+    redirect_addr: Option<String>,
+    redirect_url: Option<String>,
+    #[serde(default, rename = "Protocol")]
+    protocols: Vec<AutodiscoverProtocol>,
+}
+
+impl AutodiscoverAccount {
+    /// Map the account settings into autoconfig.
+    ///
+    /// Only IMAP and SMTP protocols are kept, the others (Exchange,
+    /// POP3, web access) are ignored.
+    fn into_config(self, uri: &Uri, domain: &str) -> Result<AutoConfig> {
+        let mut properties = vec![EmailProviderProperty::Domain(domain.to_owned())];
+        properties.extend(
+            self.protocols
+                .into_iter()
+                .filter_map(AutodiscoverProtocol::into_property),
+        );
+
+        if properties.len() == 1 {
+            return Err(Error::GetAutodiscoverProtocolNotFoundError(uri.clone()));
+        }
+
+        let config = AutoConfig {
+            version: String::from("1.1"),
+            email_provider: EmailProvider {
+                id: domain.to_owned(),
+                properties,
+            },
+            oauth2: None,
+        };
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverProtocol {
+    r#type: String,
+    server: Option<String>,
+    port: Option<u16>,
+    login_name: Option<String>,
+    #[serde(rename = "SSL")]
+    ssl: Option<String>,
+    encryption: Option<String>,
+    #[serde(rename = "SPA")]
+    spa: Option<String>,
+    auth_required: Option<String>,
+}
+
+impl AutodiscoverProtocol {
+    /// Map the protocol into an autoconfig server, if supported.
+    fn into_property(self) -> Option<EmailProviderProperty> {
+        match self.r#type.to_ascii_uppercase().as_str() {
+            #[cfg(feature = "imap")]
+            "IMAP" => self
+                .into_server(ServerType::Imap)
+                .map(EmailProviderProperty::IncomingServer),
+            #[cfg(feature = "smtp")]
+            "SMTP" => self
+                .into_server(ServerType::Smtp)
+                .map(EmailProviderProperty::OutgoingServer),
+            _ => None,
+        }
+    }
+
+    fn into_server(self, r#type: ServerType) -> Option<Server> {
+        let security = self.security_type();
+        let auth = self.authentication_type();
+
+        let mut properties = vec![ServerProperty::Hostname(self.server?)];
+
+        if let Some(port) = self.port {
+            properties.push(ServerProperty::Port(port));
+        }
+
+        properties.push(ServerProperty::SocketType(security));
+        properties.push(ServerProperty::Authentication(auth));
+
+        if let Some(login) = self.login_name {
+            properties.push(ServerProperty::Username(login));
+        }
+
+        Some(Server { r#type, properties })
+    }
+
+    /// Get the security type from the `Encryption` setting, which
+    /// takes precedence over the `SSL` one.
+    ///
+    /// When only `SSL` is on, which is the default, the port
+    /// determines whether TLS or STARTTLS should be used.
+    fn security_type(&self) -> SecurityType {
+        let is = |setting: &Option<String>, val: &str| {
+            setting
+                .as_deref()
+                .map(|setting| setting.eq_ignore_ascii_case(val))
+                .unwrap_or_default()
+        };
+
+        if is(&self.encryption, "none") {
+            SecurityType::Plain
+        } else if is(&self.encryption, "ssl") {
+            SecurityType::Tls
+        } else if is(&self.encryption, "tls") {
+            SecurityType::Starttls
+        } else if is(&self.ssl, "off") {
+            SecurityType::Plain
+        } else {
+            match self.port {
+                Some(25 | 143 | 587) => SecurityType::Starttls,
+                _ => SecurityType::Tls,
+            }
+        }
+    }
+
+    fn authentication_type(&self) -> AuthenticationType {
+        let is_on = |setting: &Option<String>| {
+            setting
+                .as_deref()
+                .map(|setting| setting.eq_ignore_ascii_case("on"))
+        };
+
+        if is_on(&self.auth_required) == Some(false) {
+            AuthenticationType::None
+        } else if is_on(&self.spa) == Some(true) {
+            AuthenticationType::Ntlm
+        } else {
+            AuthenticationType::PasswordCleartext
+        }
+    }
+}
+
+/// The response of an Autodiscover v2 (JSON) request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverV2 {
+    url: Option<String>,
+    error_code: Option<String>,
+    error_message: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use email_address::EmailAddress;
+    use hyper::Uri;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::{Error, HttpClient};
+
+    type Route = Arc<dyn Fn(&str, &str) -> String + Send + Sync>;
+
+    /// Spawn a local HTTP stub answering each request with the
+    /// response built by the given route from the request path and
+    /// body.
+    fn spawn_stub(listener: TcpListener, route: Route) {
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut req = Vec::new();
+                let mut buf = [0; 1024];
+
+                let (head, body) = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break Default::default();
+                    }
+
+                    req.extend_from_slice(&buf[..n]);
+
+                    let req = String::from_utf8_lossy(&req).to_string();
+                    let Some((head, body)) = req.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+
+                    let len = head
+                        .lines()
+                        .filter_map(|line| line.split_once(':'))
+                        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, val)| val.trim().parse::<usize>().ok())
+                        .unwrap_or_default();
+
+                    if body.len() >= len {
+                        break (head.to_owned(), body.to_owned());
+                    }
+                };
+
+                let path = head.split_whitespace().nth(1).unwrap_or_default();
+                let res = route(path, &body);
+                stream.write_all(res.as_bytes()).await.unwrap();
+            }
+        });
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        let len = body.len();
+        format!("HTTP/1.1 {status}\r\n{headers}Content-Length: {len}\r\nConnection: close\r\n\r\n{body}")
+    }
+
+    fn autodiscover_response(account: &str) -> String {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/responseschema/2006">"#,
+                r#"<Response xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a">"#,
+                "{}",
+                "</Response>",
+                "</Autodiscover>",
+            ),
+            account
+        );
+
+        response("200 OK", "Content-Type: text/xml\r\n", &body)
+    }
+
+    #[cfg(all(feature = "imap", feature = "smtp"))]
+    #[tokio::test]
+    async fn autodiscover() {
+        use crate::autoconfig::config::{AuthenticationType, SecurityType};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+
+        let route_host = host.clone();
+        spawn_stub(
+            listener,
+            Arc::new(move |path, body| match path {
+                "/autodiscover/autodiscover.json/v1.0/test@localhost?Protocol=AutodiscoverV1" => {
+                    let url = format!("http://{route_host}/redirect/autodiscover.xml");
+                    let body = format!(r#"{{"Protocol":"AutodiscoverV1","Url":"{url}"}}"#);
+                    response("200 OK", "Content-Type: application/json\r\n", &body)
+                }
+                "/redirect/autodiscover.xml" => {
+                    response("302 Found", "Location: /pox/autodiscover.xml\r\n", "")
+                }
+                "/pox/autodiscover.xml" if body.contains("test@localhost") => {
+                    autodiscover_response(concat!(
+                        "<Account>",
+                        "<Action>redirectAddr</Action>",
+                        "<RedirectAddr>user@example.org</RedirectAddr>",
+                        "</Account>",
+                    ))
+                }
+                "/pox/autodiscover.xml" if body.contains("user@example.org") => {
+                    autodiscover_response(concat!(
+                        "<Account>",
+                        "<AccountType>email</AccountType>",
+                        "<Action>settings</Action>",
+                        "<Protocol>",
+                        "<Type>IMAP</Type>",
+                        "<Server>imap.example.org</Server>",
+                        "<Port>993</Port>",
+                        "<LoginName>user@example.org</LoginName>",
+                        "<SSL>on</SSL>",
+                        "</Protocol>",
+                        "<Protocol>",
+                        "<Type>SMTP</Type>",
+                        "<Server>smtp.example.org</Server>",
+                        "<Port>587</Port>",
+                        "<Encryption>TLS</Encryption>",
+                        "<SPA>on</SPA>",
+                        "</Protocol>",
+                        "</Account>",
+                    ))
+                }
+                _ => response("404 Not Found", "", ""),
+            }),
+        );
+
+        let mut http = HttpClient::new().unwrap();
+        http.insecure_redirects = true;
+        let addr = EmailAddress::from_str("test@localhost").unwrap();
+
+        let uri = format!(
+            "http://{host}/autodiscover/autodiscover.json/v1.0/{addr}?Protocol=AutodiscoverV1"
+        );
+        let uri = http
+            .get_autodiscover_v2_uri(Uri::from_str(&uri).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            uri.to_string(),
+            format!("http://{host}/redirect/autodiscover.xml")
+        );
+
+        let config = http.get_autodiscover_config(uri, &addr).await.unwrap();
+        let provider = config.email_provider();
+        assert_eq!(provider.id(), "example.org");
+        assert_eq!(provider.domain(), vec!["example.org"]);
+
+        let imap = provider.incoming_servers();
+        assert_eq!(imap.len(), 1);
+        assert_eq!(imap[0].hostname(), Some("imap.example.org"));
+        assert_eq!(imap[0].port(), Some(&993));
+        assert_eq!(imap[0].username(), Some("user@example.org"));
+        assert!(matches!(imap[0].security_type(), Some(SecurityType::Tls)));
+        assert!(matches!(
+            imap[0].authentication_type()[..],
+            [AuthenticationType::PasswordCleartext]
+        ));
+
+        let smtp = provider.outgoing_servers();
+        assert_eq!(smtp.len(), 1);
+        assert_eq!(smtp[0].hostname(), Some("smtp.example.org"));
+        assert_eq!(smtp[0].port(), Some(&587));
+        assert_eq!(smtp[0].username(), None);
+        assert!(matches!(
+            smtp[0].security_type(),
+            Some(SecurityType::Starttls)
+        ));
+        assert!(matches!(
+            smtp[0].authentication_type()[..],
+            [AuthenticationType::Ntlm]
+        ));
+    }
+
+    #[tokio::test]
+    async fn autodiscover_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+
+        spawn_stub(
+            listener,
+            Arc::new(|path, _| match path {
+                "/error/autodiscover.xml" => autodiscover_response(concat!(
+                    r#"<Error Time="12:00:00" Id="42">"#,
+                    "<ErrorCode>600</ErrorCode>",
+                    "<Message>Invalid Request</Message>",
+                    "</Error>",
+                )),
+                "/exchange/autodiscover.xml" => autodiscover_response(concat!(
+                    "<Account>",
+                    "<Action>settings</Action>",
+                    "<Protocol>",
+                    "<Type>EXCH</Type>",
+                    "<Server>exchange.example.org</Server>",
+                    "</Protocol>",
+                    "</Account>",
+                )),
+                "/loop/autodiscover.xml" => {
+                    response("302 Found", "Location: /loop/autodiscover.xml\r\n", "")
+                }
+                "/redirect/autodiscover.xml" => {
+                    response("302 Found", "Location: /loop/autodiscover.xml\r\n", "")
+                }
+                "/redirect-url/autodiscover.xml" => autodiscover_response(concat!(
+                    "<Account>",
+                    "<Action>redirectUrl</Action>",
+                    "<RedirectUrl>http://example.org/autodiscover.xml</RedirectUrl>",
+                    "</Account>",
+                )),
+                "/error/autodiscover.json" => response(
+                    "200 OK",
+                    "Content-Type: application/json\r\n",
+                    r#"{"ErrorCode":"InvalidProtocol","ErrorMessage":"The given protocol value is invalid."}"#,
+                ),
+                _ => response("404 Not Found", "", ""),
+            }),
+        );
+
+        let mut http = HttpClient::new().unwrap();
+        let addr = EmailAddress::from_str("test@localhost").unwrap();
+        let uri = |path: &str| Uri::from_str(&format!("http://{host}{path}")).unwrap();
+
+        for path in [
+            "/redirect/autodiscover.xml",
+            "/redirect-url/autodiscover.xml",
+        ] {
+            let err = http
+                .get_autodiscover_config(uri(path), &addr)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                Error::GetAutodiscoverInsecureRedirectError(_)
+            ));
+        }
+
+        http.insecure_redirects = true;
+
+        let err = http
+            .get_autodiscover_config(uri("/error/autodiscover.xml"), &addr)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::GetAutodiscoverError(_, msg) if msg == "Invalid Request"));
+
+        let err = http
+            .get_autodiscover_config(uri("/exchange/autodiscover.xml"), &addr)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::GetAutodiscoverProtocolNotFoundError(_)
+        ));
+
+        let err = http
+            .get_autodiscover_config(uri("/loop/autodiscover.xml"), &addr)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::GetAutodiscoverTooManyRedirectsError(_)
+        ));
+
+        let err = http
+            .get_autodiscover_v2_uri(uri("/error/autodiscover.json"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::GetAutodiscoverError(_, msg) if msg == "The given protocol value is invalid."
+        ));
+
+        let err = http
+            .get_autodiscover_v2_uri(uri("/unknown"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::GetAutoConfigError(_, status) if status == 404));
+    }
 }
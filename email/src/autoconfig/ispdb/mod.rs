@@ -0,0 +1,286 @@
+//! # Local ISPDB
+//!
+//! This module contains everything needed to discover account
+//! configuration without network access, using a local copy of the
+//! Thunderbird [ISPDB].
+//!
+//! The local ISPDB is made of a small built-in fallback list of
+//! major email providers compiled into the crate (requires the
+//! `ispdb` cargo feature), and of entries loaded from local
+//! directories, which override the built-in ones. Directories follow
+//! the ISPDB layout: one `<domain>.xml` autoconfig file per email
+//! provider.
+//!
+//! The built-in list is maintained by hand and is not a snapshot of
+//! the whole ISPDB: for a complete offline lookup, load a checkout
+//! of the ISPDB repository using [`Ispdb::load_dir`].
+//!
+//! [ISPDB]: https://github.com/thunderbird/autoconfig
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::config::AutoConfig;
+#[doc(inline)]
+pub use super::{Error, Result};
+use crate::{debug, warn};
+
+/// The built-in fallback list compiled into the crate, as pairs of
+/// file name and autoconfig file content.
+#[cfg(feature = "ispdb")]
+pub const BUILTIN: &[(&str, &str)] = &[
+    ("fastmail.com.xml", include_str!("fastmail.com.xml")),
+    ("googlemail.com.xml", include_str!("googlemail.com.xml")),
+    ("icloud.com.xml", include_str!("icloud.com.xml")),
+    ("outlook.com.xml", include_str!("outlook.com.xml")),
+    ("posteo.de.xml", include_str!("posteo.de.xml")),
+    ("yahoo.com.xml", include_str!("yahoo.com.xml")),
+];
+
+/// The built-in fallback list compiled into the crate, empty since
+/// the `ispdb` cargo feature is disabled.
+#[cfg(not(feature = "ispdb"))]
+pub const BUILTIN: &[(&str, &str)] = &[];
+
+/// The local ISPDB.
+///
+/// Entries are indexed by domain: the name of the autoconfig file as
+/// well as every domain listed by its email provider.
+#[derive(Clone, Debug, Default)]
+pub struct Ispdb {
+    /// The autoconfig file contents indexed by domain.
+    entries: HashMap<String, Arc<str>>,
+
+    /// The directories entries have been loaded from, used to
+    /// refresh the ISPDB.
+    dirs: Vec<PathBuf>,
+}
+
+impl Ispdb {
+    /// Create a new empty local ISPDB.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new local ISPDB containing the built-in fallback
+    /// list.
+    ///
+    /// The ISPDB is empty if the `ispdb` cargo feature is disabled.
+    pub fn builtin() -> Self {
+        let mut ispdb = Self::new();
+        ispdb.insert_builtin();
+        ispdb
+    }
+
+    /// Load entries from the given directory, overriding the existing
+    /// ones, and return the number of loaded files.
+    ///
+    /// Files that cannot be parsed are skipped. The directory is
+    /// remembered so that it can be loaded again by
+    /// [`Ispdb::refresh`].
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let dir = dir.as_ref().to_owned();
+        let count = self.insert_dir(&dir)?;
+
+        if !self.dirs.contains(&dir) {
+            self.dirs.push(dir);
+        }
+
+        Ok(count)
+    }
+
+    /// Same as [`Ispdb::load_dir`], but using the builder pattern.
+    pub fn with_dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        self.load_dir(dir)?;
+        Ok(self)
+    }
+
+    /// Clear the ISPDB, then load again the built-in fallback list
+    /// followed by every directory previously loaded, in order.
+    pub fn refresh(&mut self) -> Result<usize> {
+        self.entries.clear();
+        self.insert_builtin();
+
+        let mut count = 0;
+
+        for dir in self.dirs.clone() {
+            count += self.insert_dir(&dir)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Insert the given autoconfig file content, overriding entries
+    /// of the same domains.
+    ///
+    /// The given name, usually the file name without the `.xml`
+    /// extension, is used as domain as well.
+    pub fn insert(&mut self, name: impl AsRef<str>, xml: impl AsRef<str>) -> Result<()> {
+        let name = name.as_ref();
+        let xml: Arc<str> = Arc::from(xml.as_ref());
+
+        let config: AutoConfig = serde_xml_rs::from_str(&xml)
+            .map_err(|err| Error::ParseIspdbEntryError(name.to_owned(), err))?;
+
+        let domains = config
+            .email_provider()
+            .domain()
+            .into_iter()
+            .chain(Some(name.trim_end_matches(".xml")));
+
+        for domain in domains {
+            let domain = domain.trim_matches('.').to_lowercase();
+            self.entries.insert(domain, xml.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Get the autoconfig matching the given domain, if any.
+    pub fn get(&self, domain: impl AsRef<str>) -> Result<Option<AutoConfig>> {
+        let domain = domain.as_ref().trim_matches('.').to_lowercase();
+
+        let Some(xml) = self.entries.get(&domain) else {
+            return Ok(None);
+        };
+
+        let config =
+            serde_xml_rs::from_str(xml).map_err(|err| Error::ParseIspdbEntryError(domain, err))?;
+
+        Ok(Some(config))
+    }
+
+    /// Return `true` if the ISPDB contains the given domain.
+    pub fn contains(&self, domain: impl AsRef<str>) -> bool {
+        let domain = domain.as_ref().trim_matches('.').to_lowercase();
+        self.entries.contains_key(&domain)
+    }
+
+    /// Return the domains of the ISPDB, in arbitrary order.
+    pub fn domains(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn insert_builtin(&mut self) {
+        for (name, xml) in BUILTIN {
+            if let Err(_err) = self.insert(name, xml) {
+                warn!("skipping invalid built-in ISPDB entry {name}: {_err}");
+            }
+        }
+    }
+
+    fn insert_dir(&mut self, dir: &Path) -> Result<usize> {
+        let entries =
+            fs::read_dir(dir).map_err(|err| Error::ReadIspdbDirError(dir.to_owned(), err))?;
+
+        let mut paths = Vec::new();
+
+        for entry in entries {
+            let path = entry
+                .map_err(|err| Error::ReadIspdbDirError(dir.to_owned(), err))?
+                .path();
+
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "xml") {
+                paths.push(path);
+            }
+        }
+
+        paths.sort();
+
+        let mut count = 0;
+
+        for path in paths {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            let xml = fs::read_to_string(&path)
+                .map_err(|err| Error::ReadIspdbFileError(path.clone(), err))?;
+
+            match self.insert(name, xml) {
+                Ok(()) => count += 1,
+                Err(_err) => {
+                    warn!("skipping invalid ISPDB entry {}: {_err}", path.display());
+                }
+            }
+        }
+
+        debug!("loaded {count} ISPDB entries from {}", dir.display());
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::Ispdb;
+
+    const EXAMPLE: &str = r#"<?xml version="1.0"?>
+<clientConfig version="1.1">
+  <emailProvider id="example.org">
+    <domain>example.org</domain>
+    <domain>example.net</domain>
+    <displayName>Example</displayName>
+  </emailProvider>
+</clientConfig>
+"#;
+
+    #[test]
+    fn builtin() {
+        let ispdb = Ispdb::builtin();
+
+        for (name, _) in super::BUILTIN {
+            let domain = name.trim_end_matches(".xml");
+            assert!(ispdb.get(domain).unwrap().is_some(), "{domain}");
+        }
+
+        #[cfg(feature = "ispdb")]
+        {
+            let config = ispdb.get("GMAIL.com").unwrap().unwrap();
+            assert_eq!(config.email_provider().id(), "googlemail.com");
+            assert!(config.oauth2().is_some());
+        }
+    }
+
+    #[test]
+    fn load_dir() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("example.org.xml"), EXAMPLE).unwrap();
+        fs::write(dir.path().join("invalid.xml"), "<clientConfig>").unwrap();
+        fs::write(dir.path().join("README.md"), "not an entry").unwrap();
+
+        let mut ispdb = Ispdb::new();
+        assert_eq!(ispdb.load_dir(dir.path()).unwrap(), 1);
+        assert_eq!(ispdb.len(), 2);
+        assert!(ispdb.contains("example.net"));
+        assert!(!ispdb.contains("invalid"));
+
+        let config = ispdb.get("example.net").unwrap().unwrap();
+        assert_eq!(config.email_provider().display_name(), Some("Example"));
+
+        let example = EXAMPLE.replace("Example", "Overridden");
+        fs::write(dir.path().join("example.org.xml"), example).unwrap();
+        assert_eq!(ispdb.refresh().unwrap(), 1);
+
+        let config = ispdb.get("example.org").unwrap().unwrap();
+        assert_eq!(config.email_provider().display_name(), Some("Overridden"));
+
+        assert!(ispdb.get("unknown.org").unwrap().is_none());
+    }
+}
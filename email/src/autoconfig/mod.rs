@@ -10,7 +10,9 @@
 //! Discovery performs actions in this order:
 //!
 //! - Check ISP databases for example.com
-//!   - Check main ISP <autoconfig.example.com>
+//!   - Check local ISPDB, see [`ispdb`]
+//!   - Check main ISP <autoconfig.example.com>, racing with Microsoft
+//!     Autodiscover <autodiscover.example.com> and <example.com>
 //!   - Check alt ISP <example.com/.well-known>
 //!   - Check Thunderbird ISPDB <autoconfig.thunderbird.net/example.com>
 //! - Check example.com DNS records
@@ -20,13 +22,18 @@
 //!   - Check mailconf URI in example.com TXT records
 //!   - Build autoconfig from imap and submission example.com SRV records
 //!
+//! The discovered configuration can then be turned into verified IMAP
+//! and SMTP configurations using the [`builder::AutoConfigBuilder`].
+//!
 //! [Autoconfiguration]: https://udn.realityripple.com/docs/Mozilla/Thunderbird/Autoconfiguration
 
+pub mod builder;
 pub mod config;
 pub mod dns;
 pub mod http;
+pub mod ispdb;
 
-use std::{io, result, str::FromStr};
+use std::{io, path::PathBuf, result, str::FromStr};
 
 use email_address::EmailAddress;
 use futures::{future::select_ok, FutureExt};
@@ -37,6 +44,7 @@
     config::{AutoConfig, EmailProvider},
     dns::DnsClient,
     http::HttpClient,
+    ispdb::Ispdb,
 };
 use crate::{debug, trace};
 
@@ -72,18 +80,49 @@ pub enum Error {
     SerdeXmlFailedForAutoConfig(Uri, #[source] serde_xml_rs::Error),
     #[error("cannot parse email {0}: {1}")]
     ParsingEmailAddress(String, #[source] email_address::Error),
+    #[error("cannot build Autodiscover request for {0}")]
+    BuildAutodiscoverRequestError(Uri, #[source] hyper::http::Error),
+    #[error("cannot decode the body of response for Autodiscover from {0}: {1}")]
+    SerdeJsonFailedForAutodiscover(Uri, #[source] serde_json::Error),
+    #[error("cannot parse Autodiscover URI {0}")]
+    ParseAutodiscoverUriError(String, #[source] hyper::http::uri::InvalidUri),
+    #[error("cannot get Autodiscover settings from {0}: {1}")]
+    GetAutodiscoverError(Uri, String),
+    #[error("cannot find any IMAP or SMTP setting in Autodiscover response from {0}")]
+    GetAutodiscoverProtocolNotFoundError(Uri),
+    #[error("cannot follow invalid Autodiscover redirection from {0}")]
+    GetAutodiscoverRedirectError(Uri),
+    #[error("cannot follow insecure Autodiscover redirection to {0}")]
+    GetAutodiscoverInsecureRedirectError(Uri),
+    #[error("cannot get Autodiscover settings from {0}: too many redirections")]
+    GetAutodiscoverTooManyRedirectsError(Uri),
+    #[error("cannot read ISPDB directory {0}")]
+    ReadIspdbDirError(PathBuf, #[source] io::Error),
+    #[error("cannot read ISPDB entry {0}")]
+    ReadIspdbFileError(PathBuf, #[source] io::Error),
+    #[error("cannot parse ISPDB entry {0}")]
+    ParseIspdbEntryError(String, #[source] serde_xml_rs::Error),
 }
 
 /// Discover configuration associated to a given email address using
 /// ISP locations then DNS, as described in the Mozilla [wiki].
 ///
+/// The built-in ISPDB fallback list is consulted first, see
+/// [`Ispdb::builtin`].
+///
 /// [wiki]: https://wiki.mozilla.org/Thunderbird:Autoconfiguration#Implementation
 pub async fn from_addr(addr: impl AsRef<str>) -> Result<AutoConfig> {
+    from_addr_with_ispdb(addr, &Ispdb::builtin()).await
+}
+
+/// Discover configuration associated to a given email address using
+/// the given local ISPDB first, then ISP locations then DNS.
+pub async fn from_addr_with_ispdb(addr: impl AsRef<str>, ispdb: &Ispdb) -> Result<AutoConfig> {
     let addr = EmailAddress::from_str(addr.as_ref())
         .map_err(|e| Error::ParsingEmailAddress(addr.as_ref().to_string(), e))?;
     let http = HttpClient::new()?;
 
-    let res = from_isps(&http, &addr).await;
+    let res = from_isps(&http, ispdb, &addr).await;
 
     #[cfg(feature = "tracing")]
     if let Err(err) = &res {
@@ -93,21 +132,29 @@ pub async fn from_addr(addr: impl AsRef<str>) -> Result<AutoConfig> {
 
     match res {
         Ok(config) => Ok(config),
-        Err(_) => from_dns(&http, &addr).await,
+        Err(_) => from_dns(&http, ispdb, &addr).await,
     }
 }
 
 /// Discover configuration associated to a given email address using
 /// different ISP locations, as described in the Mozilla [wiki].
 ///
-/// Inspect first main ISP locations, then inspect alternative ISP
+/// Inspect first the local ISPDB, then main ISP locations together
+/// with Microsoft Autodiscover locations, then alternative ISP
 /// locations.
 ///
 /// [wiki]: https://wiki.mozilla.org/Thunderbird:Autoconfiguration#Implementation
-async fn from_isps(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
+async fn from_isps(http: &HttpClient, ispdb: &Ispdb, addr: &EmailAddress) -> Result<AutoConfig> {
+    if let Some(config) = from_local_ispdb(ispdb, addr) {
+        return Ok(config);
+    }
+
     let from_main_isps = [
         from_plain_main_isp(http, addr).boxed(),
         from_secure_main_isp(http, addr).boxed(),
+        from_main_autodiscover(http, addr).boxed(),
+        from_alt_autodiscover(http, addr).boxed(),
+        from_autodiscover_v2(http, addr).boxed(),
     ];
 
     let res = select_ok(from_main_isps).await;
@@ -144,6 +191,25 @@ async fn from_isps(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig>
     }
 }
 
+/// Discover configuration associated to a given email address using
+/// the local ISPDB.
+fn from_local_ispdb(ispdb: &Ispdb, addr: &EmailAddress) -> Option<AutoConfig> {
+    let domain = addr.domain().trim_matches('.');
+
+    match ispdb.get(domain) {
+        Ok(Some(config)) => {
+            debug!("successfully discovered config from local ISPDB for {domain}");
+            trace!("{config:#?}");
+            Some(config)
+        }
+        Ok(None) => None,
+        Err(_err) => {
+            debug!("cannot get config from local ISPDB for {domain}: {_err}");
+            None
+        }
+    }
+}
+
 /// Discover configuration associated to a given email address using
 /// plain main ISP location (http).
 async fn from_plain_main_isp(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
@@ -197,6 +263,59 @@ async fn from_alt_isp(http: &HttpClient, scheme: &str, addr: &EmailAddress) -> R
     Ok(config)
 }
 
+/// Discover configuration associated to a given email address using
+/// main Microsoft Autodiscover (POX) location.
+async fn from_main_autodiscover(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
+    let domain = addr.domain().trim_matches('.');
+    let uri_str = format!("https://autodiscover.{domain}/autodiscover/autodiscover.xml");
+    from_autodiscover(http, uri_str, addr).await
+}
+
+/// Discover configuration associated to a given email address using
+/// alternative Microsoft Autodiscover (POX) location.
+async fn from_alt_autodiscover(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
+    let domain = addr.domain().trim_matches('.');
+    let uri_str = format!("https://{domain}/autodiscover/autodiscover.xml");
+    from_autodiscover(http, uri_str, addr).await
+}
+
+/// Discover configuration associated to a given email address using
+/// Microsoft Autodiscover (POX) location.
+async fn from_autodiscover(
+    http: &HttpClient,
+    uri_str: String,
+    addr: &EmailAddress,
+) -> Result<AutoConfig> {
+    let uri = Uri::from_str(&uri_str)
+        .map_err(|e| Error::ParseAutodiscoverUriError(uri_str.clone(), e))?;
+
+    let config = http.get_autodiscover_config(uri, addr).await?;
+    debug!("successfully discovered config from Autodiscover at {uri_str}");
+    trace!("{config:#?}");
+
+    Ok(config)
+}
+
+/// Discover configuration associated to a given email address using
+/// Microsoft Autodiscover v2 (JSON) location.
+///
+/// The JSON endpoint gives the location of the Autodiscover (POX)
+/// endpoint, which is then used to discover the configuration.
+async fn from_autodiscover_v2(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
+    let domain = addr.domain().trim_matches('.');
+    let uri_str = format!(
+        "https://autodiscover.{domain}/autodiscover/autodiscover.json/v1.0/{addr}?Protocol=AutodiscoverV1"
+    );
+    let uri = Uri::from_str(&uri_str).map_err(|e| Error::ParseAutodiscoverUriError(uri_str, e))?;
+
+    let uri = http.get_autodiscover_v2_uri(uri).await?;
+    let config = http.get_autodiscover_config(uri.clone(), addr).await?;
+    debug!("successfully discovered config from Autodiscover v2 at {uri}");
+    trace!("{config:#?}");
+
+    Ok(config)
+}
+
 /// Discover configuration associated to a given email address using
 /// Thunderbird ISPDB.
 async fn from_ispdb(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
@@ -216,11 +335,11 @@ async fn from_ispdb(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig
 ///
 /// Inspect first MX records, then TXT records, and finally SRV
 /// records.
-async fn from_dns(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig> {
+async fn from_dns(http: &HttpClient, ispdb: &Ispdb, addr: &EmailAddress) -> Result<AutoConfig> {
     let domain = addr.domain().trim_matches('.');
     let dns = DnsClient::new();
 
-    let res = from_dns_mx(http, &dns, addr).await;
+    let res = from_dns_mx(http, ispdb, &dns, addr).await;
 
     #[cfg(feature = "tracing")]
     if let Err(err) = &res {
@@ -251,6 +370,7 @@ async fn from_dns(http: &HttpClient, addr: &EmailAddress) -> Result<AutoConfig>
 /// MX DNS records.
 async fn from_dns_mx(
     http: &HttpClient,
+    ispdb: &Ispdb,
     dns: &DnsClient,
     addr: &EmailAddress,
 ) -> Result<AutoConfig> {
@@ -259,7 +379,7 @@ async fn from_dns_mx(
     let domain = domain.trim_matches('.');
     let addr = EmailAddress::from_str(&format!("{local_part}@{domain}")).unwrap();
 
-    let res = from_isps(http, &addr).await;
+    let res = from_isps(http, ispdb, &addr).await;
 
     #[cfg(feature = "tracing")]
     if let Err(err) = &res {
@@ -2,6 +2,7 @@
 
 use thiserror::Error;
 
+use super::layer::BackendFeatureKind;
 use crate::{AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
@@ -52,6 +53,24 @@ pub enum Error {
     DeleteMessagesNotAvailableError,
     #[error("cannot remove messages: feature not available, or backend configuration for this functionality is not set")]
     RemoveMessagesNotAvailableError,
+
+    #[error("cannot {0}: unexpected output returned by backend layer")]
+    DowncastLayerOutputError(BackendFeatureKind),
+    #[error("cannot {0}: backend is read-only")]
+    ReadOnlyBackendError(BackendFeatureKind),
+
+    #[error("cannot add account {0:?}: name must not be empty nor contain {1:?}")]
+    ParseMultiAccountNameError(String, char),
+    #[error("cannot add account {0}: account already exists")]
+    AddMultiAccountDuplicateError(String),
+    #[error("cannot find backend of account {0}")]
+    GetMultiAccountBackendError(String),
+    #[error("cannot find account of envelope {0}: id should be prefixed by an account name")]
+    ParseMultiAccountIdError(String),
+    #[error("cannot use unified folder {0}: folder should be prefixed by an account name")]
+    UnifiedFolderNotSupportedError(String),
+    #[error("cannot transfer messages from account {0} to account {1}")]
+    TransferMultiAccountError(String, String),
 }
 
 impl AnyError for Error {
@@ -0,0 +1,159 @@
+//! # Cache layer
+//!
+//! Module dedicated to the cache backend layer, which caches results
+//! of listing features in memory for a given duration.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{BackendCall, BackendFeatureKind, BackendLayer, BackendOutput};
+use crate::{
+    envelope::{Envelope, Envelopes},
+    folder::Folders,
+    AnyResult,
+};
+
+/// The cache backend layer.
+///
+/// Results of [`ListFolders`](crate::folder::list::ListFolders),
+/// [`ListEnvelopes`](crate::envelope::list::ListEnvelopes) and
+/// [`GetEnvelope`](crate::envelope::get::GetEnvelope) are cached
+/// for the given time to live. Since the cache cannot know which
+/// entries are altered by a feature, the whole cache is cleared
+/// every time a feature altering the backend is called.
+#[derive(Clone, Debug)]
+pub struct CacheLayer {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<(BackendFeatureKind, String), CacheEntry>>>,
+}
+
+impl CacheLayer {
+    /// Create a new cache layer using the given time to live.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// Clear the cache.
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear()
+    }
+
+    fn get(&self, key: &(BackendFeatureKind, String)) -> Option<BackendOutput> {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.output.clone_output()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: (BackendFeatureKind, String), output: CacheOutput) {
+        let entry = CacheEntry {
+            expires_at: Instant::now() + self.ttl,
+            output,
+        };
+
+        self.entries
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(key, entry);
+    }
+}
+
+#[async_trait]
+impl BackendLayer for CacheLayer {
+    async fn call(&self, call: BackendCall<'_>) -> AnyResult<BackendOutput> {
+        let feature = call.feature();
+
+        if feature.is_write() {
+            // NOTE: the cache is cleared after the call, so that
+            // concurrent reads cannot cache an outdated state
+            let output = call.run().await;
+            self.clear();
+            return output;
+        }
+
+        if !CacheOutput::is_cacheable(&feature) {
+            return call.run().await;
+        }
+
+        let key = (feature, call.key().to_owned());
+
+        if let Some(output) = self.get(&key) {
+            return Ok(output);
+        }
+
+        let output = call.run().await?;
+
+        if let Some(cached) = CacheOutput::from_output(&output) {
+            self.insert(key, cached);
+        }
+
+        Ok(output)
+    }
+}
+
+/// The cache entry.
+#[derive(Clone, Debug)]
+struct CacheEntry {
+    expires_at: Instant,
+    output: CacheOutput,
+}
+
+/// The cacheable backend feature output.
+#[derive(Clone, Debug)]
+enum CacheOutput {
+    Folders(Folders),
+    Envelopes(Envelopes),
+    Envelope(Envelope),
+}
+
+impl CacheOutput {
+    fn is_cacheable(feature: &BackendFeatureKind) -> bool {
+        matches!(
+            feature,
+            BackendFeatureKind::ListFolders
+                | BackendFeatureKind::ListEnvelopes
+                | BackendFeatureKind::GetEnvelope
+        )
+    }
+
+    fn from_output(output: &BackendOutput) -> Option<Self> {
+        if let Some(folders) = output.downcast_ref::<Folders>() {
+            return Some(Self::Folders(folders.clone()));
+        }
+
+        if let Some(envelopes) = output.downcast_ref::<Envelopes>() {
+            return Some(Self::Envelopes(envelopes.clone()));
+        }
+
+        if let Some(envelope) = output.downcast_ref::<Envelope>() {
+            return Some(Self::Envelope(envelope.clone()));
+        }
+
+        None
+    }
+
+    fn clone_output(&self) -> BackendOutput {
+        match self {
+            Self::Folders(folders) => Box::new(folders.clone()),
+            Self::Envelopes(envelopes) => Box::new(envelopes.clone()),
+            Self::Envelope(envelope) => Box::new(envelope.clone()),
+        }
+    }
+}
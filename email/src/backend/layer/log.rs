@@ -0,0 +1,48 @@
+//! # Log layer
+//!
+//! Module dedicated to the log backend layer, which logs every
+//! backend feature call with its duration.
+
+use std::time::Instant;
+
+use async_trait::async_trait;
+
+use super::{BackendCall, BackendLayer, BackendOutput};
+use crate::{debug, AnyResult};
+
+/// The log backend layer.
+///
+/// Calls are logged at the debug level, errors included.
+#[derive(Clone, Debug, Default)]
+pub struct LogLayer;
+
+impl LogLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl BackendLayer for LogLayer {
+    async fn call(&self, call: BackendCall<'_>) -> AnyResult<BackendOutput> {
+        let _feature = call.feature();
+        let _key = call.key().to_owned();
+        debug!("calling backend feature {_feature} {_key}");
+
+        let start = Instant::now();
+        let output = call.run().await;
+        let _duration = start.elapsed();
+
+        match &output {
+            Ok(_) => {
+                debug!("backend feature {_feature} {_key} succeeded in {_duration:?}");
+            }
+            Err(_err) => {
+                debug!("backend feature {_feature} {_key} failed in {_duration:?}: {_err}");
+                debug!("{_err:?}");
+            }
+        }
+
+        output
+    }
+}
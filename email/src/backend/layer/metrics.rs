@@ -0,0 +1,120 @@
+//! # Metrics layer
+//!
+//! Module dedicated to the metrics backend layer, which counts calls
+//! and errors, and measures time spent by backend feature.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{BackendCall, BackendFeatureKind, BackendLayer, BackendOutput};
+use crate::AnyResult;
+
+/// The metrics backend layer.
+///
+/// The layer is cheap to clone: clones share the same metrics, so a
+/// clone can be kept aside in order to read metrics after being
+/// added to the backend builder.
+#[derive(Clone, Debug, Default)]
+pub struct MetricsLayer {
+    metrics: Arc<Mutex<BackendMetrics>>,
+}
+
+impl MetricsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return a copy of the current metrics.
+    pub fn metrics(&self) -> BackendMetrics {
+        self.metrics
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Reset all metrics.
+    pub fn reset(&self) {
+        *self.metrics.lock().unwrap_or_else(|err| err.into_inner()) = Default::default();
+    }
+}
+
+#[async_trait]
+impl BackendLayer for MetricsLayer {
+    async fn call(&self, call: BackendCall<'_>) -> AnyResult<BackendOutput> {
+        let feature = call.feature();
+
+        let start = Instant::now();
+        let output = call.run().await;
+        let duration = start.elapsed();
+
+        {
+            let mut metrics = self.metrics.lock().unwrap_or_else(|err| err.into_inner());
+            let metrics = metrics.0.entry(feature).or_default();
+            metrics.calls += 1;
+            metrics.duration += duration;
+
+            if output.is_err() {
+                metrics.errors += 1;
+            }
+        }
+
+        output
+    }
+}
+
+/// The backend metrics, indexed by feature.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BackendMetrics(BTreeMap<BackendFeatureKind, BackendFeatureMetrics>);
+
+impl BackendMetrics {
+    /// Return the metrics of the given feature, if called at least
+    /// once.
+    pub fn get(&self, feature: &BackendFeatureKind) -> Option<&BackendFeatureMetrics> {
+        self.0.get(feature)
+    }
+
+    /// Return the metrics of all features.
+    pub fn iter(&self) -> impl Iterator<Item = (&BackendFeatureKind, &BackendFeatureMetrics)> {
+        self.0.iter()
+    }
+
+    /// Return the sum of the metrics of all features.
+    pub fn total(&self) -> BackendFeatureMetrics {
+        self.0
+            .values()
+            .fold(BackendFeatureMetrics::default(), |mut total, metrics| {
+                total.calls += metrics.calls;
+                total.errors += metrics.errors;
+                total.duration += metrics.duration;
+                total
+            })
+    }
+}
+
+/// The metrics of a backend feature.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BackendFeatureMetrics {
+    /// The number of calls.
+    pub calls: usize,
+
+    /// The number of calls that failed.
+    pub errors: usize,
+
+    /// The total time spent in calls.
+    pub duration: Duration,
+}
+
+impl BackendFeatureMetrics {
+    /// Return the average time spent in a call.
+    pub fn average(&self) -> Duration {
+        match u32::try_from(self.calls) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(calls) => self.duration / calls,
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! # Backend layer
+//!
+//! A [`BackendLayer`] wraps every backend feature call, which allows
+//! cross-cutting behaviours (logging, metrics, caching, access
+//! control…) to be stacked on top of any backend without
+//! reimplementing each feature trait. Layers are added to the
+//! [`BackendBuilder`](super::BackendBuilder) using
+//! [`with_layer`](super::BackendBuilder::with_layer).
+//!
+//! This module comes with few built-in layers:
+//!
+//! - [`LogLayer`](log::LogLayer), which logs every call with its
+//!   duration
+//!
+//! - [`MetricsLayer`](metrics::MetricsLayer), which counts calls and
+//!   errors, and measures time spent by feature
+//!
+//! - [`CacheLayer`](cache::CacheLayer), which caches results of
+//!   listing features for a given duration
+//!
+//! - [`ReadOnlyLayer`](read_only::ReadOnlyLayer), which rejects
+//!   features altering the backend
+
+pub mod cache;
+pub mod log;
+pub mod metrics;
+pub mod read_only;
+
+use std::{any::Any, fmt, future::Future, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+#[cfg(feature = "watch")]
+use tokio::sync::oneshot::{Receiver, Sender};
+
+use super::Error;
+#[cfg(feature = "watch")]
+use crate::envelope::watch::WatchEnvelopes;
+#[cfg(feature = "thread")]
+use crate::envelope::{thread::ThreadEnvelopes, ThreadedEnvelopes};
+use crate::{
+    envelope::{
+        get::GetEnvelope,
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Envelopes, Id, SingleId,
+    },
+    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    folder::{
+        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+        purge::PurgeFolder, Folders,
+    },
+    message::{
+        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
+        Messages,
+    },
+    AnyResult,
+};
+
+/// The type-erased output of a backend feature call.
+pub type BackendOutput = Box<dyn Any + Send>;
+
+/// The backend layer.
+///
+/// A layer receives every backend feature call, and decides how to
+/// execute it: it can run it as it is, run code before or after it,
+/// reject it or even skip it by returning an output of the right
+/// type.
+#[async_trait]
+pub trait BackendLayer: Send + Sync {
+    /// Execute the given backend feature call.
+    async fn call(&self, call: BackendCall<'_>) -> AnyResult<BackendOutput>;
+}
+
+/// The backend feature call.
+///
+/// The call contains the feature being called, a key identifying
+/// its arguments and the future executing the feature.
+pub struct BackendCall<'a> {
+    feature: BackendFeatureKind,
+    key: String,
+    future: Pin<Box<dyn Future<Output = AnyResult<BackendOutput>> + Send + 'a>>,
+}
+
+impl<'a> BackendCall<'a> {
+    /// Create a new backend feature call.
+    pub fn new<T: Send + 'static>(
+        feature: BackendFeatureKind,
+        key: impl ToString,
+        future: impl Future<Output = AnyResult<T>> + Send + 'a,
+    ) -> Self {
+        Self {
+            feature,
+            key: key.to_string(),
+            future: Box::pin(async move { Ok(Box::new(future.await?) as BackendOutput) }),
+        }
+    }
+
+    /// Return the feature being called.
+    pub fn feature(&self) -> BackendFeatureKind {
+        self.feature
+    }
+
+    /// Return the key identifying the arguments of the call.
+    ///
+    /// Raw messages are not part of the key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Execute the call.
+    pub async fn run(self) -> AnyResult<BackendOutput> {
+        self.future.await
+    }
+}
+
+/// The backend feature kind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum BackendFeatureKind {
+    AddFolder,
+    ListFolders,
+    ExpungeFolder,
+    PurgeFolder,
+    DeleteFolder,
+    GetEnvelope,
+    ListEnvelopes,
+    #[cfg(feature = "thread")]
+    ThreadEnvelopes,
+    #[cfg(feature = "watch")]
+    WatchEnvelopes,
+    AddFlags,
+    SetFlags,
+    RemoveFlags,
+    AddMessage,
+    SendMessage,
+    PeekMessages,
+    GetMessages,
+    CopyMessages,
+    MoveMessages,
+    DeleteMessages,
+    RemoveMessages,
+}
+
+impl BackendFeatureKind {
+    /// Return `true` if the feature alters the backend.
+    ///
+    /// Getting messages is considered as altering, since it adds the
+    /// [`Flag::Seen`](crate::flag::Flag::Seen) to envelopes. Sending
+    /// a message is also considered as altering.
+    pub fn is_write(&self) -> bool {
+        match self {
+            Self::ListFolders | Self::GetEnvelope | Self::ListEnvelopes | Self::PeekMessages => {
+                false
+            }
+            #[cfg(feature = "thread")]
+            Self::ThreadEnvelopes => false,
+            #[cfg(feature = "watch")]
+            Self::WatchEnvelopes => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for BackendFeatureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddFolder => write!(f, "add folder"),
+            Self::ListFolders => write!(f, "list folders"),
+            Self::ExpungeFolder => write!(f, "expunge folder"),
+            Self::PurgeFolder => write!(f, "purge folder"),
+            Self::DeleteFolder => write!(f, "delete folder"),
+            Self::GetEnvelope => write!(f, "get envelope"),
+            Self::ListEnvelopes => write!(f, "list envelopes"),
+            #[cfg(feature = "thread")]
+            Self::ThreadEnvelopes => write!(f, "thread envelopes"),
+            #[cfg(feature = "watch")]
+            Self::WatchEnvelopes => write!(f, "watch envelopes"),
+            Self::AddFlags => write!(f, "add flags"),
+            Self::SetFlags => write!(f, "set flags"),
+            Self::RemoveFlags => write!(f, "remove flags"),
+            Self::AddMessage => write!(f, "add message"),
+            Self::SendMessage => write!(f, "send message"),
+            Self::PeekMessages => write!(f, "peek messages"),
+            Self::GetMessages => write!(f, "get messages"),
+            Self::CopyMessages => write!(f, "copy messages"),
+            Self::MoveMessages => write!(f, "move messages"),
+            Self::DeleteMessages => write!(f, "delete messages"),
+            Self::RemoveMessages => write!(f, "remove messages"),
+        }
+    }
+}
+
+/// A backend feature wrapped by a backend layer.
+pub struct Layered<F: ?Sized> {
+    layer: Arc<dyn BackendLayer>,
+    feature: Box<F>,
+}
+
+impl<F: ?Sized> Layered<F> {
+    /// Execute the given call through the layer, then downcast its
+    /// output.
+    async fn call<T: Send + 'static>(&self, call: BackendCall<'_>) -> AnyResult<T> {
+        let feature = call.feature();
+        let output = self.layer.call(call).await?;
+
+        match output.downcast() {
+            Ok(output) => Ok(*output),
+            Err(_) => Err(Error::DowncastLayerOutputError(feature).into()),
+        }
+    }
+}
+
+/// The backend feature that can be wrapped by a backend layer.
+pub trait LayeredFeature {
+    /// Wrap the given feature using the given layer.
+    fn layered(layer: Arc<dyn BackendLayer>, feature: Box<Self>) -> Box<Self>;
+}
+
+/// Macro for implementing [`LayeredFeature`].
+macro_rules! layered_feature {
+    ($feat:ident) => {
+        impl LayeredFeature for dyn $feat {
+            fn layered(layer: Arc<dyn BackendLayer>, feature: Box<Self>) -> Box<Self> {
+                Box::new(Layered { layer, feature })
+            }
+        }
+    };
+}
+
+layered_feature!(AddFolder);
+layered_feature!(ListFolders);
+layered_feature!(ExpungeFolder);
+layered_feature!(PurgeFolder);
+layered_feature!(DeleteFolder);
+layered_feature!(GetEnvelope);
+layered_feature!(ListEnvelopes);
+#[cfg(feature = "thread")]
+layered_feature!(ThreadEnvelopes);
+#[cfg(feature = "watch")]
+layered_feature!(WatchEnvelopes);
+layered_feature!(AddFlags);
+layered_feature!(SetFlags);
+layered_feature!(RemoveFlags);
+layered_feature!(AddMessage);
+layered_feature!(SendMessage);
+layered_feature!(PeekMessages);
+layered_feature!(GetMessages);
+layered_feature!(CopyMessages);
+layered_feature!(MoveMessages);
+layered_feature!(DeleteMessages);
+layered_feature!(RemoveMessages);
+
+/// Wrap the given feature using the given layers.
+///
+/// The first layer is the outermost one: it receives calls first.
+pub fn apply<F: LayeredFeature + ?Sized>(
+    layers: &[Arc<dyn BackendLayer>],
+    feature: Box<F>,
+) -> Box<F> {
+    layers
+        .iter()
+        .rev()
+        .fold(feature, |feature, layer| F::layered(layer.clone(), feature))
+}
+
+#[async_trait]
+impl AddFolder for Layered<dyn AddFolder> {
+    async fn add_folder(&self, folder: &str) -> AnyResult<()> {
+        let f = self.feature.add_folder(folder);
+        self.call(BackendCall::new(BackendFeatureKind::AddFolder, folder, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl ListFolders for Layered<dyn ListFolders> {
+    async fn list_folders(&self) -> AnyResult<Folders> {
+        let f = self.feature.list_folders();
+        self.call(BackendCall::new(BackendFeatureKind::ListFolders, "", f))
+            .await
+    }
+}
+
+#[async_trait]
+impl ExpungeFolder for Layered<dyn ExpungeFolder> {
+    async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
+        let f = self.feature.expunge_folder(folder);
+        self.call(BackendCall::new(
+            BackendFeatureKind::ExpungeFolder,
+            folder,
+            f,
+        ))
+        .await
+    }
+}
+
+#[async_trait]
+impl PurgeFolder for Layered<dyn PurgeFolder> {
+    async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
+        let f = self.feature.purge_folder(folder);
+        self.call(BackendCall::new(BackendFeatureKind::PurgeFolder, folder, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl DeleteFolder for Layered<dyn DeleteFolder> {
+    async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
+        let f = self.feature.delete_folder(folder);
+        self.call(BackendCall::new(
+            BackendFeatureKind::DeleteFolder,
+            folder,
+            f,
+        ))
+        .await
+    }
+}
+
+#[async_trait]
+impl GetEnvelope for Layered<dyn GetEnvelope> {
+    async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+        let key = format!("{folder} {id:?}");
+        let f = self.feature.get_envelope(folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::GetEnvelope, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl ListEnvelopes for Layered<dyn ListEnvelopes> {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        let key = format!("{folder} {opts:?}");
+        let f = self.feature.list_envelopes(folder, opts);
+        self.call(BackendCall::new(BackendFeatureKind::ListEnvelopes, key, f))
+            .await
+    }
+}
+
+#[cfg(feature = "thread")]
+#[async_trait]
+impl ThreadEnvelopes for Layered<dyn ThreadEnvelopes> {
+    async fn thread_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<ThreadedEnvelopes> {
+        let key = format!("{folder} {opts:?}");
+        let f = self.feature.thread_envelopes(folder, opts);
+        self.call(BackendCall::new(
+            BackendFeatureKind::ThreadEnvelopes,
+            key,
+            f,
+        ))
+        .await
+    }
+
+    async fn thread_envelope(
+        &self,
+        folder: &str,
+        id: SingleId,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<ThreadedEnvelopes> {
+        let key = format!("{folder} {id:?} {opts:?}");
+        let f = self.feature.thread_envelope(folder, id, opts);
+        self.call(BackendCall::new(
+            BackendFeatureKind::ThreadEnvelopes,
+            key,
+            f,
+        ))
+        .await
+    }
+}
+
+#[cfg(feature = "watch")]
+#[async_trait]
+impl WatchEnvelopes for Layered<dyn WatchEnvelopes> {
+    async fn watch_envelopes(
+        &self,
+        folder: &str,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        let f = self
+            .feature
+            .watch_envelopes(folder, wait_for_shutdown_request, shutdown);
+        self.call(BackendCall::new(
+            BackendFeatureKind::WatchEnvelopes,
+            folder,
+            f,
+        ))
+        .await
+    }
+}
+
+#[async_trait]
+impl AddFlags for Layered<dyn AddFlags> {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        let key = format!("{folder} {id:?} {flags:?}");
+        let f = self.feature.add_flags(folder, id, flags);
+        self.call(BackendCall::new(BackendFeatureKind::AddFlags, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl SetFlags for Layered<dyn SetFlags> {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        let key = format!("{folder} {id:?} {flags:?}");
+        let f = self.feature.set_flags(folder, id, flags);
+        self.call(BackendCall::new(BackendFeatureKind::SetFlags, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl RemoveFlags for Layered<dyn RemoveFlags> {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        let key = format!("{folder} {id:?} {flags:?}");
+        let f = self.feature.remove_flags(folder, id, flags);
+        self.call(BackendCall::new(BackendFeatureKind::RemoveFlags, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl AddMessage for Layered<dyn AddMessage> {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        let key = format!("{folder} {flags:?}");
+        let f = self.feature.add_message_with_flags(folder, msg, flags);
+        self.call(BackendCall::new(BackendFeatureKind::AddMessage, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl SendMessage for Layered<dyn SendMessage> {
+    async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
+        let f = self.feature.send_message(msg);
+        self.call(BackendCall::new(BackendFeatureKind::SendMessage, "", f))
+            .await
+    }
+}
+
+#[async_trait]
+impl PeekMessages for Layered<dyn PeekMessages> {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let key = format!("{folder} {id:?}");
+        let f = self.feature.peek_messages(folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::PeekMessages, key, f))
+            .await
+    }
+
+    async fn peek_message_headers(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let key = format!("headers {folder} {id:?}");
+        let f = self.feature.peek_message_headers(folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::PeekMessages, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl GetMessages for Layered<dyn GetMessages> {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let key = format!("{folder} {id:?}");
+        let f = self.feature.get_messages(folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::GetMessages, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl CopyMessages for Layered<dyn CopyMessages> {
+    async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        let key = format!("{from_folder} {to_folder} {id:?}");
+        let f = self.feature.copy_messages(from_folder, to_folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::CopyMessages, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl MoveMessages for Layered<dyn MoveMessages> {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        let key = format!("{from_folder} {to_folder} {id:?}");
+        let f = self.feature.move_messages(from_folder, to_folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::MoveMessages, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl DeleteMessages for Layered<dyn DeleteMessages> {
+    async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        let key = format!("{folder} {id:?}");
+        let f = self.feature.delete_messages(folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::DeleteMessages, key, f))
+            .await
+    }
+}
+
+#[async_trait]
+impl RemoveMessages for Layered<dyn RemoveMessages> {
+    async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        let key = format!("{folder} {id:?}");
+        let f = self.feature.remove_messages(folder, id);
+        self.call(BackendCall::new(BackendFeatureKind::RemoveMessages, key, f))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{
+        apply, cache::CacheLayer, metrics::MetricsLayer, read_only::ReadOnlyLayer,
+        BackendFeatureKind, BackendLayer,
+    };
+    use crate::{
+        folder::{add::AddFolder, list::ListFolders, Folders},
+        AnyResult,
+    };
+
+    #[derive(Clone, Default)]
+    struct Counter(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl ListFolders for Counter {
+        async fn list_folders(&self) -> AnyResult<Folders> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Folders::default())
+        }
+    }
+
+    #[async_trait]
+    impl AddFolder for Counter {
+        async fn add_folder(&self, _folder: &str) -> AnyResult<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn layers() {
+        let counter = Counter::default();
+        let metrics = MetricsLayer::new();
+        let cache = CacheLayer::new(Duration::from_secs(60));
+
+        let layers: Vec<Arc<dyn BackendLayer>> = vec![Arc::new(metrics.clone()), Arc::new(cache)];
+        let list: Box<dyn ListFolders> = apply(&layers, Box::new(counter.clone()));
+        let add: Box<dyn AddFolder> = apply(&layers, Box::new(counter.clone()));
+
+        list.list_folders().await.unwrap();
+        list.list_folders().await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        add.add_folder("INBOX").await.unwrap();
+        list.list_folders().await.unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 3);
+
+        let metrics = metrics.metrics();
+        let list_metrics = metrics.get(&BackendFeatureKind::ListFolders).unwrap();
+        assert_eq!(list_metrics.calls, 3);
+        assert_eq!(list_metrics.errors, 0);
+        assert_eq!(metrics.total().calls, 4);
+
+        let layers: Vec<Arc<dyn BackendLayer>> = vec![Arc::new(ReadOnlyLayer::new())];
+        let add: Box<dyn AddFolder> = apply(&layers, Box::new(counter.clone()));
+        assert!(add.add_folder("INBOX").await.is_err());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,36 @@
+//! # Read-only layer
+//!
+//! Module dedicated to the read-only backend layer, which rejects
+//! backend features altering the backend.
+
+use async_trait::async_trait;
+
+use super::{BackendCall, BackendLayer, BackendOutput};
+use crate::{backend::Error, AnyResult};
+
+/// The read-only backend layer.
+///
+/// Calls to features altering the backend fail with
+/// [`Error::ReadOnlyBackendError`], see
+/// [`BackendFeatureKind::is_write`](super::BackendFeatureKind::is_write).
+#[derive(Clone, Debug, Default)]
+pub struct ReadOnlyLayer;
+
+impl ReadOnlyLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl BackendLayer for ReadOnlyLayer {
+    async fn call(&self, call: BackendCall<'_>) -> AnyResult<BackendOutput> {
+        let feature = call.feature();
+
+        if feature.is_write() {
+            return Err(Error::ReadOnlyBackendError(feature).into());
+        }
+
+        call.run().await
+    }
+}
@@ -49,7 +49,10 @@
 pub mod context;
 mod error;
 pub mod feature;
+pub mod layer;
 pub mod mapper;
+#[cfg(feature = "multi-account")]
+pub mod multi;
 pub mod macros {
     pub use email_macros::BackendContext;
 }
@@ -68,6 +71,7 @@ pub mod macros {
 use self::{
     context::{BackendContext, BackendContextBuilder},
     feature::{BackendFeature, BackendFeatureSource, CheckUp},
+    layer::{BackendLayer, LayeredFeature},
 };
 #[cfg(feature = "watch")]
 use crate::envelope::watch::WatchEnvelopes;
@@ -157,6 +161,16 @@ pub struct Backend<C>
     pub delete_messages: Option<BackendFeature<C, dyn DeleteMessages>>,
     /// The delete messages backend feature.
     pub remove_messages: Option<BackendFeature<C, dyn RemoveMessages>>,
+
+    /// The backend layers wrapping features.
+    pub layers: Vec<Arc<dyn BackendLayer>>,
+}
+
+impl<C: BackendContext> Backend<C> {
+    /// Wrap the given feature using the backend layers.
+    fn layer<F: LayeredFeature + ?Sized>(&self, feature: Box<F>) -> Box<F> {
+        layer::apply(&self.layers, feature)
+    }
 }
 
 impl<C: BackendContext> HasAccountConfig for Backend<C> {
@@ -171,6 +185,7 @@ async fn add_folder(&self, folder: &str) -> AnyResult<()> {
         self.add_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::AddFolderNotAvailableError)?
             .add_folder(folder)
             .await
@@ -180,12 +195,24 @@ async fn add_folder(&self, folder: &str) -> AnyResult<()> {
 #[async_trait]
 impl<C: BackendContext> ListFolders for Backend<C> {
     async fn list_folders(&self) -> AnyResult<Folders> {
-        self.list_folders
+        let folders = self
+            .list_folders
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::ListFoldersNotAvailableError)?
             .list_folders()
-            .await
+            .await?;
+
+        let folders = match self.account_config.get_folder_list_filter() {
+            Some(filter) => folders
+                .into_iter()
+                .filter(|folder| filter.matches(folder.get_kind_or_name()))
+                .collect(),
+            None => folders,
+        };
+
+        Ok(folders)
     }
 }
 
@@ -195,6 +222,7 @@ async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
         self.expunge_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::ExpungeFolderNotAvailableError)?
             .expunge_folder(folder)
             .await
@@ -207,6 +235,7 @@ async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
         self.purge_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::PurgeFolderNotAvailableError)?
             .purge_folder(folder)
             .await
@@ -219,6 +248,7 @@ async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
         self.delete_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::DeleteFolderNotAvailableError)?
             .delete_folder(folder)
             .await
@@ -231,6 +261,7 @@ async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope>
         self.get_envelope
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::GetEnvelopeNotAvailableError)?
             .get_envelope(folder, id)
             .await
@@ -247,6 +278,7 @@ async fn list_envelopes(
         self.list_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::ListEnvelopesNotAvailableError)?
             .list_envelopes(folder, opts)
             .await
@@ -264,6 +296,7 @@ async fn thread_envelopes(
         self.thread_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::ThreadEnvelopesNotAvailableError)?
             .thread_envelopes(folder, opts)
             .await
@@ -278,6 +311,7 @@ async fn thread_envelope(
         self.thread_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::ThreadEnvelopesNotAvailableError)?
             .thread_envelope(folder, id, opts)
             .await
@@ -296,6 +330,7 @@ async fn watch_envelopes(
         self.watch_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::WatchEnvelopesNotAvailableError)?
             .watch_envelopes(folder, wait_for_shutdown_request, shutdown)
             .await
@@ -308,6 +343,7 @@ async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
         self.add_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::AddFlagsNotAvailableError)?
             .add_flags(folder, id, flags)
             .await
@@ -320,6 +356,7 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
         self.set_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::SetFlagsNotAvailableError)?
             .set_flags(folder, id, flags)
             .await
@@ -332,6 +369,7 @@ async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<
         self.remove_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::RemoveFlagsNotAvailableError)?
             .remove_flags(folder, id, flags)
             .await
@@ -349,6 +387,7 @@ async fn add_message_with_flags(
         self.add_message
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::AddMessageNotAvailableError)?
             .add_message_with_flags(folder, msg, flags)
             .await
@@ -361,6 +400,7 @@ async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
         self.send_message
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::SendMessageNotAvailableError)?
             .send_message(msg)
             .await
@@ -373,10 +413,21 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         self.peek_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::PeekMessagesNotAvailableError)?
             .peek_messages(folder, id)
             .await
     }
+
+    async fn peek_message_headers(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        self.peek_messages
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
+            .ok_or(Error::PeekMessagesNotAvailableError)?
+            .peek_message_headers(folder, id)
+            .await
+    }
 }
 
 #[async_trait]
@@ -385,6 +436,7 @@ async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         self.get_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::GetMessagesNotAvailableError)?
             .get_messages(folder, id)
             .await
@@ -397,6 +449,7 @@ async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         self.copy_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::CopyMessagesNotAvailableError)?
             .copy_messages(from_folder, to_folder, id)
             .await
@@ -409,6 +462,7 @@ async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         self.move_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::MoveMessagesNotAvailableError)?
             .move_messages(from_folder, to_folder, id)
             .await
@@ -421,6 +475,7 @@ async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
         self.delete_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::DeleteMessagesNotAvailableError)?
             .delete_messages(folder, id)
             .await
@@ -433,6 +488,7 @@ async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
         self.remove_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
+            .map(|feature| self.layer(feature))
             .ok_or(Error::RemoveMessagesNotAvailableError)?
             .remove_messages(folder, id)
             .await
@@ -560,6 +616,9 @@ pub struct BackendBuilder<CB>
     pub delete_messages: BackendFeatureSource<CB::Context, dyn DeleteMessages>,
     /// The remove messages backend builder feature.
     pub remove_messages: BackendFeatureSource<CB::Context, dyn RemoveMessages>,
+
+    /// The backend layers wrapping features.
+    pub layers: Vec<Arc<dyn BackendLayer>>,
 }
 
 impl<CB> BackendBuilder<CB>
@@ -626,9 +685,25 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
             move_messages: BackendFeatureSource::Context,
             delete_messages: BackendFeatureSource::Context,
             remove_messages: BackendFeatureSource::Context,
+
+            layers: Vec::new(),
         }
     }
 
+    /// Add the given layer.
+    ///
+    /// Layers wrap all backend features. The first added layer is
+    /// the outermost one: it receives calls first.
+    pub fn add_layer(&mut self, layer: impl BackendLayer + 'static) {
+        self.layers.push(Arc::new(layer));
+    }
+
+    /// Add the given layer, using the builder pattern.
+    pub fn with_layer(mut self, layer: impl BackendLayer + 'static) -> Self {
+        self.add_layer(layer);
+        self
+    }
+
     /// Disable all features for this backend builder.
     pub fn without_features(mut self) -> Self {
         self.set_list_folders(BackendFeatureSource::None);
@@ -699,6 +774,8 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
             move_messages,
             delete_messages,
             remove_messages,
+
+            layers: self.layers,
         })
     }
 }
@@ -740,6 +817,8 @@ fn clone(&self) -> Self {
             move_messages: self.move_messages.clone(),
             delete_messages: self.delete_messages.clone(),
             remove_messages: self.remove_messages.clone(),
+
+            layers: self.layers.clone(),
         }
     }
 }
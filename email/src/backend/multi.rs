@@ -0,0 +1,723 @@
+//! # Multi-account backend
+//!
+//! Module dedicated to the multi-account backend. The main structure
+//! of this module is [`MultiAccountBackend`], which wraps the
+//! backends of several accounts and exposes them as a single one.
+//!
+//! Folders and envelope ids are namespaced by account name, using
+//! the [`SEPARATOR`]: `work:Archives` is the folder `Archives` of
+//! the account `work`, and `work:42` is the envelope `42` of the
+//! same account. A folder is namespaced only when its prefix is the
+//! name of a wrapped account, so that folders like `Lists:rust`
+//! keep working. Folders without namespace are unified folders:
+//! they gather the folder of the same name from all accounts, for
+//! example `INBOX` or `Sent`. Listing envelopes of a unified folder
+//! merges, sorts then paginates envelopes of all accounts, which
+//! also makes it a merged search when a query is given.
+//!
+//! Destructive folder operations (expunge, purge and delete) never
+//! fan out: they require a namespaced folder.
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+
+use super::{Error, Result};
+use crate::{
+    envelope::{
+        get::GetEnvelope,
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Envelopes, Id, SingleId,
+    },
+    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    folder::{
+        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+        purge::PurgeFolder, Folder, FolderKind, Folders, INBOX, SENT,
+    },
+    message::{
+        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, Messages,
+    },
+    AnyResult,
+};
+
+/// The separator between account names and folders or envelope
+/// ids.
+pub const SEPARATOR: char = ':';
+
+/// The backend of an account, as wrapped by the multi-account
+/// backend.
+///
+/// This trait is automatically implemented for any type
+/// implementing the required backend features, like
+/// [`Backend`](super::Backend).
+pub trait AccountBackend:
+    AddFolder
+    + ListFolders
+    + ExpungeFolder
+    + PurgeFolder
+    + DeleteFolder
+    + GetEnvelope
+    + ListEnvelopes
+    + AddFlags
+    + SetFlags
+    + RemoveFlags
+    + AddMessage
+    + PeekMessages
+    + GetMessages
+    + CopyMessages
+    + MoveMessages
+    + DeleteMessages
+    + RemoveMessages
+{
+}
+
+impl<T> AccountBackend for T where
+    T: AddFolder
+        + ListFolders
+        + ExpungeFolder
+        + PurgeFolder
+        + DeleteFolder
+        + GetEnvelope
+        + ListEnvelopes
+        + AddFlags
+        + SetFlags
+        + RemoveFlags
+        + AddMessage
+        + PeekMessages
+        + GetMessages
+        + CopyMessages
+        + MoveMessages
+        + DeleteMessages
+        + RemoveMessages
+{
+}
+
+/// The multi-account backend.
+///
+/// Accounts are identified by their name, usually the key of
+/// [`Config::accounts`](crate::config::Config::accounts).
+#[derive(Clone, Default)]
+pub struct MultiAccountBackend {
+    backends: BTreeMap<String, Arc<dyn AccountBackend>>,
+}
+
+impl MultiAccountBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the backend of the given account.
+    ///
+    /// Fails if the account name contains the [`SEPARATOR`], or if
+    /// the account has already been added.
+    pub fn add_backend(
+        &mut self,
+        account: impl ToString,
+        backend: impl AccountBackend + 'static,
+    ) -> Result<()> {
+        let account = account.to_string();
+
+        if account.is_empty() || account.contains(SEPARATOR) {
+            return Err(Error::ParseMultiAccountNameError(account, SEPARATOR));
+        }
+
+        if self.backends.contains_key(&account) {
+            return Err(Error::AddMultiAccountDuplicateError(account));
+        }
+
+        self.backends.insert(account, Arc::new(backend));
+        Ok(())
+    }
+
+    /// Add the backend of the given account, using the builder
+    /// pattern.
+    pub fn with_backend(
+        mut self,
+        account: impl ToString,
+        backend: impl AccountBackend + 'static,
+    ) -> Result<Self> {
+        self.add_backend(account, backend)?;
+        Ok(self)
+    }
+
+    /// Return the names of the wrapped accounts.
+    pub fn accounts(&self) -> impl Iterator<Item = &str> {
+        self.backends.keys().map(String::as_str)
+    }
+
+    /// Return the backend of the given account.
+    pub fn get_backend(&self, account: &str) -> Result<&dyn AccountBackend> {
+        self.backends
+            .get(account)
+            .map(AsRef::as_ref)
+            .ok_or_else(|| Error::GetMultiAccountBackendError(account.to_owned()))
+    }
+
+    /// Parse the given folder against the wrapped accounts.
+    fn parse_folder<'a>(&self, folder: &'a str) -> MultiAccountFolder<'a> {
+        MultiAccountFolder::parse(folder, self.accounts())
+    }
+
+    /// Group the given ids by account.
+    ///
+    /// Ids are expected to be namespaced, except when the folder is
+    /// namespaced: ids then belong to the account of the folder.
+    fn group_ids<'a>(
+        &self,
+        folder: &MultiAccountFolder<'a>,
+        id: &'a Id,
+    ) -> Result<BTreeMap<&'a str, Vec<&'a str>>> {
+        let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+        for id in id.iter() {
+            let (account, id) = folder.route(id)?;
+            self.get_backend(account)?;
+            groups.entry(account).or_default().push(id);
+        }
+
+        Ok(groups)
+    }
+}
+
+/// The folder of a multi-account backend.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MultiAccountFolder<'a> {
+    /// The folder of the same name from all accounts.
+    Unified(&'a str),
+
+    /// The folder of the given account.
+    Account(&'a str, &'a str),
+}
+
+impl<'a> MultiAccountFolder<'a> {
+    /// Parse the given folder.
+    ///
+    /// The folder is namespaced only if its prefix matches one of
+    /// the given account names, otherwise the separator is
+    /// considered part of a unified folder name.
+    pub fn parse<T: AsRef<str>>(folder: &'a str, accounts: impl IntoIterator<Item = T>) -> Self {
+        match folder.split_once(SEPARATOR) {
+            Some((account, name)) if accounts.into_iter().any(|a| a.as_ref() == account) => {
+                Self::Account(account, name)
+            }
+            _ => Self::Unified(folder),
+        }
+    }
+
+    /// Return the account of the folder, if namespaced.
+    pub fn account(&self) -> Option<&'a str> {
+        match self {
+            Self::Unified(_) => None,
+            Self::Account(account, _) => Some(account),
+        }
+    }
+
+    /// Return the folder name, without namespace.
+    pub fn name(&self) -> &'a str {
+        match self {
+            Self::Unified(folder) => folder,
+            Self::Account(_, folder) => folder,
+        }
+    }
+
+    /// Return the account and the folder name, or fail if the folder
+    /// is unified.
+    fn namespaced(&self) -> Result<(&'a str, &'a str)> {
+        match self {
+            Self::Unified(folder) => {
+                let folder = folder.to_string();
+                Err(Error::UnifiedFolderNotSupportedError(folder))
+            }
+            Self::Account(account, folder) => Ok((account, folder)),
+        }
+    }
+
+    /// Return the account and the id without namespace of the given
+    /// envelope id.
+    fn route(&self, id: &'a str) -> Result<(&'a str, &'a str)> {
+        match (self.account(), id.split_once(SEPARATOR)) {
+            (Some(account), Some((prefix, id))) if account == prefix => Ok((account, id)),
+            // NOTE: ids from a namespaced folder do not need to be
+            // namespaced, and raw ids may contain the separator
+            (Some(account), _) => Ok((account, id)),
+            (None, Some((account, id))) => Ok((account, id)),
+            (None, None) => Err(Error::ParseMultiAccountIdError(id.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for MultiAccountFolder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unified(folder) => write!(f, "{folder}"),
+            Self::Account(account, folder) => write!(f, "{account}{SEPARATOR}{folder}"),
+        }
+    }
+}
+
+/// Prefix the given id by the given account name.
+pub fn namespace(account: &str, id: &str) -> String {
+    format!("{account}{SEPARATOR}{id}")
+}
+
+fn namespace_envelope(account: &str, envelope: &mut Envelope) {
+    envelope.id = namespace(account, &envelope.id);
+}
+
+fn into_id(ids: Vec<&str>) -> Id {
+    match ids.as_slice() {
+        [id] => Id::single(*id),
+        ids => Id::multiple(ids),
+    }
+}
+
+/// Return the options used to list envelopes of one account, so
+/// that the merged list contains the page requested by the given
+/// options.
+fn account_list_opts(opts: &ListEnvelopesOptions) -> ListEnvelopesOptions {
+    ListEnvelopesOptions {
+        page: 0,
+        page_size: opts.page_size.saturating_mul(opts.page.saturating_add(1)),
+        query: opts.query.clone(),
+    }
+}
+
+/// Extract the page matching the given options from the given
+/// sorted envelopes.
+fn paginate(envelopes: Envelopes, opts: &ListEnvelopesOptions) -> Envelopes {
+    if opts.page_size == 0 {
+        return envelopes;
+    }
+
+    envelopes
+        .into_iter()
+        .skip(opts.page.saturating_mul(opts.page_size))
+        .take(opts.page_size)
+        .collect()
+}
+
+/// Merge messages coming from several accounts.
+fn merge_messages(mut messages: Vec<Messages>) -> AnyResult<Messages> {
+    if messages.len() == 1 {
+        if let Some(messages) = messages.pop() {
+            return Ok(messages);
+        }
+    }
+
+    let mut raw = Vec::new();
+
+    for messages in &messages {
+        for message in messages.to_vec() {
+            raw.push(message.raw()?.to_vec());
+        }
+    }
+
+    Ok(Messages::from(raw))
+}
+
+#[async_trait]
+impl AddFolder for MultiAccountBackend {
+    async fn add_folder(&self, folder: &str) -> AnyResult<()> {
+        let (account, folder) = self.parse_folder(folder).namespaced()?;
+        self.get_backend(account)?.add_folder(folder).await
+    }
+}
+
+#[async_trait]
+impl ListFolders for MultiAccountBackend {
+    async fn list_folders(&self) -> AnyResult<Folders> {
+        let tasks = self.backends.iter().map(|(account, backend)| async move {
+            let folders = backend.list_folders().await?;
+            let folders = folders.into_iter().map(|folder| Folder {
+                kind: None,
+                name: namespace(account, folder.get_kind_or_name()),
+                desc: folder.desc,
+            });
+            AnyResult::Ok(folders.collect::<Vec<_>>())
+        });
+
+        let unified = [(FolderKind::Inbox, INBOX), (FolderKind::Sent, SENT)]
+            .into_iter()
+            .map(|(kind, name)| Folder {
+                kind: Some(kind),
+                name: name.to_owned(),
+                desc: format!("Unified {name} of all accounts"),
+            });
+
+        let folders = try_join_all(tasks).await?.into_iter().flatten();
+
+        Ok(unified.chain(folders).collect())
+    }
+}
+
+/// Unified folders cannot be expunged, see the [module
+/// documentation](self).
+#[async_trait]
+impl ExpungeFolder for MultiAccountBackend {
+    async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
+        let (account, folder) = self.parse_folder(folder).namespaced()?;
+        self.get_backend(account)?.expunge_folder(folder).await
+    }
+}
+
+/// Unified folders cannot be purged, see the [module
+/// documentation](self).
+#[async_trait]
+impl PurgeFolder for MultiAccountBackend {
+    async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
+        let (account, folder) = self.parse_folder(folder).namespaced()?;
+        self.get_backend(account)?.purge_folder(folder).await
+    }
+}
+
+#[async_trait]
+impl DeleteFolder for MultiAccountBackend {
+    async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
+        let (account, folder) = self.parse_folder(folder).namespaced()?;
+        self.get_backend(account)?.delete_folder(folder).await
+    }
+}
+
+#[async_trait]
+impl GetEnvelope for MultiAccountBackend {
+    async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+        let folder = self.parse_folder(folder);
+        let (account, id) = folder.route(id.as_str())?;
+
+        let mut envelope = self
+            .get_backend(account)?
+            .get_envelope(folder.name(), &SingleId::from(id))
+            .await?;
+
+        namespace_envelope(account, &mut envelope);
+        Ok(envelope)
+    }
+}
+
+#[async_trait]
+impl ListEnvelopes for MultiAccountBackend {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        match self.parse_folder(folder) {
+            MultiAccountFolder::Account(account, folder) => {
+                let mut envelopes = self
+                    .get_backend(account)?
+                    .list_envelopes(folder, opts)
+                    .await?;
+
+                for envelope in envelopes.iter_mut() {
+                    namespace_envelope(account, envelope);
+                }
+
+                Ok(envelopes)
+            }
+            MultiAccountFolder::Unified(folder) => {
+                let account_opts = account_list_opts(&opts);
+
+                let tasks = self.backends.iter().map(|(account, backend)| {
+                    let opts = account_opts.clone();
+                    async move {
+                        let mut envelopes = backend.list_envelopes(folder, opts).await?;
+
+                        for envelope in envelopes.iter_mut() {
+                            namespace_envelope(account, envelope);
+                        }
+
+                        AnyResult::Ok(envelopes)
+                    }
+                });
+
+                let mut envelopes: Envelopes =
+                    try_join_all(tasks).await?.into_iter().flatten().collect();
+
+                opts.sort_envelopes(&mut envelopes);
+
+                Ok(paginate(envelopes, &opts))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AddFlags for MultiAccountBackend {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend.add_flags(folder.name(), &into_id(ids), flags).await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SetFlags for MultiAccountBackend {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend.set_flags(folder.name(), &into_id(ids), flags).await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RemoveFlags for MultiAccountBackend {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend
+                .remove_flags(folder.name(), &into_id(ids), flags)
+                .await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AddMessage for MultiAccountBackend {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        let (account, folder) = self.parse_folder(folder).namespaced()?;
+
+        let id = self
+            .get_backend(account)?
+            .add_message_with_flags(folder, msg, flags)
+            .await?;
+
+        Ok(SingleId::from(namespace(account, &id)))
+    }
+}
+
+/// Messages are grouped by account: when ids belong to several
+/// accounts, messages are returned account by account.
+#[async_trait]
+impl PeekMessages for MultiAccountBackend {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend.peek_messages(folder.name(), &into_id(ids)).await
+        });
+
+        merge_messages(try_join_all(tasks).await?)
+    }
+
+    async fn peek_message_headers(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend
+                .peek_message_headers(folder.name(), &into_id(ids))
+                .await
+        });
+
+        merge_messages(try_join_all(tasks).await?)
+    }
+}
+
+/// Messages are grouped by account: when ids belong to several
+/// accounts, messages are returned account by account.
+#[async_trait]
+impl GetMessages for MultiAccountBackend {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend.get_messages(folder.name(), &into_id(ids)).await
+        });
+
+        merge_messages(try_join_all(tasks).await?)
+    }
+}
+
+/// Messages cannot be copied from an account to another one.
+#[async_trait]
+impl CopyMessages for MultiAccountBackend {
+    async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        let from_folder = self.parse_folder(from_folder);
+        let to_folder = self.parse_folder(to_folder);
+        let groups = self.group_ids(&from_folder, id)?;
+        check_same_account(&groups, &to_folder)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            let (from, to, id) = (from_folder.name(), to_folder.name(), into_id(ids));
+            backend.copy_messages(from, to, &id).await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+/// Messages cannot be moved from an account to another one.
+#[async_trait]
+impl MoveMessages for MultiAccountBackend {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        let from_folder = self.parse_folder(from_folder);
+        let to_folder = self.parse_folder(to_folder);
+        let groups = self.group_ids(&from_folder, id)?;
+        check_same_account(&groups, &to_folder)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            let (from, to, id) = (from_folder.name(), to_folder.name(), into_id(ids));
+            backend.move_messages(from, to, &id).await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeleteMessages for MultiAccountBackend {
+    async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend.delete_messages(folder.name(), &into_id(ids)).await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RemoveMessages for MultiAccountBackend {
+    async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        let folder = self.parse_folder(folder);
+        let groups = self.group_ids(&folder, id)?;
+
+        let tasks = groups.into_iter().map(|(account, ids)| async move {
+            let backend = self.get_backend(account)?;
+            backend.remove_messages(folder.name(), &into_id(ids)).await
+        });
+
+        try_join_all(tasks).await?;
+        Ok(())
+    }
+}
+
+/// Check that envelopes grouped by account can be transferred to
+/// the given folder.
+fn check_same_account(
+    groups: &BTreeMap<&str, Vec<&str>>,
+    to_folder: &MultiAccountFolder<'_>,
+) -> Result<()> {
+    let Some(to_account) = to_folder.account() else {
+        return Ok(());
+    };
+
+    match groups.keys().find(|account| **account != to_account) {
+        Some(account) => Err(Error::TransferMultiAccountError(
+            account.to_string(),
+            to_account.to_owned(),
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{account_list_opts, paginate, MultiAccountFolder};
+    use crate::envelope::{list::ListEnvelopesOptions, Envelope, Envelopes, Id};
+
+    const ACCOUNTS: [&str; 2] = ["home", "work"];
+
+    #[test]
+    fn parse_folder() {
+        assert_eq!(
+            MultiAccountFolder::parse("INBOX", ACCOUNTS),
+            MultiAccountFolder::Unified("INBOX")
+        );
+        assert_eq!(
+            MultiAccountFolder::parse("work:Archives/2024", ACCOUNTS),
+            MultiAccountFolder::Account("work", "Archives/2024")
+        );
+        assert_eq!(
+            MultiAccountFolder::parse("Lists:rust", ACCOUNTS),
+            MultiAccountFolder::Unified("Lists:rust")
+        );
+        assert_eq!(
+            MultiAccountFolder::parse("work:Lists:rust", ACCOUNTS),
+            MultiAccountFolder::Account("work", "Lists:rust")
+        );
+    }
+
+    #[test]
+    fn route_ids() {
+        let unified = MultiAccountFolder::parse("INBOX", ACCOUNTS);
+        assert_eq!(unified.route("work:42").unwrap(), ("work", "42"));
+        assert_eq!(unified.route("work:<a:b@c>").unwrap(), ("work", "<a:b@c>"));
+        assert!(unified.route("42").is_err());
+
+        let work = MultiAccountFolder::parse("work:INBOX", ACCOUNTS);
+        assert_eq!(work.route("42").unwrap(), ("work", "42"));
+        assert_eq!(work.route("work:42").unwrap(), ("work", "42"));
+        assert_eq!(work.route("<a:b@c>").unwrap(), ("work", "<a:b@c>"));
+
+        let id = Id::multiple(["work:1", "home:2", "work:3"]);
+        let groups: Vec<_> = id.iter().map(|id| unified.route(id).unwrap()).collect();
+        assert_eq!(groups, [("work", "1"), ("home", "2"), ("work", "3")]);
+    }
+
+    #[test]
+    fn paginate_merged_envelopes() {
+        let opts = ListEnvelopesOptions {
+            page: 1,
+            page_size: 2,
+            query: None,
+        };
+
+        assert_eq!(account_list_opts(&opts).page, 0);
+        assert_eq!(account_list_opts(&opts).page_size, 4);
+
+        let envelopes: Envelopes = (1..=5)
+            .map(|id| Envelope {
+                id: id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        let page = paginate(envelopes.clone(), &opts);
+        let ids: Vec<_> = page.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["3", "4"]);
+
+        let opts = ListEnvelopesOptions { page: 3, ..opts };
+        assert!(paginate(envelopes, &opts).is_empty());
+    }
+}
@@ -10,6 +10,11 @@
 /// An address is composed of an optional name and
 /// an email address.
 #[derive(Clone, Debug, Default, Eq, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub struct Address {
     pub name: Option<String>,
     pub addr: String,
@@ -39,6 +39,11 @@
 /// tries to be as simple as possible and should fit most of the use
 /// cases.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum Flag {
     /// Flag used when the email envelope has been opened.
     Seen,
@@ -134,6 +139,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 /// The list of flags that can be attached to an email envelope. It
 /// uses a [`std::collections::HashSet`] to prevent duplicates.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "derive", derive(serde::Serialize, serde::Deserialize))]
 pub struct Flags(BTreeSet<Flag>);
 
 impl Hash for Flags {
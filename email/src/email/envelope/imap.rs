@@ -11,6 +11,7 @@
     fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName},
 };
 use once_cell::sync::Lazy;
+use utf7_imap::decode_utf7_imap as decode_utf7;
 
 use crate::{
     envelope::{Envelope, Envelopes},
@@ -19,14 +20,30 @@
 };
 
 /// The IMAP fetch items needed to retrieve everything we need to
-/// build an envelope: UID, flags and envelope (Message-ID, From, To,
-/// Subject, Date).
+/// build an envelope: UID, flags, envelope (Message-ID, From, To,
+/// Subject, Date) and size.
 pub static FETCH_ENVELOPES: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
     MacroOrMessageDataItemNames::MessageDataItemNames(vec![
         MessageDataItemName::Uid,
         MessageDataItemName::Flags,
         MessageDataItemName::Envelope,
         MessageDataItemName::BodyStructure,
+        MessageDataItemName::Rfc822Size,
+    ])
+});
+
+/// The IMAP fetch items of [`FETCH_ENVELOPES`], plus the Gmail
+/// message identifier and labels, for servers supporting the Gmail
+/// extension `X-GM-EXT-1`.
+pub static FETCH_ENVELOPES_GMAIL: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
+    MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+        MessageDataItemName::Uid,
+        MessageDataItemName::Flags,
+        MessageDataItemName::Envelope,
+        MessageDataItemName::BodyStructure,
+        MessageDataItemName::Rfc822Size,
+        MessageDataItemName::XGmMsgId,
+        MessageDataItemName::XGmLabels,
     ])
 });
 
@@ -54,6 +71,9 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
         let mut flags = Flags::default();
         let mut msg = Vec::default();
         let mut has_attachment = false;
+        let mut size = 0;
+        let mut gmail_message_id = None;
+        let mut gmail_labels = Vec::new();
 
         for item in items {
             match item {
@@ -149,6 +169,21 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
                 MessageDataItem::BodyStructure(body) => {
                     has_attachment = has_at_least_one_attachment([body]);
                 }
+                MessageDataItem::Rfc822Size(rfc822_size) => {
+                    size = *rfc822_size as usize;
+                }
+                MessageDataItem::XGmMsgId(msg_id) => {
+                    gmail_message_id = Some(*msg_id);
+                }
+                MessageDataItem::XGmLabels(labels) => {
+                    gmail_labels = labels
+                        .iter()
+                        .map(|label| {
+                            let label = String::from_utf8_lossy(label.as_ref());
+                            decode_utf7(label.into())
+                        })
+                        .collect();
+                }
                 _ => (),
             }
         }
@@ -156,6 +191,9 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
         let msg = Message::from(msg);
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.size = size;
+        env.gmail_message_id = gmail_message_id;
+        env.gmail_labels = gmail_labels;
         env
     }
 }
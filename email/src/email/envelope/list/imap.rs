@@ -232,6 +232,12 @@ pub fn to_imap_search_criterion(&self) -> SearchKey<'static> {
                 let date = *date + TimeDelta::try_days(1).unwrap();
                 SearchKey::SentSince(date.try_into().unwrap())
             }
+            SearchEmailsFilterQuery::BeforeRelativeDate(date) => {
+                SearchEmailsFilterQuery::BeforeDate(date.to_naive_date()).to_imap_search_criterion()
+            }
+            SearchEmailsFilterQuery::AfterRelativeDate(date) => {
+                SearchEmailsFilterQuery::AfterDate(date.to_naive_date()).to_imap_search_criterion()
+            }
             SearchEmailsFilterQuery::From(pattern) => {
                 SearchKey::From(pattern.clone().try_into().unwrap())
             }
@@ -10,15 +10,13 @@
     envelope::Envelope,
     info,
     maildir::MaildirContextSync,
-    search_query::{filter::SearchEmailsFilterQuery, SearchEmailsQuery},
+    search_query::{
+        filter::{contains_ignore_ascii_case, SearchEmailsFilterQuery},
+        SearchEmailsQuery,
+    },
     trace, warn, AnyResult,
 };
 
-#[cfg(test)]
-static USER_TZ: &chrono::Utc = &chrono::Utc;
-#[cfg(not(test))]
-static USER_TZ: &chrono::Local = &chrono::Local;
-
 #[derive(Clone)]
 pub struct ListMaildirEnvelopes {
     ctx: MaildirContextSync,
@@ -88,85 +86,29 @@ pub fn matches_maildir_search_query(&self, envelope: &Envelope, msg_path: &Path)
     }
 }
 
-fn contains_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> bool {
-    for window in haystack.windows(needle.len()) {
-        if window.eq_ignore_ascii_case(needle) {
-            return true;
-        }
-    }
-
-    false
-}
-
 impl SearchEmailsFilterQuery {
     pub fn matches_maildir_search_query(&self, envelope: &Envelope, msg_path: &Path) -> bool {
-        match self {
-            SearchEmailsFilterQuery::And(left, right) => {
-                let left = left.matches_maildir_search_query(envelope, msg_path);
-                let right = right.matches_maildir_search_query(envelope, msg_path);
-                left && right
-            }
-            SearchEmailsFilterQuery::Or(left, right) => {
-                let left = left.matches_maildir_search_query(envelope, msg_path);
-                let right = right.matches_maildir_search_query(envelope, msg_path);
-                left || right
-            }
-            SearchEmailsFilterQuery::Not(filter) => {
-                !filter.matches_maildir_search_query(envelope, msg_path)
-            }
-            SearchEmailsFilterQuery::Date(date) => {
-                &envelope.date.with_timezone(USER_TZ).date_naive() == date
-            }
-            SearchEmailsFilterQuery::BeforeDate(date) => {
-                &envelope.date.with_timezone(USER_TZ).date_naive() < date
-            }
-            SearchEmailsFilterQuery::AfterDate(date) => {
-                &envelope.date.with_timezone(USER_TZ).date_naive() > date
-            }
-            SearchEmailsFilterQuery::From(pattern) => {
-                let pattern = pattern.as_bytes();
-                if let Some(name) = &envelope.from.name {
-                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
-                        return true;
+        self.matches_envelope_with_body(envelope, &|pattern| match fs::read(msg_path) {
+            Ok(contents) => {
+                if let Some(msg) = MessageParser::new().parse(&contents) {
+                    for plain in msg.text_bodies() {
+                        if contains_ignore_ascii_case(plain.contents(), pattern.as_bytes()) {
+                            return true;
+                        }
                     }
-                }
-                contains_ignore_ascii_case(envelope.from.addr.as_bytes(), pattern)
-            }
-            SearchEmailsFilterQuery::To(pattern) => {
-                let pattern = pattern.as_bytes();
-                if let Some(name) = &envelope.to.name {
-                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
-                        return true;
+                    for html in msg.html_bodies() {
+                        if contains_ignore_ascii_case(html.contents(), pattern.as_bytes()) {
+                            return true;
+                        }
                     }
                 }
-                contains_ignore_ascii_case(envelope.to.addr.as_bytes(), pattern)
+                false
             }
-            SearchEmailsFilterQuery::Subject(pattern) => {
-                contains_ignore_ascii_case(envelope.subject.as_bytes(), pattern.as_bytes())
+            Err(_err) => {
+                warn!("cannot find message at {msg_path:?}, skipping body filter");
+                trace!("{_err:?}");
+                true
             }
-            SearchEmailsFilterQuery::Body(pattern) => match fs::read(msg_path) {
-                Ok(contents) => {
-                    if let Some(msg) = MessageParser::new().parse(&contents) {
-                        for plain in msg.text_bodies() {
-                            if contains_ignore_ascii_case(plain.contents(), pattern.as_bytes()) {
-                                return true;
-                            }
-                        }
-                        for html in msg.html_bodies() {
-                            if contains_ignore_ascii_case(html.contents(), pattern.as_bytes()) {
-                                return true;
-                            }
-                        }
-                    }
-                    false
-                }
-                Err(_err) => {
-                    warn!("cannot find message at {msg_path:?}, skipping body filter");
-                    trace!("{_err:?}");
-                    true
-                }
-            },
-            SearchEmailsFilterQuery::Flag(flag) => envelope.flags.contains(flag),
-        }
+        })
     }
 }
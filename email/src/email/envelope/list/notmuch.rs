@@ -151,6 +151,14 @@ pub fn to_notmuch_search_query(&self) -> String {
                 query.push_str(&date.to_string());
                 query.push_str("..");
             }
+            SearchEmailsFilterQuery::BeforeRelativeDate(date) => {
+                let filter = SearchEmailsFilterQuery::BeforeDate(date.to_naive_date());
+                query.push_str(&filter.to_notmuch_search_query());
+            }
+            SearchEmailsFilterQuery::AfterRelativeDate(date) => {
+                let filter = SearchEmailsFilterQuery::AfterDate(date.to_naive_date());
+                query.push_str(&filter.to_notmuch_search_query());
+            }
             SearchEmailsFilterQuery::From(pattern) => {
                 query.push_str("from:/");
                 query.push_str(pattern);
@@ -43,7 +43,9 @@ impl TryFrom<MaildirEntry> for Envelope {
 
     fn try_from(entry: MaildirEntry) -> Result<Self> {
         let id = entry.id()?.to_owned();
-        let msg = Message::from(entry.read()?);
+        let bytes = entry.read()?;
+        let size = bytes.len();
+        let msg = Message::from(bytes);
 
         let has_attachment = {
             let attachments = msg.attachments();
@@ -57,6 +59,7 @@ fn try_from(entry: MaildirEntry) -> Result<Self> {
         let flags = Flags::try_from(entry)?;
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.size = size;
         Ok(env)
     }
 }
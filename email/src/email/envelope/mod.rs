@@ -52,6 +52,11 @@
 /// [flags](self::Flags), and few headers taken from the email
 /// [message](crate::Message).
 #[derive(Clone, Debug, Default, Eq, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub struct Envelope {
     /// The shape of the envelope identifier may vary depending on the backend.
     /// For IMAP backend, it is an stringified auto-incremented integer.
@@ -78,6 +83,24 @@ pub struct Envelope {
     /// An attachment is defined here as a MIME part that is not a
     /// `text/*`.
     pub has_attachment: bool,
+
+    /// The size of the whole message, in bytes.
+    ///
+    /// Backends that cannot cheaply compute the size leave it to 0.
+    pub size: usize,
+
+    /// The Gmail message identifier (`X-GM-MSGID`).
+    ///
+    /// The identifier is the same for all the folders (labels) the
+    /// message belongs to. Only IMAP servers supporting the Gmail
+    /// extension `X-GM-EXT-1` expose it.
+    pub gmail_message_id: Option<u64>,
+
+    /// The Gmail labels of the message (`X-GM-LABELS`).
+    ///
+    /// Only IMAP servers supporting the Gmail extension `X-GM-EXT-1`
+    /// expose them.
+    pub gmail_labels: Vec<String>,
 }
 
 impl Envelope {
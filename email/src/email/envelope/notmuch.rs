@@ -21,6 +21,9 @@ pub fn from_notmuch_msg(msg: notmuch::Message) -> Self {
         let id = msg.id();
         let flags = Flags::from(&msg);
         let has_attachment = flags.contains(&Flag::custom("attachment"));
+        let size = std::fs::metadata(msg.filename())
+            .map(|meta| meta.len() as usize)
+            .unwrap_or_default();
 
         let message_id = get_header(&msg, "Message-ID");
         let subject = get_header(&msg, "Subject");
@@ -34,6 +37,7 @@ pub fn from_notmuch_msg(msg: notmuch::Message) -> Self {
 
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.size = size;
         env
     }
 }
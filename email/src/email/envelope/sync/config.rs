@@ -1,6 +1,6 @@
 use chrono::NaiveDate;
 
-use crate::search_query::filter::SearchEmailsFilterQuery;
+use crate::{envelope::Envelope, search_query::filter::SearchEmailsFilterQuery};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -26,6 +26,16 @@ pub struct EnvelopeSyncFilters {
 
     /// Filter envelopes with a `Date` header older than the given date.
     pub after: Option<NaiveDate>,
+
+    /// Filter envelopes matching the given search emails filter
+    /// query, for example `not flag deleted and not from noreply@`.
+    ///
+    /// The query is combined with the date filters above using the
+    /// `and` operator. Filters restrict the envelopes being
+    /// synchronized, but an envelope that stops matching them (for
+    /// example `not flag seen` once the message is read) is left
+    /// untouched on both sides: it is never deleted.
+    pub query: Option<SearchEmailsFilterQuery>,
 }
 
 impl EnvelopeSyncFilters {
@@ -64,18 +74,54 @@ pub fn with_before(mut self, date: impl Into<NaiveDate>) -> Self {
         self.set_before(date);
         self
     }
+
+    pub fn set_some_query(&mut self, query: Option<impl Into<SearchEmailsFilterQuery>>) {
+        self.query = query.map(Into::into);
+    }
+
+    pub fn set_query(&mut self, query: impl Into<SearchEmailsFilterQuery>) {
+        self.set_some_query(Some(query));
+    }
+
+    pub fn with_some_query(mut self, query: Option<impl Into<SearchEmailsFilterQuery>>) -> Self {
+        self.set_some_query(query);
+        self
+    }
+
+    pub fn with_query(mut self, query: impl Into<SearchEmailsFilterQuery>) -> Self {
+        self.set_query(query);
+        self
+    }
+
+    /// Check if the given envelope matches the filters.
+    ///
+    /// Filters are sent to backends when listing envelopes, but not
+    /// all backends are able to evaluate them. This function is used
+    /// to filter envelopes locally afterwards.
+    pub fn matches(&self, envelope: &Envelope) -> bool {
+        let before = self.before.map(SearchEmailsFilterQuery::BeforeDate);
+        let after = self.after.map(SearchEmailsFilterQuery::AfterDate);
+
+        [before.as_ref(), after.as_ref(), self.query.as_ref()]
+            .into_iter()
+            .flatten()
+            .all(|filter| filter.matches_envelope(envelope))
+    }
+
+    /// Return `true` if no filter is defined.
+    pub fn is_empty(&self) -> bool {
+        self.before.is_none() && self.after.is_none() && self.query.is_none()
+    }
 }
 
 impl From<EnvelopeSyncFilters> for Option<SearchEmailsFilterQuery> {
     fn from(f: EnvelopeSyncFilters) -> Self {
-        match (f.before, f.after) {
-            (None, None) => None,
-            (Some(before), None) => Some(SearchEmailsFilterQuery::BeforeDate(before)),
-            (None, Some(after)) => Some(SearchEmailsFilterQuery::AfterDate(after)),
-            (Some(before), Some(after)) => Some(SearchEmailsFilterQuery::And(
-                Box::new(SearchEmailsFilterQuery::BeforeDate(before)),
-                Box::new(SearchEmailsFilterQuery::AfterDate(after)),
-            )),
-        }
+        let before = f.before.map(SearchEmailsFilterQuery::BeforeDate);
+        let after = f.after.map(SearchEmailsFilterQuery::AfterDate);
+
+        [before, after, f.query]
+            .into_iter()
+            .flatten()
+            .reduce(|left, right| SearchEmailsFilterQuery::And(Box::new(left), Box::new(right)))
     }
 }
@@ -1,3 +1,4 @@
+use super::event::WatchEnvelopeEvent;
 use crate::watch::config::WatchHook;
 
 /// Configuration dedicated to envelope changes.
@@ -12,6 +13,75 @@ pub struct WatchEnvelopeConfig {
     /// received.
     pub received: Option<WatchHook>,
 
-    /// Watch hook configuration hook for any other case.
+    /// Watch hook configuration for when the flags of an envelope
+    /// changed.
+    pub flags_changed: Option<WatchHook>,
+
+    /// Watch hook configuration for when an envelope has been
+    /// expunged or deleted.
+    pub deleted: Option<WatchHook>,
+
+    /// Watch hook configuration for when an envelope has been moved.
+    pub moved: Option<WatchHook>,
+
+    /// Watch hook configuration for any other case: events that do
+    /// not have a dedicated hook configured.
     pub any: Option<WatchHook>,
 }
+
+impl WatchEnvelopeConfig {
+    /// Return the hook matching the given event.
+    ///
+    /// Falls back to the [`WatchEnvelopeConfig::any`] hook when the
+    /// event has no dedicated hook.
+    pub fn find_hook(&self, event: &WatchEnvelopeEvent) -> Option<&WatchHook> {
+        let hook = match event {
+            WatchEnvelopeEvent::Received(_) => self.received.as_ref(),
+            WatchEnvelopeEvent::FlagsChanged(_, _) => self.flags_changed.as_ref(),
+            WatchEnvelopeEvent::Deleted(_) => self.deleted.as_ref(),
+            WatchEnvelopeEvent::Moved(_, _) => self.moved.as_ref(),
+        };
+
+        hook.or(self.any.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchEnvelopeConfig;
+    use crate::{
+        envelope::{watch::event::WatchEnvelopeEvent, Envelope},
+        watch::config::{WatchHook, WatchNotifyConfig},
+    };
+
+    fn hook(summary: &str) -> Option<WatchHook> {
+        Some(WatchHook {
+            cmd: None,
+            notify: Some(WatchNotifyConfig {
+                summary: summary.into(),
+                ..Default::default()
+            }),
+            webhook: None,
+            json_lines: None,
+            callback: None,
+        })
+    }
+
+    #[test]
+    fn find_hook_falls_back_to_any() {
+        let config = WatchEnvelopeConfig {
+            received: hook("received"),
+            any: hook("any"),
+            ..Default::default()
+        };
+
+        let received = WatchEnvelopeEvent::Received(Envelope::default());
+        assert_eq!(config.find_hook(&received), hook("received").as_ref());
+
+        let deleted = WatchEnvelopeEvent::Deleted(Envelope::default());
+        assert_eq!(config.find_hook(&deleted), hook("any").as_ref());
+
+        let config = WatchEnvelopeConfig::default();
+        assert_eq!(config.find_hook(&deleted), None);
+    }
+}
@@ -0,0 +1,286 @@
+//! # Watch envelope event
+//!
+//! Module dedicated to envelope watch events. The main structure of
+//! this module is [`WatchEnvelopeEvent`], which represents a change
+//! detected between two states of a watched folder.
+
+use std::{collections::HashMap, fmt};
+
+use serde_json::{json, Value};
+
+use crate::{envelope::Envelope, flag::Flags};
+
+/// The envelope watch event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WatchEnvelopeEvent {
+    /// The given envelope has been received.
+    Received(Envelope),
+
+    /// The flags of the given envelope changed.
+    FlagsChanged(Envelope, WatchFlagsDiff),
+
+    /// The given envelope has been expunged or deleted.
+    Deleted(Envelope),
+
+    /// The first envelope has been moved to the second one.
+    ///
+    /// A move is detected when an envelope disappears and another
+    /// one with the same Message-ID appears at the same time, for
+    /// example when the IMAP UID of a message changes. Moves between
+    /// folders watched at the same time are detected as well, see
+    /// [`super::moves`].
+    Moved(Envelope, Envelope),
+}
+
+impl WatchEnvelopeEvent {
+    /// Build the list of events that occurred between the previous
+    /// and the next envelopes of a folder, indexed by envelope id.
+    ///
+    /// Events are ordered by envelope id, moves first.
+    pub fn diff(
+        prev_envelopes: &HashMap<String, Envelope>,
+        next_envelopes: &HashMap<String, Envelope>,
+    ) -> Vec<Self> {
+        let mut events = Vec::new();
+
+        let mut removed: Vec<_> = prev_envelopes
+            .values()
+            .filter(|envelope| !next_envelopes.contains_key(&envelope.id))
+            .collect();
+        removed.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut added: Vec<_> = next_envelopes
+            .values()
+            .filter(|envelope| !prev_envelopes.contains_key(&envelope.id))
+            .collect();
+        added.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut kept: Vec<_> = next_envelopes
+            .values()
+            .filter_map(|next| Some((prev_envelopes.get(&next.id)?, next)))
+            .collect();
+
+        removed.retain(|prev| {
+            let moved = added
+                .iter()
+                .position(|next| !prev.message_id.is_empty() && prev.message_id == next.message_id);
+
+            match moved {
+                Some(pos) => {
+                    let next = added.remove(pos);
+                    events.push(Self::Moved((*prev).clone(), next.clone()));
+                    kept.push((*prev, next));
+                    false
+                }
+                None => true,
+            }
+        });
+
+        kept.sort_by(|(_, a), (_, b)| a.id.cmp(&b.id));
+
+        for (prev, next) in kept {
+            let diff = WatchFlagsDiff::new(&prev.flags, &next.flags);
+
+            if !diff.is_empty() {
+                events.push(Self::FlagsChanged(next.clone(), diff));
+            }
+        }
+
+        for envelope in added {
+            events.push(Self::Received(envelope.clone()));
+        }
+
+        for envelope in removed {
+            events.push(Self::Deleted(envelope.clone()));
+        }
+
+        events
+    }
+
+    /// Return the envelope concerned by the event.
+    ///
+    /// For moves, this is the envelope after the move.
+    pub fn envelope(&self) -> &Envelope {
+        match self {
+            Self::Received(envelope) => envelope,
+            Self::FlagsChanged(envelope, _) => envelope,
+            Self::Deleted(envelope) => envelope,
+            Self::Moved(_, envelope) => envelope,
+        }
+    }
+
+    /// Return the flags diff of the event, if any.
+    pub fn flags_diff(&self) -> Option<&WatchFlagsDiff> {
+        match self {
+            Self::FlagsChanged(_, diff) => Some(diff),
+            _ => None,
+        }
+    }
+
+    /// Build the JSON representation of the event, as sent by the
+    /// webhook and the JSON lines hooks.
+    pub fn to_json(&self, account: &str) -> Value {
+        let mut event = json!({
+            "account": account,
+            "event": self.to_string(),
+            "envelope": envelope_to_json(self.envelope()),
+        });
+
+        match self {
+            Self::FlagsChanged(_, diff) => {
+                event["flags-added"] = flags_to_json(&diff.added);
+                event["flags-removed"] = flags_to_json(&diff.removed);
+            }
+            Self::Moved(prev, _) => {
+                event["previous-envelope"] = envelope_to_json(prev);
+            }
+            _ => (),
+        }
+
+        event
+    }
+}
+
+fn envelope_to_json(envelope: &Envelope) -> Value {
+    json!({
+        "id": envelope.id,
+        "message-id": envelope.message_id,
+        "in-reply-to": envelope.in_reply_to,
+        "flags": flags_to_json(&envelope.flags),
+        "from": {
+            "name": envelope.from.name,
+            "address": envelope.from.addr,
+        },
+        "to": {
+            "name": envelope.to.name,
+            "address": envelope.to.addr,
+        },
+        "subject": envelope.subject,
+        "date": envelope.date.to_rfc3339(),
+        "has-attachment": envelope.has_attachment,
+        "size": envelope.size,
+    })
+}
+
+fn flags_to_json(flags: &Flags) -> Value {
+    Value::from(Vec::<String>::from(flags.clone()))
+}
+
+impl fmt::Display for WatchEnvelopeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Received(_) => write!(f, "received"),
+            Self::FlagsChanged(_, _) => write!(f, "flags-changed"),
+            Self::Deleted(_) => write!(f, "deleted"),
+            Self::Moved(_, _) => write!(f, "moved"),
+        }
+    }
+}
+
+/// The flags diff of an envelope.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WatchFlagsDiff {
+    /// The flags that have been added.
+    pub added: Flags,
+
+    /// The flags that have been removed.
+    pub removed: Flags,
+}
+
+impl WatchFlagsDiff {
+    /// Build the diff between the previous and the next flags.
+    pub fn new(prev: &Flags, next: &Flags) -> Self {
+        Self {
+            added: next.difference(prev).cloned().collect(),
+            removed: prev.difference(next).cloned().collect(),
+        }
+    }
+
+    /// Return `true` if no flag changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{WatchEnvelopeEvent, WatchFlagsDiff};
+    use crate::{envelope::Envelope, flag::Flags};
+
+    fn envelope(id: &str, message_id: &str, flags: &str) -> Envelope {
+        Envelope {
+            id: id.into(),
+            message_id: message_id.into(),
+            flags: Flags::from(flags),
+            ..Default::default()
+        }
+    }
+
+    fn envelopes(envelopes: impl IntoIterator<Item = Envelope>) -> HashMap<String, Envelope> {
+        HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)))
+    }
+
+    #[test]
+    fn diff() {
+        let prev = envelopes([
+            envelope("1", "<a@localhost>", ""),
+            envelope("2", "<b@localhost>", "seen"),
+            envelope("3", "<c@localhost>", ""),
+            envelope("4", "<d@localhost>", ""),
+        ]);
+
+        let next = envelopes([
+            envelope("1", "<a@localhost>", "seen flagged"),
+            envelope("2", "<b@localhost>", "seen"),
+            envelope("5", "<d@localhost>", "seen"),
+            envelope("6", "<e@localhost>", ""),
+        ]);
+
+        let events = WatchEnvelopeEvent::diff(&prev, &next);
+
+        assert_eq!(
+            events,
+            vec![
+                WatchEnvelopeEvent::Moved(
+                    envelope("4", "<d@localhost>", ""),
+                    envelope("5", "<d@localhost>", "seen"),
+                ),
+                WatchEnvelopeEvent::FlagsChanged(
+                    envelope("1", "<a@localhost>", "seen flagged"),
+                    WatchFlagsDiff {
+                        added: Flags::from("seen flagged"),
+                        removed: Flags::default(),
+                    },
+                ),
+                WatchEnvelopeEvent::FlagsChanged(
+                    envelope("5", "<d@localhost>", "seen"),
+                    WatchFlagsDiff {
+                        added: Flags::from("seen"),
+                        removed: Flags::default(),
+                    },
+                ),
+                WatchEnvelopeEvent::Received(envelope("6", "<e@localhost>", "")),
+                WatchEnvelopeEvent::Deleted(envelope("3", "<c@localhost>", "")),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_json() {
+        let event = WatchEnvelopeEvent::FlagsChanged(
+            envelope("1", "<a@localhost>", "seen flagged"),
+            WatchFlagsDiff::new(&Flags::from("flagged"), &Flags::from("seen flagged")),
+        );
+
+        let json = event.to_json("account");
+
+        assert_eq!(json["account"], "account");
+        assert_eq!(json["event"], "flags-changed");
+        assert_eq!(json["envelope"]["id"], "1");
+        assert_eq!(json["envelope"]["message-id"], "<a@localhost>");
+        assert_eq!(json["flags-added"], serde_json::json!(["seen"]));
+        assert_eq!(json["flags-removed"], serde_json::json!([]));
+    }
+}
@@ -4,7 +4,7 @@
 use tokio::sync::oneshot::{Receiver, Sender};
 use utf7_imap::encode_utf7_imap as encode_utf7;
 
-use super::WatchEnvelopes;
+use super::{moves::WatchedFolder, WatchEnvelopes};
 use crate::{debug, envelope::Envelope, imap::ImapContext, info, AnyResult};
 
 #[derive(Clone, Debug)]
@@ -53,6 +53,7 @@ pub async fn watch_envelopes_loop(
 
         let mut envelopes: HashMap<String, Envelope> =
             HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
+        let watched = WatchedFolder::register(config, &folder, envelopes.values());
 
         loop {
             client.idle(wait_for_shutdown_request).await?;
@@ -61,7 +62,8 @@ pub async fn watch_envelopes_loop(
             let next_envelopes: HashMap<String, Envelope> =
                 HashMap::from_iter(next_envelopes.into_iter().map(|e| (e.id.clone(), e)));
 
-            self.exec_hooks(config, &envelopes, &next_envelopes).await;
+            self.exec_folder_hooks(config, &watched, &envelopes, &next_envelopes)
+                .await;
 
             envelopes = next_envelopes;
         }
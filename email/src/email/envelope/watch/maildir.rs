@@ -4,7 +4,7 @@
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::sync::oneshot::{Receiver, Sender};
 
-use super::WatchEnvelopes;
+use super::{moves::WatchedFolder, WatchEnvelopes};
 use crate::{
     debug,
     email::error::Error,
@@ -50,6 +50,7 @@ async fn watch_envelopes(
         let envelopes = Envelopes::from_mdir_entries(entries, None);
         let mut envelopes: HashMap<String, Envelope> =
             HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
+        let watched = WatchedFolder::register(config, folder, envelopes.values());
 
         let (tx, rx) = mpsc::channel();
         let mut watcher =
@@ -69,7 +70,8 @@ async fn watch_envelopes(
                     let next_envelopes: HashMap<String, Envelope> =
                         HashMap::from_iter(next_envelopes.into_iter().map(|e| (e.id.clone(), e)));
 
-                    self.exec_hooks(config, &envelopes, &next_envelopes).await;
+                    self.exec_folder_hooks(config, &watched, &envelopes, &next_envelopes)
+                        .await;
 
                     envelopes = next_envelopes;
                 }
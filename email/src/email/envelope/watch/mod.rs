@@ -1,14 +1,17 @@
 pub mod config;
+pub mod event;
 #[cfg(feature = "imap")]
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+pub mod moves;
 
 use std::collections::HashMap;
 
 use async_trait::async_trait;
 use tokio::sync::oneshot::{Receiver, Sender};
 
+use self::{event::WatchEnvelopeEvent, moves::WatchedFolder};
 use crate::{account::config::AccountConfig, debug, envelope::Envelope, AnyResult};
 
 #[async_trait]
@@ -21,6 +24,11 @@ async fn watch_envelopes(
         shutdown: Sender<()>,
     ) -> AnyResult<()>;
 
+    /// Execute hooks of the events that occurred between the given
+    /// previous and next envelopes.
+    ///
+    /// Moves cannot be detected without knowing the folder the
+    /// envelopes belong to, see [`WatchEnvelopes::exec_folder_hooks`].
     async fn exec_hooks(
         &self,
         config: &AccountConfig,
@@ -28,16 +36,25 @@ async fn exec_hooks(
         next_envelopes: &HashMap<String, Envelope>,
     ) {
         debug!("executing watch hooks…");
-        for (id, envelope) in next_envelopes {
-            // a new envelope has been added
-            if !prev_envelopes.contains_key(id) {
-                debug!("processing received envelope event…");
-                config.exec_received_envelope_hook(envelope).await;
-            } else {
-                // TODO
-                // debug!("processing any envelope event…");
-                // config.exec_any_envelope_hook(envelope).await;
-            }
+        for event in WatchEnvelopeEvent::diff(prev_envelopes, next_envelopes) {
+            debug!("processing {event} envelope event…");
+            config.exec_envelope_event_hook(&event).await;
         }
     }
+
+    /// Execute hooks of the events that occurred between the given
+    /// previous and next envelopes of the given watched folder,
+    /// detecting moves across watched folders.
+    async fn exec_folder_hooks(
+        &self,
+        config: &AccountConfig,
+        folder: &WatchedFolder,
+        prev_envelopes: &HashMap<String, Envelope>,
+        next_envelopes: &HashMap<String, Envelope>,
+    ) {
+        debug!("executing watch hooks…");
+        folder
+            .exec_hooks(config, prev_envelopes, next_envelopes)
+            .await
+    }
 }
@@ -0,0 +1,341 @@
+//! # Watch envelope moves
+//!
+//! Module dedicated to the detection of envelopes moved between
+//! watched folders. The main structure of this module is
+//! [`WatchedFolder`], which registers a folder being watched.
+//!
+//! A watcher only sees its own folder: an envelope moved to another
+//! folder looks like a deletion from the source folder watcher, and
+//! like a reception from the target folder watcher. When several
+//! folders of the same account are watched, such events are held
+//! back for [`MOVE_DETECTION_DELAY`], so that a deletion and a
+//! reception of the same Message-ID in two different folders can be
+//! merged into a single [`WatchEnvelopeEvent::Moved`] event.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+
+use super::event::WatchEnvelopeEvent;
+use crate::{account::config::AccountConfig, debug, envelope::Envelope};
+
+/// The time a deletion or a reception is held back, waiting for its
+/// counterpart from another watched folder.
+pub const MOVE_DETECTION_DELAY: Duration = Duration::from_secs(5);
+
+/// The watched folders, indexed by account name.
+static ACCOUNTS: Lazy<Mutex<HashMap<String, WatchedAccount>>> = Lazy::new(Default::default);
+
+/// The generator of pending event identifiers.
+static NEXT_PENDING_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The watched folders of an account.
+#[derive(Default)]
+struct WatchedAccount {
+    /// The Message-IDs of each watched folder.
+    folders: HashMap<String, HashSet<String>>,
+
+    /// The events held back, indexed by Message-ID.
+    pending: HashMap<String, PendingEvent>,
+}
+
+/// An event held back, waiting for its counterpart.
+struct PendingEvent {
+    id: u64,
+    folder: String,
+    event: WatchEnvelopeEvent,
+}
+
+/// A folder being watched.
+///
+/// The folder is unregistered when dropped.
+pub struct WatchedFolder {
+    account: String,
+    folder: String,
+}
+
+impl WatchedFolder {
+    /// Register the given folder of the given account, containing
+    /// the given envelopes.
+    pub fn register<'a>(
+        config: &AccountConfig,
+        folder: impl ToString,
+        envelopes: impl IntoIterator<Item = &'a Envelope>,
+    ) -> Self {
+        let account = config.name.clone();
+        let folder = folder.to_string();
+
+        if let Ok(mut accounts) = ACCOUNTS.lock() {
+            let ids = envelopes.into_iter().map(|e| e.message_id.clone());
+            let watched = accounts.entry(account.clone()).or_default();
+            watched
+                .folders
+                .insert(folder.clone(), HashSet::from_iter(ids));
+        }
+
+        Self { account, folder }
+    }
+
+    /// Execute hooks of the events that occurred between the previous
+    /// and the next envelopes of the folder.
+    ///
+    /// Deletions and receptions that may be part of a move are held
+    /// back, see the [module documentation](self).
+    pub async fn exec_hooks(
+        &self,
+        config: &AccountConfig,
+        prev_envelopes: &HashMap<String, Envelope>,
+        next_envelopes: &HashMap<String, Envelope>,
+    ) {
+        let events = WatchEnvelopeEvent::diff(prev_envelopes, next_envelopes);
+        let ids = next_envelopes.values().map(|e| e.message_id.clone());
+        let events = self.detect_moves(events, HashSet::from_iter(ids));
+
+        for event in events {
+            match event {
+                Dispatch::Now(event) => {
+                    debug!("processing {event} envelope event…");
+                    config.exec_envelope_event_hook(&event).await;
+                }
+                Dispatch::Later(message_id, id) => {
+                    let config = config.clone();
+                    let account = self.account.clone();
+
+                    tokio::spawn(async move {
+                        tokio::time::sleep(MOVE_DETECTION_DELAY).await;
+
+                        if let Some(event) = take_pending(&account, &message_id, id) {
+                            debug!("processing {event} envelope event…");
+                            config.exec_envelope_event_hook(&event).await;
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Merge the given events with events held back by other watched
+    /// folders, then update the Message-IDs of the folder.
+    fn detect_moves(
+        &self,
+        events: Vec<WatchEnvelopeEvent>,
+        next_ids: HashSet<String>,
+    ) -> Vec<Dispatch> {
+        let Ok(mut accounts) = ACCOUNTS.lock() else {
+            return events.into_iter().map(Dispatch::Now).collect();
+        };
+
+        let watched = accounts.entry(self.account.clone()).or_default();
+        let mut dispatches = Vec::new();
+
+        for event in events {
+            let message_id = event.envelope().message_id.clone();
+
+            let movable = matches!(
+                event,
+                WatchEnvelopeEvent::Received(_) | WatchEnvelopeEvent::Deleted(_)
+            );
+
+            if message_id.is_empty() || !movable {
+                dispatches.push(Dispatch::Now(event));
+                continue;
+            }
+
+            let counterpart = watched.pending.remove(&message_id);
+
+            match (counterpart, event) {
+                (Some(pending), event) if pending.folder != self.folder => {
+                    match (pending.event, event) {
+                        (WatchEnvelopeEvent::Deleted(prev), WatchEnvelopeEvent::Received(next))
+                        | (WatchEnvelopeEvent::Received(next), WatchEnvelopeEvent::Deleted(prev)) =>
+                        {
+                            dispatches.push(Dispatch::Now(WatchEnvelopeEvent::Moved(prev, next)));
+                        }
+                        (pending_event, event) => {
+                            dispatches.push(Dispatch::Now(pending_event));
+                            dispatches.push(Dispatch::Now(event));
+                        }
+                    }
+                }
+                (pending, event) => {
+                    if let Some(pending) = pending {
+                        watched.pending.insert(message_id.clone(), pending);
+                    }
+
+                    // NOTE: a reception can only be part of a move if
+                    // another watched folder owns the message, and a
+                    // deletion only if another folder is watched
+                    let others = watched.folders.iter().filter(|(f, _)| **f != self.folder);
+                    let hold = match &event {
+                        WatchEnvelopeEvent::Received(_) => {
+                            others.clone().any(|(_, ids)| ids.contains(&message_id))
+                        }
+                        _ => others.clone().next().is_some(),
+                    };
+
+                    if hold && !watched.pending.contains_key(&message_id) {
+                        let id = NEXT_PENDING_ID.fetch_add(1, Ordering::SeqCst);
+                        let folder = self.folder.clone();
+                        let pending = PendingEvent { id, folder, event };
+                        watched.pending.insert(message_id.clone(), pending);
+                        dispatches.push(Dispatch::Later(message_id, id));
+                    } else {
+                        dispatches.push(Dispatch::Now(event));
+                    }
+                }
+            }
+        }
+
+        watched.folders.insert(self.folder.clone(), next_ids);
+        dispatches
+    }
+}
+
+impl Drop for WatchedFolder {
+    fn drop(&mut self) {
+        if let Ok(mut accounts) = ACCOUNTS.lock() {
+            if let Some(watched) = accounts.get_mut(&self.account) {
+                watched.folders.remove(&self.folder);
+            }
+        }
+    }
+}
+
+/// The way an event is dispatched.
+#[derive(Debug, Eq, PartialEq)]
+enum Dispatch {
+    /// The event is processed straight away.
+    Now(WatchEnvelopeEvent),
+
+    /// The event matching the given Message-ID and pending id is
+    /// processed after [`MOVE_DETECTION_DELAY`], unless it has been
+    /// merged into a move in the meantime.
+    Later(String, u64),
+}
+
+/// Take the given pending event, if it has not been merged into a
+/// move yet.
+fn take_pending(account: &str, message_id: &str, id: u64) -> Option<WatchEnvelopeEvent> {
+    let mut accounts = ACCOUNTS.lock().ok()?;
+    let watched = accounts.get_mut(account)?;
+
+    match watched.pending.get(message_id) {
+        Some(pending) if pending.id == id => Some(watched.pending.remove(message_id)?.event),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{Dispatch, WatchedFolder};
+    use crate::{
+        account::config::AccountConfig,
+        envelope::{watch::event::WatchEnvelopeEvent, Envelope},
+    };
+
+    fn envelope(id: &str, message_id: &str) -> Envelope {
+        Envelope {
+            id: id.into(),
+            message_id: message_id.into(),
+            ..Default::default()
+        }
+    }
+
+    fn config(name: &str) -> AccountConfig {
+        AccountConfig {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn single_folder() {
+        let config = config("single-folder");
+        let inbox = WatchedFolder::register(&config, "INBOX", [&envelope("1", "<a@localhost>")]);
+
+        let dispatches = inbox.detect_moves(
+            vec![WatchEnvelopeEvent::Deleted(envelope("1", "<a@localhost>"))],
+            ids(&[]),
+        );
+
+        assert_eq!(
+            dispatches,
+            vec![Dispatch::Now(WatchEnvelopeEvent::Deleted(envelope(
+                "1",
+                "<a@localhost>"
+            )))]
+        );
+    }
+
+    #[test]
+    fn deleted_then_received() {
+        let config = config("deleted-then-received");
+        let inbox = WatchedFolder::register(&config, "INBOX", [&envelope("1", "<a@localhost>")]);
+        let archives = WatchedFolder::register(&config, "Archives", []);
+
+        let dispatches = inbox.detect_moves(
+            vec![WatchEnvelopeEvent::Deleted(envelope("1", "<a@localhost>"))],
+            ids(&[]),
+        );
+        assert!(matches!(dispatches[..], [Dispatch::Later(_, _)]));
+
+        let dispatches = archives.detect_moves(
+            vec![WatchEnvelopeEvent::Received(envelope("7", "<a@localhost>"))],
+            ids(&["<a@localhost>"]),
+        );
+        assert_eq!(
+            dispatches,
+            vec![Dispatch::Now(WatchEnvelopeEvent::Moved(
+                envelope("1", "<a@localhost>"),
+                envelope("7", "<a@localhost>"),
+            ))]
+        );
+    }
+
+    #[test]
+    fn received_then_deleted() {
+        let config = config("received-then-deleted");
+        let inbox = WatchedFolder::register(&config, "INBOX", [&envelope("1", "<a@localhost>")]);
+        let archives = WatchedFolder::register(&config, "Archives", []);
+
+        let dispatches = archives.detect_moves(
+            vec![
+                WatchEnvelopeEvent::Received(envelope("7", "<a@localhost>")),
+                WatchEnvelopeEvent::Received(envelope("8", "<b@localhost>")),
+            ],
+            ids(&["<a@localhost>", "<b@localhost>"]),
+        );
+        assert!(matches!(
+            dispatches[..],
+            [
+                Dispatch::Later(_, _),
+                Dispatch::Now(WatchEnvelopeEvent::Received(_))
+            ]
+        ));
+
+        let dispatches = inbox.detect_moves(
+            vec![WatchEnvelopeEvent::Deleted(envelope("1", "<a@localhost>"))],
+            ids(&[]),
+        );
+        assert_eq!(
+            dispatches,
+            vec![Dispatch::Now(WatchEnvelopeEvent::Moved(
+                envelope("1", "<a@localhost>"),
+                envelope("7", "<a@localhost>"),
+            ))]
+        );
+    }
+}
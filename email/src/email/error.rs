@@ -44,6 +44,10 @@ pub enum Error {
     GetMaildirFlagsError(#[source] maildirs::Error, PathBuf),
     #[error("cannot find message associated to envelope {0}")]
     FindMessageError(String),
+    #[error("cannot find full message of placeholder {1} from folder {0}")]
+    FindPlaceholderMessageError(String, String),
+    #[error("cannot copy placeholder {1} from folder {0}")]
+    CopyPlaceholderError(String, String),
     #[error("cannot parse search emails query `{1}`")]
     ParseError(Vec<Rich<'static, char>>, String),
     #[error("cannot interpret message as template")]
@@ -175,6 +179,8 @@ pub enum Error {
     ListRightEnvelopesCachedError(#[source] AnyBoxedError),
     #[error("cannot list envelopes from right sync backend")]
     ListRightEnvelopesError(#[source] AnyBoxedError),
+    #[error("cannot build email patch of folder {0}")]
+    BuildEmailPatchError(String, #[source] AnyBoxedError),
 
     #[cfg(feature = "maildir")]
     #[error(transparent)]
@@ -1,7 +1,13 @@
+use std::fs;
+
 use async_trait::async_trait;
+use maildirs::{Maildir, MaildirEntry};
 
 use super::CopyMessages;
-use crate::{email::error::Error, envelope::Id, info, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    account::config::AccountConfig, debug, email::error::Error, envelope::Id, info,
+    maildir::MaildirContextSync, AnyResult,
+};
 
 #[derive(Clone)]
 pub struct CopyMaildirMessages {
@@ -30,10 +36,15 @@ async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         let ctx = self.ctx.lock().await;
         let from_mdir = ctx.get_maildir_from_folder_alias(from_folder)?;
         let to_mdir = ctx.get_maildir_from_folder_alias(to_folder)?;
+        let hard_link_copies = is_labels_mode(&ctx.account_config);
 
         id.iter()
             .filter_map(|id| from_mdir.find(id).ok().flatten())
             .try_for_each(|entry| {
+                if hard_link_copies && hard_link(&entry, &to_mdir) {
+                    return Ok(());
+                }
+
                 entry.copy(&to_mdir).map_err(|err| {
                     Error::CopyMessagesMaildirError(
                         err,
@@ -48,3 +59,52 @@ async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         Ok(())
     }
 }
+
+/// Return `true` if the given account synchronizes messages in
+/// labels mode.
+///
+/// The same message then belongs to several folders at once: copies
+/// are hard links, so that each message is stored once on disk.
+/// Maildir messages are never modified in place, so copies can
+/// safely share the same file.
+#[cfg(feature = "sync")]
+fn is_labels_mode(config: &AccountConfig) -> bool {
+    use crate::message::sync::config::MessageSyncMode;
+
+    config
+        .message
+        .as_ref()
+        .and_then(|c| c.sync.as_ref())
+        .map(|c| c.mode == MessageSyncMode::Labels)
+        .unwrap_or_default()
+}
+
+#[cfg(not(feature = "sync"))]
+fn is_labels_mode(_config: &AccountConfig) -> bool {
+    false
+}
+
+/// Hard link the given entry into the given Maildir.
+///
+/// The entry keeps its file name, as well as its subdirectory (`cur`
+/// or `new`). Returns `false` if the link could not be created.
+fn hard_link(entry: &MaildirEntry, mdir: &Maildir) -> bool {
+    let path = entry.path();
+
+    let (Some(name), Some(subdir)) = (
+        path.file_name(),
+        path.parent().and_then(|dir| dir.file_name()),
+    ) else {
+        return false;
+    };
+
+    let link = mdir.path().join(subdir).join(name);
+
+    match fs::hard_link(path, &link) {
+        Ok(()) => true,
+        Err(_err) => {
+            debug!("cannot hard link {path:?} to {link:?}, copying it instead: {_err}");
+            false
+        }
+    }
+}
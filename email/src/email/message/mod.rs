@@ -169,8 +169,7 @@ enum RawMessages {
     Imap(Vec<Vec1<MessageDataItem<'static>>>),
     #[cfg(feature = "maildir")]
     MailEntries(Vec<MaildirEntry>),
-    #[cfg(feature = "notmuch")]
-    Notmuch(Vec<Vec<u8>>),
+    Bytes(Vec<Vec<u8>>),
     #[allow(dead_code)]
     None,
 }
@@ -201,8 +200,7 @@ fn emails_builder<'a>(raw: &'a mut RawMessages) -> Vec<Message<'a>> {
                 .collect(),
             #[cfg(feature = "maildir")]
             RawMessages::MailEntries(entries) => entries.iter_mut().map(Message::from).collect(),
-            #[cfg(feature = "notmuch")]
-            RawMessages::Notmuch(raw) => raw
+            RawMessages::Bytes(raw) => raw
                 .iter()
                 .map(|raw| Message::from(raw.as_slice()))
                 .collect(),
@@ -247,11 +245,10 @@ fn try_from(entries: Vec<MaildirEntry>) -> Result<Self, Error> {
     }
 }
 
-#[cfg(feature = "notmuch")]
 impl From<Vec<Vec<u8>>> for Messages {
     fn from(raw: Vec<Vec<u8>>) -> Self {
         MessagesBuilder {
-            raw: RawMessages::Notmuch(raw),
+            raw: RawMessages::Bytes(raw),
             emails_builder: Messages::emails_builder,
         }
         .build()
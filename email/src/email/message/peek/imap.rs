@@ -3,7 +3,12 @@
 use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{Messages, PeekMessages};
-use crate::{debug, envelope::Id, imap::ImapContext, info, AnyResult};
+use crate::{
+    debug,
+    envelope::Id,
+    imap::{ImapClient, ImapContext, Result},
+    info, AnyResult,
+};
 
 #[derive(Clone, Debug)]
 pub struct PeekImapMessages {
@@ -24,12 +29,10 @@ pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn PeekMessages>> {
     }
 }
 
-#[async_trait]
-impl PeekMessages for PeekImapMessages {
-    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        info!("peeking imap messages {id} from folder {folder}");
-
-        let mut client = self.ctx.client().await;
+impl PeekImapMessages {
+    /// Select the given folder, then return the sequence set matching
+    /// the given ids.
+    async fn select(&self, client: &mut ImapClient, folder: &str, id: &Id) -> Result<SequenceSet> {
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -47,8 +50,30 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         };
 
         client.select_mailbox(&folder_encoded).await?;
+
+        Ok(uids)
+    }
+}
+
+#[async_trait]
+impl PeekMessages for PeekImapMessages {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        info!("peeking imap messages {id} from folder {folder}");
+
+        let mut client = self.ctx.client().await;
+        let uids = self.select(&mut client, folder, id).await?;
         let msgs = client.peek_messages(uids).await?;
 
         Ok(msgs)
     }
+
+    async fn peek_message_headers(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        info!("peeking imap message headers {id} from folder {folder}");
+
+        let mut client = self.ctx.client().await;
+        let uids = self.select(&mut client, folder, id).await?;
+        let msgs = client.peek_message_headers(uids).await?;
+
+        Ok(msgs)
+    }
 }
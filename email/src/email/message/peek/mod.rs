@@ -20,4 +20,14 @@ pub trait PeekMessages: Send + Sync {
     /// automatically added to envelopes, see
     /// [`GetMessages`](super::get::GetMessages).
     async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages>;
+
+    /// Peek the header section of email messages from the given
+    /// folder matching the given ids.
+    ///
+    /// This is useful to avoid downloading big messages when only
+    /// their headers are needed. Backends that cannot retrieve
+    /// headers alone return full messages.
+    async fn peek_message_headers(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        self.peek_messages(folder, id).await
+    }
 }
@@ -7,6 +7,19 @@
 pub struct MessageSyncConfig {
     #[cfg_attr(feature = "derive", serde(default))]
     pub permissions: MessageSyncPermissions,
+
+    /// Limit the size of messages being synchronized.
+    ///
+    /// When omitted, messages are synchronized whatever their size.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub size_limit: Option<MessageSyncSizeLimit>,
+
+    /// How folders relate to messages.
+    ///
+    /// Set it to `labels` for providers exposing labels as folders,
+    /// like Gmail.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub mode: MessageSyncMode,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -47,3 +60,101 @@ fn default() -> Self {
         }
     }
 }
+
+/// The message synchronization size limit.
+///
+/// Messages bigger than the maximum size are not fully copied to the
+/// target side: they are either skipped, or replaced by a lightweight
+/// placeholder, depending on the [oversized
+/// strategy](MessageSyncOversizedStrategy).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct MessageSyncSizeLimit {
+    /// The maximum size of a message, in bytes.
+    pub max_size: usize,
+
+    /// What to do with messages exceeding the maximum size.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub oversized: MessageSyncOversizedStrategy,
+}
+
+impl MessageSyncSizeLimit {
+    pub fn new(max_size: usize, oversized: MessageSyncOversizedStrategy) -> Self {
+        Self {
+            max_size,
+            oversized,
+        }
+    }
+
+    /// Return `true` if the given message size exceeds the limit.
+    ///
+    /// A size of 0 means that the size is unknown, in which case the
+    /// message is never considered oversized.
+    pub fn is_exceeded_by(&self, size: usize) -> bool {
+        size > 0 && size > self.max_size
+    }
+}
+
+/// The strategy applied to messages exceeding the size limit.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum MessageSyncOversizedStrategy {
+    /// Do not synchronize oversized messages at all.
+    #[default]
+    Skip,
+
+    /// Synchronize only the headers of oversized messages.
+    ///
+    /// When the size of a message is known in advance (like with
+    /// IMAP), only its headers are downloaded. Otherwise the full
+    /// message is downloaded, and the placeholder also contains a
+    /// short text preview of its body.
+    ///
+    /// The full message can then be retrieved on demand, see
+    /// [`SyncPlaceholderResolver`](super::SyncPlaceholderResolver).
+    Placeholder,
+}
+
+/// The message synchronization mode.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum MessageSyncMode {
+    /// Every folder contains its own messages.
+    ///
+    /// A message found in several folders is copied once per folder.
+    #[default]
+    Folders,
+
+    /// Folders are labels: the same message can belong to several
+    /// folders at the same time.
+    ///
+    /// Messages are identified across folders by their Gmail message
+    /// identifier (`X-GM-MSGID`) when the IMAP server exposes it,
+    /// otherwise by their Message-ID and their size. Left messages
+    /// inherit the Gmail identifier of the right message sharing
+    /// their Message-ID in the same folder.
+    ///
+    /// When a message needs to be copied to a folder while the
+    /// target side already owns it in another folder, it is copied
+    /// within the target side instead of being transferred again.
+    /// For Gmail, copying a message to a folder adds the matching
+    /// label, and deleting it from a folder removes the label.
+    ///
+    /// Maildir copies are then hard links, so that each message is
+    /// stored only once on disk. Set the mode on the account
+    /// configuration for the Maildir backend to take it into
+    /// account.
+    Labels,
+}
@@ -1 +1,473 @@
+//! # Message synchronization
+//!
+//! Module dedicated to message synchronization. It mostly contains
+//! the configuration, plus helpers around placeholders: lightweight
+//! messages replacing the ones exceeding the synchronization [size
+//! limit](config::MessageSyncSizeLimit).
+
 pub mod config;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mail_parser::MessageParser;
+
+use super::{
+    add::AddMessage, get::GetMessages, peek::PeekMessages, remove::RemoveMessages, Messages,
+};
+use crate::{
+    backend::{context::BackendContextBuilder, BackendBuilder},
+    debug,
+    email::error::Error,
+    envelope::{
+        get::GetEnvelope,
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Id, SingleId,
+    },
+    flag::Flags,
+    folder::sync::config::FolderSyncMapping,
+    sync::SyncDestination,
+    trace, AnyResult,
+};
+
+/// The header containing the identifier of the full message, from
+/// the side it has been synchronized from.
+pub const PLACEHOLDER_ID_HEADER: &str = "X-Sync-Placeholder-Id";
+
+/// The header containing the size of the full message, in bytes.
+pub const PLACEHOLDER_SIZE_HEADER: &str = "X-Sync-Placeholder-Size";
+
+/// The maximum amount of characters kept from the text body of the
+/// full message.
+pub const PLACEHOLDER_PREVIEW_LEN: usize = 1024;
+
+/// Headers describing the structure of the full message. They are
+/// dropped from placeholders since their body is plain text.
+const MIME_HEADERS: [&str; 5] = [
+    "mime-version",
+    "content-type",
+    "content-transfer-encoding",
+    "content-disposition",
+    "content-id",
+];
+
+/// Build a placeholder from the given full message.
+///
+/// The placeholder keeps the headers of the full message, plus two
+/// extra headers pointing to the full message. The body is replaced
+/// by a plain text preview, which is empty when the given message
+/// only contains headers.
+pub fn build_placeholder(id: &str, size: usize, msg: &[u8]) -> Vec<u8> {
+    let mut placeholder = Vec::new();
+
+    for header in split_headers(msg) {
+        let name = header
+            .split(|b| *b == b':')
+            .next()
+            .map(|name| String::from_utf8_lossy(name).trim().to_lowercase())
+            .unwrap_or_default();
+
+        if MIME_HEADERS.contains(&name.as_str()) || name.starts_with("x-sync-placeholder-") {
+            continue;
+        }
+
+        placeholder.extend(header);
+        placeholder.extend(b"\r\n");
+    }
+
+    placeholder.extend(format!("{PLACEHOLDER_ID_HEADER}: {id}\r\n").as_bytes());
+    placeholder.extend(format!("{PLACEHOLDER_SIZE_HEADER}: {size}\r\n").as_bytes());
+    placeholder.extend(b"MIME-Version: 1.0\r\n");
+    placeholder.extend(b"Content-Type: text/plain; charset=utf-8\r\n");
+    placeholder.extend(b"Content-Transfer-Encoding: 8bit\r\n");
+    placeholder.extend(b"\r\n");
+
+    let preview = MessageParser::new()
+        .parse(msg)
+        .and_then(|msg| msg.body_text(0).map(|text| text.into_owned()))
+        .unwrap_or_default();
+
+    for line in preview
+        .chars()
+        .take(PLACEHOLDER_PREVIEW_LEN)
+        .collect::<String>()
+        .lines()
+    {
+        placeholder.extend(line.as_bytes());
+        placeholder.extend(b"\r\n");
+    }
+
+    placeholder
+}
+
+/// Extract the identifier and the size of the full message from the
+/// given placeholder.
+///
+/// Returns `None` if the given message is not a placeholder.
+pub fn parse_placeholder(msg: &[u8]) -> Option<(String, usize)> {
+    let mut id = None;
+    let mut size = None;
+
+    for header in split_headers(msg) {
+        let header = String::from_utf8_lossy(&header);
+        let Some((name, val)) = header.split_once(':') else {
+            continue;
+        };
+
+        if name.trim().eq_ignore_ascii_case(PLACEHOLDER_ID_HEADER) {
+            id = Some(val.trim().to_owned());
+        } else if name.trim().eq_ignore_ascii_case(PLACEHOLDER_SIZE_HEADER) {
+            size = val.trim().parse().ok();
+        }
+    }
+
+    Some((id?, size.unwrap_or_default()))
+}
+
+/// Split the header section of the given message into unfolded
+/// header lines, without line terminators.
+fn split_headers(msg: &[u8]) -> Vec<Vec<u8>> {
+    let mut headers: Vec<Vec<u8>> = Vec::new();
+
+    for line in msg.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if line.is_empty() {
+            break;
+        }
+
+        match headers.last_mut() {
+            Some(header) if line[0] == b' ' || line[0] == b'\t' => {
+                header.extend(b"\r\n");
+                header.extend(line);
+            }
+            _ => headers.push(line.to_vec()),
+        }
+    }
+
+    headers
+}
+
+/// Get messages feature resolving placeholders.
+///
+/// This wrapper gets messages from the local side (the one holding
+/// placeholders). When a message turns out to be a placeholder, the
+/// full message is fetched from the remote side, then replaces the
+/// placeholder on the local side so that next reads do not need the
+/// remote side anymore.
+///
+/// Backends usually get this wrapper from
+/// [`BackendBuilder::with_sync_placeholders`].
+pub struct SyncPlaceholderResolver<L, R: ?Sized> {
+    local: L,
+    remote: Arc<R>,
+    mapping: Option<(FolderSyncMapping, SyncDestination)>,
+}
+
+impl<L, R> SyncPlaceholderResolver<L, R>
+where
+    L: GetMessages + GetEnvelope + AddMessage + RemoveMessages,
+    R: PeekMessages + ListEnvelopes + ?Sized,
+{
+    pub fn new(local: L, remote: Arc<R>) -> Self {
+        Self {
+            local,
+            remote,
+            mapping: None,
+        }
+    }
+
+    /// Map local folder names to remote folder names using the given
+    /// synchronization mapping.
+    ///
+    /// The given destination is the side of the local backend, the
+    /// one holding placeholders.
+    pub fn with_folder_mapping(
+        mut self,
+        mapping: FolderSyncMapping,
+        local: SyncDestination,
+    ) -> Self {
+        self.mapping = Some((mapping, local));
+        self
+    }
+
+    /// Return the remote name of the given local folder.
+    fn remote_folder(&self, folder: &str) -> String {
+        match &self.mapping {
+            None => folder.to_owned(),
+            Some((mapping, SyncDestination::Left)) => mapping.to_right(folder),
+            Some((mapping, SyncDestination::Right)) => mapping.to_left(folder),
+        }
+    }
+
+    /// Fetch the full message of the given placeholder from the
+    /// remote side.
+    ///
+    /// The full message is first searched by identifier, then by
+    /// Message-ID in case the identifier changed on the remote side.
+    async fn fetch_full_message(
+        &self,
+        folder: &str,
+        remote_id: &str,
+        message_id: &str,
+    ) -> AnyResult<Vec<u8>> {
+        let msgs = self
+            .remote
+            .peek_messages(folder, &Id::single(remote_id))
+            .await;
+
+        if let Ok(msgs) = msgs {
+            if let Some(msg) = msgs.first().and_then(|msg| msg.raw().ok()) {
+                // NOTE: identifiers may be reused by some backends,
+                // so the Message-ID needs to match as well
+                let same_message = MessageParser::new()
+                    .parse(msg)
+                    .and_then(|msg| msg.message_id().map(|id| format!("<{id}>")))
+                    .map(|id| id == message_id)
+                    .unwrap_or_default();
+
+                if same_message {
+                    return Ok(msg.to_vec());
+                }
+            }
+        }
+
+        debug!("cannot find placeholder {remote_id} by id, searching it by Message-ID");
+
+        let opts = ListEnvelopesOptions {
+            page: 0,
+            page_size: 0,
+            query: None,
+        };
+
+        let envelope = self
+            .remote
+            .list_envelopes(folder, opts)
+            .await?
+            .into_iter()
+            .find(|envelope| envelope.message_id == message_id)
+            .ok_or_else(|| {
+                Error::FindPlaceholderMessageError(folder.to_owned(), remote_id.to_owned())
+            })?;
+
+        let msgs = self
+            .remote
+            .peek_messages(folder, &Id::single(&envelope.id))
+            .await?;
+
+        let msg = msgs.first().ok_or_else(|| {
+            Error::FindPlaceholderMessageError(folder.to_owned(), remote_id.to_owned())
+        })?;
+
+        Ok(msg.raw()?.to_vec())
+    }
+
+    /// Replace the given local placeholder by its full message.
+    async fn resolve(&self, folder: &str, id: &str, remote_id: &str) -> AnyResult<Vec<u8>> {
+        let envelope = self.local.get_envelope(folder, &SingleId::from(id)).await?;
+        let remote_folder = self.remote_folder(folder);
+        let msg = self
+            .fetch_full_message(&remote_folder, remote_id, &envelope.message_id)
+            .await?;
+
+        self.local
+            .add_message_with_flags(folder, &msg, &envelope.flags)
+            .await?;
+        self.local.remove_messages(folder, &Id::single(id)).await?;
+
+        Ok(msg)
+    }
+}
+
+#[async_trait]
+impl<L, R> GetMessages for SyncPlaceholderResolver<L, R>
+where
+    L: GetMessages + GetEnvelope + AddMessage + RemoveMessages,
+    R: PeekMessages + ListEnvelopes + ?Sized,
+{
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        let mut msgs = Vec::new();
+
+        // NOTE: messages are retrieved one by one, so that each
+        // placeholder can be associated to its local identifier
+        for id in id.iter() {
+            let local_msgs = self.local.get_messages(folder, &Id::single(id)).await?;
+            let raw = match local_msgs.first() {
+                Some(msg) => msg.raw()?.to_vec(),
+                None => continue,
+            };
+
+            match parse_placeholder(&raw) {
+                None => msgs.push(raw),
+                Some((remote_id, _)) => match self.resolve(folder, id, &remote_id).await {
+                    Ok(msg) => msgs.push(msg),
+                    Err(err) => {
+                        debug!("cannot resolve placeholder {id}, returning it as is: {err}");
+                        trace!("{err:?}");
+                        msgs.push(raw);
+                    }
+                },
+            }
+        }
+
+        Ok(Messages::from(msgs))
+    }
+}
+
+/// The local features needed by the [`SyncPlaceholderResolver`],
+/// taken from a backend context.
+struct SyncPlaceholderLocal {
+    get_messages: Box<dyn GetMessages>,
+    get_envelope: Box<dyn GetEnvelope>,
+    add_message: Box<dyn AddMessage>,
+    remove_messages: Box<dyn RemoveMessages>,
+}
+
+#[async_trait]
+impl GetMessages for SyncPlaceholderLocal {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        self.get_messages.get_messages(folder, id).await
+    }
+}
+
+#[async_trait]
+impl GetEnvelope for SyncPlaceholderLocal {
+    async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+        self.get_envelope.get_envelope(folder, id).await
+    }
+}
+
+#[async_trait]
+impl AddMessage for SyncPlaceholderLocal {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        self.add_message
+            .add_message_with_flags(folder, msg, flags)
+            .await
+    }
+}
+
+#[async_trait]
+impl RemoveMessages for SyncPlaceholderLocal {
+    async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        self.remove_messages.remove_messages(folder, id).await
+    }
+}
+
+impl<CB> BackendBuilder<CB>
+where
+    CB: BackendContextBuilder,
+    CB::Context: 'static,
+{
+    /// Resolve synchronization placeholders when getting messages.
+    ///
+    /// The get messages feature of this builder is wrapped by a
+    /// [`SyncPlaceholderResolver`], fetching full messages from the
+    /// given remote backend. The local folder names are mapped to
+    /// remote ones using the folder synchronization mapping of the
+    /// account, this builder being the given side of the
+    /// synchronization.
+    ///
+    /// Messages are returned as they are when one of the get
+    /// messages, get envelope, add message or remove messages
+    /// features is not available.
+    pub fn with_sync_placeholders<R>(mut self, remote: Arc<R>, side: SyncDestination) -> Self
+    where
+        R: PeekMessages + ListEnvelopes + 'static,
+    {
+        let mapping = self
+            .account_config
+            .folder
+            .as_ref()
+            .and_then(|c| c.sync.as_ref())
+            .map(|c| c.mapping.clone())
+            .unwrap_or_default();
+
+        let get_messages = self.get_get_messages();
+        let get_envelope = self.get_get_envelope();
+        let add_message = self.get_add_message();
+        let remove_messages = self.get_remove_messages();
+
+        self.set_get_messages(move |ctx: &CB::Context| {
+            let get_messages = get_messages.as_ref().and_then(|f| f(ctx))?;
+
+            let local = SyncPlaceholderLocal {
+                get_envelope: match get_envelope.as_ref().and_then(|f| f(ctx)) {
+                    Some(f) => f,
+                    None => return Some(get_messages),
+                },
+                add_message: match add_message.as_ref().and_then(|f| f(ctx)) {
+                    Some(f) => f,
+                    None => return Some(get_messages),
+                },
+                remove_messages: match remove_messages.as_ref().and_then(|f| f(ctx)) {
+                    Some(f) => f,
+                    None => return Some(get_messages),
+                },
+                get_messages,
+            };
+
+            let resolver = SyncPlaceholderResolver::new(local, remote.clone())
+                .with_folder_mapping(mapping.clone(), side.clone());
+
+            Some(Box::new(resolver) as Box<dyn GetMessages>)
+        });
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_placeholder, parse_placeholder};
+
+    const MSG: &str = concat!(
+        "Message-ID: <id@localhost>\r\n",
+        "From: alice@localhost\r\n",
+        "To: bob@localhost\r\n",
+        "Subject: Big\r\n",
+        " attachment\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"sep\"\r\n",
+        "\r\n",
+        "--sep\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Hello, world!\r\n",
+        "--sep\r\n",
+        "Content-Type: application/octet-stream\r\n",
+        "Content-Disposition: attachment; filename=big.bin\r\n",
+        "\r\n",
+        "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\r\n",
+        "--sep--\r\n",
+    );
+
+    #[test]
+    fn build_then_parse_placeholder() {
+        let placeholder = build_placeholder("42", MSG.len(), MSG.as_bytes());
+        let placeholder = String::from_utf8(placeholder).unwrap();
+
+        assert!(placeholder.starts_with(concat!(
+            "Message-ID: <id@localhost>\r\n",
+            "From: alice@localhost\r\n",
+            "To: bob@localhost\r\n",
+            "Subject: Big\r\n",
+            " attachment\r\n",
+        )));
+        assert!(placeholder.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(!placeholder.contains("multipart/mixed"));
+        assert!(placeholder.ends_with("\r\n\r\nHello, world!\r\n"));
+
+        let parsed = parse_placeholder(placeholder.as_bytes());
+        assert_eq!(parsed, Some(("42".to_owned(), MSG.len())));
+    }
+
+    #[test]
+    fn parse_regular_message() {
+        assert_eq!(parse_placeholder(MSG.as_bytes()), None);
+    }
+}
@@ -21,8 +21,6 @@
 pub mod sync;
 pub mod utils;
 
-#[cfg(feature = "sync")]
-pub(crate) use sync::sync;
 #[doc(inline)]
 pub use {
     self::utils::*,
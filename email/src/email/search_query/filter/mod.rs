@@ -8,9 +8,17 @@
 
 pub mod parser;
 
-use chrono::NaiveDate;
+use std::{fmt, str::FromStr};
 
-use crate::flag::Flag;
+use chrono::{Days, Months, NaiveDate};
+
+use super::error::Error;
+use crate::{envelope::Envelope, flag::Flag};
+
+#[cfg(test)]
+static USER_TZ: &chrono::Utc = &chrono::Utc;
+#[cfg(not(test))]
+static USER_TZ: &chrono::Local = &chrono::Local;
 
 /// The search emails filter query.
 ///
@@ -53,6 +61,22 @@ pub enum SearchEmailsFilterQuery {
     /// consideration.
     AfterDate(NaiveDate),
 
+    /// Filter emails where the `Date` header of the message is
+    /// strictly less than the given date relative to the current
+    /// day, for example `before 1 year ago`.
+    ///
+    /// The relative date is resolved each time the filter is
+    /// evaluated, see [`RelativeDate::to_naive_date`].
+    BeforeRelativeDate(RelativeDate),
+
+    /// Filter emails where the `Date` header of the message is
+    /// strictly greater than the given date relative to the current
+    /// day, for example `after 90 days ago`.
+    ///
+    /// The relative date is resolved each time the filter is
+    /// evaluated, see [`RelativeDate::to_naive_date`].
+    AfterRelativeDate(RelativeDate),
+
     /// Filter emails where the `From` header of the message contains
     /// the given pattern.
     From(String),
@@ -73,3 +97,401 @@ pub enum SearchEmailsFilterQuery {
     /// envelope flags.
     Flag(Flag),
 }
+
+impl SearchEmailsFilterQuery {
+    /// Check if the given envelope matches the current filter.
+    ///
+    /// This is the backend-agnostic version of the filter, used when
+    /// a backend cannot evaluate it by itself. Since envelopes do not
+    /// contain any body, body conditions cannot be evaluated: an
+    /// envelope matches as long as the rest of the filter does not
+    /// exclude it, whatever the body conditions.
+    pub fn matches_envelope(&self, envelope: &Envelope) -> bool {
+        self.eval(envelope, &|_| None).unwrap_or(true)
+    }
+
+    /// Check if the given envelope matches the current filter, using
+    /// the given function to evaluate body conditions.
+    pub(crate) fn matches_envelope_with_body(
+        &self,
+        envelope: &Envelope,
+        matches_body: &impl Fn(&str) -> bool,
+    ) -> bool {
+        self.eval(envelope, &|pattern| Some(matches_body(pattern)))
+            .unwrap_or(true)
+    }
+
+    /// Evaluate the current filter against the given envelope.
+    ///
+    /// The evaluation follows a three-valued logic: `None` means
+    /// that the result is unknown, because it depends on a body
+    /// condition that cannot be evaluated.
+    fn eval(
+        &self,
+        envelope: &Envelope,
+        matches_body: &impl Fn(&str) -> Option<bool>,
+    ) -> Option<bool> {
+        match self {
+            SearchEmailsFilterQuery::And(left, right) => {
+                let left = left.eval(envelope, matches_body);
+                let right = right.eval(envelope, matches_body);
+
+                match (left, right) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            }
+            SearchEmailsFilterQuery::Or(left, right) => {
+                let left = left.eval(envelope, matches_body);
+                let right = right.eval(envelope, matches_body);
+
+                match (left, right) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }
+            }
+            SearchEmailsFilterQuery::Not(filter) => {
+                filter.eval(envelope, matches_body).map(|matches| !matches)
+            }
+            SearchEmailsFilterQuery::Date(date) => {
+                Some(&envelope.date.with_timezone(USER_TZ).date_naive() == date)
+            }
+            SearchEmailsFilterQuery::BeforeDate(date) => {
+                Some(&envelope.date.with_timezone(USER_TZ).date_naive() < date)
+            }
+            SearchEmailsFilterQuery::AfterDate(date) => {
+                Some(&envelope.date.with_timezone(USER_TZ).date_naive() > date)
+            }
+            SearchEmailsFilterQuery::BeforeRelativeDate(date) => {
+                Some(envelope.date.with_timezone(USER_TZ).date_naive() < date.to_naive_date())
+            }
+            SearchEmailsFilterQuery::AfterRelativeDate(date) => {
+                Some(envelope.date.with_timezone(USER_TZ).date_naive() > date.to_naive_date())
+            }
+            SearchEmailsFilterQuery::From(pattern) => {
+                let pattern = pattern.as_bytes();
+                if let Some(name) = &envelope.from.name {
+                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
+                        return Some(true);
+                    }
+                }
+                Some(contains_ignore_ascii_case(
+                    envelope.from.addr.as_bytes(),
+                    pattern,
+                ))
+            }
+            SearchEmailsFilterQuery::To(pattern) => {
+                let pattern = pattern.as_bytes();
+                if let Some(name) = &envelope.to.name {
+                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
+                        return Some(true);
+                    }
+                }
+                Some(contains_ignore_ascii_case(
+                    envelope.to.addr.as_bytes(),
+                    pattern,
+                ))
+            }
+            SearchEmailsFilterQuery::Subject(pattern) => Some(contains_ignore_ascii_case(
+                envelope.subject.as_bytes(),
+                pattern.as_bytes(),
+            )),
+            SearchEmailsFilterQuery::Body(pattern) => matches_body(pattern),
+            SearchEmailsFilterQuery::Flag(flag) => Some(envelope.flags.contains(flag)),
+        }
+    }
+
+    /// Return `true` if the current filter contains at least one
+    /// body condition.
+    pub fn has_body_condition(&self) -> bool {
+        match self {
+            SearchEmailsFilterQuery::And(left, right)
+            | SearchEmailsFilterQuery::Or(left, right) => {
+                left.has_body_condition() || right.has_body_condition()
+            }
+            SearchEmailsFilterQuery::Not(filter) => filter.has_body_condition(),
+            SearchEmailsFilterQuery::Body(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Write the given operand, wrapped into parentheses if it is a
+    /// binary operator so that precedence is preserved.
+    fn fmt_operand(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchEmailsFilterQuery::And(..) | SearchEmailsFilterQuery::Or(..) => {
+                write!(f, "({self})")
+            }
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+/// Format the filter query using the [parser](parser::query) syntax,
+/// so that the output can be parsed back.
+impl fmt::Display for SearchEmailsFilterQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchEmailsFilterQuery::And(left, right) => {
+                left.fmt_operand(f)?;
+                write!(f, " and ")?;
+                right.fmt_operand(f)
+            }
+            SearchEmailsFilterQuery::Or(left, right) => {
+                left.fmt_operand(f)?;
+                write!(f, " or ")?;
+                right.fmt_operand(f)
+            }
+            SearchEmailsFilterQuery::Not(filter) => {
+                write!(f, "not ")?;
+                filter.fmt_operand(f)
+            }
+            SearchEmailsFilterQuery::Date(date) => write!(f, "date {}", date.format("%Y-%m-%d")),
+            SearchEmailsFilterQuery::BeforeDate(date) => {
+                write!(f, "before {}", date.format("%Y-%m-%d"))
+            }
+            SearchEmailsFilterQuery::AfterDate(date) => {
+                write!(f, "after {}", date.format("%Y-%m-%d"))
+            }
+            SearchEmailsFilterQuery::BeforeRelativeDate(date) => write!(f, "before {date}"),
+            SearchEmailsFilterQuery::AfterRelativeDate(date) => write!(f, "after {date}"),
+            SearchEmailsFilterQuery::From(pattern) => write!(f, "from {}", escape(pattern)),
+            SearchEmailsFilterQuery::To(pattern) => write!(f, "to {}", escape(pattern)),
+            SearchEmailsFilterQuery::Subject(pattern) => {
+                write!(f, "subject {}", escape(pattern))
+            }
+            SearchEmailsFilterQuery::Body(pattern) => write!(f, "body {}", escape(pattern)),
+            SearchEmailsFilterQuery::Flag(flag) => write!(f, "flag {}", escape(&flag.to_string())),
+        }
+    }
+}
+
+impl FromStr for SearchEmailsFilterQuery {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        super::parser::parse_filter(s)
+    }
+}
+
+#[cfg(feature = "derive")]
+impl serde::Serialize for SearchEmailsFilterQuery {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "derive")]
+impl<'de> serde::Deserialize<'de> for SearchEmailsFilterQuery {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let query = <String as serde::Deserialize>::deserialize(deserializer)?;
+        query.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A date relative to the current day, for example `90 days ago`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct RelativeDate {
+    /// The amount of units to go back in time.
+    pub amount: u32,
+
+    /// The unit of the amount.
+    pub unit: RelativeDateUnit,
+}
+
+impl RelativeDate {
+    pub fn new(amount: u32, unit: RelativeDateUnit) -> Self {
+        Self { amount, unit }
+    }
+
+    /// Resolve the relative date, using the current day of the user
+    /// timezone.
+    pub fn to_naive_date(&self) -> NaiveDate {
+        let today = chrono::Utc::now().with_timezone(USER_TZ).date_naive();
+
+        let date = match self.unit {
+            RelativeDateUnit::Day => today.checked_sub_days(Days::new(self.amount.into())),
+            RelativeDateUnit::Week => today.checked_sub_days(Days::new(self.amount as u64 * 7)),
+            RelativeDateUnit::Month => today.checked_sub_months(Months::new(self.amount)),
+            RelativeDateUnit::Year => {
+                today.checked_sub_months(Months::new(self.amount.saturating_mul(12)))
+            }
+        };
+
+        date.unwrap_or(NaiveDate::MIN)
+    }
+}
+
+impl fmt::Display for RelativeDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            RelativeDateUnit::Day => "day",
+            RelativeDateUnit::Week => "week",
+            RelativeDateUnit::Month => "month",
+            RelativeDateUnit::Year => "year",
+        };
+
+        let plural = if self.amount == 1 { "" } else { "s" };
+        write!(f, "{} {unit}{plural} ago", self.amount)
+    }
+}
+
+/// The unit of a [`RelativeDate`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum RelativeDateUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Escape the given pattern so that it can be parsed back as an
+/// unquoted pattern.
+///
+/// Quoted patterns are kept as it is, since the parser keeps their
+/// surrounding double quotes.
+fn escape(pattern: &str) -> String {
+    if pattern.len() > 1 && pattern.starts_with('"') && pattern.ends_with('"') {
+        return pattern.to_owned();
+    }
+
+    let mut escaped = String::with_capacity(pattern.len());
+
+    for c in pattern.chars() {
+        if matches!(c, '\\' | ' ' | '(' | ')') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+pub(crate) fn contains_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> bool {
+    // an empty pattern matches everything, and would make
+    // `windows` panic
+    if needle.is_empty() {
+        return true;
+    }
+
+    for window in haystack.windows(needle.len()) {
+        if window.eq_ignore_ascii_case(needle) {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::SearchEmailsFilterQuery::{self, AfterDate, And, Not, Or, Subject};
+    use crate::{
+        envelope::{Address, Envelope},
+        flag::{Flag, Flags},
+    };
+
+    #[test]
+    fn display_then_parse() {
+        let filter = And(
+            Box::new(Not(Box::new(SearchEmailsFilterQuery::Flag(Flag::Deleted)))),
+            Box::new(Or(
+                Box::new(AfterDate(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())),
+                Box::new(Not(Box::new(SearchEmailsFilterQuery::From(
+                    "noreply@".into(),
+                )))),
+            )),
+        );
+
+        assert_eq!(
+            filter.to_string(),
+            "not flag deleted and (after 2024-01-01 or not from noreply@)"
+        );
+        assert_eq!(
+            filter
+                .to_string()
+                .parse::<SearchEmailsFilterQuery>()
+                .unwrap(),
+            filter
+        );
+
+        let filter = Subject("foo (bar)".into());
+        assert_eq!(filter.to_string(), "subject foo\\ \\(bar\\)");
+        assert_eq!(
+            filter
+                .to_string()
+                .parse::<SearchEmailsFilterQuery>()
+                .unwrap(),
+            filter
+        );
+    }
+
+    #[test]
+    fn matches_envelope() {
+        let envelope = Envelope {
+            from: Address::new_nameless("noreply@localhost"),
+            flags: Flags::from_iter([Flag::Seen]),
+            date: "2024-06-01T00:00:00+00:00".parse().unwrap(),
+            ..Default::default()
+        };
+
+        let filter = "not flag deleted and after 2024-01-01 and not from noreply@";
+        let filter = filter.parse::<SearchEmailsFilterQuery>().unwrap();
+        assert!(!filter.matches_envelope(&envelope));
+
+        let filter = "not flag deleted and after 2024-01-01 and body foo";
+        let filter = filter.parse::<SearchEmailsFilterQuery>().unwrap();
+        assert!(filter.matches_envelope(&envelope));
+
+        let filter = "not body foo";
+        let filter = filter.parse::<SearchEmailsFilterQuery>().unwrap();
+        assert!(filter.matches_envelope(&envelope));
+
+        let filter = "not (body foo or flag seen)";
+        let filter = filter.parse::<SearchEmailsFilterQuery>().unwrap();
+        assert!(!filter.matches_envelope(&envelope));
+    }
+
+    #[test]
+    fn matches_envelope_with_relative_date() {
+        let envelope = Envelope {
+            date: chrono::Utc::now().fixed_offset() - chrono::Duration::days(10),
+            ..Default::default()
+        };
+
+        let filter = "after 1 month ago and before 1 week ago";
+        let filter = filter.parse::<SearchEmailsFilterQuery>().unwrap();
+        assert_eq!(
+            filter.to_string(),
+            "after 1 month ago and before 1 week ago"
+        );
+        assert!(filter.matches_envelope(&envelope));
+
+        let filter = "after 9 days ago"
+            .parse::<SearchEmailsFilterQuery>()
+            .unwrap();
+        assert!(!filter.matches_envelope(&envelope));
+    }
+
+    #[test]
+    fn matches_envelope_with_empty_pattern() {
+        let envelope = Envelope {
+            from: Address::new_nameless("noreply@localhost"),
+            ..Default::default()
+        };
+
+        assert!(SearchEmailsFilterQuery::From(String::new()).matches_envelope(&envelope));
+        assert!(SearchEmailsFilterQuery::To(String::new()).matches_envelope(&envelope));
+        assert!(Subject(String::new()).matches_envelope(&envelope));
+    }
+}
@@ -8,7 +8,7 @@
 use chrono::NaiveDate;
 use chumsky::prelude::*;
 
-use super::SearchEmailsFilterQuery;
+use super::{RelativeDate, RelativeDateUnit, SearchEmailsFilterQuery};
 use crate::search_query::parser::ParserError;
 
 /// The emails search filter query string parser.
@@ -36,8 +36,8 @@
 /// [`SearchEmailsFilterQuery`]:
 ///
 /// - `date <yyyy-mm-dd>`
-/// - `before <yyyy-mm-dd>`
-/// - `after <yyyy-mm-dd>`
+/// - `before <yyyy-mm-dd>` or `before <n> <unit> ago`
+/// - `after <yyyy-mm-dd>` or `after <n> <unit> ago`
 /// - `from <pattern>`
 /// - `to <pattern>`
 /// - `subject <pattern>`
@@ -48,6 +48,10 @@
 /// unquoted (spaces need to be escaped using back slash: `subject
 /// foo\ bar`).
 ///
+/// `<unit>` can be `day`, `week`, `month` or `year`, in singular or
+/// plural form: `after 90 days ago` keeps matching messages of the
+/// last 90 days whenever the filter is evaluated.
+///
 /// # ABNF
 ///
 /// ```abnf,ignore
@@ -143,8 +147,14 @@ fn before_date<'a>() -> impl Parser<'a, &'a str, SearchEmailsFilterQuery, Parser
                 .repeated()
                 .at_least(1),
         )
-        .ignore_then(naive_date().labelled("pattern after `before`"))
-        .map(SearchEmailsFilterQuery::BeforeDate)
+        .ignore_then(choice((
+            relative_date()
+                .labelled("relative date after `before`")
+                .map(SearchEmailsFilterQuery::BeforeRelativeDate),
+            naive_date()
+                .labelled("pattern after `before`")
+                .map(SearchEmailsFilterQuery::BeforeDate),
+        )))
 }
 
 fn after_date<'a>() -> impl Parser<'a, &'a str, SearchEmailsFilterQuery, ParserError<'a>> + Clone {
@@ -160,8 +170,14 @@ fn after_date<'a>() -> impl Parser<'a, &'a str, SearchEmailsFilterQuery, ParserE
                 .repeated()
                 .at_least(1),
         )
-        .ignore_then(naive_date().labelled("pattern after `after`"))
-        .map(SearchEmailsFilterQuery::AfterDate)
+        .ignore_then(choice((
+            relative_date()
+                .labelled("relative date after `after`")
+                .map(SearchEmailsFilterQuery::AfterRelativeDate),
+            naive_date()
+                .labelled("pattern after `after`")
+                .map(SearchEmailsFilterQuery::AfterDate),
+        )))
 }
 
 fn from<'a>() -> impl Parser<'a, &'a str, SearchEmailsFilterQuery, ParserError<'a>> + Clone {
@@ -253,6 +269,27 @@ fn naive_date<'a>() -> impl Parser<'a, &'a str, NaiveDate, ParserError<'a>> + Cl
     ))
 }
 
+fn relative_date<'a>() -> impl Parser<'a, &'a str, RelativeDate, ParserError<'a>> + Clone {
+    let unit = choice((
+        just("days").to(RelativeDateUnit::Day),
+        just("day").to(RelativeDateUnit::Day),
+        just("weeks").to(RelativeDateUnit::Week),
+        just("week").to(RelativeDateUnit::Week),
+        just("months").to(RelativeDateUnit::Month),
+        just("month").to(RelativeDateUnit::Month),
+        just("years").to(RelativeDateUnit::Year),
+        just("year").to(RelativeDateUnit::Year),
+    ));
+
+    text::int(10)
+        .try_map(|n: &str, span| n.parse::<u32>().map_err(|err| Rich::custom(span, err)))
+        .then_ignore(space().repeated().at_least(1))
+        .then(unit)
+        .then_ignore(space().repeated().at_least(1))
+        .then_ignore(just("ago"))
+        .map(|(amount, unit)| RelativeDate::new(amount, unit))
+}
+
 fn naive_date_with_fmt(fmt: &str) -> impl Parser<&str, NaiveDate, ParserError> + Clone {
     pattern().try_map(move |ref s, span| {
         NaiveDate::parse_from_str(s, fmt).map_err(|err| Rich::custom(span, err))
@@ -316,7 +353,7 @@ mod tests {
     use chrono::NaiveDate;
     use chumsky::prelude::*;
 
-    use super::SearchEmailsFilterQuery::*;
+    use super::{RelativeDate, RelativeDateUnit, SearchEmailsFilterQuery::*};
 
     #[test]
     fn pattern() {
@@ -363,6 +400,32 @@ fn after_date() {
         );
     }
 
+    #[test]
+    fn relative_date() {
+        assert_eq!(
+            super::after_date().parse("after 90 days ago").into_result(),
+            Ok(AfterRelativeDate(RelativeDate::new(
+                90,
+                RelativeDateUnit::Day
+            )))
+        );
+
+        assert_eq!(
+            super::before_date()
+                .parse("before 1 year ago")
+                .into_result(),
+            Ok(BeforeRelativeDate(RelativeDate::new(
+                1,
+                RelativeDateUnit::Year
+            )))
+        );
+
+        assert!(super::after_date()
+            .parse("after 90 days")
+            .into_result()
+            .is_err());
+    }
+
     #[test]
     fn from() {
         assert_eq!(
@@ -16,6 +16,11 @@
 
 /// The email synchronization hunk.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum EmailSyncHunk {
     /// The email matching the given identifier from the given folder
     /// needs to be retrieved for the given source then cached.
@@ -101,6 +106,16 @@ pub fn folder(&self) -> &str {
             Self::Delete(folder, _, _) => folder.as_str(),
         }
     }
+
+    /// Return the amount of bytes transferred by the hunk.
+    ///
+    /// Only copies transfer messages, other hunks weigh 0 bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::CopyThenCache(_, envelope, _, _, _) => envelope.size,
+            _ => 0,
+        }
+    }
 }
 
 /// The email synchronization cache hunk.
@@ -0,0 +1,356 @@
+//! # Email sync labels
+//!
+//! Module dedicated to the labels-aware email synchronization, see
+//! [`MessageSyncMode::Labels`]. The main structure of the module is
+//! the [`EmailSyncLabels`], which locates messages across folders.
+//!
+//! [`MessageSyncMode::Labels`]: crate::message::sync::config::MessageSyncMode::Labels
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use super::hunk::{EmailSyncHunk, Id};
+use crate::{
+    envelope::Envelope, flag::Flag, folder::sync::hunk::FolderName, sync::SyncDestination,
+};
+
+/// The location of a message: the folder it belongs to and its
+/// identifier within this folder.
+pub type MessageLocation = (FolderName, Id);
+
+/// The cross-folder index of messages.
+///
+/// Messages are indexed by identity (see [`EmailSyncLabels::key`]),
+/// on both sides. The index is filled while listing envelopes, then
+/// kept up to date while messages are being copied.
+#[derive(Debug, Default)]
+pub struct EmailSyncLabels {
+    left: Mutex<HashMap<String, Vec<MessageLocation>>>,
+    right: Mutex<HashMap<String, Vec<MessageLocation>>>,
+}
+
+impl EmailSyncLabels {
+    fn side(&self, side: &SyncDestination) -> &Mutex<HashMap<String, Vec<MessageLocation>>> {
+        match side {
+            SyncDestination::Left => &self.left,
+            SyncDestination::Right => &self.right,
+        }
+    }
+
+    /// Compute the identity of the given envelope.
+    ///
+    /// Gmail exposes the identifier of a message across all its
+    /// labels (`X-GM-MSGID`), which is used whenever available. See
+    /// [`EmailSyncLabels::fallback_key`] otherwise.
+    pub fn key(envelope: &Envelope) -> String {
+        match envelope.gmail_message_id {
+            Some(id) => format!("X-GM-MSGID:{id}"),
+            None => Self::fallback_key(envelope),
+        }
+    }
+
+    /// Compute the identity of the given envelope, without relying
+    /// on Gmail identifiers.
+    ///
+    /// A message belonging to several labels is exposed several
+    /// times with the same Message-ID and the same size. The size
+    /// tells apart distinct messages sharing a Message-ID, like a
+    /// sent message and its mailing list copy, or messages without
+    /// Message-ID received at the same date.
+    pub fn fallback_key(envelope: &Envelope) -> String {
+        format!("{}:{}", envelope.message_id, envelope.size)
+    }
+
+    /// Compute all the identities of the given envelope, the most
+    /// reliable first.
+    fn keys(envelope: &Envelope) -> Vec<String> {
+        let mut keys = vec![Self::key(envelope)];
+
+        if envelope.gmail_message_id.is_some() {
+            keys.push(Self::fallback_key(envelope));
+        }
+
+        keys
+    }
+
+    /// Register the location of the given envelope.
+    ///
+    /// The location is registered under all the identities of the
+    /// envelope, see [`EmailSyncLabels::key`].
+    pub fn insert(&self, side: &SyncDestination, envelope: &Envelope, folder: impl ToString) {
+        let location = (folder.to_string(), envelope.id.clone());
+
+        if let Ok(mut index) = self.side(side).lock() {
+            for key in Self::keys(envelope) {
+                index.entry(key).or_default().push(location.clone());
+            }
+        }
+    }
+
+    /// Register the envelopes of the given folder, on both sides.
+    ///
+    /// Only the right side may expose Gmail identifiers: left
+    /// envelopes sharing the Message-ID of a right envelope of the
+    /// same folder are the same message, so they inherit its Gmail
+    /// identifier and labels. Copies of this message to other
+    /// folders are then found on the left side by Gmail identifier,
+    /// whatever the size of the message on each side.
+    pub fn insert_folder(
+        &self,
+        folder: &str,
+        left: &mut HashMap<String, Envelope>,
+        right: &HashMap<String, Envelope>,
+    ) {
+        for (message_id, envelope) in left.iter_mut() {
+            let Some(right) = right.get(message_id) else {
+                continue;
+            };
+
+            if envelope.gmail_message_id.is_none() {
+                envelope.gmail_message_id = right.gmail_message_id;
+                envelope.gmail_labels = right.gmail_labels.clone();
+            }
+        }
+
+        for envelope in left.values() {
+            if !envelope.flags.contains(&Flag::Deleted) {
+                self.insert(&SyncDestination::Left, envelope, folder);
+            }
+        }
+
+        for envelope in right.values() {
+            if !envelope.flags.contains(&Flag::Deleted) {
+                self.insert(&SyncDestination::Right, envelope, folder);
+            }
+        }
+    }
+
+    /// Unregister the given message location.
+    pub fn remove(&self, side: &SyncDestination, folder: &str, id: &str) {
+        if let Ok(mut index) = self.side(side).lock() {
+            index.retain(|_, locations| {
+                locations.retain(|(f, i)| f != folder || i != id);
+                !locations.is_empty()
+            });
+        }
+    }
+
+    /// Find the given envelope in any folder but the given one.
+    ///
+    /// Identities of the envelope are tried one after the other,
+    /// the most reliable first.
+    pub fn find(
+        &self,
+        side: &SyncDestination,
+        envelope: &Envelope,
+        folder: &str,
+    ) -> Option<MessageLocation> {
+        let index = self.side(side).lock().ok()?;
+
+        Self::keys(envelope)
+            .into_iter()
+            .find_map(|key| index.get(&key)?.iter().find(|(f, _)| f != folder).cloned())
+    }
+
+    /// Split the given hunks into two waves.
+    ///
+    /// Hunks of the first wave are processed in parallel, hence
+    /// copies of the same message to several folders of the same
+    /// side would all transfer it. Only the first copy stays in the
+    /// first wave, the other ones are postponed to the second wave:
+    /// by then, the message is registered in the target side and
+    /// the remaining copies are done within that side.
+    pub fn split_duplicates(
+        hunks: impl IntoIterator<Item = EmailSyncHunk>,
+    ) -> (Vec<EmailSyncHunk>, Vec<EmailSyncHunk>) {
+        let mut keys = HashSet::new();
+
+        hunks.into_iter().partition(|hunk| match hunk {
+            EmailSyncHunk::CopyThenCache(_, envelope, _, target, _) => {
+                keys.insert((target.clone(), Self::key(envelope)))
+            }
+            _ => true,
+        })
+    }
+
+    /// Unregister locations of messages about to be deleted.
+    ///
+    /// Copying a message from a location being deleted would also
+    /// copy its deletion flag.
+    pub fn discard_deleted<'a>(&self, hunks: impl IntoIterator<Item = &'a EmailSyncHunk>) {
+        for hunk in hunks {
+            if let EmailSyncHunk::Delete(folder, id, side) = hunk {
+                self.remove(side, folder, id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::EmailSyncLabels;
+    use crate::{email::sync::hunk::EmailSyncHunk, envelope::Envelope, sync::SyncDestination};
+
+    fn envelope(id: &str, message_id: &str, size: usize) -> Envelope {
+        Envelope {
+            id: id.into(),
+            message_id: message_id.into(),
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_in_other_folders() {
+        let labels = EmailSyncLabels::default();
+        labels.insert(
+            &SyncDestination::Left,
+            &envelope("1", "<a@localhost>", 42),
+            "INBOX",
+        );
+        labels.insert(
+            &SyncDestination::Left,
+            &envelope("2", "<a@localhost>", 42),
+            "Work",
+        );
+
+        let a = envelope("3", "<a@localhost>", 42);
+
+        assert_eq!(
+            labels.find(&SyncDestination::Left, &a, "INBOX"),
+            Some(("Work".into(), "2".into()))
+        );
+        assert_eq!(
+            labels.find(&SyncDestination::Left, &a, "Archive"),
+            Some(("INBOX".into(), "1".into()))
+        );
+        assert_eq!(labels.find(&SyncDestination::Right, &a, "Archive"), None);
+
+        labels.remove(&SyncDestination::Left, "Work", "2");
+        assert_eq!(labels.find(&SyncDestination::Left, &a, "INBOX"), None);
+    }
+
+    #[test]
+    fn distinct_messages_sharing_message_id() {
+        let labels = EmailSyncLabels::default();
+        labels.insert(
+            &SyncDestination::Left,
+            &envelope("1", "<a@localhost>", 42),
+            "Sent",
+        );
+
+        let a = envelope("2", "<a@localhost>", 1337);
+        assert_eq!(labels.find(&SyncDestination::Left, &a, "INBOX"), None);
+    }
+
+    #[test]
+    fn find_by_gmail_message_id() {
+        let labels = EmailSyncLabels::default();
+        let mut left = HashMap::from_iter([(
+            "<a@localhost>".to_owned(),
+            envelope("1", "<a@localhost>", 40),
+        )]);
+        let right = HashMap::from_iter([(
+            "<a@localhost>".to_owned(),
+            Envelope {
+                gmail_message_id: Some(1337),
+                ..envelope("2", "<a@localhost>", 42)
+            },
+        )]);
+        labels.insert_folder("INBOX", &mut left, &right);
+
+        assert_eq!(left["<a@localhost>"].gmail_message_id, Some(1337));
+
+        // the size differs, but the Gmail message identifier matches
+        let a = Envelope {
+            gmail_message_id: Some(1337),
+            ..envelope("3", "<a@localhost>", 42)
+        };
+
+        assert_eq!(
+            labels.find(&SyncDestination::Left, &a, "Work"),
+            Some(("INBOX".into(), "1".into()))
+        );
+        assert_eq!(
+            labels.find(&SyncDestination::Right, &a, "Work"),
+            Some(("INBOX".into(), "2".into()))
+        );
+
+        // envelopes without Gmail message identifier fall back to
+        // the Message-ID and the size
+        let a = envelope("4", "<a@localhost>", 42);
+
+        assert_eq!(
+            labels.find(&SyncDestination::Right, &a, "Work"),
+            Some(("INBOX".into(), "2".into()))
+        );
+
+        let b = Envelope {
+            gmail_message_id: Some(42),
+            ..envelope("5", "<b@localhost>", 42)
+        };
+
+        assert_eq!(labels.find(&SyncDestination::Left, &b, "Work"), None);
+    }
+
+    #[test]
+    fn discard_deleted() {
+        let labels = EmailSyncLabels::default();
+        let a = envelope("1", "<a@localhost>", 42);
+        let b = envelope("2", "<b@localhost>", 42);
+        labels.insert(&SyncDestination::Right, &a, "INBOX");
+        labels.insert(&SyncDestination::Right, &b, "INBOX");
+
+        labels.discard_deleted(&[EmailSyncHunk::Delete(
+            "INBOX".into(),
+            "1".into(),
+            SyncDestination::Right,
+        )]);
+
+        assert_eq!(labels.find(&SyncDestination::Right, &a, "Work"), None);
+        assert_eq!(
+            labels.find(&SyncDestination::Right, &b, "Work"),
+            Some(("INBOX".into(), "2".into()))
+        );
+    }
+
+    #[test]
+    fn split_duplicates() {
+        let copy = |folder: &str, envelope: &Envelope, target: SyncDestination| {
+            EmailSyncHunk::CopyThenCache(
+                folder.into(),
+                envelope.clone(),
+                SyncDestination::Right,
+                target,
+                true,
+            )
+        };
+
+        let a = envelope("1", "<a@localhost>", 42);
+        let b = envelope("2", "<b@localhost>", 42);
+        let uncache = EmailSyncHunk::Uncache("INBOX".into(), "1".into(), SyncDestination::Left);
+
+        let (first, second) = EmailSyncLabels::split_duplicates([
+            copy("INBOX", &a, SyncDestination::Left),
+            copy("Work", &a, SyncDestination::Left),
+            copy("INBOX", &b, SyncDestination::Left),
+            copy("Archive", &a, SyncDestination::Right),
+            uncache.clone(),
+        ]);
+
+        assert_eq!(
+            first,
+            vec![
+                copy("INBOX", &a, SyncDestination::Left),
+                copy("INBOX", &b, SyncDestination::Left),
+                copy("Archive", &a, SyncDestination::Right),
+                uncache,
+            ]
+        );
+        assert_eq!(second, vec![copy("Work", &a, SyncDestination::Left)]);
+    }
+}
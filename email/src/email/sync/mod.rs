@@ -3,22 +3,30 @@
 //! Module dedicated to email synchronization.
 
 pub mod hunk;
+pub mod labels;
 pub mod patch;
 pub mod report;
 
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     string::String,
     sync::Arc,
+    time::Instant,
 };
 
 use futures::{stream::FuturesUnordered, StreamExt};
 
-use self::{hunk::EmailSyncHunk, report::EmailSyncReport};
+use self::{
+    hunk::EmailSyncHunk,
+    labels::EmailSyncLabels,
+    patch::{EmailSyncDeletedEnvelopes, EmailSyncPatches, Envelopes},
+    report::EmailSyncReport,
+};
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
-    backend::context::BackendContextBuilder,
+    backend::context::{BackendContext, BackendContextBuilder},
     debug,
     envelope::{
         get::GetEnvelope,
@@ -26,44 +34,73 @@
         Envelope, Id, SingleId,
     },
     flag::{add::AddFlags, set::SetFlags, Flag},
-    message::{add::AddMessage, peek::PeekMessages},
+    folder::sync::{hunk::FolderSyncHunk, patch::FolderSyncPatches},
+    message::{
+        add::AddMessage,
+        copy::CopyMessages,
+        peek::PeekMessages,
+        sync::{build_placeholder, config::MessageSyncOversizedStrategy, parse_placeholder},
+    },
     search_query::SearchEmailsQuery,
-    sync::{pool::SyncPoolContext, SyncDestination, SyncEvent},
-    trace, AnyBoxedError,
+    sync::{
+        journal::SyncJournal, pool::SyncPoolContext, progress::SyncProgressTracker,
+        SyncDestination, SyncEvent,
+    },
+    trace, AnyBoxedError, AnyResult,
 };
 
-/// Errors related to email synchronization.
-
-pub(crate) async fn sync<L, R>(
+/// Build the email synchronization patch of the given folders.
+///
+/// Envelopes are listed from both sides and their caches, then
+/// compared in order to generate the list of hunks (changes) to
+/// apply.
+///
+/// Folders are given along with their folder patch. In dry run mode,
+/// folders are not created, hence listing envelopes of a folder the
+/// folder patch creates is expected to fail: such listing is
+/// considered empty. Any other listing failure skips the folder, or
+/// fails the whole patch when building a plan (`plan` is `true`): a
+/// listing wrongly considered empty would turn all its envelopes
+/// into deletions.
+///
+/// When building a plan, the envelopes of deleted emails are also
+/// returned, so that deletions can be reviewed.
+pub(crate) async fn build_patch<L, R>(
     ctx_ref: Arc<SyncPoolContext<L::Context, R::Context>>,
-    folders: &HashSet<String>,
-) -> Result<EmailSyncReport>
+    folders: &FolderSyncPatches,
+    plan: bool,
+) -> Result<(EmailSyncPatches, EmailSyncDeletedEnvelopes)>
 where
     L: BackendContextBuilder + 'static,
     R: BackendContextBuilder + 'static,
 {
-    let mut report = EmailSyncReport::default();
-    let patch = FuturesUnordered::from_iter(folders.iter().map(|folder| {
+    let patch = FuturesUnordered::from_iter(folders.iter().map(|(folder, hunks)| {
+        let missing = |hunk: FolderSyncHunk| ctx_ref.dry_run && hunks.contains(&hunk);
+        let left_cache_missing =
+            missing(FolderSyncHunk::Cache(folder.clone(), SyncDestination::Left));
+        let left_missing = missing(FolderSyncHunk::Create(
+            folder.clone(),
+            SyncDestination::Left,
+        ));
+        let right_cache_missing = missing(FolderSyncHunk::Cache(
+            folder.clone(),
+            SyncDestination::Right,
+        ));
+        let right_missing = missing(FolderSyncHunk::Create(
+            folder.clone(),
+            SyncDestination::Right,
+        ));
+
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
 
         let left_cached_envelopes = tokio::spawn(async move {
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.left_cache
-                    .list_envelopes(
-                        &folder_ref,
-                        ListEnvelopesOptions {
-                            page: 0,
-                            page_size: 0,
-                            query: Some(SearchEmailsQuery {
-                                filter: ctx.envelope_filters.clone().into(),
-                                sort: None,
-                            }),
-                        },
-                    )
+                    .list_envelopes(&folder_ref, Default::default())
                     .await
                     .or_else(|err| {
-                        if ctx.dry_run {
+                        if left_cache_missing {
                             Ok(Default::default())
                         } else {
                             Err(Error::ListLeftEnvelopesCachedError(err))
@@ -98,13 +135,14 @@ pub(crate) async fn sync<L, R>(
                     )
                     .await
                     .or_else(|err| {
-                        if ctx.dry_run {
+                        if left_missing {
                             Ok(Default::default())
                         } else {
                             Err(Error::ListLeftEnvelopesError(err))
                         }
                     })?
                     .into_iter()
+                    .filter(|e| ctx.envelope_filters.matches(e))
                     .map(|e| (e.message_id.clone(), e)),
             );
 
@@ -121,19 +159,12 @@ pub(crate) async fn sync<L, R>(
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.right_cache
                     .list_envelopes(
-                        &folder_ref,
-                        ListEnvelopesOptions {
-                            page: 0,
-                            page_size: 0,
-                            query: Some(SearchEmailsQuery {
-                                filter: ctx.envelope_filters.clone().into(),
-                                sort: None,
-                            }),
-                        },
+                        &ctx.folder_mapping.to_right(&folder_ref),
+                        Default::default(),
                     )
                     .await
                     .or_else(|err| {
-                        if ctx.dry_run {
+                        if right_cache_missing {
                             Ok(Default::default())
                         } else {
                             Err(Error::ListRightEnvelopesCachedError(err))
@@ -156,7 +187,7 @@ pub(crate) async fn sync<L, R>(
             let envelopes: HashMap<String, Envelope> = HashMap::from_iter(
                 ctx.right
                     .list_envelopes(
-                        &folder_ref,
+                        &ctx.folder_mapping.to_right(&folder_ref),
                         ListEnvelopesOptions {
                             page: 0,
                             page_size: 0,
@@ -168,13 +199,14 @@ pub(crate) async fn sync<L, R>(
                     )
                     .await
                     .or_else(|err| {
-                        if ctx.dry_run {
+                        if right_missing {
                             Ok(Default::default())
                         } else {
                             Err(Error::ListRightEnvelopesError(err))
                         }
                     })?
                     .into_iter()
+                    .filter(|e| ctx.envelope_filters.matches(e))
                     .map(|e| (e.message_id.clone(), e)),
             );
 
@@ -193,180 +225,251 @@ pub(crate) async fn sync<L, R>(
                 right_envelopes
             );
 
-            Result::Ok((folder.clone(), envelopes))
+            (folder.clone(), envelopes)
         }
     }))
-    .filter_map(|patch| async {
+    .then(|(folder, envelopes)| async {
         let task = async {
-            let (folder, envelopes) = patch?;
             let (lc, l, rc, r) = envelopes.map_err(|e| Error::FailedToGetEnvelopes(e))?;
-            let patch = patch::build(&folder, lc?, l?, rc?, r?);
-            Ok::<(String, HashSet<Vec<EmailSyncHunk>>), AnyBoxedError>((folder, patch))
+            let (mut lc, mut l, mut rc, mut r) = (lc?, l?, rc?, r?);
+            if let Some(labels) = &ctx_ref.labels {
+                labels.insert_folder(&folder, &mut l, &r);
+            }
+            exclude_filtered_out(&ctx_ref, &folder, &mut lc, &mut l, &mut rc, &mut r).await?;
+            let envelopes = plan.then(|| (l.clone(), r.clone()));
+            let patch = patch::build(&folder, lc, l, rc, r);
+            Ok::<_, AnyBoxedError>((patch, envelopes))
         };
-        match task.await {
-            Ok(patch) => Some(patch),
+        (folder, task.await)
+    })
+    .collect::<Vec<_>>()
+    .await;
+
+    let mut patches = BTreeMap::new();
+    let mut deleted = BTreeMap::new();
+
+    for (folder, p) in patch {
+        let (p, envelopes) = match p {
+            Ok(p) => p,
+            Err(err) if plan => return Err(Error::BuildEmailPatchError(folder, err)),
             Err(err) => {
-                debug!("cannot generate email patch: {err}");
+                debug!("cannot generate email patch of folder {folder}: {err}");
                 trace!("{err:?}");
-                None
+                continue;
             }
-        }
-    })
-    .fold(BTreeMap::new(), |mut patches, (folder, p)| async {
+        };
+
         let mut patch = p.into_iter().flatten().collect::<BTreeSet<_>>();
         ctx_ref.apply_flag_and_message_permissions(&mut patch);
+        ctx_ref.apply_message_size_limits(&mut patch);
+
+        if let Some(labels) = &ctx_ref.labels {
+            labels.discard_deleted(&patch);
+        }
+
+        if let Some((left, right)) = envelopes {
+            let envelopes = deleted_envelopes(&patch, &left, &right);
+
+            if !envelopes.is_empty() {
+                deleted.insert(folder.clone(), envelopes);
+            }
+        }
 
         patches.insert(folder, patch);
-        patches
-    })
-    .await;
+    }
+
+    let patch = patches;
 
     SyncEvent::GeneratedEmailPatch(patch.clone())
         .emit(&ctx_ref.handler)
         .await;
 
-    report.patch = FuturesUnordered::from_iter(patch.into_values().flatten().map(|hunk| {
-        let ctx = ctx_ref.clone();
-        tokio::spawn(async move {
-            let hunk_clone = hunk.clone();
-            let handler = ctx.handler.clone();
-
-            let task = async move {
-                if ctx.dry_run {
-                    return Ok(());
-                }
+    Ok((patch, deleted))
+}
 
-                match hunk_clone {
-                    EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Left) => {
-                        let envelope = ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
-                        let flags = envelope.flags.clone();
-                        let msg = envelope.to_sync_cache_msg();
-                        ctx.left_cache
-                            .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Right) => {
-                        let envelope = ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
-                        let flags = envelope.flags.clone();
-                        let msg = envelope.to_sync_cache_msg();
-                        ctx.right_cache
-                            .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::CopyThenCache(
-                        folder,
-                        envelope,
-                        source,
-                        target,
-                        refresh_source_cache,
-                    ) => {
-                        let id = Id::single(&envelope.id);
-                        let msgs = match source {
-                            SyncDestination::Left => {
-                                if refresh_source_cache {
-                                    let flags = envelope.flags.clone();
-                                    let msg = envelope.to_sync_cache_msg();
-                                    ctx.left_cache
-                                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                        .await?;
-                                };
-                                ctx.left.peek_messages(&folder, &id).await?
-                            }
-                            SyncDestination::Right => {
-                                if refresh_source_cache {
-                                    let flags = envelope.flags.clone();
-                                    let msg = envelope.to_sync_cache_msg();
-                                    ctx.right_cache
-                                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                        .await?;
-                                };
-                                ctx.right.peek_messages(&folder, &id).await?
-                            }
-                        };
-
-                        let msgs = msgs.to_vec();
-                        let msg = msgs
-                            .first()
-                            .ok_or_else(|| Error::FindMessageError(envelope.id.clone()))?;
-
-                        match target {
-                            SyncDestination::Left => {
-                                let id = ctx
-                                    .left
-                                    .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
-                                    .await?;
-                                let envelope =
-                                    ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
-                                let flags = envelope.flags.clone();
-                                let msg = envelope.to_sync_cache_msg();
-                                ctx.left_cache
-                                    .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                    .await?;
-                            }
-                            SyncDestination::Right => {
-                                let id = ctx
-                                    .right
-                                    .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
-                                    .await?;
-                                let envelope =
-                                    ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
-                                let flags = envelope.flags.clone();
-                                let msg = envelope.to_sync_cache_msg();
-                                ctx.right_cache
-                                    .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                    .await?;
-                            }
-                        };
-                    }
-                    EmailSyncHunk::Uncache(folder, id, SyncDestination::Left) => {
-                        ctx.left_cache
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::Delete(folder, id, SyncDestination::Left) => {
-                        ctx.left
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::Uncache(folder, id, SyncDestination::Right) => {
-                        ctx.right_cache
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::Delete(folder, id, SyncDestination::Right) => {
-                        ctx.right
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Left) => {
-                        ctx.left_cache
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
-                        ctx.left
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Right) => {
-                        ctx.right_cache
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Right) => {
-                        ctx.right
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
+/// Find the envelopes of emails deleted by the given hunks, among
+/// the given left and right envelopes.
+fn deleted_envelopes(
+    hunks: &BTreeSet<EmailSyncHunk>,
+    left: &Envelopes,
+    right: &Envelopes,
+) -> Vec<(SyncDestination, Envelope)> {
+    fn index(envelopes: &Envelopes) -> HashMap<&str, &Envelope> {
+        envelopes.values().map(|e| (e.id.as_str(), e)).collect()
+    }
+
+    let (left, right) = (index(left), index(right));
+
+    hunks
+        .iter()
+        .filter_map(|hunk| match hunk {
+            EmailSyncHunk::Delete(_, id, dest) => {
+                let envelopes = match dest {
+                    SyncDestination::Left => &left,
+                    SyncDestination::Right => &right,
                 };
 
-                Ok(())
-            };
+                let envelope = *envelopes.get(id.as_str())?;
+                Some((dest.clone(), envelope.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
 
-            let output = task.await;
+/// Remove envelopes filtered out by the envelope filters from the
+/// given envelopes.
+///
+/// Caches are listed without filters, whereas backends are listed
+/// with filters. A cached envelope missing from the listing of its
+/// backend has then either been deleted, or does not match the
+/// filters anymore (for example after a flag change). Backends are
+/// listed again without filters to tell them apart: envelopes
+/// filtered out on one side are left untouched on both sides, so
+/// that filters never lead to deletions.
+async fn exclude_filtered_out<L: BackendContext, R: BackendContext>(
+    ctx: &SyncPoolContext<L, R>,
+    folder: &str,
+    left_cached: &mut Envelopes,
+    left: &mut Envelopes,
+    right_cached: &mut Envelopes,
+    right: &mut Envelopes,
+) -> Result<()> {
+    if ctx.envelope_filters.is_empty() {
+        return Ok(());
+    }
+
+    let mut filtered_out = HashSet::new();
+
+    let missing: HashSet<&String> = left_cached
+        .keys()
+        .filter(|id| !left.contains_key(*id))
+        .collect();
+
+    if !missing.is_empty() {
+        let envelopes = ctx
+            .left
+            .list_envelopes(folder, Default::default())
+            .await
+            .map_err(Error::ListLeftEnvelopesError)?;
+
+        filtered_out.extend(
+            envelopes
+                .into_iter()
+                .map(|e| e.message_id)
+                .filter(|id| missing.contains(id)),
+        );
+    }
+
+    let missing: HashSet<&String> = right_cached
+        .keys()
+        .filter(|id| !right.contains_key(*id))
+        .collect();
+
+    if !missing.is_empty() {
+        let envelopes = ctx
+            .right
+            .list_envelopes(&ctx.folder_mapping.to_right(folder), Default::default())
+            .await
+            .map_err(Error::ListRightEnvelopesError)?;
+
+        filtered_out.extend(
+            envelopes
+                .into_iter()
+                .map(|e| e.message_id)
+                .filter(|id| missing.contains(id)),
+        );
+    }
+
+    for id in filtered_out {
+        debug!("envelope {id} from folder {folder} is filtered out, skipping it");
+        left_cached.remove(&id);
+        left.remove(&id);
+        right_cached.remove(&id);
+        right.remove(&id);
+    }
+
+    Ok(())
+}
+
+/// Apply the given email synchronization hunks.
+///
+/// Hunks are processed in parallel. If a journal is given, each hunk
+/// is marked as done in the journal as soon as it has been
+/// successfully applied. Hunks are added to the given progress
+/// tracker, which can be shared between several patches.
+///
+/// In labels mode, copies of the same message to several folders
+/// are processed after the first one, so that the message is
+/// transferred only once.
+pub(crate) async fn apply_patch<L, R>(
+    ctx_ref: Arc<SyncPoolContext<L::Context, R::Context>>,
+    patch: impl IntoIterator<Item = EmailSyncHunk>,
+    journal: Option<Arc<SyncJournal>>,
+    progress: Arc<SyncProgressTracker>,
+) -> EmailSyncReport
+where
+    L: BackendContextBuilder + 'static,
+    R: BackendContextBuilder + 'static,
+{
+    let started_at = Instant::now();
+    let patch = Vec::from_iter(patch);
+    let bytes_total = patch.iter().map(EmailSyncHunk::size).sum();
+    progress.extend(patch.len(), bytes_total);
+
+    SyncEvent::StartedEmailHunks(progress.get())
+        .emit(&ctx_ref.handler)
+        .await;
+
+    let (first, second) = match &ctx_ref.labels {
+        Some(_) => EmailSyncLabels::split_duplicates(patch),
+        None => (patch, Vec::new()),
+    };
+
+    let mut patch = process_hunks::<L, R>(&ctx_ref, first, &journal, &progress).await;
+    patch.extend(process_hunks::<L, R>(&ctx_ref, second, &journal, &progress).await);
+
+    EmailSyncReport {
+        patch,
+        duration: started_at.elapsed(),
+    }
+}
+
+/// Process the given email synchronization hunks in parallel.
+async fn process_hunks<L, R>(
+    ctx_ref: &Arc<SyncPoolContext<L::Context, R::Context>>,
+    hunks: Vec<EmailSyncHunk>,
+    journal: &Option<Arc<SyncJournal>>,
+    progress: &Arc<SyncProgressTracker>,
+) -> Vec<(EmailSyncHunk, Option<AnyBoxedError>)>
+where
+    L: BackendContextBuilder + 'static,
+    R: BackendContextBuilder + 'static,
+{
+    FuturesUnordered::from_iter(hunks.into_iter().map(|hunk| {
+        let ctx = ctx_ref.clone();
+        let journal = journal.clone();
+        let progress = progress.clone();
+        tokio::spawn(async move {
+            let started_at = Instant::now();
+            let output = process_hunk::<L, R>(&ctx, hunk.clone()).await;
+            let duration = started_at.elapsed();
+
+            if let (Ok(()), Some(journal)) = (&output, journal) {
+                if let Err(err) = journal.mark_done(&hunk) {
+                    debug!("cannot mark email hunk as done in sync journal: {err}");
+                    trace!("{err:?}");
+                }
+            }
 
             SyncEvent::ProcessedEmailHunk(hunk.clone())
-                .emit(&handler)
+                .emit(&ctx.handler)
+                .await;
+
+            let size = hunk.size();
+            let progress = progress.add(size);
+            SyncEvent::ProgressedEmailHunk(hunk.clone(), size, duration, progress)
+                .emit(&ctx.handler)
                 .await;
 
             match output {
@@ -386,11 +489,326 @@ pub(crate) async fn sync<L, R>(
         }
     })
     .collect::<Vec<_>>()
-    .await;
+    .await
+}
 
-    SyncEvent::ProcessedAllEmailHunks
-        .emit(&ctx_ref.handler)
-        .await;
+/// Discard copy hunks that were already applied to their target.
+///
+/// When a synchronization is interrupted, a message may have been
+/// copied to its target without the hunk being marked as done in the
+/// journal. Replaying such hunk would duplicate the message, so
+/// target folders are checked for the Message-ID first.
+pub(crate) async fn discard_applied_hunks<L, R>(
+    ctx: &SyncPoolContext<L::Context, R::Context>,
+    hunks: &mut BTreeSet<EmailSyncHunk>,
+) where
+    L: BackendContextBuilder,
+    R: BackendContextBuilder,
+{
+    let targets = BTreeSet::from_iter(hunks.iter().filter_map(|hunk| match hunk {
+        EmailSyncHunk::CopyThenCache(folder, _, _, target, _) => {
+            Some((folder.clone(), target.clone()))
+        }
+        _ => None,
+    }));
+
+    let mut message_ids = HashMap::new();
+
+    for (folder, target) in targets {
+        let opts = ListEnvelopesOptions {
+            page: 0,
+            page_size: 0,
+            query: None,
+        };
+
+        let envelopes = match target {
+            SyncDestination::Left => ctx.left.list_envelopes(&folder, opts).await,
+            SyncDestination::Right => {
+                let folder = ctx.folder_mapping.to_right(&folder);
+                ctx.right.list_envelopes(&folder, opts).await
+            }
+        };
+
+        match envelopes {
+            Ok(envelopes) => {
+                let ids = HashSet::<String>::from_iter(envelopes.into_iter().map(|e| e.message_id));
+                message_ids.insert((folder, target), ids);
+            }
+            Err(err) => {
+                debug!("cannot list {target} envelopes from {folder}, skipping it: {err}");
+                trace!("{err:?}");
+            }
+        }
+    }
+
+    hunks.retain(|hunk| match hunk {
+        EmailSyncHunk::CopyThenCache(folder, envelope, _, target, _) => {
+            let key = (folder.clone(), target.clone());
+            let applied = match message_ids.get(&key) {
+                Some(ids) => ids.contains(&envelope.message_id),
+                None => false,
+            };
+
+            if applied {
+                debug!("email hunk already applied, discarding it: {hunk}");
+            }
+
+            !applied
+        }
+        _ => true,
+    });
+}
+
+/// Process the given email synchronization hunk.
+async fn process_hunk<L, R>(
+    ctx: &SyncPoolContext<L::Context, R::Context>,
+    hunk: EmailSyncHunk,
+) -> AnyResult<()>
+where
+    L: BackendContextBuilder,
+    R: BackendContextBuilder,
+{
+    if ctx.dry_run {
+        return Ok(());
+    }
+
+    match hunk {
+        EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Left) => {
+            let envelope = ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
+            let flags = envelope.flags.clone();
+            let msg = envelope.to_sync_cache_msg();
+            ctx.left_cache
+                .add_message_with_flags(&folder, msg.as_bytes(), &flags)
+                .await?;
+        }
+        EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Right) => {
+            let folder = ctx.folder_mapping.to_right(&folder);
+            let envelope = ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
+            let flags = envelope.flags.clone();
+            let msg = envelope.to_sync_cache_msg();
+            ctx.right_cache
+                .add_message_with_flags(&folder, msg.as_bytes(), &flags)
+                .await?;
+        }
+        EmailSyncHunk::CopyThenCache(folder, envelope, source, target, refresh_source_cache) => {
+            let right_folder = ctx.folder_mapping.to_right(&folder);
+
+            let location = ctx
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.find(&target, &envelope, &folder));
+
+            // NOTE: in labels mode, a message already owned by the
+            // target side in another folder is copied within the
+            // target side rather than transferred again
+            if let Some((from_folder, from_id)) = location {
+                let id = Id::single(from_id);
+                let copy = match target {
+                    SyncDestination::Left => {
+                        ctx.left.copy_messages(&from_folder, &folder, &id).await
+                    }
+                    SyncDestination::Right => {
+                        let from_folder = ctx.folder_mapping.to_right(&from_folder);
+                        ctx.right
+                            .copy_messages(&from_folder, &right_folder, &id)
+                            .await
+                    }
+                };
+
+                match copy {
+                    Ok(()) => {
+                        if refresh_source_cache {
+                            cache_envelope::<L, R>(ctx, &folder, &envelope, &source).await?;
+                        }
+                        cache_envelope::<L, R>(ctx, &folder, &envelope, &target).await?;
+                        return Ok(());
+                    }
+                    Err(_err) => {
+                        debug!(
+                            "cannot copy message {} from {target} folder {from_folder}, transferring it instead: {_err}",
+                            envelope.message_id
+                        );
+                    }
+                }
+            }
+
+            let size_limit = match target {
+                SyncDestination::Left => ctx.left_message_size_limit.as_ref(),
+                SyncDestination::Right => ctx.right_message_size_limit.as_ref(),
+            };
+
+            // NOTE: when the size of the message is known, only the
+            // headers of oversized messages are downloaded
+            let headers_only = size_limit.is_some_and(|limit| {
+                limit.oversized == MessageSyncOversizedStrategy::Placeholder
+                    && limit.is_exceeded_by(envelope.size)
+            });
+
+            let id = Id::single(&envelope.id);
+            let msgs = match (&source, headers_only) {
+                (SyncDestination::Left, false) => ctx.left.peek_messages(&folder, &id).await?,
+                (SyncDestination::Left, true) => {
+                    ctx.left.peek_message_headers(&folder, &id).await?
+                }
+                (SyncDestination::Right, false) => {
+                    ctx.right.peek_messages(&right_folder, &id).await?
+                }
+                (SyncDestination::Right, true) => {
+                    ctx.right.peek_message_headers(&right_folder, &id).await?
+                }
+            };
+
+            let msgs = msgs.to_vec();
+            let msg = msgs
+                .first()
+                .ok_or_else(|| Error::FindMessageError(envelope.id.clone()))?;
+            let mut raw = Cow::Borrowed(msg.raw()?);
+
+            // NOTE: placeholders only make sense on the side they
+            // have been created for, copying them would propagate
+            // a truncated message
+            if parse_placeholder(&raw).is_some() {
+                return Err(Error::CopyPlaceholderError(folder, envelope.id).into());
+            }
+
+            if headers_only {
+                debug!(
+                    "message {} exceeds size limit, replacing it by a placeholder",
+                    envelope.id
+                );
+                raw = Cow::Owned(build_placeholder(&envelope.id, envelope.size, &raw));
+            } else if let Some(limit) = size_limit {
+                // NOTE: the size of the message may be unknown until
+                // it is downloaded
+                if limit.is_exceeded_by(raw.len()) {
+                    // NOTE: the source cache is left untouched, so
+                    // that the message is not considered as deleted
+                    // from the target at next synchronization
+                    if limit.oversized == MessageSyncOversizedStrategy::Skip {
+                        debug!("message {} exceeds size limit, skipping it", envelope.id);
+                        return Ok(());
+                    }
+
+                    debug!(
+                        "message {} exceeds size limit, replacing it by a placeholder",
+                        envelope.id
+                    );
+                    raw = Cow::Owned(build_placeholder(&envelope.id, raw.len(), &raw));
+                }
+            }
+
+            if refresh_source_cache {
+                cache_envelope::<L, R>(ctx, &folder, &envelope, &source).await?;
+            }
+
+            let envelope = match target {
+                SyncDestination::Left => {
+                    let id = ctx
+                        .left
+                        .add_message_with_flags(&folder, &raw, &envelope.flags)
+                        .await?;
+                    let envelope = ctx.left.get_envelope(&folder, &id).await?;
+                    let flags = envelope.flags.clone();
+                    let msg = envelope.to_sync_cache_msg();
+                    ctx.left_cache
+                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
+                        .await?;
+                    envelope
+                }
+                SyncDestination::Right => {
+                    let id = ctx
+                        .right
+                        .add_message_with_flags(&right_folder, &raw, &envelope.flags)
+                        .await?;
+                    let envelope = ctx.right.get_envelope(&right_folder, &id).await?;
+                    let flags = envelope.flags.clone();
+                    let msg = envelope.to_sync_cache_msg();
+                    ctx.right_cache
+                        .add_message_with_flags(&right_folder, msg.as_bytes(), &flags)
+                        .await?;
+                    envelope
+                }
+            };
+
+            if let Some(labels) = &ctx.labels {
+                labels.insert(&target, &envelope, &folder);
+            }
+        }
+        EmailSyncHunk::Uncache(folder, id, SyncDestination::Left) => {
+            ctx.left_cache
+                .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                .await?;
+        }
+        EmailSyncHunk::Delete(folder, id, SyncDestination::Left) => {
+            ctx.left
+                .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                .await?;
+        }
+        EmailSyncHunk::Uncache(folder, id, SyncDestination::Right) => {
+            let folder = ctx.folder_mapping.to_right(&folder);
+            ctx.right_cache
+                .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                .await?;
+        }
+        EmailSyncHunk::Delete(folder, id, SyncDestination::Right) => {
+            let folder = ctx.folder_mapping.to_right(&folder);
+            ctx.right
+                .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                .await?;
+        }
+        EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Left) => {
+            ctx.left_cache
+                .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                .await?;
+        }
+        EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
+            ctx.left
+                .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                .await?;
+        }
+        EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Right) => {
+            let folder = ctx.folder_mapping.to_right(&folder);
+            ctx.right_cache
+                .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                .await?;
+        }
+        EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Right) => {
+            let folder = ctx.folder_mapping.to_right(&folder);
+            ctx.right
+                .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                .await?;
+        }
+    };
+
+    Ok(())
+}
+
+/// Add the given envelope to the cache of the given side.
+async fn cache_envelope<L, R>(
+    ctx: &SyncPoolContext<L::Context, R::Context>,
+    folder: &str,
+    envelope: &Envelope,
+    side: &SyncDestination,
+) -> AnyResult<()>
+where
+    L: BackendContextBuilder,
+    R: BackendContextBuilder,
+{
+    let msg = envelope.to_sync_cache_msg();
+
+    match side {
+        SyncDestination::Left => {
+            ctx.left_cache
+                .add_message_with_flags(folder, msg.as_bytes(), &envelope.flags)
+                .await?;
+        }
+        SyncDestination::Right => {
+            let folder = ctx.folder_mapping.to_right(folder);
+            ctx.right_cache
+                .add_message_with_flags(&folder, msg.as_bytes(), &envelope.flags)
+                .await?;
+        }
+    };
 
-    Ok(report)
+    Ok(())
 }
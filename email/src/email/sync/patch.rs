@@ -4,10 +4,10 @@
 //! structure of the module is the [`EmailSyncPatch`], which
 //! represents a list of changes (hunks).
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use super::*;
-use crate::flag;
+use crate::{flag, folder::sync::hunk::FolderName};
 
 /// Alias for an envelope hash map where the key is its identifier.
 pub type Envelopes = HashMap<String, Envelope>;
@@ -17,6 +17,15 @@
 // TODO: remove HashSet
 pub type EmailSyncPatch = HashSet<Vec<EmailSyncHunk>>;
 
+/// Email synchronization patches associate a folder with its own
+/// set of hunks.
+pub type EmailSyncPatches = BTreeMap<FolderName, BTreeSet<EmailSyncHunk>>;
+
+/// The envelopes of emails deleted by email synchronization patches,
+/// along with the side they are deleted from, indexed by folder
+/// name.
+pub type EmailSyncDeletedEnvelopes = BTreeMap<FolderName, Vec<(SyncDestination, Envelope)>>;
+
 /// Email synchronization patch builder.
 ///
 /// Contains the core algorithm of the email synchronization. It has
@@ -3,12 +3,74 @@
 //! Module dedicated to email synchronization reporting. The main
 //! structure of this module is [`EmailSyncReport`].
 
+use std::time::Duration;
+
 use super::hunk::EmailSyncHunk;
-use crate::AnyBoxedError;
+use crate::{
+    sync::{
+        progress,
+        report::{SyncFailure, SyncSummary},
+    },
+    AnyBoxedError,
+};
 
 /// The email synchronization report.
 #[derive(Debug, Default)]
 pub struct EmailSyncReport {
     /// The list of processed hunks associated with an optional error.
     pub patch: Vec<(EmailSyncHunk, Option<AnyBoxedError>)>,
+
+    /// The time spent processing hunks.
+    pub duration: Duration,
+}
+
+impl EmailSyncReport {
+    /// Return the amount of bytes transferred by successfully
+    /// processed hunks.
+    pub fn bytes(&self) -> usize {
+        self.patch
+            .iter()
+            .filter(|(_, err)| err.is_none())
+            .map(|(hunk, _)| hunk.size())
+            .sum()
+    }
+
+    /// Return the average throughput, in bytes per second.
+    pub fn throughput(&self) -> Option<f64> {
+        progress::throughput(self.bytes(), self.duration)
+    }
+
+    /// Build the summary of the report.
+    pub fn summary(&self) -> SyncSummary {
+        let mut summary = SyncSummary {
+            bytes: self.bytes(),
+            duration: self.duration,
+            ..Default::default()
+        };
+
+        for (hunk, err) in &self.patch {
+            let folder = summary.folders.entry(hunk.folder().to_owned()).or_default();
+
+            match (hunk, err) {
+                (hunk, Some(err)) => {
+                    folder.failed += 1;
+                    summary
+                        .failures
+                        .push(SyncFailure::new(hunk.folder(), hunk, err));
+                }
+                (EmailSyncHunk::CopyThenCache(..), None) => folder.emails_copied += 1,
+                (EmailSyncHunk::UpdateFlags(..), None) => folder.flags_updated += 1,
+                (EmailSyncHunk::Delete(..), None) => folder.emails_deleted += 1,
+                _ => (),
+            }
+        }
+
+        summary
+    }
+
+    /// Merge the given report into this one.
+    pub fn extend(&mut self, report: EmailSyncReport) {
+        self.patch.extend(report.patch);
+        self.duration += report.duration;
+    }
 }
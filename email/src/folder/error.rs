@@ -31,6 +31,12 @@ pub enum Error {
     RemoveMaildirEntryError(#[source] maildirs::Error, std::path::PathBuf),
     #[error("cannot parse folder kind {0}")]
     ParseFolderKindError(String),
+    #[error("cannot parse folder pattern {1}")]
+    ParseFolderPatternError(#[source] regex::Error, String),
+    #[error("cannot map folders {1} and {2} to the same folder {0}")]
+    DuplicateFolderMappingError(String, String, String),
+    #[error("cannot map folder {0} to {1}: it maps back to {2}")]
+    MapFolderNameError(String, String, String),
     #[error("cannot get uid of imap folder {0}: uid is missing")]
     GetUidMissingImapError(u32),
     #[error("cannot gather folders: {0}")]
@@ -0,0 +1,316 @@
+//! # Folder filter
+//!
+//! Module dedicated to folder selection. The same selection language
+//! is shared by the folder listing (see
+//! [`FolderListConfig`](super::list::config::FolderListConfig)) and
+//! the folder synchronization (see
+//! [`FolderSyncStrategy`](super::sync::config::FolderSyncStrategy)).
+
+use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use regex::Regex;
+
+use super::{Error, Result};
+use crate::debug;
+
+/// The prefix of glob folder patterns.
+pub const GLOB_PREFIX: &str = "glob:";
+
+/// The prefix of regex folder patterns.
+pub const REGEX_PREFIX: &str = "regex:";
+
+/// The folder pattern.
+///
+/// A folder pattern can be:
+///
+/// - An exact folder name, for example `INBOX` or `[Gmail]/Sent Mail`.
+///
+/// - A glob prefixed by `glob:`, for example `glob:Projects/*` or
+///   `glob:Archive/20??`. `*` matches any sequence of characters
+///   except `/`, `**` matches any sequence of characters and `?`
+///   matches exactly one character except `/`.
+///
+/// - A regular expression prefixed by `regex:`, for example
+///   `regex:Archive/\d{4}`. The regular expression needs to match the
+///   whole folder name.
+///
+/// Invalid globs or regular expressions are rejected when
+/// deserializing or when using [`FolderPattern::try_new`]. Patterns
+/// built with [`FolderPattern::new`] never match instead.
+#[derive(Clone, Debug)]
+pub struct FolderPattern {
+    pattern: String,
+    regex: Option<Regex>,
+}
+
+impl FolderPattern {
+    /// Build a folder pattern, which never matches if it is an
+    /// invalid glob or regular expression.
+    pub fn new(pattern: impl ToString) -> Self {
+        let pattern = pattern.to_string();
+
+        match Self::try_new(&pattern) {
+            Ok(pattern) => pattern,
+            Err(_err) => {
+                debug!("{_err}, skipping it");
+                Self {
+                    pattern,
+                    regex: None,
+                }
+            }
+        }
+    }
+
+    /// Build a folder pattern, failing if it is an invalid glob or
+    /// regular expression.
+    pub fn try_new(pattern: impl ToString) -> Result<Self> {
+        let pattern = pattern.to_string();
+
+        let regex = if let Some(glob) = pattern.strip_prefix(GLOB_PREFIX) {
+            Some(glob_to_regex(glob))
+        } else {
+            pattern
+                .strip_prefix(REGEX_PREFIX)
+                .map(|regex| format!("^(?:{regex})$"))
+        };
+
+        let regex = match regex {
+            Some(regex) => match Regex::new(&regex) {
+                Ok(regex) => Some(regex),
+                Err(err) => return Err(Error::ParseFolderPatternError(err, pattern)),
+            },
+            None => None,
+        };
+
+        Ok(Self { pattern, regex })
+    }
+
+    /// Return the pattern as it was given.
+    pub fn as_str(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    /// Return `true` if the given folder matches the pattern.
+    pub fn matches(&self, folder: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(folder),
+            None if self.is_exact() => self.pattern == folder,
+            None => false,
+        }
+    }
+
+    /// Return `true` if the pattern is an exact folder name.
+    pub fn is_exact(&self) -> bool {
+        !self.pattern.starts_with(GLOB_PREFIX) && !self.pattern.starts_with(REGEX_PREFIX)
+    }
+}
+
+impl PartialEq for FolderPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for FolderPattern {}
+
+impl PartialOrd for FolderPattern {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FolderPattern {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.pattern.cmp(&other.pattern)
+    }
+}
+
+impl Hash for FolderPattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state)
+    }
+}
+
+impl fmt::Display for FolderPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+impl From<&str> for FolderPattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+impl From<String> for FolderPattern {
+    fn from(pattern: String) -> Self {
+        Self::new(pattern)
+    }
+}
+
+#[cfg(feature = "derive")]
+impl serde::Serialize for FolderPattern {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+#[cfg(feature = "derive")]
+impl<'de> serde::Deserialize<'de> for FolderPattern {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let pattern = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Self::try_new(pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The folder filter.
+///
+/// Folders are first included, then excluded: a folder matches the
+/// filter if it matches at least one include pattern (or if there is
+/// no include pattern at all) and if it does not match any exclude
+/// pattern.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct FolderFilter {
+    /// The patterns of folders to include.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub include: BTreeSet<FolderPattern>,
+
+    /// The patterns of folders to exclude.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub exclude: BTreeSet<FolderPattern>,
+}
+
+impl FolderFilter {
+    pub fn new(
+        include: impl IntoIterator<Item = impl Into<FolderPattern>>,
+        exclude: impl IntoIterator<Item = impl Into<FolderPattern>>,
+    ) -> Self {
+        Self {
+            include: include.into_iter().map(Into::into).collect(),
+            exclude: exclude.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Return `true` if the given folder matches the filter.
+    pub fn matches(&self, folder: &str) -> bool {
+        let included = self.include.is_empty() || matches_any(&self.include, folder);
+        included && !matches_any(&self.exclude, folder)
+    }
+}
+
+/// Return `true` if the given folder matches at least one of the
+/// given patterns.
+pub fn matches_any<'a>(
+    patterns: impl IntoIterator<Item = &'a FolderPattern>,
+    folder: &str,
+) -> bool {
+    patterns.into_iter().any(|pattern| pattern.matches(folder))
+}
+
+/// Translate the given glob into an anchored regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FolderFilter, FolderPattern};
+
+    #[test]
+    fn exact_pattern() {
+        let pattern = FolderPattern::new("[Gmail]/Sent Mail");
+        assert!(pattern.matches("[Gmail]/Sent Mail"));
+        assert!(!pattern.matches("[Gmail]/Sent"));
+    }
+
+    #[test]
+    fn glob_pattern() {
+        let pattern = FolderPattern::new("glob:Projects/*");
+        assert!(pattern.matches("Projects/foo"));
+        assert!(!pattern.matches("Projects/foo/bar"));
+        assert!(!pattern.matches("Projects"));
+
+        let pattern = FolderPattern::new("glob:Projects/**");
+        assert!(pattern.matches("Projects/foo/bar"));
+
+        let pattern = FolderPattern::new("glob:Archive/20??");
+        assert!(pattern.matches("Archive/2024"));
+        assert!(!pattern.matches("Archive/202"));
+        assert!(!pattern.matches("Archive/20245"));
+
+        let pattern = FolderPattern::new("glob:a.b");
+        assert!(pattern.matches("a.b"));
+        assert!(!pattern.matches("axb"));
+    }
+
+    #[test]
+    fn regex_pattern() {
+        let pattern = FolderPattern::new(r"regex:Archive/\d{4}");
+        assert!(pattern.matches("Archive/2024"));
+        assert!(!pattern.matches("Old/Archive/2024"));
+
+        let pattern = FolderPattern::new("regex:(");
+        assert!(!pattern.matches("("));
+        assert!(FolderPattern::try_new("regex:(").is_err());
+        assert!(FolderPattern::try_new("regex:a").is_ok());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn deserialize_invalid_pattern() {
+        use serde::{de::value::StrDeserializer, Deserialize};
+
+        let de = StrDeserializer::<serde::de::value::Error>::new("regex:(");
+        let err = FolderPattern::deserialize(de).unwrap_err();
+        assert!(err.to_string().contains("regex:("), "{err}");
+
+        let de = StrDeserializer::<serde::de::value::Error>::new("glob:Projects/*");
+        assert!(FolderPattern::deserialize(de).is_ok());
+    }
+
+    #[test]
+    fn include_then_exclude() {
+        let filter = FolderFilter::new(["glob:Projects/**", "INBOX"], ["glob:**/Old"]);
+        assert!(filter.matches("INBOX"));
+        assert!(filter.matches("Projects/foo"));
+        assert!(!filter.matches("Projects/foo/Old"));
+        assert!(!filter.matches("Sent"));
+
+        let filter = FolderFilter::new(Vec::<&str>::new(), ["Trash"]);
+        assert!(filter.matches("INBOX"));
+        assert!(!filter.matches("Trash"));
+    }
+}
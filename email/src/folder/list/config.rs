@@ -1,3 +1,5 @@
+use crate::folder::filter::FolderFilter;
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
@@ -10,4 +12,12 @@ pub struct FolderListConfig {
     /// A page size of 0 disables the pagination and displays all
     /// available folders.
     pub page_size: Option<usize>,
+
+    /// Filter folders being listed.
+    ///
+    /// Folders not matching the filter are hidden from the listing,
+    /// as well as from the synchronization. See
+    /// [`FolderPattern`](crate::folder::filter::FolderPattern) for
+    /// the pattern syntax.
+    pub filter: Option<FolderFilter>,
 }
@@ -17,6 +17,7 @@
 pub mod delete;
 mod error;
 pub mod expunge;
+pub mod filter;
 #[cfg(feature = "imap")]
 pub mod imap;
 pub mod list;
@@ -33,9 +34,6 @@
     str::FromStr,
 };
 
-#[cfg(feature = "sync")]
-pub(crate) use sync::sync;
-
 #[doc(inline)]
 pub use self::error::{Error, Result};
 
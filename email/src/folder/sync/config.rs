@@ -1,6 +1,11 @@
 //! # Folder sync config
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::folder::{
+    filter::{matches_any, FolderFilter, FolderPattern},
+    Error, Result,
+};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -14,6 +19,9 @@ pub struct FolderSyncConfig {
 
     #[cfg_attr(feature = "derive", serde(default))]
     pub permissions: FolderSyncPermissions,
+
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub mapping: FolderSyncMapping,
 }
 
 /// The folder synchronization strategy.
@@ -28,21 +36,204 @@ pub enum FolderSyncStrategy {
     #[default]
     All,
 
-    /// Synchronizes only folders matching the given names.
-    Include(BTreeSet<String>),
+    /// Synchronizes only folders matching the given patterns.
+    ///
+    /// See [`FolderPattern`] for the pattern syntax.
+    Include(BTreeSet<FolderPattern>),
 
     /// Synchronizes all folders except the ones matching the given
-    /// names.
-    Exclude(BTreeSet<String>),
+    /// patterns.
+    ///
+    /// See [`FolderPattern`] for the pattern syntax.
+    Exclude(BTreeSet<FolderPattern>),
+
+    /// Synchronizes folders matching the include patterns, except
+    /// the ones matching the exclude patterns.
+    Filter(FolderFilter),
 }
 
 impl FolderSyncStrategy {
+    /// Build an include strategy from folder names or patterns.
+    pub fn include(patterns: impl IntoIterator<Item = impl Into<FolderPattern>>) -> Self {
+        Self::Include(patterns.into_iter().map(Into::into).collect())
+    }
+
+    /// Build an exclude strategy from folder names or patterns.
+    pub fn exclude(patterns: impl IntoIterator<Item = impl Into<FolderPattern>>) -> Self {
+        Self::Exclude(patterns.into_iter().map(Into::into).collect())
+    }
+
     pub fn matches(&self, folder: &str) -> bool {
         match self {
             FolderSyncStrategy::All => true,
-            FolderSyncStrategy::Include(folders) => folders.contains(folder),
-            FolderSyncStrategy::Exclude(folders) => !folders.contains(folder),
+            FolderSyncStrategy::Include(patterns) => matches_any(patterns, folder),
+            FolderSyncStrategy::Exclude(patterns) => !matches_any(patterns, folder),
+            FolderSyncStrategy::Filter(filter) => filter.matches(folder),
+        }
+    }
+}
+
+/// The folder synchronization name mapping.
+///
+/// By default, a folder is synchronized with the folder having the
+/// exact same name on the other side. The mapping allows folders to
+/// have different names on each side, for example `[Gmail]/Sent Mail`
+/// on the right side and `Sent` on the left side.
+///
+/// Names are first looked up in the explicit [names](Self::names)
+/// mapping, which also applies to subfolders. Other names get their
+/// hierarchy delimiter translated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct FolderSyncMapping {
+    /// The explicit folder names mapping, from right names to left
+    /// names.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub names: BTreeMap<String, String>,
+
+    /// The hierarchy delimiter of the left side.
+    ///
+    /// Defaults to `/`.
+    pub left_delimiter: Option<char>,
+
+    /// The hierarchy delimiter of the right side.
+    ///
+    /// Defaults to `/`.
+    pub right_delimiter: Option<char>,
+}
+
+impl FolderSyncMapping {
+    pub const DEFAULT_DELIMITER: char = '/';
+
+    pub fn with_name(mut self, right: impl ToString, left: impl ToString) -> Self {
+        self.names.insert(right.to_string(), left.to_string());
+        self
+    }
+
+    pub fn with_left_delimiter(mut self, delim: char) -> Self {
+        self.left_delimiter = Some(delim);
+        self
+    }
+
+    pub fn with_right_delimiter(mut self, delim: char) -> Self {
+        self.right_delimiter = Some(delim);
+        self
+    }
+
+    pub fn get_left_delimiter(&self) -> char {
+        self.left_delimiter.unwrap_or(Self::DEFAULT_DELIMITER)
+    }
+
+    pub fn get_right_delimiter(&self) -> char {
+        self.right_delimiter.unwrap_or(Self::DEFAULT_DELIMITER)
+    }
+
+    /// Return `true` if the mapping does not change any name.
+    pub fn is_identity(&self) -> bool {
+        self.names.is_empty() && self.get_left_delimiter() == self.get_right_delimiter()
+    }
+
+    /// Map the given right folder name to its left name.
+    pub fn to_left(&self, folder: &str) -> String {
+        let names = self.names.iter().map(|(r, l)| (r.as_str(), l.as_str()));
+        let (from, to) = (self.get_right_delimiter(), self.get_left_delimiter());
+        map_name(names, from, to, folder)
+    }
+
+    /// Map the given left folder name to its right name.
+    pub fn to_right(&self, folder: &str) -> String {
+        let names = self.names.iter().map(|(r, l)| (l.as_str(), r.as_str()));
+        let (from, to) = (self.get_left_delimiter(), self.get_right_delimiter());
+        map_name(names, from, to, folder)
+    }
+
+    /// Map the given right folder name to its left name, making sure
+    /// that the left name maps back to the given right name.
+    ///
+    /// A name may not map back when it contains the hierarchy
+    /// delimiter of the other side, or when it is shadowed by an
+    /// explicit name. Synchronizing such folder would silently merge
+    /// it with another one.
+    pub fn try_to_left(&self, folder: &str) -> Result<String> {
+        let left = self.to_left(folder);
+        let right = self.to_right(&left);
+
+        if right == folder {
+            Ok(left)
+        } else {
+            Err(Error::MapFolderNameError(folder.to_owned(), left, right))
+        }
+    }
+
+    /// Map the given left folder name to its right name, making sure
+    /// that the right name maps back to the given left name.
+    ///
+    /// See [`FolderSyncMapping::try_to_left`].
+    pub fn try_to_right(&self, folder: &str) -> Result<String> {
+        let right = self.to_right(folder);
+        let left = self.to_left(&right);
+
+        if left == folder {
+            Ok(right)
+        } else {
+            Err(Error::MapFolderNameError(folder.to_owned(), right, left))
+        }
+    }
+
+    /// Check that the mapping can be reversed.
+    ///
+    /// Explicit names must map distinct right names to distinct left
+    /// names, otherwise left names cannot be mapped back.
+    pub fn validate(&self) -> Result<()> {
+        let mut rights = BTreeMap::<&str, &str>::new();
+
+        for (right, left) in &self.names {
+            if let Some(other) = rights.insert(left, right) {
+                return Err(Error::DuplicateFolderMappingError(
+                    left.to_owned(),
+                    other.to_owned(),
+                    right.to_owned(),
+                ));
+            }
         }
+
+        Ok(())
+    }
+}
+
+/// Map the given folder name using the given explicit names, then
+/// using the given delimiters.
+///
+/// When several explicit names match, the longest one wins, so that
+/// a mapping defined for a subfolder takes precedence over a mapping
+/// defined for its parent.
+fn map_name<'a>(
+    names: impl IntoIterator<Item = (&'a str, &'a str)>,
+    from_delim: char,
+    to_delim: char,
+    folder: &str,
+) -> String {
+    let translate = |name: &str| name.replace(from_delim, &to_delim.to_string());
+
+    let mapped = names
+        .into_iter()
+        .filter_map(|(from, to)| {
+            if folder == from {
+                return Some((from.len(), to.to_owned()));
+            }
+
+            let rest = folder.strip_prefix(from)?.strip_prefix(from_delim)?;
+            Some((from.len(), format!("{to}{to_delim}{}", translate(rest))))
+        })
+        .max_by_key(|(len, _)| *len);
+
+    match mapped {
+        Some((_, name)) => name,
+        None => translate(folder),
     }
 }
 
@@ -84,3 +275,87 @@ fn default() -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FolderSyncMapping;
+    use crate::folder::Error;
+
+    #[test]
+    fn identity_mapping() {
+        let mapping = FolderSyncMapping::default();
+
+        assert!(mapping.is_identity());
+        assert_eq!(mapping.to_left("INBOX/foo"), "INBOX/foo");
+        assert_eq!(mapping.to_right("INBOX/foo"), "INBOX/foo");
+    }
+
+    #[test]
+    fn explicit_names_mapping() {
+        let mapping = FolderSyncMapping::default()
+            .with_name("[Gmail]/Sent Mail", "Sent")
+            .with_name("[Gmail]/All Mail", "Archive")
+            .with_name("[Gmail]/All Mail/2024", "2024");
+
+        assert_eq!(mapping.to_left("[Gmail]/Sent Mail"), "Sent");
+        assert_eq!(mapping.to_right("Sent"), "[Gmail]/Sent Mail");
+        assert_eq!(mapping.to_left("[Gmail]/All Mail/2023"), "Archive/2023");
+        assert_eq!(mapping.to_right("Archive/2023"), "[Gmail]/All Mail/2023");
+        assert_eq!(mapping.to_left("[Gmail]/All Mail/2024"), "2024");
+        assert_eq!(mapping.to_right("2024"), "[Gmail]/All Mail/2024");
+        assert_eq!(
+            mapping.to_left("[Gmail]/Sent Mailbox"),
+            "[Gmail]/Sent Mailbox"
+        );
+    }
+
+    #[test]
+    fn delimiters_mapping() {
+        let mapping = FolderSyncMapping::default()
+            .with_right_delimiter('.')
+            .with_name("INBOX.Archive", "Archive");
+
+        assert_eq!(mapping.to_left("INBOX.Archive"), "Archive");
+        assert_eq!(mapping.to_left("INBOX.Archive.2024"), "Archive/2024");
+        assert_eq!(mapping.to_left("INBOX.Projects.foo"), "INBOX/Projects/foo");
+        assert_eq!(mapping.to_right("Archive/2024"), "INBOX.Archive.2024");
+        assert_eq!(mapping.to_right("INBOX/Projects/foo"), "INBOX.Projects.foo");
+    }
+
+    #[test]
+    fn duplicate_names_mapping() {
+        let mapping = FolderSyncMapping::default()
+            .with_name("[Gmail]/Sent Mail", "Sent")
+            .with_name("Sent Items", "Sent");
+
+        assert!(matches!(
+            mapping.validate(),
+            Err(Error::DuplicateFolderMappingError(left, a, b))
+                if left == "Sent" && a == "Sent Items" && b == "[Gmail]/Sent Mail"
+        ));
+
+        let mapping = FolderSyncMapping::default().with_name("[Gmail]/Sent Mail", "Sent");
+        assert!(mapping.validate().is_ok());
+    }
+
+    #[test]
+    fn irreversible_names_mapping() {
+        let mapping = FolderSyncMapping::default().with_right_delimiter('.');
+
+        assert_eq!(mapping.try_to_left("INBOX.foo").unwrap(), "INBOX/foo");
+        assert_eq!(mapping.try_to_right("INBOX/foo").unwrap(), "INBOX.foo");
+        assert!(matches!(
+            mapping.try_to_left("a/b"),
+            Err(Error::MapFolderNameError(folder, left, right))
+                if folder == "a/b" && left == "a/b" && right == "a.b"
+        ));
+        assert!(mapping.try_to_right("a.b").is_err());
+
+        let mapping = FolderSyncMapping::default()
+            .with_name("Archive", "Old")
+            .with_name("Trash", "Old/Trash");
+
+        assert_eq!(mapping.try_to_left("Archive/2024").unwrap(), "Old/2024");
+        assert!(mapping.try_to_left("Archive/Trash").is_err());
+    }
+}
@@ -16,6 +16,11 @@
 
 /// The folder synchronization hunk.
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum FolderSyncHunk {
     /// The given folder name needs to be created to the given
     /// destination.
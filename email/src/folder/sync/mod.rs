@@ -12,7 +12,7 @@
 
 use futures::{stream::FuturesUnordered, StreamExt};
 
-use self::{hunk::FolderSyncHunk, report::FolderSyncReport};
+use self::{hunk::FolderSyncHunk, patch::FolderSyncPatches, report::FolderSyncReport};
 use super::{
     add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders, Folder,
 };
@@ -25,15 +25,17 @@
     trace,
 };
 
-pub(crate) async fn sync<L, R>(
+/// Build the folder synchronization patch.
+///
+/// Folders are listed from both sides and their caches, then compared
+/// in order to generate the hunks (changes) to apply to each folder.
+pub(crate) async fn build_patch<L, R>(
     ctx_ref: Arc<SyncPoolContext<L::Context, R::Context>>,
-) -> Result<FolderSyncReport>
+) -> Result<FolderSyncPatches>
 where
     L: BackendContextBuilder + 'static,
     R: BackendContextBuilder + 'static,
 {
-    let mut report = FolderSyncReport::default();
-
     let ctx = ctx_ref.clone();
     let left_cached_folders = tokio::spawn(async move {
         let folders = ctx
@@ -115,7 +117,10 @@ pub(crate) async fn sync<L, R>(
                 // new backend fn called `search_folders` and to set
                 // up a common search API across backends.
                 .filter_map(|folder| {
-                    if ctx.folder_filters.matches(folder) {
+                    if ctx
+                        .folder_filters
+                        .matches(&ctx.folder_mapping.to_left(folder))
+                    {
                         Some(folder.to_owned())
                     } else {
                         None
@@ -147,7 +152,10 @@ pub(crate) async fn sync<L, R>(
                 // new backend fn called `search_folders` and to set
                 // up a common search API across backends.
                 .filter_map(|folder| {
-                    if ctx.folder_filters.matches(folder) {
+                    if ctx
+                        .folder_filters
+                        .matches(&ctx.folder_mapping.to_left(folder))
+                    {
                         Some(folder.to_owned())
                     } else {
                         None
@@ -173,19 +181,53 @@ pub(crate) async fn sync<L, R>(
 
     SyncEvent::ListedAllFolders.emit(&ctx_ref.handler).await;
 
-    let mut patch = patch::build(
+    let (left_cached_folders, left_folders, right_cached_folders, right_folders) = (
         left_cached_folders?,
         left_folders?,
         right_cached_folders?,
         right_folders?,
     );
 
+    // folders that cannot be mapped back would be silently merged
+    // with other folders, so they abort the synchronization
+    let mapping = &ctx_ref.folder_mapping;
+    if !mapping.is_identity() {
+        for folder in left_cached_folders.iter().chain(&left_folders) {
+            mapping.try_to_right(folder)?;
+        }
+        for folder in right_cached_folders.iter().chain(&right_folders) {
+            mapping.try_to_left(folder)?;
+        }
+    }
+
+    let mut patch = patch::build_with_mapping(
+        left_cached_folders,
+        left_folders,
+        right_cached_folders,
+        right_folders,
+        mapping,
+    );
+
     ctx_ref.apply_folder_permissions(&mut patch);
 
     SyncEvent::GeneratedFolderPatch(patch.clone())
         .emit(&ctx_ref.handler)
         .await;
 
+    Ok(patch)
+}
+
+/// Apply the given folder synchronization patch.
+pub(crate) async fn apply_patch<L, R>(
+    ctx_ref: Arc<SyncPoolContext<L::Context, R::Context>>,
+    patch: FolderSyncPatches,
+) -> FolderSyncReport
+where
+    L: BackendContextBuilder + 'static,
+    R: BackendContextBuilder + 'static,
+{
+    let mut report = FolderSyncReport::default();
+
     let (folders, patch) = patch.into_iter().fold(
         (HashSet::default(), vec![]),
         |(mut folders, mut patch), (folder, hunks)| {
@@ -214,10 +256,14 @@ pub(crate) async fn sync<L, R>(
                         ctx.left.add_folder(&folder).await?;
                     }
                     FolderSyncHunk::Cache(folder, SyncDestination::Right) => {
-                        ctx.right_cache.add_folder(&folder).await?;
+                        ctx.right_cache
+                            .add_folder(&ctx.folder_mapping.to_right(&folder))
+                            .await?;
                     }
                     FolderSyncHunk::Create(folder, SyncDestination::Right) => {
-                        ctx.right.add_folder(&folder).await?;
+                        ctx.right
+                            .add_folder(&ctx.folder_mapping.to_right(&folder))
+                            .await?;
                     }
                     FolderSyncHunk::Uncache(folder, SyncDestination::Left) => {
                         ctx.left_cache.delete_folder(&folder).await?;
@@ -226,10 +272,14 @@ pub(crate) async fn sync<L, R>(
                         ctx.left.delete_folder(&folder).await?;
                     }
                     FolderSyncHunk::Uncache(folder, SyncDestination::Right) => {
-                        ctx.right_cache.delete_folder(&folder).await?;
+                        ctx.right_cache
+                            .delete_folder(&ctx.folder_mapping.to_right(&folder))
+                            .await?;
                     }
                     FolderSyncHunk::Delete(folder, SyncDestination::Right) => {
-                        ctx.right.delete_folder(&folder).await?;
+                        ctx.right
+                            .delete_folder(&ctx.folder_mapping.to_right(&folder))
+                            .await?;
                     }
                 };
 
@@ -265,7 +315,7 @@ pub(crate) async fn sync<L, R>(
         .emit(&ctx_ref.handler)
         .await;
 
-    Ok(report)
+    report
 }
 
 pub(crate) async fn expunge<L, R>(
@@ -302,7 +352,9 @@ pub(crate) async fn expunge<L, R>(
             if ctx.dry_run {
                 Ok(())
             } else {
-                ctx.right_cache.expunge_folder(&folder).await
+                ctx.right_cache
+                    .expunge_folder(&ctx.folder_mapping.to_right(&folder))
+                    .await
             }
         };
 
@@ -312,7 +364,9 @@ pub(crate) async fn expunge<L, R>(
             if ctx.dry_run {
                 Ok(())
             } else {
-                ctx.right.expunge_folder(&folder).await
+                ctx.right
+                    .expunge_folder(&ctx.folder_mapping.to_right(&folder))
+                    .await
             }
         };
 
@@ -8,7 +8,10 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
-use super::hunk::{FolderName, FolderSyncHunk, FoldersName};
+use super::{
+    config::FolderSyncMapping,
+    hunk::{FolderName, FolderSyncHunk, FoldersName},
+};
 use crate::sync::SyncDestination;
 
 /// A folder synchronization patch is just a list of folder
@@ -19,6 +22,30 @@
 /// patch.
 pub type FolderSyncPatches = BTreeMap<FolderName, FolderSyncPatch>;
 
+/// Folder synchronization patch builder, with name mapping.
+///
+/// Right folder names are mapped to their left name before being
+/// compared, so that folders with different names on each side are
+/// still considered as the same folder. Hunks of the resulting patch
+/// always refer to left names, see [`FolderSyncMapping::to_right`] to
+/// get back right names.
+pub fn build_with_mapping(
+    local_cache: FoldersName,
+    local: FoldersName,
+    remote_cache: FoldersName,
+    remote: FoldersName,
+    mapping: &FolderSyncMapping,
+) -> FolderSyncPatches {
+    if mapping.is_identity() {
+        return build(local_cache, local, remote_cache, remote);
+    }
+
+    let remote_cache = remote_cache.iter().map(|f| mapping.to_left(f)).collect();
+    let remote = remote.iter().map(|f| mapping.to_left(f)).collect();
+
+    build(local_cache, local, remote_cache, remote)
+}
+
 /// Folder synchronization patch builder.
 ///
 /// Contains the core algorithm of the folder synchronization. It has
@@ -151,7 +178,7 @@ pub fn build(
 mod tests {
     use std::collections::{BTreeMap, BTreeSet};
 
-    use super::{FolderSyncHunk, FoldersName};
+    use super::{FolderSyncHunk, FolderSyncMapping, FoldersName};
     use crate::sync::SyncDestination;
 
     #[test]
@@ -420,4 +447,32 @@ fn build_folder_patch() {
             BTreeMap::from_iter([("folder".into(), BTreeSet::from_iter([]))])
         );
     }
+
+    #[test]
+    fn build_folder_patch_with_mapping() {
+        let mapping = FolderSyncMapping::default()
+            .with_right_delimiter('.')
+            .with_name("[Gmail].Sent Mail", "Sent");
+
+        assert_eq!(
+            super::build_with_mapping(
+                FoldersName::from_iter(["Sent".into(), "a/b".into()]),
+                FoldersName::from_iter(["Sent".into(), "a/b".into()]),
+                FoldersName::from_iter(["[Gmail].Sent Mail".into(), "a.b".into()]),
+                FoldersName::from_iter(["[Gmail].Sent Mail".into()]),
+                &mapping,
+            ),
+            BTreeMap::from_iter([
+                ("Sent".into(), BTreeSet::from_iter([])),
+                (
+                    "a/b".into(),
+                    BTreeSet::from_iter([
+                        FolderSyncHunk::Uncache("a/b".into(), SyncDestination::Left),
+                        FolderSyncHunk::Delete("a/b".into(), SyncDestination::Left),
+                        FolderSyncHunk::Uncache("a/b".into(), SyncDestination::Right),
+                    ]),
+                ),
+            ]),
+        );
+    }
 }
@@ -3,7 +3,10 @@
 //! The core structure of this module is the [`FolderSyncReport`].
 
 use super::hunk::{FolderSyncHunk, FoldersName};
-use crate::AnyBoxedError;
+use crate::{
+    sync::report::{SyncFailure, SyncSummary},
+    AnyBoxedError,
+};
 
 /// The folder synchronization report.
 #[derive(Debug, Default)]
@@ -15,3 +18,35 @@ pub struct FolderSyncReport {
     /// error. Hunks that could not be processed are ignored.
     pub patch: Vec<(FolderSyncHunk, Option<AnyBoxedError>)>,
 }
+
+impl FolderSyncReport {
+    /// Build the summary of the report.
+    ///
+    /// Every folder found during the synchronization process is part
+    /// of the summary, even folders without any change.
+    pub fn summary(&self) -> SyncSummary {
+        let mut summary = SyncSummary::default();
+
+        for name in &self.names {
+            summary.folders.entry(name.clone()).or_default();
+        }
+
+        for (hunk, err) in &self.patch {
+            let folder = summary.folders.entry(hunk.folder().to_owned()).or_default();
+
+            match (hunk, err) {
+                (hunk, Some(err)) => {
+                    folder.failed += 1;
+                    summary
+                        .failures
+                        .push(SyncFailure::new(hunk.folder(), hunk, err));
+                }
+                (FolderSyncHunk::Create(..), None) => folder.folders_created += 1,
+                (FolderSyncHunk::Delete(..), None) => folder.folders_deleted += 1,
+                _ => (),
+            }
+        }
+
+        summary
+    }
+}
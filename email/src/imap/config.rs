@@ -11,7 +11,11 @@
 use super::{Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::OAuth2Config;
-use crate::account::config::passwd::PasswdConfig;
+use crate::{
+    account::config::passwd::{PasswdConfig, SaslMechanism},
+    retry::RetryConfig,
+    tls::TlsConfig,
+};
 
 /// Errors related to the IMAP backend configuration.
 
@@ -38,6 +42,13 @@ pub struct ImapConfig {
     )]
     pub encryption: Option<ImapEncryptionKind>,
 
+    /// The IMAP TLS configuration.
+    ///
+    /// Customizes the trust store, the client certificate, the
+    /// minimum TLS version and the server certificate verification
+    /// of SSL/TLS and STARTTLS connections. See [TlsConfig].
+    pub tls: Option<TlsConfig>,
+
     /// The IMAP server login.
     ///
     /// Usually, the login is either the email address or its left
@@ -50,6 +61,17 @@ pub struct ImapConfig {
     /// See [ImapAuthConfig].
     pub auth: ImapAuthConfig,
 
+    /// The SASL mechanism used by the password authentication.
+    ///
+    /// When undefined, the most secure mechanism supported by the
+    /// server is negotiated, see [`SaslMechanism::NEGOTIABLE`].
+    /// When defined, authentication fails if the server does not
+    /// support it, and no other mechanism is tried. This is the way
+    /// to make sure that the password is never sent in clear, by
+    /// choosing a SCRAM mechanism. Ignored by the OAuth 2.0
+    /// authentication.
+    pub sasl_mechanism: Option<SaslMechanism>,
+
     /// The IMAP extensions configuration.
     pub extensions: Option<ImapExtensionsConfig>,
 
@@ -64,6 +86,12 @@ pub struct ImapConfig {
     /// Defines the number of clients that are created and managed
     /// simultaneously by the IMAP context. Defaults to 1.
     pub clients_pool_size: Option<u8>,
+
+    /// The IMAP requests retry policy.
+    ///
+    /// Defines timeouts, backoff and retryable errors of IMAP
+    /// requests. See [RetryConfig].
+    pub retry: Option<RetryConfig>,
 }
 
 impl ImapConfig {
@@ -1,4 +1,4 @@
-use std::{any::Any, collections::HashSet, result};
+use std::{any::Any, collections::HashSet, io, result};
 
 use imap_client::ClientError;
 use imap_next::{
@@ -8,8 +8,13 @@
 };
 use thiserror::Error;
 use tokio::task::JoinError;
+use tokio_rustls::rustls::pki_types::InvalidDnsNameError;
 
-use crate::{account, AnyBoxedError, AnyError};
+use crate::{
+    account::{self, config::passwd::SaslMechanism},
+    retry::RetryHistory,
+    sasl, tls, AnyBoxedError, AnyError,
+};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -21,12 +26,24 @@ pub enum Error {
     JoinClientError(#[source] JoinError),
     #[error("cannot build IMAP client")]
     BuildClientError(#[source] Box<Error>),
-    #[error("cannot connect to IMAP server {1}:{2} using TCP")]
-    BuildInsecureClientError(#[source] ClientError, String, u16),
-    #[error("cannot connect to IMAP server {1}:{2} using STARTTLS")]
-    BuildStartTlsClientError(#[source] ClientError, String, u16),
-    #[error("cannot connect to IMAP server {1}:{2} using SSL/TLS")]
-    BuildTlsClientError(#[source] ClientError, String, u16),
+    #[error("cannot connect to IMAP server {1}:{2}")]
+    ConnectTcpError(#[source] io::Error, String, u16),
+    #[error("cannot receive greeting from IMAP server {1}:{2}")]
+    ReadGreetingError(#[source] io::Error, String, u16),
+    #[error("cannot connect to IMAP server {1}:{2}: greeting rejected: {0}")]
+    RejectGreetingError(String, String, u16),
+    #[error("cannot upgrade connection to IMAP server {1}:{2} using STARTTLS")]
+    StartTlsError(#[source] io::Error, String, u16),
+    #[error("cannot upgrade connection to IMAP server {1}:{2} using STARTTLS: {0}")]
+    RejectStartTlsError(String, String, u16),
+    #[error("cannot build IMAP TLS configuration")]
+    BuildTlsConfigError(#[source] tls::Error),
+    #[error("cannot parse IMAP server name {1}")]
+    ParseServerNameError(#[source] InvalidDnsNameError, String),
+    #[error("cannot perform TLS handshake with IMAP server {1}:{2}")]
+    TlsHandshakeError(#[source] io::Error, String, u16),
+    #[error("cannot get capabilities of IMAP server {1}:{2}")]
+    BuildClientCapabilitiesError(#[source] ClientError, String, u16),
 
     #[error("cannot get imap password from global keyring")]
     GetPasswdImapError(#[source] secret::Error),
@@ -62,8 +79,10 @@ pub enum Error {
     RequestRetryError(#[source] ClientError),
     #[error("cannot send IMAP request")]
     ClientRetryError(#[source] ClientError),
-    #[error("cannot send IMAP request: request timed out after 3 attempts")]
-    RequestRetryTimeoutError,
+    #[error("cannot send IMAP request: request timed out after {0}")]
+    RequestRetryTimeoutError(RetryHistory),
+    #[error("cannot send IMAP request: connection lost after {1}")]
+    ConnectionLostError(#[source] Box<Error>, RetryHistory),
     #[error("cannot enable IMAP capability")]
     EnableCapabilityError(#[source] ClientError),
     #[error("cannot authenticate to IMAP server: no valid auth mechanism found")]
@@ -76,67 +95,71 @@ pub enum Error {
     AuthenticateXOauth2Error(#[source] ClientError),
     #[error("cannot authenticate to IMAP server using SASL OAUTHBEARER mechanism")]
     AuthenticateOAuthBearerError(#[source] ClientError),
+    #[error("cannot authenticate to IMAP server using SASL {0} mechanism")]
+    AuthenticateSaslError(SaslMechanism, #[source] ClientError),
+    #[error("cannot authenticate to IMAP server using SASL {0} mechanism")]
+    AuthenticateSaslExchangeError(SaslMechanism, #[source] sasl::Error),
 
     #[error("cannot create IMAP mailbox")]
     CreateMailboxError(#[source] ClientError),
-    #[error("cannot create IMAP mailbox: request timed out")]
-    CreateMailboxTimedOutError,
+    #[error("cannot create IMAP mailbox: request timed out after {0}")]
+    CreateMailboxTimedOutError(RetryHistory),
 
     #[error("cannot select IMAP mailbox")]
     SelectMailboxError(#[source] ClientError),
-    #[error("cannot select IMAP mailbox: request timed out")]
-    SelectMailboxTimedOutError,
+    #[error("cannot select IMAP mailbox: request timed out after {0}")]
+    SelectMailboxTimedOutError(RetryHistory),
 
     #[error("cannot examine IMAP mailbox")]
     ExamineMailboxError(#[source] ClientError),
-    #[error("cannot examine IMAP mailbox: request timed out")]
-    ExamineMailboxTimedOutError,
+    #[error("cannot examine IMAP mailbox: request timed out after {0}")]
+    ExamineMailboxTimedOutError(RetryHistory),
 
     #[error("cannot list IMAP mailboxes")]
     ListMailboxesError(#[source] ClientError),
-    #[error("cannot list IMAP mailboxes: request timed out")]
-    ListMailboxesTimedOutError,
+    #[error("cannot list IMAP mailboxes: request timed out after {0}")]
+    ListMailboxesTimedOutError(RetryHistory),
 
     #[error("cannot expunge selected IMAP mailbox")]
     ExpungeMailboxError(#[source] ClientError),
-    #[error("cannot expunge selected IMAP mailbox: request timed out")]
-    ExpungeMailboxTimedOutError,
+    #[error("cannot expunge selected IMAP mailbox: request timed out after {0}")]
+    ExpungeMailboxTimedOutError(RetryHistory),
 
     #[error("cannot delete IMAP mailbox")]
     DeleteMailboxError(#[source] ClientError),
-    #[error("cannot delete IMAP mailbox: request timed out")]
-    DeleteMailboxTimedOutError,
+    #[error("cannot delete IMAP mailbox: request timed out after {0}")]
+    DeleteMailboxTimedOutError(RetryHistory),
 
     #[error("cannot fetch IMAP messages")]
     FetchMessagesError(#[source] ClientError),
-    #[error("cannot fetch IMAP messages: request timed out")]
-    FetchMessagesTimedOutError,
+    #[error("cannot fetch IMAP messages: request timed out after {0}")]
+    FetchMessagesTimedOutError(RetryHistory),
 
     #[error("cannot thread IMAP messages")]
     ThreadMessagesError(#[source] ClientError),
-    #[error("cannot thread IMAP messages: request timed out")]
-    ThreadMessagesTimedOutError,
+    #[error("cannot thread IMAP messages: request timed out after {0}")]
+    ThreadMessagesTimedOutError(RetryHistory),
 
     #[error("cannot store IMAP flag(s)")]
     StoreFlagsError(#[source] ClientError),
-    #[error("cannot store IMAP flag(s): request timed out")]
-    StoreFlagsTimedOutError,
+    #[error("cannot store IMAP flag(s): request timed out after {0}")]
+    StoreFlagsTimedOutError(RetryHistory),
     #[error("cannot add IMAP message")]
     AddMessageError(#[source] ClientError),
-    #[error("cannot add IMAP message: request timed out")]
-    AddMessageTimedOutError,
+    #[error("cannot add IMAP message: request timed out after {0}")]
+    AddMessageTimedOutError(RetryHistory),
     #[error("cannot copy IMAP message(s)")]
     CopyMessagesError(#[source] ClientError),
-    #[error("cannot copy IMAP message(s): request timed out")]
-    CopyMessagesTimedOutError,
+    #[error("cannot copy IMAP message(s): request timed out after {0}")]
+    CopyMessagesTimedOutError(RetryHistory),
     #[error("cannot move IMAP message(s)")]
     MoveMessagesError(#[source] ClientError),
-    #[error("cannot move IMAP message(s): request timed out")]
-    MoveMessagesTimedOutError,
+    #[error("cannot move IMAP message(s): request timed out after {0}")]
+    MoveMessagesTimedOutError(RetryHistory),
     #[error("cannot execute no-operation")]
     NoOpError(#[source] ClientError),
-    #[error("cannot execute no-operation: request timed out")]
-    NoOpTimedOutError,
+    #[error("cannot execute no-operation: request timed out after {0}")]
+    NoOpTimedOutError(RetryHistory),
 
     #[error("cannot exchange IMAP client/server ids")]
     ExchangeIdsError(#[source] ClientError),
@@ -146,12 +169,12 @@ pub enum Error {
     SortMessagesError(#[source] ClientError),
     #[error("cannot sort IMAP envelope UIDs")]
     SortUidsError(#[source] ClientError),
-    #[error("cannot sort IMAP envelope UIDs: request timed out")]
-    SortUidsTimedOutError,
+    #[error("cannot sort IMAP envelope UIDs: request timed out after {0}")]
+    SortUidsTimedOutError(RetryHistory),
     #[error("cannot search IMAP envelope UIDs")]
     SearchUidsError(#[source] ClientError),
-    #[error("cannot search IMAP envelope UIDs: request timed out")]
-    SearchUidsTimedOutError,
+    #[error("cannot search IMAP envelope UIDs: request timed out after {0}")]
+    SearchUidsTimedOutError(RetryHistory),
     #[error("cannot start IMAP IDLE mode")]
     StartIdleError(#[source] StreamError<ClientFlowError>),
     #[error("cannot stop IMAP IDLE mode")]
@@ -178,6 +201,8 @@ pub enum Error {
     AuthenticateXOAuth2NotSupportedError(HashSet<AuthMechanism<'static>>),
     #[error("OAuthBearer authentication not supported (available: {0:?})")]
     AuthenticateOAuthBearerNotSupportedError(HashSet<AuthMechanism<'static>>),
+    #[error("SASL {0} authentication not supported (available: {1:?})")]
+    AuthenticateSaslNotSupportedError(SaslMechanism, HashSet<AuthMechanism<'static>>),
 
     // tasks
     #[error("cannot execute IMAP action")]
@@ -1,5 +1,7 @@
 pub mod config;
 mod error;
+mod sasl;
+mod stream;
 
 use std::{
     collections::HashMap, env, fmt, io::ErrorKind::ConnectionReset, num::NonZeroU32, sync::Arc,
@@ -20,7 +22,7 @@
             sort::SortCriterion,
             thread::{Thread, ThreadingAlgorithm},
         },
-        fetch::MessageDataItem,
+        fetch::{MacroOrMessageDataItemNames, MessageDataItem},
         flag::{Flag, StoreType},
         search::SearchKey,
         sequence::SequenceSet,
@@ -34,19 +36,20 @@
     time::sleep,
 };
 
-use self::config::{ImapAuthConfig, ImapConfig};
 #[doc(inline)]
 pub use self::error::{Error, Result};
+use self::{
+    config::{ImapAuthConfig, ImapConfig},
+    sasl::AuthenticateSaslTask,
+};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::OAuth2Method;
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{imap::ThreadImapEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
 use crate::envelope::watch::{imap::WatchImapEnvelopes, WatchEnvelopes};
-#[cfg(feature = "oauth2")]
-use crate::warn;
 use crate::{
-    account::config::AccountConfig,
+    account::config::{passwd::SaslMechanism, AccountConfig},
     backend::{
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
@@ -54,7 +57,7 @@
     debug,
     envelope::{
         get::{imap::GetImapEnvelope, GetEnvelope},
-        imap::FETCH_ENVELOPES,
+        imap::{FETCH_ENVELOPES, FETCH_ENVELOPES_GMAIL},
         list::{imap::ListImapEnvelopes, ListEnvelopes},
         Envelope, Envelopes,
     },
@@ -71,20 +74,20 @@
         purge::{imap::PurgeImapFolder, PurgeFolder},
         Folders,
     },
-    imap::config::ImapEncryptionKind,
     message::{
         add::{imap::AddImapMessage, AddMessage},
         copy::{imap::CopyImapMessages, CopyMessages},
         delete::{imap::DeleteImapMessages, DeleteMessages},
         get::{imap::GetImapMessages, GetMessages},
-        imap::{FETCH_MESSAGES, PEEK_MESSAGES},
+        imap::{FETCH_MESSAGES, PEEK_MESSAGES, PEEK_MESSAGE_HEADERS},
         peek::{imap::PeekImapMessages, PeekMessages},
         r#move::{imap::MoveImapMessages, MoveMessages},
         remove::{imap::RemoveImapMessages, RemoveMessages},
         Messages,
     },
-    retry::{self, Retry, RetryState},
-    AnyResult,
+    retry::{self, Retry, RetryOperation, RetryState, RetryableError},
+    sasl::SaslClient,
+    warn, AnyResult,
 };
 
 static ID_PARAMS: Lazy<Vec<(IString<'static>, NString<'static>)>> = Lazy::new(|| {
@@ -127,6 +130,7 @@
 enum ImapRetryState<T> {
     Retry,
     TimedOut,
+    ConnectionLost(ClientError),
     Ok(std::result::Result<T, ClientError>),
 }
 
@@ -156,6 +160,34 @@ pub struct ImapClient {
 }
 
 impl ImapClient {
+    /// Re-connects the client, then re-selects the previously selected
+    /// mailbox if any.
+    async fn reconnect(&mut self) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("re-connecting…");
+
+        self.inner = self.client_builder.build().await?;
+
+        if let Some(mbox) = &self.mailbox {
+            self.inner
+                .select(mbox.clone())
+                .await
+                .map_err(Error::SelectMailboxError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the duration left before the OAuth 2.0 access token
+    /// needs to be renewed, if any.
+    async fn access_token_renews_in(&self) -> Option<Duration> {
+        match &self.imap_config.auth {
+            #[cfg(feature = "oauth2")]
+            ImapAuthConfig::OAuth2(oauth2) => oauth2.access_token_renews_in().await,
+            _ => None,
+        }
+    }
+
     async fn retry<T>(
         &mut self,
         res: retry::Result<std::result::Result<T, ClientError>>,
@@ -163,71 +195,96 @@ async fn retry<T>(
         match self.retry.next(res) {
             RetryState::Retry => {
                 debug!(attempt = self.retry.attempts, "request timed out");
+                self.retry.backoff().await;
                 Ok(ImapRetryState::Retry)
             }
             RetryState::TimedOut => {
                 return Ok(ImapRetryState::TimedOut);
             }
             RetryState::Ok(Err(ClientError::Stream(err))) => {
-                match err {
+                let reason = match &err {
                     StreamError::State(SchedulerError::UnexpectedByeResponse(bye)) => {
-                        #[cfg(feature = "tracing")]
-                        tracing::debug!(reason = bye.text.to_string(), "stream closed");
+                        Some(format!("stream closed: {}", bye.text))
                     }
                     StreamError::Io(err) if err.kind() == ConnectionReset => {
-                        #[cfg(feature = "tracing")]
-                        tracing::debug!("connection reset");
-                    }
-                    StreamError::Closed => {
-                        #[cfg(feature = "tracing")]
-                        tracing::debug!("stream closed");
-                    }
-                    StreamError::Io(err) if err.kind() == ConnectionReset => {
-                        #[cfg(feature = "tracing")]
-                        tracing::debug!("connection reset");
-                    }
-                    err => {
-                        let err = ClientError::Stream(err);
-                        return Ok(ImapRetryState::Ok(Err(err)));
+                        Some(String::from("connection reset"))
                     }
+                    StreamError::Closed => Some(String::from("stream closed")),
+                    _ => None,
                 };
 
-                #[cfg(feature = "tracing")]
-                tracing::debug!("re-connecting…");
+                let Some(reason) = reason else {
+                    self.retry.reset();
+                    let err = ClientError::Stream(err);
+                    return Ok(ImapRetryState::Ok(Err(err)));
+                };
 
-                self.inner = self.client_builder.build().await?;
+                debug!(reason, "connection lost");
 
-                if let Some(mbox) = &self.mailbox {
-                    self.inner
-                        .select(mbox.clone())
-                        .await
-                        .map_err(Error::SelectMailboxError)?;
+                if !self.retry.fail(RetryableError::ConnectionLost, reason) {
+                    let err = ClientError::Stream(err);
+                    return Ok(ImapRetryState::ConnectionLost(err));
                 }
 
-                self.retry.attempts = 0;
+                self.retry.backoff().await;
+                self.reconnect().await?;
+
                 Ok(ImapRetryState::Retry)
             }
             RetryState::Ok(res) => {
+                self.retry.reset();
                 return Ok(ImapRetryState::Ok(res));
             }
         }
     }
 
+    /// Wrap the given error into a connection lost error, together
+    /// with the history of the failed attempts.
+    fn connection_lost(&mut self, err: Error) -> Error {
+        Error::ConnectionLostError(Box::new(err), self.retry.take_history())
+    }
+
     pub fn ext_sort_supported(&self) -> bool {
         self.inner.ext_sort_supported()
     }
 
+    /// Return `true` if the server supports the Gmail extension
+    /// `X-GM-EXT-1`.
+    pub fn ext_gmail_supported(&self) -> bool {
+        self.inner
+            .capabilities_iter()
+            .any(|cap| cap.to_string().eq_ignore_ascii_case("X-GM-EXT-1"))
+    }
+
+    /// The fetch items needed to build envelopes, including Gmail
+    /// identifiers and labels when the server exposes them.
+    fn fetch_envelopes_items(&self) -> MacroOrMessageDataItemNames<'static> {
+        if self.ext_gmail_supported() {
+            FETCH_ENVELOPES_GMAIL.clone()
+        } else {
+            FETCH_ENVELOPES.clone()
+        }
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn noop(&mut self) -> Result<()> {
         self.retry.reset();
 
         loop {
-            let res = self.retry.timeout(self.inner.noop()).await;
+            let res = self
+                .retry
+                .timeout(RetryOperation::Read, self.inner.noop())
+                .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::NoOpTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::NoOpTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::NoOpError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::NoOpError(err)))
+                }
             }
         }
     }
@@ -239,13 +296,18 @@ pub async fn select_mailbox(&mut self, mbox: impl ToString) -> Result<SelectData
         let data = loop {
             let res = self
                 .retry
-                .timeout(self.inner.select(mbox.to_string()))
+                .timeout(RetryOperation::Read, self.inner.select(mbox.to_string()))
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::SelectMailboxTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::SelectMailboxTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::SelectMailboxError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::SelectMailboxError(err)))
+                }
             }
         }?;
 
@@ -261,13 +323,20 @@ pub async fn examine_mailbox(&mut self, mbox: impl ToString) -> Result<SelectDat
         loop {
             let res = self
                 .retry
-                .timeout(self.inner.examine(mbox.to_string()))
+                .timeout(RetryOperation::Read, self.inner.examine(mbox.to_string()))
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ExamineMailboxTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::ExamineMailboxTimedOutError(
+                        self.retry.take_history(),
+                    ))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::ExamineMailboxError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::ExamineMailboxError(err)))
+                }
             }
         }
     }
@@ -279,13 +348,18 @@ pub async fn create_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
         loop {
             let res = self
                 .retry
-                .timeout(self.inner.create(mbox.to_string()))
+                .timeout(RetryOperation::Write, self.inner.create(mbox.to_string()))
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::CreateMailboxTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::CreateMailboxTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::CreateMailboxError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::CreateMailboxError(err)))
+                }
             }
         }
     }
@@ -295,12 +369,20 @@ pub async fn list_all_mailboxes(&mut self, config: &AccountConfig) -> Result<Fol
         self.retry.reset();
 
         let mboxes = loop {
-            let res = self.retry.timeout(self.inner.list("", "*")).await;
+            let res = self
+                .retry
+                .timeout(RetryOperation::Read, self.inner.list("", "*"))
+                .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ListMailboxesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::ListMailboxesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::ListMailboxesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::ListMailboxesError(err)))
+                }
             }
         }?;
 
@@ -316,12 +398,22 @@ pub async fn expunge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         self.retry.reset();
 
         let expunged = loop {
-            let res = self.retry.timeout(self.inner.expunge()).await;
+            let res = self
+                .retry
+                .timeout(RetryOperation::Write, self.inner.expunge())
+                .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ExpungeMailboxTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::ExpungeMailboxTimedOutError(
+                        self.retry.take_history(),
+                    ))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::ExpungeMailboxError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::ExpungeMailboxError(err)))
+                }
             }
         }?;
 
@@ -336,12 +428,22 @@ pub async fn purge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
             .await?;
 
         let expunged = loop {
-            let res = self.retry.timeout(self.inner.expunge()).await;
+            let res = self
+                .retry
+                .timeout(RetryOperation::Write, self.inner.expunge())
+                .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ExpungeMailboxTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::ExpungeMailboxTimedOutError(
+                        self.retry.take_history(),
+                    ))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::ExpungeMailboxError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::ExpungeMailboxError(err)))
+                }
             }
         }?;
 
@@ -355,13 +457,18 @@ pub async fn delete_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
         loop {
             let res = self
                 .retry
-                .timeout(self.inner.delete(mbox.to_string()))
+                .timeout(RetryOperation::Write, self.inner.delete(mbox.to_string()))
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::DeleteMailboxTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::DeleteMailboxTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::DeleteMailboxError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::DeleteMailboxError(err)))
+                }
             }
         }
     }
@@ -369,17 +476,26 @@ pub async fn delete_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn fetch_envelopes(&mut self, uids: SequenceSet) -> Result<Envelopes> {
         self.retry.reset();
+        let items = self.fetch_envelopes_items();
 
         let fetches = loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()))
+                .timeout(
+                    RetryOperation::Fetch,
+                    self.inner.uid_fetch(uids.clone(), items.clone()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -391,16 +507,26 @@ pub async fn fetch_envelopes_map(
         &mut self,
         uids: SequenceSet,
     ) -> Result<HashMap<String, Envelope>> {
+        let items = self.fetch_envelopes_items();
+
         let fetches = loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()))
+                .timeout(
+                    RetryOperation::Fetch,
+                    self.inner.uid_fetch(uids.clone(), items.clone()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -417,17 +543,24 @@ pub async fn fetch_envelopes_map(
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
+        let fetch_items = self.fetch_envelopes_items();
+
         let items = loop {
             let task = self
                 .inner
-                .uid_fetch_first(uid.try_into().unwrap(), FETCH_ENVELOPES.clone());
+                .uid_fetch_first(uid.try_into().unwrap(), fetch_items.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Fetch, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -436,16 +569,26 @@ pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn fetch_envelopes_by_sequence(&mut self, seq: SequenceSet) -> Result<Envelopes> {
+        let items = self.fetch_envelopes_items();
+
         let fetches = loop {
             let res = self
                 .retry
-                .timeout(self.inner.fetch(seq.clone(), FETCH_ENVELOPES.clone()))
+                .timeout(
+                    RetryOperation::Fetch,
+                    self.inner.fetch(seq.clone(), items.clone()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -469,12 +612,17 @@ pub async fn sort_uids(
                 .inner
                 .uid_sort(sort_criteria.clone(), search_criteria.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Read, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::SortUidsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::SortUidsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::SortUidsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::SortUidsError(err)))
+                }
             }
         }
     }
@@ -487,13 +635,21 @@ pub async fn search_uids(
         loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_search(search_criteria.clone()))
+                .timeout(
+                    RetryOperation::Read,
+                    self.inner.uid_search(search_criteria.clone()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::SearchUidsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::SearchUidsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::SearchUidsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::SearchUidsError(err)))
+                }
             }
         }
     }
@@ -504,19 +660,26 @@ pub async fn sort_envelopes(
         sort_criteria: impl IntoIterator<Item = SortCriterion> + Clone,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Envelopes> {
+        let items = self.fetch_envelopes_items();
+
         let fetches = loop {
             let task = self.inner.uid_sort_or_fallback(
                 sort_criteria.clone(),
                 search_criteria.clone(),
-                FETCH_ENVELOPES.clone(),
+                items.clone(),
             );
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Fetch, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -533,12 +696,19 @@ pub async fn thread_envelopes(
                 .inner
                 .uid_thread(ThreadingAlgorithm::References, search_criteria.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Read, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ThreadMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::ThreadMessagesTimedOutError(
+                        self.retry.take_history(),
+                    ))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::ThreadMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::ThreadMessagesError(err)))
+                }
             }
         }
     }
@@ -548,6 +718,15 @@ pub async fn idle(
         &mut self,
         wait_for_shutdown_request: &mut oneshot::Receiver<()>,
     ) -> Result<()> {
+        // The session needs to be re-authenticated before the access
+        // token expires, otherwise the server may close it. A zero
+        // duration means that the access token could not be renewed:
+        // idling goes on until the server closes the session.
+        let renews_in = self
+            .access_token_renews_in()
+            .await
+            .filter(|renews_in| !renews_in.is_zero());
+
         let tag = self.inner.enqueue_idle();
 
         select! {
@@ -555,6 +734,11 @@ pub async fn idle(
                 output.map_err(Error::StartIdleError)?;
                 Ok(())
             },
+            _ = sleep(renews_in.unwrap_or(Duration::MAX)), if renews_in.is_some() => {
+                debug!("access token about to expire, sending done command…");
+                self.inner.idle_done(tag.clone()).await.map_err(Error::StopIdleError)?;
+                self.reconnect().await
+            },
             _ = wait_for_shutdown_request => {
                 debug!("shutdown requested, sending done command…");
                 self.inner.idle_done(tag.clone()).await.map_err(Error::StopIdleError)?;
@@ -574,12 +758,17 @@ pub async fn add_flags(
                 .inner
                 .uid_store(uids.clone(), StoreType::Add, flags.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -594,12 +783,17 @@ pub async fn add_deleted_flag(
                 .inner
                 .uid_store(uids.clone(), StoreType::Add, Some(Flag::Deleted));
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -611,12 +805,17 @@ pub async fn add_deleted_flag_silently(&mut self, uids: SequenceSet) -> Result<(
                 self.inner
                     .uid_silent_store(uids.clone(), StoreType::Add, Some(Flag::Deleted));
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -632,12 +831,17 @@ pub async fn add_flags_silently(
                 .inner
                 .uid_silent_store(uids.clone(), StoreType::Add, flags.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -653,12 +857,17 @@ pub async fn set_flags(
                 .inner
                 .uid_store(uids.clone(), StoreType::Replace, flags.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -674,12 +883,17 @@ pub async fn set_flags_silently(
                 .inner
                 .uid_silent_store(uids.clone(), StoreType::Replace, flags.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -695,12 +909,17 @@ pub async fn remove_flags(
                 .inner
                 .uid_store(uids.clone(), StoreType::Remove, flags.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -716,12 +935,17 @@ pub async fn remove_flags_silently(
                 .inner
                 .uid_silent_store(uids.clone(), StoreType::Remove, flags.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Store, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::StoreFlagsTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::StoreFlagsError(err)))
+                }
             }
         }
     }
@@ -738,12 +962,17 @@ pub async fn add_message(
                 self.inner
                     .appenduid_or_fallback(mbox.to_string(), flags.clone(), msg.clone());
 
-            let res = self.retry.timeout(task).await;
+            let res = self.retry.timeout(RetryOperation::Write, task).await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::AddMessageTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::AddMessageTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::AddMessageError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::AddMessageError(err)))
+                }
             }
         }?;
 
@@ -755,13 +984,21 @@ pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         let mut fetches = loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_MESSAGES.clone()))
+                .timeout(
+                    RetryOperation::Fetch,
+                    self.inner.uid_fetch(uids.clone(), FETCH_MESSAGES.clone()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -775,16 +1012,39 @@ pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
     pub async fn peek_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
+        self.uid_fetch_messages(uids, &PEEK_MESSAGES).await
+    }
+
+    /// Same as [`ImapClient::peek_messages`], but for the header
+    /// section of messages only.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(client = self.id)))]
+    pub async fn peek_message_headers(&mut self, uids: SequenceSet) -> Result<Messages> {
+        self.uid_fetch_messages(uids, &PEEK_MESSAGE_HEADERS).await
+    }
+
+    async fn uid_fetch_messages(
+        &mut self,
+        uids: SequenceSet,
+        items: &MacroOrMessageDataItemNames<'static>,
+    ) -> Result<Messages> {
         let mut fetches = loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), PEEK_MESSAGES.clone()))
+                .timeout(
+                    RetryOperation::Fetch,
+                    self.inner.uid_fetch(uids.clone(), items.clone()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::FetchMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::FetchMessagesError(err)))
+                }
             }
         }?;
 
@@ -801,13 +1061,21 @@ pub async fn copy_messages(&mut self, uids: SequenceSet, mbox: impl ToString) ->
         loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_copy(uids.clone(), mbox.to_string()))
+                .timeout(
+                    RetryOperation::Write,
+                    self.inner.uid_copy(uids.clone(), mbox.to_string()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::CopyMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::CopyMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::CopyMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::CopyMessagesError(err)))
+                }
             }
         }
     }
@@ -817,13 +1085,21 @@ pub async fn move_messages(&mut self, uids: SequenceSet, mbox: impl ToString) ->
         loop {
             let res = self
                 .retry
-                .timeout(self.inner.uid_move(uids.clone(), mbox.to_string()))
+                .timeout(
+                    RetryOperation::Write,
+                    self.inner.uid_move(uids.clone(), mbox.to_string()),
+                )
                 .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::MoveMessagesTimedOutError),
+                ImapRetryState::TimedOut => {
+                    break Err(Error::MoveMessagesTimedOutError(self.retry.take_history()))
+                }
                 ImapRetryState::Ok(res) => break res.map_err(Error::MoveMessagesError),
+                ImapRetryState::ConnectionLost(err) => {
+                    break Err(self.connection_lost(Error::MoveMessagesError(err)))
+                }
             }
         }
     }
@@ -1042,7 +1318,7 @@ async fn build(self) -> AnyResult<Self::Context> {
                 client_builder,
                 inner,
                 mailbox: Default::default(),
-                retry: Default::default(),
+                retry: Retry::new(self.imap_config.retry.clone().unwrap_or_default()),
             }))),
         })
         .collect::<Vec<_>>()
@@ -1112,33 +1388,7 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
         tracing::instrument(name = "client::build", skip(self))
     )]
     pub async fn build(&mut self) -> Result<Client> {
-        let mut client = match &self.config.encryption {
-            Some(ImapEncryptionKind::None) | None => {
-                Client::insecure(&self.config.host, self.config.port)
-                    .await
-                    .map_err(|err| {
-                        let host = self.config.host.clone();
-                        let port = self.config.port.clone();
-                        Error::BuildInsecureClientError(err, host, port)
-                    })?
-            }
-            Some(ImapEncryptionKind::StartTls) => {
-                Client::starttls(&self.config.host, self.config.port)
-                    .await
-                    .map_err(|err| {
-                        let host = self.config.host.clone();
-                        let port = self.config.port.clone();
-                        Error::BuildStartTlsClientError(err, host, port)
-                    })?
-            }
-            Some(ImapEncryptionKind::Tls) => Client::tls(&self.config.host, self.config.port)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildTlsClientError(err, host, port)
-                })?,
-        };
+        let (mut client, channel_binding) = stream::connect(&self.config).await?;
 
         client.set_some_idle_timeout(self.config.find_watch_timeout().map(Duration::from_secs));
 
@@ -1147,8 +1397,11 @@ pub async fn build(&mut self) -> Result<Client> {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("using password authentication");
 
+                let mechanism = self.config.sasl_mechanism;
+
                 let passwd = match self.credentials.as_ref() {
                     Some(passwd) => passwd.to_string(),
+                    None if !mechanism.map_or(true, |m| m.needs_passwd()) => String::new(),
                     None => passwd
                         .get()
                         .await
@@ -1159,47 +1412,81 @@ pub async fn build(&mut self) -> Result<Client> {
                         .to_owned(),
                 };
 
-                let mechanisms: Vec<_> = client.supported_auth_mechanisms().cloned().collect();
+                // channel binding variants can only be used when
+                // the channel binding data is available
+                let usable = |m: SaslMechanism| {
+                    supports_sasl_mechanism(&client, m, false)
+                        || (channel_binding.is_some() && supports_sasl_mechanism(&client, m, true))
+                };
+
+                // an explicit mechanism is the only one tried,
+                // otherwise the most secure mechanisms supported by
+                // the server are tried first
+                let mechanisms: Vec<_> = match mechanism {
+                    Some(mechanism) if usable(mechanism) => vec![mechanism],
+                    Some(mechanism) => {
+                        let auth = client.supported_auth_mechanisms().cloned().collect();
+                        return Err(Error::AuthenticateSaslNotSupportedError(mechanism, auth));
+                    }
+                    None => SaslMechanism::NEGOTIABLE
+                        .into_iter()
+                        .filter(|m| usable(*m))
+                        .collect(),
+                };
+
                 let mut authenticated = false;
 
                 #[cfg(feature = "tracing")]
                 tracing::debug!(?mechanisms, "supported auth mechanisms");
 
-                for mechanism in mechanisms {
-                    #[cfg(feature = "tracing")]
-                    tracing::debug!(?mechanism, "trying auth mechanism…");
+                for sasl_mechanism in mechanisms {
+                    debug!("trying SASL {sasl_mechanism} auth mechanism…");
 
-                    let auth = match mechanism {
-                        AuthMechanism::Plain => {
-                            client
-                                .authenticate_plain(self.config.login.as_str(), passwd.as_str())
-                                .await
+                    let login = &self.config.login;
+
+                    let sasl = match &channel_binding {
+                        Some(cb) if supports_sasl_mechanism(&client, sasl_mechanism, true) => {
+                            SaslClient::new(sasl_mechanism, login, &passwd, Some(cb.clone()))
                         }
-                        // TODO
-                        // AuthMechanism::Login => {
-                        //     client
-                        //         .authenticate_login(self.config.login.as_str(), passwd.as_str())
-                        //         .await
-                        // }
-                        _ => {
-                            continue;
+                        Some(_) if sasl_mechanism.supports_channel_binding() => {
+                            SaslClient::new(sasl_mechanism, login, &passwd, None)
+                                .with_channel_binding_support()
                         }
+                        _ => SaslClient::new(sasl_mechanism, login, &passwd, None),
                     };
 
-                    #[cfg(feature = "tracing")]
-                    if let Err(ref err) = auth {
-                        tracing::warn!(?mechanism, ?err, "authentication failed");
-                    }
+                    let task = AuthenticateSaslTask::new(sasl, client.ext_sasl_ir_supported());
 
-                    if auth.is_ok() {
-                        #[cfg(feature = "tracing")]
-                        tracing::debug!(?mechanism, "authentication succeeded!");
-                        authenticated = true;
-                        break;
+                    let auth = match client.resolve(task).await {
+                        Ok(Ok(())) => Ok(()),
+                        Ok(Err(err)) => {
+                            Err(Error::AuthenticateSaslExchangeError(sasl_mechanism, err))
+                        }
+                        Err(err) => Err(Error::AuthenticateSaslError(sasl_mechanism, err)),
+                    };
+
+                    match auth {
+                        Ok(()) => {
+                            debug!("authentication using SASL {sasl_mechanism} succeeded!");
+                            authenticated = true;
+                            break;
+                        }
+                        Err(err) if mechanism.is_some() => {
+                            return Err(err);
+                        }
+                        Err(_err) => {
+                            warn!("authentication using SASL {sasl_mechanism} failed: {_err}");
+                        }
                     }
                 }
 
-                if !authenticated {
+                if authenticated {
+                    // capabilities may change once authenticated
+                    client
+                        .refresh_capabilities()
+                        .await
+                        .map_err(Error::AuthenticateError)?;
+                } else {
                     if !client.login_supported() {
                         return Err(Error::LoginNotSupportedError);
                     }
@@ -1230,13 +1517,12 @@ pub async fn build(&mut self) -> Result<Client> {
 
                         debug!("using XOAUTH2 auth mechanism");
 
-                        let access_token = match self.credentials.as_ref() {
-                            Some(access_token) => access_token.to_string(),
-                            None => oauth2
-                                .access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?,
-                        };
+                        // pre-built credentials are ignored, since the
+                        // access token may have been renewed meanwhile
+                        let access_token = oauth2
+                            .access_token()
+                            .await
+                            .map_err(Error::RefreshAccessTokenError)?;
 
                         let auth = client
                             .authenticate_xoauth2(self.config.login.as_str(), access_token.as_str())
@@ -1246,7 +1532,7 @@ pub async fn build(&mut self) -> Result<Client> {
                             warn!("authentication failed, refreshing access token and retrying…");
 
                             let access_token = oauth2
-                                .refresh_access_token()
+                                .renew_access_token(&access_token)
                                 .await
                                 .map_err(Error::RefreshAccessTokenError)?;
 
@@ -1257,8 +1543,6 @@ pub async fn build(&mut self) -> Result<Client> {
                                 )
                                 .await
                                 .map_err(Error::AuthenticateXOauth2Error)?;
-
-                            self.credentials = Some(access_token);
                         }
                     }
                     OAuth2Method::OAuthBearer => {
@@ -1269,13 +1553,12 @@ pub async fn build(&mut self) -> Result<Client> {
 
                         debug!("using OAUTHBEARER auth mechanism");
 
-                        let access_token = match self.credentials.as_ref() {
-                            Some(access_token) => access_token.to_string(),
-                            None => oauth2
-                                .access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?,
-                        };
+                        // pre-built credentials are ignored, since the
+                        // access token may have been renewed meanwhile
+                        let access_token = oauth2
+                            .access_token()
+                            .await
+                            .map_err(Error::RefreshAccessTokenError)?;
 
                         let auth = client
                             .authenticate_oauthbearer(
@@ -1290,7 +1573,7 @@ pub async fn build(&mut self) -> Result<Client> {
                             warn!("authentication failed, refreshing access token and retrying");
 
                             let access_token = oauth2
-                                .refresh_access_token()
+                                .renew_access_token(&access_token)
                                 .await
                                 .map_err(Error::RefreshAccessTokenError)?;
 
@@ -1303,8 +1586,6 @@ pub async fn build(&mut self) -> Result<Client> {
                                 )
                                 .await
                                 .map_err(Error::AuthenticateOAuthBearerError)?;
-
-                            self.credentials = Some(access_token);
                         }
                     }
                 }
@@ -1340,3 +1621,16 @@ pub async fn build(&mut self) -> Result<Client> {
         Ok(client)
     }
 }
+
+/// Return `true` if the server supports the given SASL mechanism, or
+/// its channel binding variant when `plus` is `true`.
+fn supports_sasl_mechanism(client: &Client, mechanism: SaslMechanism, plus: bool) -> bool {
+    let name = if plus {
+        format!("{}-PLUS", mechanism.as_str())
+    } else {
+        mechanism.as_str().to_owned()
+    };
+
+    // the mechanism name is always a valid atom
+    client.supports_auth_mechanism(AuthMechanism::try_from(name).unwrap())
+}
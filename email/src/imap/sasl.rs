@@ -0,0 +1,94 @@
+//! Module dedicated to IMAP SASL authentication.
+//!
+//! This module contains the IMAP task driving a SASL exchange, see
+//! [`crate::sasl::SaslClient`].
+
+use std::borrow::Cow;
+
+use imap_client::tasks::Task;
+use imap_next::imap_types::{
+    auth::{AuthMechanism, AuthenticateData},
+    command::CommandBody,
+    response::{CommandContinuationRequest, StatusBody, StatusKind},
+    secret::Secret,
+};
+
+use crate::sasl::{self, SaslClient};
+
+/// The IMAP `AUTHENTICATE` task, as defined in the
+/// [RFC9051](https://datatracker.ietf.org/doc/html/rfc9051#section-6.2.2).
+///
+/// The initial response is sent along with the command when the
+/// server supports the SASL-IR extension, otherwise it is sent in
+/// response to the first (empty) challenge.
+pub(crate) struct AuthenticateSaslTask {
+    mechanism: AuthMechanism<'static>,
+    initial_response: Option<Vec<u8>>,
+    sasl: SaslClient,
+    error: Option<sasl::Error>,
+}
+
+impl AuthenticateSaslTask {
+    pub fn new(mut sasl: SaslClient, sasl_ir: bool) -> Self {
+        // the mechanism name is always a valid atom
+        let mechanism = AuthMechanism::try_from(sasl.name()).unwrap();
+        let initial_response = if sasl_ir {
+            sasl.initial_response()
+        } else {
+            None
+        };
+
+        Self {
+            mechanism,
+            initial_response,
+            sasl,
+            error: None,
+        }
+    }
+}
+
+impl Task for AuthenticateSaslTask {
+    type Output = sasl::Result<()>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::Authenticate {
+            mechanism: self.mechanism.clone(),
+            initial_response: self
+                .initial_response
+                .clone()
+                .map(|res| Secret::new(Cow::Owned(res))),
+        }
+    }
+
+    fn process_continuation_request_authenticate(
+        &mut self,
+        continuation: CommandContinuationRequest<'static>,
+    ) -> Result<AuthenticateData<'static>, CommandContinuationRequest<'static>> {
+        let challenge = match &continuation {
+            CommandContinuationRequest::Base64(challenge) => challenge.as_ref(),
+            // an empty challenge is not valid base64
+            CommandContinuationRequest::Basic(_) => &[],
+        };
+
+        match self.sasl.respond(challenge) {
+            Ok(res) => Ok(AuthenticateData::r#continue(res)),
+            Err(err) => {
+                self.error = Some(err);
+                Ok(AuthenticateData::Cancel)
+            }
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        match status_body.kind {
+            StatusKind::Ok => self.sasl.finish(),
+            StatusKind::No | StatusKind::Bad => Err(sasl::Error::RejectedError(
+                status_body.text.inner().to_owned(),
+            )),
+        }
+    }
+}
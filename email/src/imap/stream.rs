@@ -0,0 +1,241 @@
+//! Module dedicated to IMAP connections.
+//!
+//! TCP and TLS connections are established here rather than by the
+//! IMAP client, so that the TLS connection can be inspected before
+//! being handed over: the `tls-exporter` channel binding data is
+//! exported from it, see [`crate::sasl::ChannelBinding`].
+
+use std::{io, sync::Arc, time::Duration};
+
+use imap_client::Client;
+use imap_next::stream::Stream;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time,
+};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{pki_types::ServerName, ClientConfig, ProtocolVersion},
+    TlsConnector,
+};
+
+use super::{
+    config::{ImapConfig, ImapEncryptionKind},
+    Error, Result,
+};
+use crate::{
+    debug,
+    retry::RetryOperation,
+    sasl::ChannelBinding,
+    tls::{self, TlsConfig},
+};
+
+/// The tag of the `STARTTLS` command.
+///
+/// The command is sent before the IMAP client takes over the
+/// connection, so it cannot clash with its own tags.
+const STARTTLS_TAG: &str = "A0";
+
+/// The maximum length of a line read before the IMAP client takes
+/// over the connection, CRLF included.
+///
+/// The greeting and the `STARTTLS` response are short, a longer line
+/// means the server is misbehaving.
+const MAX_LINE_LEN: usize = 8192;
+
+/// Connect to the IMAP server matching the given configuration.
+///
+/// The channel binding data is returned along with the client when
+/// the connection is encrypted using TLS 1.3.
+pub(crate) async fn connect(config: &ImapConfig) -> Result<(Client, Option<ChannelBinding>)> {
+    let host = config.host.as_str();
+    let port = config.port;
+    let timeout = config
+        .retry
+        .clone()
+        .unwrap_or_default()
+        .timeout(&RetryOperation::Connect);
+
+    let mut tcp_stream = TcpStream::connect((host, port))
+        .await
+        .map_err(|err| Error::ConnectTcpError(err, host.to_owned(), port))?;
+
+    match &config.encryption {
+        Some(ImapEncryptionKind::None) | None => {
+            read_greeting(&mut tcp_stream, timeout, host, port).await?;
+            let client = build_client(Stream::insecure(tcp_stream), host, port).await?;
+            Ok((client, None))
+        }
+        Some(ImapEncryptionKind::StartTls) => {
+            read_greeting(&mut tcp_stream, timeout, host, port).await?;
+            start_tls(&mut tcp_stream, timeout, host, port).await?;
+
+            // no greeting is sent once the connection is upgraded
+            let tls_stream = handshake(config, tcp_stream).await?;
+            let cb = tls_channel_binding(&tls_stream);
+            let client = build_client(Stream::tls(tls_stream), host, port).await?;
+            Ok((client, cb))
+        }
+        Some(ImapEncryptionKind::Tls) => {
+            let mut tls_stream = handshake(config, tcp_stream).await?;
+            read_greeting(&mut tls_stream, timeout, host, port).await?;
+            let cb = tls_channel_binding(&tls_stream);
+            let client = build_client(Stream::tls(tls_stream), host, port).await?;
+            Ok((client, cb))
+        }
+    }
+}
+
+/// Build the IMAP client on top of an established connection, once
+/// the server greeting has been received.
+async fn build_client(stream: Stream, host: &str, port: u16) -> Result<Client> {
+    let mut client = Client::new(stream);
+
+    client
+        .refresh_capabilities()
+        .await
+        .map_err(|err| Error::BuildClientCapabilitiesError(err, host.to_owned(), port))?;
+
+    Ok(client)
+}
+
+/// Build the rustls client configuration from the TLS configuration,
+/// if any.
+fn build_tls_config(config: &ImapConfig) -> tls::Result<ClientConfig> {
+    match &config.tls {
+        Some(tls) => tls.build_rustls_config(),
+        None => TlsConfig::default().build_rustls_config(),
+    }
+}
+
+/// Perform the TLS handshake over the given TCP stream.
+async fn handshake(config: &ImapConfig, tcp_stream: TcpStream) -> Result<TlsStream<TcpStream>> {
+    let host = config.host.as_str();
+    let port = config.port;
+
+    let tls_config = build_tls_config(config).map_err(Error::BuildTlsConfigError)?;
+    let connector = TlsConnector::from(Arc::new(tls_config));
+
+    let server_name = ServerName::try_from(host.to_owned())
+        .map_err(|err| Error::ParseServerNameError(err, host.to_owned()))?;
+
+    connector
+        .connect(server_name, tcp_stream)
+        .await
+        .map_err(|err| Error::TlsHandshakeError(err, host.to_owned(), port))
+}
+
+/// Read the server greeting, as defined in the
+/// [RFC9051](https://datatracker.ietf.org/doc/html/rfc9051#section-7.1).
+///
+/// Capabilities sent along with the greeting are ignored, since
+/// they are refreshed once the client is built.
+async fn read_greeting<S>(stream: &mut S, timeout: Duration, host: &str, port: u16) -> Result<()>
+where
+    S: AsyncRead + Unpin,
+{
+    let line = read_line(stream, timeout)
+        .await
+        .map_err(|err| Error::ReadGreetingError(err, host.to_owned(), port))?;
+
+    if line.starts_with("* OK") || line.starts_with("* PREAUTH") {
+        debug!("received IMAP greeting: {line}");
+        Ok(())
+    } else {
+        Err(Error::RejectGreetingError(line, host.to_owned(), port))
+    }
+}
+
+/// Upgrade the connection using the `STARTTLS` command, as defined in
+/// the [RFC9051](https://datatracker.ietf.org/doc/html/rfc9051#section-6.2.1).
+async fn start_tls(
+    tcp_stream: &mut TcpStream,
+    timeout: Duration,
+    host: &str,
+    port: u16,
+) -> Result<()> {
+    let map_err = |err: io::Error| Error::StartTlsError(err, host.to_owned(), port);
+
+    let cmd = format!("{STARTTLS_TAG} STARTTLS\r\n");
+    tcp_stream
+        .write_all(cmd.as_bytes())
+        .await
+        .map_err(map_err)?;
+    tcp_stream.flush().await.map_err(map_err)?;
+
+    let tag = format!("{STARTTLS_TAG} ");
+
+    loop {
+        let line = read_line(tcp_stream, timeout).await.map_err(map_err)?;
+
+        // untagged responses may be sent before the tagged one
+        let Some(status) = line.strip_prefix(&tag) else {
+            continue;
+        };
+
+        return if status.starts_with("OK") {
+            Ok(())
+        } else {
+            Err(Error::RejectStartTlsError(line, host.to_owned(), port))
+        };
+    }
+}
+
+/// Read a single CRLF-terminated line from the given stream.
+///
+/// Bytes are read one by one, so that nothing sent after the line
+/// gets consumed: the stream may be upgraded to TLS right after.
+///
+/// Fails if the line exceeds [`MAX_LINE_LEN`] bytes or if it is not
+/// received within the given timeout.
+async fn read_line<S>(stream: &mut S, timeout: Duration) -> io::Result<String>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut line = Vec::new();
+
+    let read = async {
+        while !line.ends_with(b"\r\n") {
+            if line.len() >= MAX_LINE_LEN {
+                let err = format!("line exceeds {MAX_LINE_LEN} bytes");
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+
+            line.push(stream.read_u8().await?);
+        }
+
+        Ok(())
+    };
+
+    time::timeout(timeout, read).await.map_err(|_| {
+        let err = format!("no line received within {}s", timeout.as_secs());
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    })??;
+
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// Export the `tls-exporter` channel binding data of the given TLS
+/// stream, as defined in the
+/// [RFC9266](https://datatracker.ietf.org/doc/html/rfc9266).
+///
+/// The channel binding type is only defined for TLS 1.3.
+fn tls_channel_binding(tls_stream: &TlsStream<TcpStream>) -> Option<ChannelBinding> {
+    let (_, conn) = tls_stream.get_ref();
+
+    if conn.protocol_version() != Some(ProtocolVersion::TLSv1_3) {
+        return None;
+    }
+
+    let data = conn
+        .export_keying_material(
+            vec![0; ChannelBinding::TLS_EXPORTER_LEN],
+            ChannelBinding::TLS_EXPORTER_LABEL,
+            None,
+        )
+        .ok()?;
+
+    Some(ChannelBinding::tls_exporter(data))
+}
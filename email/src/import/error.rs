@@ -0,0 +1,33 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+use thiserror::Error;
+
+use crate::{AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot read configuration file {0}")]
+    ReadFileError(PathBuf, #[source] io::Error),
+    #[error("cannot parse line {0}: unbalanced quotes")]
+    ParseQuotesError(usize),
+    #[error("cannot parse line {0}: {1}")]
+    ParseLineError(usize, String),
+    #[error("cannot parse server URL {0}")]
+    ParseUrlError(String),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
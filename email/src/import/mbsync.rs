@@ -0,0 +1,354 @@
+//! # mbsync import
+//!
+//! Module dedicated to the import of the mbsync (isync) configuration
+//! file `.mbsyncrc`.
+//!
+//! One account is imported per `IMAPAccount` section. Stores and
+//! channels referring to this account complete its configuration:
+//! channel patterns become folder filters, the Maildir near store
+//! becomes the Maildir configuration as well as the synchronization
+//! directory.
+
+use std::collections::HashMap;
+
+use secret::Secret;
+
+use super::{split_args, Encryption, ImportedAccount, Result, Server};
+use crate::debug;
+
+/// Parse the given `.mbsyncrc` content.
+pub fn parse(content: &str) -> Result<Vec<ImportedAccount>> {
+    let sections = parse_sections(content)?;
+
+    let sections_of = |kind: &str| {
+        sections
+            .iter()
+            .filter(move |section| section.kind == kind)
+            .map(|section| (section.name.as_str(), section))
+    };
+
+    let maildir_stores: HashMap<_, _> = sections_of("maildirstore").collect();
+    let imap_stores: HashMap<_, _> = sections_of("imapstore").collect();
+
+    let mut accounts: Vec<ImportedAccount> = Vec::new();
+
+    // IMAP stores can define the account inline, in which case the
+    // store acts as account.
+    let account_sections = sections_of("imapaccount")
+        .chain(
+            imap_stores
+                .iter()
+                .filter_map(|(name, store)| store.get("host").map(|_| (*name, *store))),
+        )
+        .collect::<Vec<_>>();
+
+    for (name, section) in account_sections {
+        let mut account = ImportedAccount::new(name);
+
+        let (mut include, mut exclude) = (Vec::new(), Vec::new());
+
+        let login = section.get("user").unwrap_or_default();
+        if login.contains('@') {
+            account.set_email(login);
+        }
+
+        account.set_imap(parse_server(section));
+
+        let stores = imap_stores.iter().filter(|(store_name, store)| {
+            *store_name == name || store.get("account") == Some(name)
+        });
+
+        for (store_name, store) in stores {
+            if let Some(trash) = store.get("trash") {
+                account.set_folder_alias("trash", trash);
+            }
+
+            let channels = sections_of("channel")
+                .map(|(_, channel)| channel)
+                .filter(|channel| channel.far().is_some_and(|(far, _)| far == *store_name));
+
+            for channel in channels {
+                apply_maildir_store(&mut account, channel, &maildir_stores);
+                collect_patterns(&mut account, channel, &mut include, &mut exclude);
+            }
+        }
+
+        #[cfg(feature = "sync")]
+        account.set_folder_filter(include, exclude);
+
+        accounts.push(account);
+    }
+
+    Ok(accounts)
+}
+
+/// Apply the Maildir near store of the given channel, if any.
+fn apply_maildir_store(
+    account: &mut ImportedAccount,
+    channel: &Section,
+    maildir_stores: &HashMap<&str, &Section>,
+) {
+    let Some((near, _)) = channel.near() else {
+        return;
+    };
+
+    let Some(store) = maildir_stores.get(near) else {
+        return;
+    };
+
+    #[cfg(feature = "maildir")]
+    if let Some(path) = store.get("path") {
+        let maildirpp = store
+            .get("subfolders")
+            .is_some_and(|layout| layout.eq_ignore_ascii_case("maildir++"));
+        account.set_maildir(path, maildirpp);
+    }
+
+    if let Some(trash) = store.get("trash") {
+        account.set_folder_alias("trash", trash);
+    }
+}
+
+/// Collect the folder patterns of the given channel, and map its far
+/// box to its near box.
+#[cfg_attr(not(feature = "sync"), allow(unused_variables))]
+fn collect_patterns(
+    account: &mut ImportedAccount,
+    channel: &Section,
+    include: &mut Vec<String>,
+    exclude: &mut Vec<String>,
+) {
+    let (_, far_box) = channel.far().unwrap_or_default();
+    let (_, near_box) = channel.near().unwrap_or_default();
+    let mut patterns = channel.get_all("patterns").peekable();
+
+    if patterns.peek().is_none() && !far_box.is_empty() {
+        include.push(far_box.to_owned());
+    }
+
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(pattern) => exclude.push(to_folder_pattern(pattern)),
+            None => include.push(to_folder_pattern(pattern)),
+        }
+    }
+
+    #[cfg(feature = "sync")]
+    if !far_box.is_empty() && !near_box.is_empty() && far_box != near_box {
+        account.add_folder_mapping(far_box, near_box);
+    }
+}
+
+fn parse_server(section: &Section) -> Server {
+    let encryption = match section.get("tlstype").or_else(|| section.get("ssltype")) {
+        Some(kind) if kind.eq_ignore_ascii_case("imaps") => Encryption::Tls,
+        Some(kind) if kind.eq_ignore_ascii_case("none") => Encryption::None,
+        _ => Encryption::StartTls,
+    };
+
+    let passwd = match (section.get("passcmd"), section.get("pass")) {
+        (Some(cmd), _) => Secret::new_command(cmd.trim_start_matches('+')),
+        (None, Some(passwd)) => Secret::new_raw(passwd),
+        (None, None) => Secret::default(),
+    };
+
+    let port = section.get("port").and_then(|port| port.parse().ok());
+
+    Server {
+        host: section.get("host").unwrap_or_default().to_owned(),
+        port,
+        encryption,
+        login: section.get("user").unwrap_or_default().to_owned(),
+        passwd,
+    }
+}
+
+/// Convert the given mbsync pattern into a folder pattern.
+///
+/// In mbsync patterns, `*` matches any character including the
+/// hierarchy delimiter, whereas `%` does not.
+fn to_folder_pattern(pattern: &str) -> String {
+    if pattern.contains(['*', '%']) {
+        format!("glob:{}", pattern.replace('*', "**").replace('%', "*"))
+    } else {
+        pattern.to_owned()
+    }
+}
+
+/// The mbsync configuration section.
+#[derive(Debug, Default)]
+struct Section {
+    /// The lowercase section keyword, like `imapaccount`.
+    kind: String,
+
+    /// The section name.
+    name: String,
+
+    /// The section options as pairs of lowercase key and values.
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl Section {
+    /// Get the first value of the given option.
+    fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, values)| values.first())
+            .map(String::as_str)
+    }
+
+    /// Get all values of all occurrences of the given option.
+    fn get_all(&self, key: &str) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .flat_map(|(_, values)| values.iter().map(String::as_str))
+    }
+
+    fn far(&self) -> Option<(&str, &str)> {
+        self.get("far")
+            .or_else(|| self.get("master"))
+            .map(parse_box)
+    }
+
+    fn near(&self) -> Option<(&str, &str)> {
+        self.get("near")
+            .or_else(|| self.get("slave"))
+            .map(parse_box)
+    }
+}
+
+/// Parse the given `:store:box` channel side.
+fn parse_box(side: &str) -> (&str, &str) {
+    side.trim_start_matches(':')
+        .split_once(':')
+        .unwrap_or((side.trim_start_matches(':'), ""))
+}
+
+fn parse_sections(content: &str) -> Result<Vec<Section>> {
+    const KINDS: [&str; 6] = [
+        "imapaccount",
+        "imapstore",
+        "maildirstore",
+        "channel",
+        "group",
+        "globalconfig",
+    ];
+
+    let mut sections: Vec<Section> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let mut args = split_args(line_no, line)?.into_iter();
+
+        let Some(key) = args.next() else {
+            continue;
+        };
+
+        let key = key.to_ascii_lowercase();
+
+        if KINDS.contains(&key.as_str()) {
+            sections.push(Section {
+                kind: key,
+                name: args.next().unwrap_or_default(),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        match sections.last_mut() {
+            Some(section) => section.entries.push((key, args.collect())),
+            None => debug!("skipping global mbsync option {key} at line {line_no}"),
+        }
+    }
+
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    const MBSYNCRC: &str = r#"
+# work account
+IMAPAccount work
+Host imap.example.org
+User me@example.org
+PassCmd "+pass show \"mail/work\""
+SSLType IMAPS
+
+IMAPStore work-remote
+Account work
+Trash Deleted
+
+MaildirStore work-local
+Path ~/Mail/work/
+Inbox ~/Mail/work/INBOX
+SubFolders Verbatim
+
+Channel work
+Far :work-remote:
+Near :work-local:
+Patterns * !Spam !"Lists/%"
+Create Both
+"#;
+
+    #[test]
+    fn mbsync() {
+        let accounts = parse(MBSYNCRC).unwrap();
+        assert_eq!(accounts.len(), 1);
+
+        let account = &accounts[0];
+        assert_eq!(account.name(), "work");
+        assert_eq!(account.account_config.email, "me@example.org");
+
+        let aliases = account.account_config.folder.as_ref().unwrap();
+        let aliases = aliases.aliases.as_ref().unwrap();
+        assert_eq!(aliases.get("trash").unwrap(), "Deleted");
+
+        #[cfg(feature = "imap")]
+        {
+            use secret::Secret;
+
+            use crate::{account::config::passwd::PasswdConfig, imap::config::*};
+
+            let imap = account.imap.as_ref().unwrap();
+            assert_eq!(imap.host, "imap.example.org");
+            assert_eq!(imap.port, 993);
+            assert_eq!(imap.encryption, Some(ImapEncryptionKind::Tls));
+            assert_eq!(imap.login, "me@example.org");
+            assert_eq!(
+                imap.auth,
+                ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_command(
+                    "pass show \"mail/work\""
+                )))
+            );
+        }
+
+        #[cfg(feature = "sync")]
+        {
+            use std::path::PathBuf;
+
+            use crate::folder::{filter::FolderPattern, sync::config::FolderSyncStrategy};
+
+            let maildir = account.maildir.as_ref().unwrap();
+            assert_eq!(maildir.root_dir, PathBuf::from("~/Mail/work/"));
+            assert!(!maildir.maildirpp);
+
+            let sync = account.account_config.sync.as_ref().unwrap();
+            assert_eq!(sync.enable, Some(true));
+            assert_eq!(sync.dir, Some(PathBuf::from("~/Mail/work/")));
+
+            let folder = account.account_config.folder.as_ref().unwrap();
+            let filter = &folder.sync.as_ref().unwrap().filter;
+            let FolderSyncStrategy::Filter(filter) = filter else {
+                panic!("unexpected folder filter {filter:?}");
+            };
+
+            assert!(filter.include.contains(&FolderPattern::new("glob:**")));
+            assert!(filter.exclude.contains(&FolderPattern::new("Spam")));
+            assert!(filter.exclude.contains(&FolderPattern::new("glob:Lists/*")));
+        }
+    }
+}
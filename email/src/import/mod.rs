@@ -0,0 +1,433 @@
+//! # Account import
+//!
+//! This module contains everything needed to import accounts from the
+//! configuration of other email clients: [`mbsync`],
+//! [`offlineimap`], [`mutt`] and [`thunderbird`].
+//!
+//! An [`ImportedAccount`] contains the account configuration (folder
+//! aliases and synchronization filters included) as well as the IMAP,
+//! SMTP and Maildir configurations found. Passwords are imported
+//! either as raw secrets or as commands.
+//!
+//! When the imported client already synchronizes the account in a
+//! local Maildir, this Maildir is used as synchronization directory,
+//! so that messages do not need to be downloaded again.
+
+mod error;
+pub mod mbsync;
+pub mod mutt;
+pub mod offlineimap;
+pub mod thunderbird;
+
+#[cfg(feature = "maildir")]
+use std::path::PathBuf;
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use secret::Secret;
+
+#[doc(inline)]
+pub use self::error::{Error, Result};
+#[cfg(feature = "imap")]
+use crate::imap::config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind};
+#[cfg(feature = "maildir")]
+use crate::maildir::config::MaildirConfig;
+#[cfg(feature = "smtp")]
+use crate::smtp::config::{SmtpAuthConfig, SmtpConfig, SmtpEncryptionKind};
+use crate::{
+    account::config::{passwd::PasswdConfig, AccountConfig},
+    config::Config,
+    folder::config::FolderConfig,
+};
+#[cfg(feature = "sync")]
+use crate::{
+    account::sync::config::SyncConfig,
+    folder::{
+        filter::{FolderFilter, FolderPattern},
+        sync::config::{FolderSyncConfig, FolderSyncStrategy},
+    },
+};
+
+/// The email client an import comes from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportSource {
+    /// The `.mbsyncrc` configuration file of mbsync (isync).
+    Mbsync,
+
+    /// The `.offlineimaprc` configuration file of OfflineIMAP.
+    OfflineImap,
+
+    /// The `muttrc` configuration file of mutt and neomutt.
+    Mutt,
+
+    /// The `prefs.js` file of a Thunderbird profile.
+    Thunderbird,
+}
+
+impl ImportSource {
+    /// Parse the given configuration file content.
+    pub fn parse(&self, content: &str) -> Result<Vec<ImportedAccount>> {
+        match self {
+            Self::Mbsync => mbsync::parse(content),
+            Self::OfflineImap => offlineimap::parse(content),
+            Self::Mutt => mutt::parse(content),
+            Self::Thunderbird => thunderbird::parse(content),
+        }
+    }
+
+    /// Read then parse the given configuration file.
+    pub fn from_path(&self, path: impl AsRef<Path>) -> Result<Vec<ImportedAccount>> {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).map_err(|err| Error::ReadFileError(path.to_owned(), err))?;
+        self.parse(&content)
+    }
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mbsync => write!(f, "mbsync"),
+            Self::OfflineImap => write!(f, "OfflineIMAP"),
+            Self::Mutt => write!(f, "mutt"),
+            Self::Thunderbird => write!(f, "Thunderbird"),
+        }
+    }
+}
+
+/// The account imported from another email client.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImportedAccount {
+    /// The account configuration.
+    pub account_config: AccountConfig,
+
+    /// The IMAP configuration, if any.
+    #[cfg(feature = "imap")]
+    pub imap: Option<ImapConfig>,
+
+    /// The SMTP configuration, if any.
+    #[cfg(feature = "smtp")]
+    pub smtp: Option<SmtpConfig>,
+
+    /// The local Maildir configuration, if any.
+    #[cfg(feature = "maildir")]
+    pub maildir: Option<MaildirConfig>,
+}
+
+impl ImportedAccount {
+    /// Create a new imported account with the given name.
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            account_config: AccountConfig {
+                name: name.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Get the account name.
+    pub fn name(&self) -> &str {
+        &self.account_config.name
+    }
+
+    fn set_email(&mut self, email: impl ToString) {
+        self.account_config.email = email.to_string();
+    }
+
+    fn set_display_name(&mut self, name: impl ToString) {
+        self.account_config.display_name = Some(name.to_string());
+    }
+
+    fn set_signature(&mut self, signature: impl ToString) {
+        self.account_config.signature = Some(signature.to_string());
+    }
+
+    /// Set the folder alias of the given kind (inbox, sent, drafts,
+    /// trash).
+    fn set_folder_alias(&mut self, alias: &str, folder: impl ToString) {
+        self.account_config
+            .folder
+            .get_or_insert_with(FolderConfig::default)
+            .aliases
+            .get_or_insert_with(HashMap::default)
+            .insert(alias.to_owned(), folder.to_string());
+    }
+
+    #[cfg_attr(not(feature = "imap"), allow(unused_variables))]
+    fn set_imap(&mut self, server: Server) {
+        #[cfg(feature = "imap")]
+        {
+            self.imap = Some(server.into_imap());
+        }
+    }
+
+    #[cfg_attr(not(feature = "smtp"), allow(unused_variables))]
+    fn set_smtp(&mut self, server: Server) {
+        #[cfg(feature = "smtp")]
+        {
+            self.smtp = Some(server.into_smtp());
+        }
+    }
+
+    /// Set the local Maildir of the account.
+    ///
+    /// Unless it uses the Maildir++ layout, the Maildir is also used
+    /// as synchronization directory.
+    #[cfg(feature = "maildir")]
+    fn set_maildir(&mut self, root_dir: impl Into<PathBuf>, maildirpp: bool) {
+        let root_dir = root_dir.into();
+
+        #[cfg(feature = "sync")]
+        if !maildirpp {
+            self.account_config.sync = Some(SyncConfig {
+                enable: Some(true),
+                dir: Some(root_dir.clone()),
+                ..Default::default()
+            });
+        }
+
+        self.maildir = Some(MaildirConfig {
+            root_dir,
+            maildirpp,
+            ..Default::default()
+        });
+    }
+
+    /// Set the folders to synchronize, from patterns using the
+    /// [`FolderPattern`] syntax.
+    #[cfg(feature = "sync")]
+    fn set_folder_filter(&mut self, include: Vec<String>, exclude: Vec<String>) {
+        let include = include.into_iter().map(FolderPattern::new);
+        let exclude = exclude.into_iter().map(FolderPattern::new);
+
+        let filter = match (include.len(), exclude.len()) {
+            (0, 0) => FolderSyncStrategy::All,
+            (0, _) => FolderSyncStrategy::Exclude(exclude.collect()),
+            (_, 0) => FolderSyncStrategy::Include(include.collect()),
+            _ => FolderSyncStrategy::Filter(FolderFilter::new(include, exclude)),
+        };
+
+        self.folder_sync_config().filter = filter;
+    }
+
+    /// Map the given right (remote) folder name to the given left
+    /// (local) folder name.
+    #[cfg(feature = "sync")]
+    fn add_folder_mapping(&mut self, right: impl ToString, left: impl ToString) {
+        self.folder_sync_config()
+            .mapping
+            .names
+            .insert(right.to_string(), left.to_string());
+    }
+
+    #[cfg(feature = "sync")]
+    fn folder_sync_config(&mut self) -> &mut FolderSyncConfig {
+        self.account_config
+            .folder
+            .get_or_insert_with(FolderConfig::default)
+            .sync
+            .get_or_insert_with(FolderSyncConfig::default)
+    }
+}
+
+/// Build the global configuration containing the given accounts.
+///
+/// Backend configurations are not part of the global configuration,
+/// they need to be taken from the imported accounts.
+pub fn to_config<'a>(accounts: impl IntoIterator<Item = &'a ImportedAccount>) -> Config {
+    let accounts = accounts
+        .into_iter()
+        .map(|account| (account.name().to_owned(), account.account_config.clone()))
+        .collect();
+
+    Config {
+        accounts,
+        ..Default::default()
+    }
+}
+
+/// The encryption of an imported server.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Encryption {
+    #[default]
+    Tls,
+    StartTls,
+    None,
+}
+
+/// The imported IMAP or SMTP server.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(not(any(feature = "imap", feature = "smtp")), allow(dead_code))]
+struct Server {
+    host: String,
+    port: Option<u16>,
+    encryption: Encryption,
+    login: String,
+    passwd: Secret,
+}
+
+impl Server {
+    #[cfg(feature = "imap")]
+    fn into_imap(self) -> ImapConfig {
+        let (port, encryption) = match self.encryption {
+            Encryption::Tls => (993, ImapEncryptionKind::Tls),
+            Encryption::StartTls => (143, ImapEncryptionKind::StartTls),
+            Encryption::None => (143, ImapEncryptionKind::None),
+        };
+
+        ImapConfig {
+            host: self.host,
+            port: self.port.unwrap_or(port),
+            encryption: Some(encryption),
+            login: self.login,
+            auth: ImapAuthConfig::Passwd(PasswdConfig::from(self.passwd)),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "smtp")]
+    fn into_smtp(self) -> SmtpConfig {
+        let (port, encryption) = match self.encryption {
+            Encryption::Tls => (465, SmtpEncryptionKind::Tls),
+            Encryption::StartTls => (587, SmtpEncryptionKind::StartTls),
+            Encryption::None => (25, SmtpEncryptionKind::None),
+        };
+
+        SmtpConfig {
+            host: self.host,
+            port: self.port.unwrap_or(port),
+            encryption: Some(encryption),
+            login: self.login,
+            auth: SmtpAuthConfig::Passwd(PasswdConfig::from(self.passwd)),
+            ..Default::default()
+        }
+    }
+
+    /// Parse the given server URL, for example
+    /// `imaps://user@imap.example.com:993/INBOX`, and return the
+    /// server with the path of the URL.
+    ///
+    /// Supported schemes are `imap`, `imaps`, `smtp` and `smtps`.
+    fn from_url(url: &str) -> Result<(Self, String)> {
+        let err = || Error::ParseUrlError(url.to_owned());
+
+        let (scheme, rest) = url.split_once("://").ok_or_else(err)?;
+
+        let encryption = match scheme.to_ascii_lowercase().as_str() {
+            "imaps" | "smtps" => Encryption::Tls,
+            "imap" | "smtp" => Encryption::StartTls,
+            _ => return Err(err()),
+        };
+
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+        let (login, host) = match authority.rsplit_once('@') {
+            Some((login, host)) => (login.replace("%40", "@"), host),
+            None => (String::new(), authority),
+        };
+
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().map_err(|_| err())?)),
+            None => (host, None),
+        };
+
+        if host.is_empty() {
+            return Err(err());
+        }
+
+        let server = Server {
+            host: host.to_owned(),
+            port,
+            encryption,
+            login,
+            ..Default::default()
+        };
+
+        Ok((server, path.to_owned()))
+    }
+}
+
+/// Split the given configuration line into arguments.
+///
+/// Arguments are separated by white spaces, unless they are quoted
+/// using double or single quotes. A backslash escapes the next
+/// character inside double quotes. Backticks group arguments as well
+/// but are kept, so that commands can be detected. A `#` outside
+/// quotes starts a comment.
+fn split_args(line_no: usize, line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                if let Some(c) = chars.next() {
+                    arg.get_or_insert_with(String::new).push(c);
+                }
+            }
+            (Some('`'), '`') => {
+                arg.get_or_insert_with(String::new).push(c);
+                quote = None;
+            }
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (None, '`') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new).push(c);
+            }
+            (None, '#') => break,
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(Error::ParseQuotesError(line_no));
+    }
+
+    args.extend(arg);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_args, Encryption, Server};
+
+    #[test]
+    fn split_args_quotes() {
+        let args = split_args(1, r#"PassCmd "pass show \"mail\"" 'a b' c # comment"#).unwrap();
+        assert_eq!(args, vec!["PassCmd", r#"pass show "mail""#, "a b", "c"]);
+
+        let args = split_args(1, r#"Path """#).unwrap();
+        assert_eq!(args, vec!["Path", ""]);
+
+        let args = split_args(1, "set imap_pass=`pass show mail`").unwrap();
+        assert_eq!(args, vec!["set", "imap_pass=`pass show mail`"]);
+
+        assert!(split_args(1, r#"Path "~/Mail"#).is_err());
+    }
+
+    #[test]
+    fn server_from_url() {
+        let (server, path) = Server::from_url("imaps://me@example.org@imap.example.org/").unwrap();
+        assert_eq!(server.host, "imap.example.org");
+        assert_eq!(server.port, None);
+        assert_eq!(server.encryption, Encryption::Tls);
+        assert_eq!(server.login, "me@example.org");
+        assert_eq!(path, "");
+
+        let (server, path) = Server::from_url("smtp://smtp.example.org:587/Sent").unwrap();
+        assert_eq!(server.port, Some(587));
+        assert_eq!(server.encryption, Encryption::StartTls);
+        assert_eq!(server.login, "");
+        assert_eq!(path, "Sent");
+
+        assert!(Server::from_url("pop://example.org").is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! # mutt import
+//!
+//! Module dedicated to the import of the mutt (and neomutt)
+//! configuration file `muttrc`.
+//!
+//! Each `account-hook` becomes an account, made of the global `set`
+//! statements overridden by the ones of the hook. Without any
+//! `account-hook`, a single `default` account is imported from the
+//! global `set` statements. Files included with `source` are not
+//! followed.
+
+use std::collections::HashMap;
+
+use secret::Secret;
+
+use super::{split_args, ImportedAccount, Result, Server};
+use crate::debug;
+
+/// The variables set by `set` statements.
+type Vars = HashMap<String, String>;
+
+/// Parse the given `muttrc` content.
+pub fn parse(content: &str) -> Result<Vec<ImportedAccount>> {
+    let mut vars = Vars::new();
+    let mut hooks: Vec<(String, Vars)> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let args = split_args(line_no, line)?;
+
+        match args.first().map(String::as_str) {
+            Some("set") => parse_set(&args[1..], &mut vars),
+            Some("account-hook") if args.len() > 2 => {
+                let mut hook_vars = Vars::new();
+
+                for cmd in args[2..].join(" ").split(';') {
+                    let args = split_args(line_no, cmd)?;
+                    if args.first().is_some_and(|cmd| cmd == "set") {
+                        parse_set(&args[1..], &mut hook_vars);
+                    }
+                }
+
+                match hooks.iter_mut().find(|(pattern, _)| *pattern == args[1]) {
+                    Some((_, vars)) => vars.extend(hook_vars),
+                    None => hooks.push((args[1].clone(), hook_vars)),
+                }
+            }
+            Some("source") => {
+                debug!("skipping sourced mutt file at line {line_no}");
+            }
+            _ => (),
+        }
+    }
+
+    if hooks.is_empty() {
+        return Ok(vec![build_account("default", &vars)]);
+    }
+
+    let accounts = hooks
+        .into_iter()
+        .map(|(pattern, hook_vars)| {
+            let name = match Server::from_url(&pattern) {
+                Ok((server, _)) if !server.login.is_empty() => server.login,
+                Ok((server, _)) => server.host,
+                Err(_) => pattern,
+            };
+
+            let mut vars = vars.clone();
+            vars.extend(hook_vars);
+            build_account(name, &vars)
+        })
+        .collect();
+
+    Ok(accounts)
+}
+
+/// Parse the arguments of a `set` statement, which can be either
+/// `var=value`, `var = value` or `var` for booleans.
+fn parse_set(args: &[String], vars: &mut Vars) {
+    let mut args = args.iter().peekable();
+
+    while let Some(arg) = args.next() {
+        let (key, val) = match arg.split_once('=') {
+            Some((key, "")) => (key, args.next().cloned()),
+            Some((key, val)) => (key, Some(val.to_owned())),
+            None if args.peek().is_some_and(|arg| *arg == "=") => {
+                args.next();
+                (arg.as_str(), args.next().cloned())
+            }
+            None => (arg.as_str(), Some(String::from("yes"))),
+        };
+
+        vars.insert(key.to_owned(), val.unwrap_or_default());
+    }
+}
+
+fn build_account(name: impl ToString, vars: &Vars) -> ImportedAccount {
+    let mut account = ImportedAccount::new(name);
+    let var = |key: &str| vars.get(key).map(String::as_str).filter(|v| !v.is_empty());
+
+    if let Some(from) = var("from") {
+        match from.rsplit_once('<') {
+            Some((name, email)) => {
+                account.set_email(email.trim_end_matches('>').trim());
+                let name = name.trim().trim_matches('"');
+                if !name.is_empty() {
+                    account.set_display_name(name);
+                }
+            }
+            None => account.set_email(from.trim()),
+        }
+    }
+
+    if let Some(name) = var("realname").or_else(|| var("real_name")) {
+        account.set_display_name(name);
+    }
+
+    if let Some(signature) = var("signature").filter(|sig| !sig.ends_with('|')) {
+        account.set_signature(signature);
+    }
+
+    let folder = var("folder").unwrap_or_default();
+
+    match Server::from_url(folder) {
+        Ok((mut server, _)) => {
+            if let Some(login) = var("imap_user") {
+                server.login = login.to_owned();
+            }
+
+            if let Some(passwd) = var("imap_pass") {
+                server.passwd = to_secret(passwd);
+            }
+
+            account.set_imap(server);
+        }
+        #[cfg(feature = "maildir")]
+        Err(_) if !folder.is_empty() => {
+            let is_maildir =
+                var("mbox_type").is_some_and(|kind| kind.eq_ignore_ascii_case("maildir"));
+
+            if is_maildir {
+                account.set_maildir(folder, false);
+            }
+        }
+        Err(_) => (),
+    }
+
+    for (key, alias) in [
+        ("spoolfile", "inbox"),
+        ("spool_file", "inbox"),
+        ("record", "sent"),
+        ("postponed", "drafts"),
+        ("trash", "trash"),
+    ] {
+        if let Some(mailbox) = var(key).and_then(|mailbox| to_folder_name(folder, mailbox)) {
+            account.set_folder_alias(alias, mailbox);
+        }
+    }
+
+    if let Some(Ok((mut server, _))) = var("smtp_url").map(Server::from_url) {
+        if let Some(passwd) = var("smtp_pass") {
+            server.passwd = to_secret(passwd);
+        }
+
+        account.set_smtp(server);
+    }
+
+    account
+}
+
+/// Convert the given mutt password into a secret.
+///
+/// Backtick-quoted values are commands.
+fn to_secret(passwd: &str) -> Secret {
+    match passwd
+        .strip_prefix('`')
+        .and_then(|cmd| cmd.strip_suffix('`'))
+    {
+        Some(cmd) => Secret::new_command(cmd),
+        None => Secret::new_raw(passwd),
+    }
+}
+
+/// Convert the given mutt mailbox into a folder name relative to the
+/// given root folder.
+///
+/// Shortcuts `+` and `=` as well as the root folder are stripped.
+fn to_folder_name(root: &str, mailbox: &str) -> Option<String> {
+    let mailbox = match Server::from_url(mailbox) {
+        Ok((_, path)) => path,
+        Err(_) => mailbox
+            .strip_prefix(root)
+            .unwrap_or(mailbox)
+            .trim_start_matches(['+', '=', '/'])
+            .to_owned(),
+    };
+
+    Some(mailbox).filter(|mailbox| !mailbox.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    const MUTTRC: &str = r#"
+set realname = "John Doe"
+set signature = ~/.signature
+set record = "+Sent"
+source ~/.mutt/colors
+
+account-hook imaps://john@imap.example.org/ 'set imap_user=john@example.org imap_pass="`pass show mail`"'
+account-hook imaps://john@imap.example.org/ 'set from="John <john@example.org>"; set smtp_url=smtps://john@example.org@smtp.example.org'
+
+set folder = imaps://john@imap.example.org/
+set spoolfile = +INBOX
+set postponed = "imaps://john@imap.example.org/Drafts"
+set trash=+Trash
+"#;
+
+    #[test]
+    fn mutt() {
+        let accounts = parse(MUTTRC).unwrap();
+        assert_eq!(accounts.len(), 1);
+
+        let account = &accounts[0];
+        assert_eq!(account.name(), "john");
+        assert_eq!(account.account_config.email, "john@example.org");
+        assert_eq!(
+            account.account_config.display_name.as_deref(),
+            Some("John Doe")
+        );
+        assert_eq!(
+            account.account_config.signature.as_deref(),
+            Some("~/.signature")
+        );
+
+        let aliases = account.account_config.folder.as_ref().unwrap();
+        let aliases = aliases.aliases.as_ref().unwrap();
+        assert_eq!(aliases.get("inbox").unwrap(), "INBOX");
+        assert_eq!(aliases.get("sent").unwrap(), "Sent");
+        assert_eq!(aliases.get("drafts").unwrap(), "Drafts");
+        assert_eq!(aliases.get("trash").unwrap(), "Trash");
+
+        #[cfg(feature = "imap")]
+        {
+            use secret::Secret;
+
+            use crate::{account::config::passwd::PasswdConfig, imap::config::*};
+
+            let imap = account.imap.as_ref().unwrap();
+            assert_eq!(imap.host, "imap.example.org");
+            assert_eq!(imap.port, 993);
+            assert_eq!(imap.login, "john@example.org");
+            assert_eq!(
+                imap.auth,
+                ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_command("pass show mail")))
+            );
+        }
+
+        #[cfg(feature = "smtp")]
+        {
+            use crate::smtp::config::SmtpEncryptionKind;
+
+            let smtp = account.smtp.as_ref().unwrap();
+            assert_eq!(smtp.host, "smtp.example.org");
+            assert_eq!(smtp.port, 465);
+            assert_eq!(smtp.encryption, Some(SmtpEncryptionKind::Tls));
+            assert_eq!(smtp.login, "john@example.org");
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! # OfflineIMAP import
+//!
+//! Module dedicated to the import of the OfflineIMAP configuration
+//! file `.offlineimaprc`.
+//!
+//! One account is imported per `[Account <name>]` section. The remote
+//! repository becomes the IMAP configuration, the local Maildir
+//! repository becomes the Maildir configuration as well as the
+//! synchronization directory.
+
+use std::collections::HashMap;
+
+use secret::Secret;
+
+use super::{Encryption, Error, ImportedAccount, Result, Server};
+use crate::warn;
+
+/// Parse the given `.offlineimaprc` content.
+pub fn parse(content: &str) -> Result<Vec<ImportedAccount>> {
+    let sections = parse_ini(content)?;
+    let empty = HashMap::new();
+
+    let section = |name: &str| sections.get(name).unwrap_or(&empty);
+
+    let mut names: Vec<&str> = match section("general").get("accounts") {
+        Some(accounts) => accounts.split(',').map(str::trim).collect(),
+        None => sections
+            .keys()
+            .filter_map(|name| name.strip_prefix("Account "))
+            .collect(),
+    };
+
+    names.retain(|name| !name.is_empty());
+    names.sort_unstable();
+    names.dedup();
+
+    let mut accounts = Vec::new();
+
+    for name in names {
+        let mut account = ImportedAccount::new(name);
+        let account_section = section(&format!("Account {name}"));
+
+        if let Some(repo) = account_section.get("remoterepository") {
+            apply_remote(&mut account, section(&format!("Repository {repo}")));
+        }
+
+        #[cfg(feature = "maildir")]
+        if let Some(repo) = account_section.get("localrepository") {
+            let repo = section(&format!("Repository {repo}"));
+            let is_maildir = repo
+                .get("type")
+                .is_some_and(|kind| kind.to_ascii_lowercase().ends_with("maildir"));
+
+            if let Some(dir) = repo.get("localfolders").filter(|_| is_maildir) {
+                account.set_maildir(dir, false);
+            }
+        }
+
+        accounts.push(account);
+    }
+
+    Ok(accounts)
+}
+
+fn apply_remote(account: &mut ImportedAccount, repo: &HashMap<String, String>) {
+    let is_gmail = repo
+        .get("type")
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("gmail"));
+
+    let host = match repo.get("remotehost") {
+        Some(host) => host.clone(),
+        None if is_gmail => String::from("imap.gmail.com"),
+        None => String::new(),
+    };
+
+    let encryption = if repo.get("ssl").map_or(true, |ssl| is_true(ssl)) {
+        Encryption::Tls
+    } else if repo.get("starttls").map_or(true, |tls| is_true(tls)) {
+        Encryption::StartTls
+    } else {
+        Encryption::None
+    };
+
+    let passwd = if let Some(passwd) = repo.get("remotepass") {
+        Secret::new_raw(passwd)
+    } else if let Some(path) = repo.get("remotepassfile") {
+        Secret::new_command(format!("cat {}", quote_path(path)))
+    } else {
+        if repo.contains_key("remotepasseval") {
+            warn!("cannot import python password evaluation, please set it manually");
+        }
+        Secret::default()
+    };
+
+    let login = repo.get("remoteuser").cloned().unwrap_or_default();
+    if login.contains('@') {
+        account.set_email(&login);
+    }
+
+    account.set_imap(Server {
+        host,
+        port: repo.get("remoteport").and_then(|port| port.parse().ok()),
+        encryption,
+        login,
+        passwd,
+    });
+
+    if let Some(trash) = repo.get("trashfolder") {
+        account.set_folder_alias("trash", trash);
+    }
+
+    #[cfg(feature = "sync")]
+    if let Some(filter) = repo.get("folderfilter") {
+        match parse_folder_filter(filter) {
+            Some((folders, true)) => account.set_folder_filter(vec![], folders),
+            Some((folders, false)) => account.set_folder_filter(folders, vec![]),
+            None => warn!("cannot import folder filter {filter}, please set it manually"),
+        }
+    }
+}
+
+/// Quote the given path so that it can be used as a shell command
+/// argument.
+///
+/// A leading `~/` is kept unquoted, so that the shell still expands
+/// it to the home directory like OfflineIMAP does.
+fn quote_path(path: &str) -> String {
+    let (home, path) = match path.strip_prefix("~/") {
+        Some(path) => ("~/", path),
+        None => ("", path),
+    };
+
+    format!("{home}'{}'", path.replace('\'', r"'\''"))
+}
+
+fn is_true(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "yes" | "true" | "on" | "1"
+    )
+}
+
+/// Parse the given python folder filter.
+///
+/// Only lambdas checking the presence of the folder in a list are
+/// supported, like `lambda folder: folder in ['INBOX', 'Sent']`. The
+/// returned boolean is `true` when the filter excludes the listed
+/// folders.
+#[cfg(feature = "sync")]
+fn parse_folder_filter(filter: &str) -> Option<(Vec<String>, bool)> {
+    let (_, body) = filter.trim().strip_prefix("lambda")?.split_once(':')?;
+    let (_, list) = body.split_once(" in ")?;
+    let exclude = body.contains(" not in ");
+    let list = list.trim().strip_prefix('[')?.strip_suffix(']')?;
+
+    let folders = list
+        .split(',')
+        .map(|folder| folder.trim().trim_matches(['\'', '"']).to_owned())
+        .filter(|folder| !folder.is_empty())
+        .collect();
+
+    Some((folders, exclude))
+}
+
+/// Parse the given INI content into sections of lowercase keys and
+/// values.
+///
+/// Lines starting with white spaces continue the value of the
+/// previous line.
+fn parse_ini(content: &str) -> Result<HashMap<String, HashMap<String, String>>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut section: Option<String> = None;
+    let mut key: Option<String> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            let value = section
+                .as_ref()
+                .zip(key.as_ref())
+                .and_then(|(section, key)| sections.get_mut(section)?.get_mut(key));
+
+            match value {
+                Some(value) => {
+                    value.push(' ');
+                    value.push_str(trimmed);
+                }
+                None => return Err(Error::ParseLineError(line_no, "unexpected indent".into())),
+            }
+
+            continue;
+        }
+
+        if let Some(name) = trimmed.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| Error::ParseLineError(line_no, "unclosed section".into()))?;
+            let name = name.trim().to_owned();
+            sections.entry(name.clone()).or_default();
+            section = Some(name);
+            key = None;
+            continue;
+        }
+
+        let Some((k, v)) = trimmed.split_once(['=', ':']) else {
+            return Err(Error::ParseLineError(line_no, "missing value".into()));
+        };
+
+        let Some(section) = &section else {
+            return Err(Error::ParseLineError(
+                line_no,
+                "option outside section".into(),
+            ));
+        };
+
+        let k = k.trim().to_ascii_lowercase();
+        let v = v.trim().to_owned();
+
+        sections
+            .entry(section.clone())
+            .or_default()
+            .insert(k.clone(), v);
+        key = Some(k);
+    }
+
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, quote_path};
+
+    const OFFLINEIMAPRC: &str = r#"
+[general]
+accounts = personal
+# pythonfile = ~/.offlineimap.py
+
+[Account personal]
+localrepository = personal-local
+remoterepository = personal-remote
+
+[Repository personal-local]
+type = Maildir
+localfolders = ~/Mail/personal
+
+[Repository personal-remote]
+type = IMAP
+remotehost = imap.example.org
+remoteuser = me@example.org
+remotepassfile = ~/.config/mail/passwd
+ssl = no
+starttls = yes
+folderfilter = lambda folder: folder not in ['Spam',
+    'Junk']
+"#;
+
+    #[test]
+    fn offlineimap() {
+        let accounts = parse(OFFLINEIMAPRC).unwrap();
+        assert_eq!(accounts.len(), 1);
+
+        let account = &accounts[0];
+        assert_eq!(account.name(), "personal");
+        assert_eq!(account.account_config.email, "me@example.org");
+
+        #[cfg(feature = "imap")]
+        {
+            use secret::Secret;
+
+            use crate::{account::config::passwd::PasswdConfig, imap::config::*};
+
+            let imap = account.imap.as_ref().unwrap();
+            assert_eq!(imap.host, "imap.example.org");
+            assert_eq!(imap.port, 143);
+            assert_eq!(imap.encryption, Some(ImapEncryptionKind::StartTls));
+            assert_eq!(
+                imap.auth,
+                ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_command(
+                    "cat ~/'.config/mail/passwd'"
+                )))
+            );
+        }
+
+        #[cfg(feature = "sync")]
+        {
+            use std::path::PathBuf;
+
+            use crate::folder::{filter::FolderPattern, sync::config::FolderSyncStrategy};
+
+            let sync = account.account_config.sync.as_ref().unwrap();
+            assert_eq!(sync.dir, Some(PathBuf::from("~/Mail/personal")));
+
+            let folder = account.account_config.folder.as_ref().unwrap();
+            let filter = &folder.sync.as_ref().unwrap().filter;
+            let expected = FolderSyncStrategy::Exclude(
+                [FolderPattern::new("Junk"), FolderPattern::new("Spam")].into(),
+            );
+            assert_eq!(filter, &expected);
+        }
+    }
+
+    #[test]
+    fn quote_passwd_file() {
+        assert_eq!(quote_path("/tmp/pass file"), "'/tmp/pass file'");
+        assert_eq!(quote_path("~/it's; rm -rf"), r"~/'it'\''s; rm -rf'");
+    }
+}
@@ -0,0 +1,243 @@
+//! # Thunderbird import
+//!
+//! Module dedicated to the import of the `prefs.js` file of a
+//! Thunderbird profile.
+//!
+//! One account is imported per IMAP account of the account manager,
+//! using its default identity and the SMTP server of this
+//! identity. Passwords are stored encrypted outside of `prefs.js`,
+//! they are not imported. POP3 and local folders accounts are
+//! skipped.
+
+use std::collections::HashMap;
+
+use super::{Encryption, Error, ImportedAccount, Result, Server};
+use crate::debug;
+
+/// Parse the given `prefs.js` content.
+pub fn parse(content: &str) -> Result<Vec<ImportedAccount>> {
+    let prefs = parse_prefs(content)?;
+    let pref = |key: String| {
+        prefs
+            .get(&key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    };
+
+    let mut accounts = Vec::new();
+
+    let keys = pref("mail.accountmanager.accounts".into()).unwrap_or_default();
+
+    for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+        let Some(server) = pref(format!("mail.account.{key}.server")) else {
+            continue;
+        };
+
+        let server_pref = |name: &str| pref(format!("mail.server.{server}.{name}"));
+
+        if server_pref("type") != Some("imap") {
+            debug!("skipping non-IMAP Thunderbird account {key}");
+            continue;
+        }
+
+        let name = server_pref("name")
+            .or_else(|| server_pref("userName"))
+            .unwrap_or(key);
+
+        let mut account = ImportedAccount::new(name);
+
+        account.set_imap(Server {
+            host: server_pref("hostname").unwrap_or_default().to_owned(),
+            port: server_pref("port").and_then(|port| port.parse().ok()),
+            encryption: to_encryption(server_pref("socketType")),
+            login: server_pref("userName").unwrap_or_default().to_owned(),
+            ..Default::default()
+        });
+
+        if let Some(trash) = server_pref("trash_folder_name") {
+            account.set_folder_alias("trash", trash);
+        }
+
+        let identities = pref(format!("mail.account.{key}.identities"));
+        let identity = identities
+            .and_then(|ids| ids.split(',').next())
+            .map(str::trim);
+
+        if let Some(identity) = identity {
+            let identity_pref = |name: &str| pref(format!("mail.identity.{identity}.{name}"));
+
+            if let Some(email) = identity_pref("useremail") {
+                account.set_email(email);
+            }
+
+            if let Some(name) = identity_pref("fullName") {
+                account.set_display_name(name);
+            }
+
+            for (key, alias) in [("fcc_folder", "sent"), ("draft_folder", "drafts")] {
+                let folder = identity_pref(key)
+                    .and_then(|uri| Server::from_url(uri).ok())
+                    .and_then(|(_, folder)| Some(urlencoding::decode(&folder).ok()?.into_owned()));
+
+                if let Some(folder) = folder.filter(|folder| !folder.is_empty()) {
+                    account.set_folder_alias(alias, folder);
+                }
+            }
+
+            let smtp =
+                identity_pref("smtpServer").or_else(|| pref("mail.smtp.defaultserver".into()));
+
+            if let Some(smtp) = smtp {
+                let smtp_pref = |name: &str| pref(format!("mail.smtpserver.{smtp}.{name}"));
+
+                if let Some(host) = smtp_pref("hostname") {
+                    account.set_smtp(Server {
+                        host: host.to_owned(),
+                        port: smtp_pref("port").and_then(|port| port.parse().ok()),
+                        encryption: to_encryption(smtp_pref("try_ssl")),
+                        login: smtp_pref("username").unwrap_or_default().to_owned(),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        accounts.push(account);
+    }
+
+    Ok(accounts)
+}
+
+/// Convert the given Thunderbird socket type into an encryption.
+fn to_encryption(socket_type: Option<&str>) -> Encryption {
+    match socket_type {
+        Some("3") => Encryption::Tls,
+        Some("1" | "2") => Encryption::StartTls,
+        _ => Encryption::None,
+    }
+}
+
+/// Parse the given `prefs.js` content into preference values.
+///
+/// String values are unescaped, other values are kept as is.
+fn parse_prefs(content: &str) -> Result<HashMap<String, String>> {
+    let mut prefs = HashMap::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let err = |reason: &str| Error::ParseLineError(line_no, reason.to_owned());
+
+        let Some(pref) = line.trim().strip_prefix("user_pref(") else {
+            continue;
+        };
+
+        let pref = pref
+            .strip_suffix(");")
+            .ok_or_else(|| err("missing closing parenthesis"))?;
+
+        let (key, rest) = parse_js_string(pref).ok_or_else(|| err("invalid preference name"))?;
+
+        let value = rest
+            .trim_start()
+            .strip_prefix(',')
+            .ok_or_else(|| err("missing preference value"))?
+            .trim();
+
+        let value = match parse_js_string(value) {
+            Some((value, _)) => value,
+            None => value.to_owned(),
+        };
+
+        prefs.insert(key, value);
+    }
+
+    Ok(prefs)
+}
+
+/// Parse the double-quoted JavaScript string at the beginning of the
+/// given input, and return it along with the rest of the input.
+fn parse_js_string(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &input[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    const PREFS_JS: &str = r#"// Mozilla User Preferences
+
+user_pref("mail.accountmanager.accounts", "account1,account2");
+user_pref("mail.account.account1.identities", "id1");
+user_pref("mail.account.account1.server", "server1");
+user_pref("mail.account.account2.server", "server2");
+user_pref("mail.identity.id1.draft_folder", "imap://john%40example.org@imap.example.org/My%20Drafts");
+user_pref("mail.identity.id1.fullName", "John \"Doe\"");
+user_pref("mail.identity.id1.smtpServer", "smtp1");
+user_pref("mail.identity.id1.useremail", "john@example.org");
+user_pref("mail.server.server1.hostname", "imap.example.org");
+user_pref("mail.server.server1.name", "john@example.org");
+user_pref("mail.server.server1.port", 993);
+user_pref("mail.server.server1.socketType", 3);
+user_pref("mail.server.server1.type", "imap");
+user_pref("mail.server.server1.userName", "john@example.org");
+user_pref("mail.server.server2.type", "none");
+user_pref("mail.smtpserver.smtp1.hostname", "smtp.example.org");
+user_pref("mail.smtpserver.smtp1.port", 587);
+user_pref("mail.smtpserver.smtp1.try_ssl", 2);
+user_pref("mail.smtpserver.smtp1.username", "john@example.org");
+"#;
+
+    #[test]
+    fn thunderbird() {
+        let accounts = parse(PREFS_JS).unwrap();
+        assert_eq!(accounts.len(), 1);
+
+        let account = &accounts[0];
+        assert_eq!(account.name(), "john@example.org");
+        assert_eq!(account.account_config.email, "john@example.org");
+        assert_eq!(
+            account.account_config.display_name.as_deref(),
+            Some("John \"Doe\"")
+        );
+
+        let aliases = account.account_config.folder.as_ref().unwrap();
+        let aliases = aliases.aliases.as_ref().unwrap();
+        assert_eq!(aliases.get("drafts").unwrap(), "My Drafts");
+
+        #[cfg(feature = "imap")]
+        {
+            use crate::imap::config::ImapEncryptionKind;
+
+            let imap = account.imap.as_ref().unwrap();
+            assert_eq!(imap.host, "imap.example.org");
+            assert_eq!(imap.port, 993);
+            assert_eq!(imap.encryption, Some(ImapEncryptionKind::Tls));
+            assert_eq!(imap.login, "john@example.org");
+        }
+
+        #[cfg(feature = "smtp")]
+        {
+            use crate::smtp::config::SmtpEncryptionKind;
+
+            let smtp = account.smtp.as_ref().unwrap();
+            assert_eq!(smtp.host, "smtp.example.org");
+            assert_eq!(smtp.port, 587);
+            assert_eq!(smtp.encryption, Some(SmtpEncryptionKind::StartTls));
+        }
+    }
+}
@@ -58,12 +58,16 @@
 pub mod folder;
 #[cfg(feature = "imap")]
 pub mod imap;
+#[cfg(feature = "import")]
+pub mod import;
 pub mod log;
 #[cfg(feature = "maildir")]
 pub mod maildir;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 pub mod retry;
+#[cfg(feature = "sasl")]
+pub mod sasl;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
 #[cfg(feature = "derive")]
@@ -72,6 +76,8 @@
 pub mod smtp;
 #[cfg(feature = "sync")]
 pub mod sync;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod watch;
 
 #[doc(inline)]
@@ -1,9 +1,37 @@
-use std::{future::IntoFuture, time::Duration};
+//! # Retry
+//!
+//! Module dedicated to request retries. The main structures of this
+//! module are [`RetryConfig`], the retry policy shared by the IMAP
+//! and SMTP backends, and [`Retry`], the state of the retry loop of
+//! a request.
 
-use tokio::time::{error::Elapsed, timeout, Timeout};
+use std::{
+    collections::{hash_map::RandomState, BTreeSet},
+    fmt,
+    future::IntoFuture,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant},
+};
+
+use tokio::time::{error::Elapsed, sleep, timeout, Timeout};
 
 pub type Result<T> = std::result::Result<T, Elapsed>;
 
+/// The default maximum number of retries.
+const DEFAULT_ATTEMPTS: u8 = 3;
+
+/// The default request timeout, in seconds.
+const DEFAULT_TIMEOUT: u64 = 30;
+
+/// The default initial backoff delay, in milliseconds.
+const DEFAULT_BACKOFF_DELAY: u64 = 1000;
+
+/// The default maximum backoff delay, in milliseconds.
+const DEFAULT_BACKOFF_MAX_DELAY: u64 = 30_000;
+
+/// Whether only idempotent operations are retried by default.
+const DEFAULT_IDEMPOTENT_ONLY: bool = true;
+
 #[derive(Debug)]
 pub enum RetryState<T> {
     Ok(T),
@@ -11,32 +39,495 @@ pub enum RetryState<T> {
     TimedOut,
 }
 
-#[derive(Debug, Default)]
+/// The retry configuration.
+///
+/// Defines how requests sent by the IMAP and SMTP backends are
+/// retried when they time out or when the connection is lost.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct RetryConfig {
+    /// The maximum number of retries after the first attempt.
+    ///
+    /// Defaults to 3.
+    pub attempts: Option<u8>,
+
+    /// The request timeouts, by operation class.
+    pub timeout: Option<RetryTimeoutConfig>,
+
+    /// The delay between two attempts.
+    pub backoff: Option<RetryBackoffConfig>,
+
+    /// The errors triggering a retry.
+    ///
+    /// Defaults to timeouts, connection losses and unexpected
+    /// replies.
+    pub on: Option<BTreeSet<RetryableError>>,
+
+    /// Retry only idempotent operations.
+    ///
+    /// A write operation that timed out may have been applied by
+    /// the server anyway: retrying it could for example add the
+    /// same message twice. Storing flags and establishing the
+    /// connection are idempotent, so they are always retried.
+    /// Defaults to `true`, set it to `false` to retry write and send
+    /// operations as well.
+    pub idempotent_only: Option<bool>,
+}
+
+impl RetryConfig {
+    /// Return the maximum number of retries.
+    pub fn attempts(&self) -> u8 {
+        self.attempts.unwrap_or(DEFAULT_ATTEMPTS)
+    }
+
+    /// Return the timeout of the given operation class.
+    pub fn timeout(&self, op: &RetryOperation) -> Duration {
+        self.timeout
+            .as_ref()
+            .map(|config| config.get(op))
+            .unwrap_or(Duration::from_secs(DEFAULT_TIMEOUT))
+    }
+
+    /// Return `true` if only idempotent operations are retried.
+    pub fn idempotent_only(&self) -> bool {
+        self.idempotent_only.unwrap_or(DEFAULT_IDEMPOTENT_ONLY)
+    }
+
+    /// Return the backoff delay before the given retry.
+    ///
+    /// The first retry is the number 0.
+    pub fn backoff(&self, retry: u8) -> Duration {
+        self.backoff.clone().unwrap_or_default().delay(retry)
+    }
+
+    /// Return `true` if the given error triggers a retry of the
+    /// given operation class.
+    pub fn is_retryable(&self, op: &RetryOperation, err: &RetryableError) -> bool {
+        if self.idempotent_only() && !op.is_idempotent() {
+            return false;
+        }
+
+        match &self.on {
+            Some(errs) => errs.contains(err),
+            None => true,
+        }
+    }
+}
+
+/// The request timeouts configuration, by operation class.
+///
+/// Timeouts are expressed in seconds. An operation without its own
+/// timeout uses the default one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct RetryTimeoutConfig {
+    /// The default timeout.
+    ///
+    /// Defaults to 30 seconds.
+    pub default: Option<u64>,
+
+    /// The timeout of read operations (listing folders, searching
+    /// and sorting envelopes…).
+    pub read: Option<u64>,
+
+    /// The timeout of fetch operations (fetching envelopes and
+    /// messages).
+    pub fetch: Option<u64>,
+
+    /// The timeout of write operations (adding messages, storing
+    /// flags, copying and moving messages…).
+    pub write: Option<u64>,
+
+    /// The timeout of send operations.
+    pub send: Option<u64>,
+
+    /// The timeout of connection establishments (TCP connection,
+    /// TLS handshake, greeting and authentication).
+    pub connect: Option<u64>,
+}
+
+impl RetryTimeoutConfig {
+    /// Return the timeout of the given operation class.
+    pub fn get(&self, op: &RetryOperation) -> Duration {
+        let secs = match op {
+            RetryOperation::Read => self.read,
+            RetryOperation::Fetch => self.fetch,
+            RetryOperation::Write | RetryOperation::Store => self.write,
+            RetryOperation::Send => self.send,
+            RetryOperation::Connect => self.connect,
+        };
+
+        Duration::from_secs(secs.or(self.default).unwrap_or(DEFAULT_TIMEOUT))
+    }
+}
+
+/// The backoff configuration.
+///
+/// The delay doubles after each retry, until it reaches the maximum
+/// delay. Delays are expressed in milliseconds.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct RetryBackoffConfig {
+    /// The delay before the first retry.
+    ///
+    /// Defaults to 1000 milliseconds.
+    pub delay: Option<u64>,
+
+    /// The maximum delay between two attempts.
+    ///
+    /// Defaults to 30000 milliseconds.
+    pub max_delay: Option<u64>,
+
+    /// Randomize delays.
+    ///
+    /// When enabled, the actual delay is picked between half and
+    /// the whole computed delay, so that clients sharing the same
+    /// network do not retry all at once. Defaults to `true`.
+    pub jitter: Option<bool>,
+}
+
+impl RetryBackoffConfig {
+    /// Return the delay before the given retry.
+    ///
+    /// The first retry is the number 0.
+    pub fn delay(&self, retry: u8) -> Duration {
+        let delay = self.delay.unwrap_or(DEFAULT_BACKOFF_DELAY);
+        let max_delay = self.max_delay.unwrap_or(DEFAULT_BACKOFF_MAX_DELAY);
+        let delay = delay.saturating_mul(1 << retry.min(16)).min(max_delay);
+
+        if self.jitter.unwrap_or(true) && delay > 1 {
+            let half = delay / 2;
+            Duration::from_millis(half + random() % (delay - half + 1))
+        } else {
+            Duration::from_millis(delay)
+        }
+    }
+}
+
+/// Return a random number.
+///
+/// Based on the randomly seeded hasher of the standard library,
+/// which is enough for jittering delays.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// The operation class, used to pick the right timeout.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum RetryOperation {
+    /// Operations reading data from the server, except envelopes
+    /// and messages.
+    #[default]
+    Read,
+
+    /// Operations fetching envelopes or messages.
+    Fetch,
+
+    /// Operations altering data on the server.
+    Write,
+
+    /// Operations storing flags.
+    ///
+    /// Adding, replacing or removing the same flags twice gives the
+    /// same result, so unlike other write operations they can be
+    /// safely retried. They share the timeout of write operations.
+    Store,
+
+    /// Operations sending messages.
+    Send,
+
+    /// Operations establishing the connection to the server.
+    ///
+    /// Nothing has been sent yet when the connection cannot be
+    /// established, so they can be safely retried, including when
+    /// sending a message.
+    Connect,
+}
+
+impl RetryOperation {
+    /// Return `true` if the operation can be safely executed more
+    /// than once.
+    pub fn is_idempotent(&self) -> bool {
+        matches!(self, Self::Read | Self::Fetch | Self::Store | Self::Connect)
+    }
+}
+
+/// The error triggering a retry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum RetryableError {
+    /// The request timed out.
+    Timeout,
+
+    /// The connection has been closed or reset. The client
+    /// re-connects before retrying.
+    ConnectionLost,
+
+    /// The server sent an unexpected reply (SMTP only). The client
+    /// re-connects before retrying.
+    UnexpectedReply,
+}
+
+impl fmt::Display for RetryableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "timeout"),
+            Self::ConnectionLost => write!(f, "connection lost"),
+            Self::UnexpectedReply => write!(f, "unexpected reply"),
+        }
+    }
+}
+
+/// The failed attempt of a request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryAttempt {
+    /// The error that made the attempt fail.
+    pub error: RetryableError,
+
+    /// The reason of the failure.
+    pub reason: String,
+
+    /// The time spent by the attempt.
+    pub duration: Duration,
+}
+
+impl fmt::Display for RetryAttempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration = self.duration;
+        write!(f, "{} after {duration:?}: {}", self.error, self.reason)
+    }
+}
+
+/// The history of the failed attempts of a request.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetryHistory(Vec<RetryAttempt>);
+
+impl RetryHistory {
+    /// Return the number of failed attempts.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return `true` if no attempt failed.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return the failed attempts, from the oldest to the newest.
+    pub fn iter(&self) -> impl Iterator<Item = &RetryAttempt> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for RetryHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} attempt(s)", self.0.len())?;
+
+        for (i, attempt) in self.0.iter().enumerate() {
+            let sep = if i == 0 { " (" } else { ", " };
+            write!(f, "{sep}#{}: {attempt}", i + 1)?;
+        }
+
+        if !self.0.is_empty() {
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of the retry loop of a request.
+#[derive(Debug)]
 pub struct Retry {
     pub attempts: u8,
+    config: RetryConfig,
+    operation: RetryOperation,
+    started_at: Instant,
+    history: RetryHistory,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
 }
 
 impl Retry {
+    /// Create a new retry state using the given policy.
+    pub fn new(config: RetryConfig) -> Self {
+        Self {
+            attempts: 0,
+            config,
+            operation: Default::default(),
+            started_at: Instant::now(),
+            history: Default::default(),
+        }
+    }
+
     pub fn reset(&mut self) {
         self.attempts = 0;
+        self.history = Default::default();
+    }
+
+    /// Return the history of the failed attempts, and reset the
+    /// state.
+    pub fn take_history(&mut self) -> RetryHistory {
+        self.attempts = 0;
+        std::mem::take(&mut self.history)
     }
 
-    pub fn timeout<F: IntoFuture>(&self, f: F) -> Timeout<F::IntoFuture> {
-        timeout(Duration::from_secs(30), f)
+    /// Start a new attempt of the given operation, limited by the
+    /// timeout of its class.
+    pub fn timeout<F: IntoFuture>(&mut self, op: RetryOperation, f: F) -> Timeout<F::IntoFuture> {
+        self.operation = op;
+        self.started_at = Instant::now();
+        timeout(self.config.timeout(&op), f)
+    }
+
+    /// Record the failure of the current attempt, and return `true`
+    /// if the request should be retried.
+    pub fn fail(&mut self, error: RetryableError, reason: impl ToString) -> bool {
+        self.history.0.push(RetryAttempt {
+            error,
+            reason: reason.to_string(),
+            duration: self.started_at.elapsed(),
+        });
+
+        if self.attempts < self.config.attempts()
+            && self.config.is_retryable(&self.operation, &error)
+        {
+            self.attempts += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Wait before the next attempt, following the backoff policy.
+    pub async fn backoff(&self) {
+        let delay = self.config.backoff(self.attempts.saturating_sub(1));
+        sleep(delay).await
     }
 
     pub fn next<T>(&mut self, res: Result<T>) -> RetryState<T> {
-        match res.ok() {
-            Some(res) => {
-                return RetryState::Ok(res);
-            }
-            None if self.attempts < 3 => {
-                self.attempts += 1;
-                return RetryState::Retry;
-            }
-            None => {
-                return RetryState::TimedOut;
+        match res {
+            Ok(res) => RetryState::Ok(res),
+            Err(_) => {
+                let timeout = self.config.timeout(&self.operation);
+                let reason = format!("request timed out after {timeout:?}");
+
+                if self.fail(RetryableError::Timeout, reason) {
+                    RetryState::Retry
+                } else {
+                    RetryState::TimedOut
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, time::Duration};
+
+    use super::{
+        Retry, RetryBackoffConfig, RetryConfig, RetryOperation, RetryState, RetryTimeoutConfig,
+        RetryableError,
+    };
+
+    #[test]
+    fn backoff() {
+        let config = RetryBackoffConfig {
+            delay: Some(100),
+            max_delay: Some(1000),
+            jitter: Some(false),
+        };
+
+        assert_eq!(config.delay(0), Duration::from_millis(100));
+        assert_eq!(config.delay(1), Duration::from_millis(200));
+        assert_eq!(config.delay(3), Duration::from_millis(800));
+        assert_eq!(config.delay(4), Duration::from_millis(1000));
+
+        let config = RetryBackoffConfig {
+            jitter: Some(true),
+            ..config
+        };
+
+        for retry in 0..8 {
+            let delay = config.delay(retry).as_millis();
+            assert!((50..=1000).contains(&delay));
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let config = RetryTimeoutConfig {
+            default: Some(10),
+            fetch: Some(120),
+            ..Default::default()
+        };
+
+        assert_eq!(config.get(&RetryOperation::Read), Duration::from_secs(10));
+        assert_eq!(config.get(&RetryOperation::Fetch), Duration::from_secs(120));
+        assert_eq!(config.get(&RetryOperation::Store), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn history() {
+        let mut retry = Retry::new(RetryConfig {
+            attempts: Some(1),
+            on: Some(BTreeSet::from_iter([RetryableError::Timeout])),
+            ..Default::default()
+        });
+
+        let _ = retry.timeout(RetryOperation::Read, async {});
+        assert!(!retry.fail(RetryableError::ConnectionLost, "reset"));
+
+        let elapsed = tokio::time::timeout(Duration::ZERO, std::future::pending::<()>()).await;
+        assert!(matches!(retry.next(elapsed), RetryState::Retry));
+
+        let elapsed = tokio::time::timeout(Duration::ZERO, std::future::pending::<()>()).await;
+        assert!(matches!(retry.next(elapsed), RetryState::TimedOut));
+
+        let history = retry.take_history();
+        assert_eq!(history.len(), 3);
+        assert!(history
+            .to_string()
+            .starts_with("3 attempt(s) (#1: connection lost"));
+        assert!(retry.take_history().is_empty());
+    }
+
+    #[test]
+    fn idempotent_only() {
+        let config = RetryConfig::default();
+
+        assert!(config.is_retryable(&RetryOperation::Fetch, &RetryableError::Timeout));
+        assert!(config.is_retryable(&RetryOperation::Store, &RetryableError::Timeout));
+        assert!(config.is_retryable(&RetryOperation::Connect, &RetryableError::ConnectionLost));
+        assert!(!config.is_retryable(&RetryOperation::Write, &RetryableError::Timeout));
+        assert!(!config.is_retryable(&RetryOperation::Send, &RetryableError::Timeout));
+
+        let config = RetryConfig {
+            idempotent_only: Some(false),
+            ..Default::default()
+        };
+
+        assert!(config.is_retryable(&RetryOperation::Write, &RetryableError::Timeout));
+    }
+}
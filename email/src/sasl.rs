@@ -0,0 +1,715 @@
+//! # SASL
+//!
+//! Module dedicated to SASL authentication. The main structure of
+//! this module is [`SaslClient`], the client side of a SASL exchange
+//! shared by the IMAP and SMTP backends. It only deals with raw
+//! challenges and responses: encoding them is up to the protocol.
+//!
+//! Supported mechanisms are SCRAM-SHA-256 and SCRAM-SHA-1
+//! ([RFC5802](https://datatracker.ietf.org/doc/html/rfc5802)), with
+//! channel binding when available, CRAM-MD5
+//! ([RFC2195](https://datatracker.ietf.org/doc/html/rfc2195)), PLAIN
+//! ([RFC4616](https://datatracker.ietf.org/doc/html/rfc4616)), LOGIN
+//! and EXTERNAL
+//! ([RFC4422](https://datatracker.ietf.org/doc/html/rfc4422#appendix-A)).
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+    result,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{
+    digest::{core_api::BlockSizeUser, Digest, KeyInit},
+    Mac, SimpleHmac,
+};
+use md5::Md5;
+use rand::{distributions::Alphanumeric, Rng};
+use sha1::Sha1;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::account::config::passwd::SaslMechanism;
+
+/// The maximum SCRAM iteration count accepted from the server.
+///
+/// The iteration count is chosen by the server: a malicious server
+/// could make the client spin for minutes by sending a huge one.
+/// Common servers use 4096 iterations.
+pub const SCRAM_MAX_ITERATIONS: u32 = 100_000;
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot decode sasl challenge")]
+    DecodeChallengeError(#[source] base64::DecodeError),
+    #[error("unexpected sasl challenge")]
+    UnexpectedChallengeError,
+    #[error("invalid scram server first message: {0}")]
+    InvalidServerFirstMessageError(String),
+    #[error("invalid scram server nonce: it does not extend the client nonce")]
+    InvalidServerNonceError,
+    #[error("invalid scram server final message: {0}")]
+    InvalidServerFinalMessageError(String),
+    #[error("invalid scram server signature: the server may not know the password")]
+    InvalidServerSignatureError,
+    #[error("missing scram server signature: the server did not prove its identity")]
+    MissingServerSignatureError,
+    #[error("scram authentication failed: {0}")]
+    ScramServerError(String),
+    #[error("authentication rejected by the server: {0}")]
+    RejectedError(String),
+}
+
+/// The channel binding data of a TLS connection, as defined in the
+/// [RFC5056](https://datatracker.ietf.org/doc/html/rfc5056).
+///
+/// Binding the SCRAM authentication to the TLS connection prevents
+/// man-in-the-middle attacks, even when the server certificate is
+/// not verified.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelBinding {
+    /// The channel binding type, for example `tls-exporter`.
+    pub name: &'static str,
+
+    /// The channel binding data.
+    pub data: Vec<u8>,
+}
+
+impl ChannelBinding {
+    /// The label used to export the `tls-exporter` channel binding
+    /// data, as defined in the
+    /// [RFC9266](https://datatracker.ietf.org/doc/html/rfc9266).
+    pub const TLS_EXPORTER_LABEL: &'static [u8] = b"EXPORTER-Channel-Binding";
+
+    /// The length of the `tls-exporter` channel binding data.
+    pub const TLS_EXPORTER_LEN: usize = 32;
+
+    /// Build a `tls-exporter` channel binding from the keying
+    /// material exported from a TLS 1.3 connection.
+    pub fn tls_exporter(data: Vec<u8>) -> Self {
+        Self {
+            name: "tls-exporter",
+            data,
+        }
+    }
+}
+
+/// The client side of a SASL exchange.
+///
+/// The exchange starts with the optional initial response, see
+/// [`SaslClient::initial_response`]. Every challenge sent by the
+/// server is then answered using [`SaslClient::respond`]. Once the
+/// server accepts the authentication, [`SaslClient::finish`] makes
+/// sure that the server proved its identity, when the mechanism
+/// supports it.
+pub struct SaslClient {
+    mechanism: SaslMechanism,
+    login: String,
+    passwd: String,
+    started: bool,
+    step: usize,
+    scram: Option<Scram>,
+}
+
+impl SaslClient {
+    pub fn new(
+        mechanism: SaslMechanism,
+        login: impl ToString,
+        passwd: impl ToString,
+        channel_binding: Option<ChannelBinding>,
+    ) -> Self {
+        let login = login.to_string();
+
+        let scram = match mechanism {
+            SaslMechanism::ScramSha256 => Some(Scram::new(
+                ScramHash::Sha256,
+                &login,
+                channel_binding,
+                Scram::generate_nonce(),
+            )),
+            SaslMechanism::ScramSha1 => Some(Scram::new(
+                ScramHash::Sha1,
+                &login,
+                channel_binding,
+                Scram::generate_nonce(),
+            )),
+            _ => None,
+        };
+
+        Self {
+            mechanism,
+            login,
+            passwd: passwd.to_string(),
+            started: false,
+            step: 0,
+            scram,
+        }
+    }
+
+    /// Tell the server that the client supports channel binding,
+    /// even if it is not used.
+    ///
+    /// This is the case when the connection is encrypted, but the
+    /// server did not advertise SCRAM mechanisms with channel
+    /// binding. The server can then detect that the `-PLUS` variants
+    /// were stripped from its capabilities by an attacker, see the
+    /// [RFC5802](https://datatracker.ietf.org/doc/html/rfc5802#section-6).
+    pub fn with_channel_binding_support(mut self) -> Self {
+        if let Some(scram) = &mut self.scram {
+            if scram.channel_binding.is_none() {
+                scram.gs2_header = String::from("y,,");
+            }
+        }
+
+        self
+    }
+
+    /// Return the name of the mechanism, as advertised by servers.
+    ///
+    /// The name of SCRAM mechanisms ends with `-PLUS` when channel
+    /// binding is used.
+    pub fn name(&self) -> String {
+        match &self.scram {
+            Some(scram) if scram.channel_binding.is_some() => {
+                format!("{}-PLUS", self.mechanism.as_str())
+            }
+            _ => self.mechanism.as_str().to_owned(),
+        }
+    }
+
+    /// Return the initial response, if the mechanism has one.
+    ///
+    /// If the protocol does not support initial responses, this
+    /// function should not be called: the initial response is then
+    /// sent in response to the first (empty) challenge.
+    pub fn initial_response(&mut self) -> Option<Vec<u8>> {
+        self.started = true;
+
+        match self.mechanism {
+            SaslMechanism::Plain => Some(format!("\0{}\0{}", self.login, self.passwd).into_bytes()),
+            SaslMechanism::External => Some(Vec::new()),
+            SaslMechanism::ScramSha256 | SaslMechanism::ScramSha1 => {
+                self.scram.as_ref().map(Scram::client_first_message)
+            }
+            SaslMechanism::Login | SaslMechanism::CramMd5 => None,
+        }
+    }
+
+    /// Answer the given (decoded) challenge.
+    pub fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        if !self.started {
+            if let Some(res) = self.initial_response() {
+                return Ok(res);
+            }
+        }
+
+        let step = self.step;
+        self.step += 1;
+
+        match (self.mechanism, step) {
+            (SaslMechanism::Login, 0) => Ok(self.login.clone().into_bytes()),
+            (SaslMechanism::Login, 1) => Ok(self.passwd.clone().into_bytes()),
+            (SaslMechanism::CramMd5, 0) => {
+                let digest = hmac::<Md5>(self.passwd.as_bytes(), challenge);
+                Ok(format!("{} {}", self.login, hex(&digest)).into_bytes())
+            }
+            (SaslMechanism::ScramSha256 | SaslMechanism::ScramSha1, 0) => {
+                let scram = self.scram.as_mut().ok_or(Error::UnexpectedChallengeError)?;
+                scram.client_final_message(&self.passwd, challenge)
+            }
+            (SaslMechanism::ScramSha256 | SaslMechanism::ScramSha1, 1) => {
+                let scram = self.scram.as_mut().ok_or(Error::UnexpectedChallengeError)?;
+                scram.verify_server_final_message(challenge)?;
+                Ok(Vec::new())
+            }
+            _ => Err(Error::UnexpectedChallengeError),
+        }
+    }
+
+    /// Make sure the exchange completed, once the server accepted
+    /// the authentication.
+    ///
+    /// SCRAM mechanisms require the server to prove its identity by
+    /// sending a valid signature before accepting the
+    /// authentication.
+    pub fn finish(&self) -> Result<()> {
+        match &self.scram {
+            Some(scram) if !scram.verified => Err(Error::MissingServerSignatureError),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The password is redacted, so that it does not leak in logs.
+impl fmt::Debug for SaslClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaslClient")
+            .field("mechanism", &self.mechanism)
+            .field("login", &self.login)
+            .field("passwd", &"<redacted>")
+            .field("started", &self.started)
+            .field("step", &self.step)
+            .field("scram", &self.scram)
+            .finish()
+    }
+}
+
+/// The hash function of a SCRAM mechanism.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ScramHash {
+    Sha1,
+    Sha256,
+}
+
+/// The state of a SCRAM exchange, as defined in the
+/// [RFC5802](https://datatracker.ietf.org/doc/html/rfc5802#section-3).
+#[derive(Debug)]
+struct Scram {
+    hash: ScramHash,
+    channel_binding: Option<ChannelBinding>,
+    gs2_header: String,
+    client_nonce: String,
+    client_first_message_bare: String,
+    server_signature: Vec<u8>,
+    verified: bool,
+}
+
+impl Scram {
+    fn new(
+        hash: ScramHash,
+        login: &str,
+        channel_binding: Option<ChannelBinding>,
+        client_nonce: String,
+    ) -> Self {
+        // The client does not support channel binding when the
+        // connection is not encrypted, or when the TLS connection
+        // cannot export the binding data, see
+        // [`SaslClient::with_channel_binding_support`].
+        let gs2_header = match &channel_binding {
+            Some(cb) => format!("p={},,", cb.name),
+            None => String::from("n,,"),
+        };
+
+        let login = saslprep(login).replace('=', "=3D").replace(',', "=2C");
+        let client_first_message_bare = format!("n={login},r={client_nonce}");
+
+        Self {
+            hash,
+            channel_binding,
+            gs2_header,
+            client_nonce,
+            client_first_message_bare,
+            server_signature: Vec::new(),
+            verified: false,
+        }
+    }
+
+    fn generate_nonce() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect()
+    }
+
+    fn client_first_message(&self) -> Vec<u8> {
+        format!("{}{}", self.gs2_header, self.client_first_message_bare).into_bytes()
+    }
+
+    fn client_final_message(&mut self, passwd: &str, server_first: &[u8]) -> Result<Vec<u8>> {
+        let server_first = String::from_utf8_lossy(server_first).into_owned();
+
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+
+        for (i, attr) in server_first.split(',').enumerate() {
+            match attr.split_once('=') {
+                Some(("m", _)) if i == 0 => {
+                    let reason = String::from("mandatory extensions are not supported");
+                    return Err(Error::InvalidServerFirstMessageError(reason));
+                }
+                Some(("r", val)) => nonce = Some(val),
+                Some(("s", val)) => salt = Some(val),
+                Some(("i", val)) => iterations = Some(val),
+                _ => (),
+            }
+        }
+
+        let nonce = nonce
+            .ok_or_else(|| Error::InvalidServerFirstMessageError(String::from("missing nonce")))?;
+
+        if nonce.len() <= self.client_nonce.len() || !nonce.starts_with(&self.client_nonce) {
+            return Err(Error::InvalidServerNonceError);
+        }
+
+        let salt = salt
+            .and_then(|salt| BASE64.decode(salt).ok())
+            .ok_or_else(|| Error::InvalidServerFirstMessageError(String::from("invalid salt")))?;
+
+        let iterations = iterations
+            .and_then(|i| i.parse::<u32>().ok())
+            .filter(|i| *i > 0)
+            .ok_or_else(|| {
+                Error::InvalidServerFirstMessageError(String::from("invalid iteration count"))
+            })?;
+
+        if iterations > SCRAM_MAX_ITERATIONS {
+            let reason = format!(
+                "iteration count {iterations} exceeds the maximum of {SCRAM_MAX_ITERATIONS}"
+            );
+            return Err(Error::InvalidServerFirstMessageError(reason));
+        }
+
+        let passwd = saslprep(passwd);
+
+        let mut cbind_input = self.gs2_header.clone().into_bytes();
+        if let Some(cb) = &self.channel_binding {
+            cbind_input.extend_from_slice(&cb.data);
+        }
+
+        let client_final_without_proof = format!("c={},r={nonce}", BASE64.encode(cbind_input));
+
+        let auth_message = format!(
+            "{},{server_first},{client_final_without_proof}",
+            self.client_first_message_bare
+        );
+
+        let (client_proof, server_signature) = match self.hash {
+            ScramHash::Sha1 => {
+                scram_proof::<Sha1>(passwd.as_bytes(), &salt, iterations, &auth_message)
+            }
+            ScramHash::Sha256 => {
+                scram_proof::<Sha256>(passwd.as_bytes(), &salt, iterations, &auth_message)
+            }
+        };
+
+        self.server_signature = server_signature;
+
+        let client_final = format!(
+            "{client_final_without_proof},p={}",
+            BASE64.encode(client_proof)
+        );
+
+        Ok(client_final.into_bytes())
+    }
+
+    fn verify_server_final_message(&mut self, server_final: &[u8]) -> Result<()> {
+        let server_final = String::from_utf8_lossy(server_final);
+
+        match server_final
+            .split(',')
+            .next()
+            .and_then(|a| a.split_once('='))
+        {
+            Some(("v", signature)) => {
+                let signature = BASE64.decode(signature).map_err(|_| {
+                    Error::InvalidServerFinalMessageError(String::from("invalid signature"))
+                })?;
+
+                if signature != self.server_signature {
+                    return Err(Error::InvalidServerSignatureError);
+                }
+
+                self.verified = true;
+                Ok(())
+            }
+            Some(("e", err)) => Err(Error::ScramServerError(err.to_owned())),
+            _ => Err(Error::InvalidServerFinalMessageError(
+                server_final.into_owned(),
+            )),
+        }
+    }
+}
+
+/// Prepare the given string using SASLprep, as defined in the
+/// [RFC4013](https://datatracker.ietf.org/doc/html/rfc4013).
+///
+/// Strings containing prohibited characters are kept as they are,
+/// like most SCRAM implementations do: the server then decides
+/// whether they match.
+fn saslprep(s: &str) -> Cow<'_, str> {
+    stringprep::saslprep(s).unwrap_or(Cow::Borrowed(s))
+}
+
+/// Compute the SCRAM client proof and the expected server signature.
+fn scram_proof<D>(
+    passwd: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    auth_message: &str,
+) -> (Vec<u8>, Vec<u8>)
+where
+    D: Digest + BlockSizeUser + Clone,
+{
+    let salted_passwd = hi::<D>(passwd, salt, iterations);
+
+    let client_key = hmac::<D>(&salted_passwd, b"Client Key");
+    let stored_key = D::digest(&client_key);
+    let client_signature = hmac::<D>(&stored_key, auth_message.as_bytes());
+    let client_proof = client_key
+        .iter()
+        .zip(client_signature)
+        .map(|(key, sig)| key ^ sig)
+        .collect();
+
+    let server_key = hmac::<D>(&salted_passwd, b"Server Key");
+    let server_signature = hmac::<D>(&server_key, auth_message.as_bytes());
+
+    (client_proof, server_signature)
+}
+
+/// The `Hi` function of SCRAM, which is PBKDF2 using HMAC as
+/// pseudorandom function.
+fn hi<D>(passwd: &[u8], salt: &[u8], iterations: u32) -> Vec<u8>
+where
+    D: Digest + BlockSizeUser + Clone,
+{
+    let mac = <SimpleHmac<D> as KeyInit>::new_from_slice(passwd)
+        .expect("HMAC should accept keys of any size");
+
+    let mut u = mac
+        .clone()
+        .chain_update(salt)
+        .chain_update(1u32.to_be_bytes())
+        .finalize()
+        .into_bytes()
+        .to_vec();
+
+    let mut res = u.clone();
+
+    for _ in 1..iterations {
+        u = mac
+            .clone()
+            .chain_update(&u)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        res.iter_mut().zip(&u).for_each(|(r, u)| *r ^= u);
+    }
+
+    res
+}
+
+fn hmac<D>(key: &[u8], data: &[u8]) -> Vec<u8>
+where
+    D: Digest + BlockSizeUser,
+{
+    <SimpleHmac<D> as KeyInit>::new_from_slice(key)
+        .expect("HMAC should accept keys of any size")
+        .chain_update(data)
+        .finalize()
+        .into_bytes()
+        .to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Encode the given SASL response in base64, as expected by most
+/// text protocols.
+pub fn encode(res: impl AsRef<[u8]>) -> String {
+    BASE64.encode(res)
+}
+
+/// Decode the given base64 SASL challenge.
+pub fn decode(challenge: impl AsRef<[u8]>) -> Result<Vec<u8>> {
+    let challenge: Vec<u8> = challenge
+        .as_ref()
+        .iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .copied()
+        .collect();
+
+    BASE64
+        .decode(challenge)
+        .map_err(Error::DecodeChallengeError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scram_client(hash: ScramHash, nonce: &str) -> SaslClient {
+        let mechanism = match hash {
+            ScramHash::Sha1 => SaslMechanism::ScramSha1,
+            ScramHash::Sha256 => SaslMechanism::ScramSha256,
+        };
+
+        SaslClient {
+            mechanism,
+            login: String::from("user"),
+            passwd: String::from("pencil"),
+            started: false,
+            step: 0,
+            scram: Some(Scram::new(hash, "user", None, nonce.to_owned())),
+        }
+    }
+
+    #[test]
+    fn scram_sha1() {
+        // https://datatracker.ietf.org/doc/html/rfc5802#section-5
+        let mut sasl = scram_client(ScramHash::Sha1, "fyko+d2lbbFgONRv9qkxdawL");
+
+        assert_eq!(sasl.name(), "SCRAM-SHA-1");
+        assert_eq!(
+            sasl.initial_response().unwrap(),
+            b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"
+        );
+
+        let res = sasl
+            .respond(b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096")
+            .unwrap();
+        assert_eq!(
+            res,
+            b"c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="
+        );
+
+        assert!(matches!(
+            sasl.finish(),
+            Err(Error::MissingServerSignatureError)
+        ));
+
+        let res = sasl.respond(b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ=").unwrap();
+        assert!(res.is_empty());
+        assert!(sasl.finish().is_ok());
+    }
+
+    #[test]
+    fn scram_sha256() {
+        // https://datatracker.ietf.org/doc/html/rfc7677#section-3
+        let mut sasl = scram_client(ScramHash::Sha256, "rOprNGfwEbeRWgbNEkqO");
+
+        assert_eq!(sasl.name(), "SCRAM-SHA-256");
+
+        // the initial response is sent in response to the first
+        // challenge when the protocol does not support initial
+        // responses
+        assert_eq!(
+            sasl.respond(b"").unwrap(),
+            b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO"
+        );
+
+        let res = sasl
+            .respond(b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096")
+            .unwrap();
+        assert_eq!(
+            res,
+            b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+
+        sasl.respond(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+            .unwrap();
+        assert!(sasl.finish().is_ok());
+    }
+
+    #[test]
+    fn scram_invalid_server() {
+        let mut sasl = scram_client(ScramHash::Sha256, "rOprNGfwEbeRWgbNEkqO");
+        sasl.initial_response();
+
+        assert!(matches!(
+            sasl.respond(b"r=forged,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096"),
+            Err(Error::InvalidServerNonceError)
+        ));
+
+        let mut sasl = scram_client(ScramHash::Sha256, "rOprNGfwEbeRWgbNEkqO");
+        sasl.initial_response();
+        sasl.respond(b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096")
+            .unwrap();
+
+        assert!(matches!(
+            sasl.respond(b"v=cm1GOXBxVjhTN3N1QW9aV2phNGRKUmtGc0tRPQ=="),
+            Err(Error::InvalidServerSignatureError)
+        ));
+        assert!(sasl.finish().is_err());
+
+        let mut sasl = scram_client(ScramHash::Sha256, "rOprNGfwEbeRWgbNEkqO");
+        sasl.initial_response();
+
+        assert!(matches!(
+            sasl.respond(b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4294967295"),
+            Err(Error::InvalidServerFirstMessageError(_))
+        ));
+    }
+
+    #[test]
+    fn scram_saslprep() {
+        // https://datatracker.ietf.org/doc/html/rfc4013#section-3
+        assert_eq!(saslprep("I\u{00AD}X"), "IX");
+        assert_eq!(saslprep("\u{2168}"), "IX");
+        assert_eq!(saslprep("user"), "user");
+
+        // prohibited characters are kept as they are
+        assert_eq!(saslprep("\u{0007}"), "\u{0007}");
+    }
+
+    #[test]
+    fn debug_redacts_passwd() {
+        let sasl = SaslClient::new(SaslMechanism::Plain, "user", "pencil", None);
+        let debug = format!("{sasl:?}");
+
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("pencil"));
+    }
+
+    #[test]
+    fn scram_channel_binding() {
+        let cb = ChannelBinding::tls_exporter(vec![0; 32]);
+        let mut sasl = SaslClient::new(SaslMechanism::ScramSha256, "user", "pencil", Some(cb));
+
+        assert_eq!(sasl.name(), "SCRAM-SHA-256-PLUS");
+
+        let res = sasl.initial_response().unwrap();
+        assert!(res.starts_with(b"p=tls-exporter,,n=user,r="));
+
+        let mut sasl = SaslClient::new(SaslMechanism::ScramSha256, "user", "pencil", None)
+            .with_channel_binding_support();
+
+        assert_eq!(sasl.name(), "SCRAM-SHA-256");
+
+        let res = sasl.initial_response().unwrap();
+        assert!(res.starts_with(b"y,,n=user,r="));
+    }
+
+    #[test]
+    fn cram_md5() {
+        // https://datatracker.ietf.org/doc/html/rfc2195#section-2
+        let mut sasl = SaslClient::new(SaslMechanism::CramMd5, "tim", "tanstaaftanstaaf", None);
+
+        assert_eq!(sasl.initial_response(), None);
+        assert_eq!(
+            sasl.respond(b"<1896.697170952@postoffice.reston.mci.net>")
+                .unwrap(),
+            b"tim b913a602c7eda7a495b4e6e7334d3890"
+        );
+    }
+
+    #[test]
+    fn login() {
+        let mut sasl = SaslClient::new(SaslMechanism::Login, "user", "pencil", None);
+
+        assert_eq!(sasl.initial_response(), None);
+        assert_eq!(sasl.respond(b"Username:").unwrap(), b"user");
+        assert_eq!(sasl.respond(b"Password:").unwrap(), b"pencil");
+        assert!(sasl.respond(b"").is_err());
+    }
+
+    #[test]
+    fn plain() {
+        let mut sasl = SaslClient::new(SaslMechanism::Plain, "user", "pencil", None);
+        assert_eq!(sasl.initial_response().unwrap(), b"\0user\0pencil");
+
+        let mut sasl = SaslClient::new(SaslMechanism::Plain, "user", "pencil", None);
+        assert_eq!(sasl.respond(b"").unwrap(), b"\0user\0pencil");
+    }
+}
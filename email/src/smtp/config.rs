@@ -13,7 +13,12 @@
 pub use super::{Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::{OAuth2Config, OAuth2Method};
-use crate::{account::config::passwd::PasswdConfig, debug};
+use crate::{
+    account::config::passwd::{PasswdConfig, SaslMechanism},
+    debug,
+    retry::RetryConfig,
+    tls::TlsConfig,
+};
 
 /// The SMTP sender configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -38,6 +43,13 @@ pub struct SmtpConfig {
     )]
     pub encryption: Option<SmtpEncryptionKind>,
 
+    /// The SMTP TLS configuration.
+    ///
+    /// Customizes the trust store, the client certificate, the
+    /// minimum TLS version and the server certificate verification
+    /// of SSL/TLS and STARTTLS connections. See [TlsConfig].
+    pub tls: Option<TlsConfig>,
+
     /// The SMTP server login.
     ///
     /// Usually, the login is either the email address or its left
@@ -49,6 +61,23 @@ pub struct SmtpConfig {
     /// Authentication can be done using password or OAuth 2.0.
     /// See [SmtpAuthConfig].
     pub auth: SmtpAuthConfig,
+
+    /// The SASL mechanism used by the password authentication.
+    ///
+    /// When undefined, the most secure mechanism supported by the
+    /// server is negotiated, see [`SaslMechanism::NEGOTIABLE`].
+    /// When defined, authentication fails if the server does not
+    /// support it, and no other mechanism is tried. This is the way
+    /// to make sure that the password is never sent in clear, by
+    /// choosing a SCRAM mechanism. Ignored by the OAuth 2.0
+    /// authentication.
+    pub sasl_mechanism: Option<SaslMechanism>,
+
+    /// The SMTP requests retry policy.
+    ///
+    /// Defines timeouts, backoff and retryable errors of SMTP
+    /// requests. See [RetryConfig].
+    pub retry: Option<RetryConfig>,
 }
 
 impl SmtpConfig {
@@ -78,12 +107,7 @@ pub fn is_encryption_disabled(&self) -> bool {
     pub async fn credentials(&self) -> Result<Credentials<String>> {
         Ok(match &self.auth {
             SmtpAuthConfig::Passwd(passwd) => {
-                let passwd = passwd.get().await.map_err(Error::GetPasswdSmtpError)?;
-                let passwd = passwd
-                    .lines()
-                    .next()
-                    .ok_or(Error::GetPasswdEmptySmtpError)?;
-                Credentials::new(self.login.clone(), passwd.to_owned())
+                Credentials::new(self.login.clone(), Self::passwd(passwd).await?)
             }
             #[cfg(feature = "oauth2")]
             SmtpAuthConfig::OAuth2(oauth2) => {
@@ -92,15 +116,36 @@ pub async fn credentials(&self) -> Result<Credentials<String>> {
                     .await
                     .map_err(|_| Error::AccessTokenWasNotAvailable)?;
 
-                match oauth2.method {
-                    OAuth2Method::XOAuth2 => {
-                        Credentials::new_xoauth2(self.login.clone(), access_token)
-                    }
-                    OAuth2Method::OAuthBearer => Credentials::new_oauth(access_token),
-                }
+                self.oauth2_credentials(oauth2, access_token)
             }
         })
     }
+
+    /// Gets the SMTP password from the given password configuration.
+    ///
+    /// Only the first line of the password secret is kept.
+    pub async fn passwd(passwd: &PasswdConfig) -> Result<String> {
+        let passwd = passwd.get().await.map_err(Error::GetPasswdSmtpError)?;
+        let passwd = passwd
+            .lines()
+            .next()
+            .ok_or(Error::GetPasswdEmptySmtpError)?;
+        Ok(passwd.to_owned())
+    }
+
+    /// Builds the SMTP credentials string from the given OAuth 2.0
+    /// access token.
+    #[cfg(feature = "oauth2")]
+    pub fn oauth2_credentials(
+        &self,
+        oauth2: &OAuth2Config,
+        access_token: String,
+    ) -> Credentials<String> {
+        match oauth2.method {
+            OAuth2Method::XOAuth2 => Credentials::new_xoauth2(self.login.clone(), access_token),
+            OAuth2Method::OAuthBearer => Credentials::new_oauth(access_token),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
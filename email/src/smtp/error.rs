@@ -2,7 +2,9 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{
+    account::config::passwd::SaslMechanism, retry::RetryHistory, sasl, tls, AnyBoxedError, AnyError,
+};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -14,14 +16,32 @@ pub enum Error {
     SendMessageMissingSenderError,
     #[error("cannot send message without a recipient")]
     SendMessageMissingRecipientError,
-    #[error("cannot send message: request timed out")]
-    SendMessageTimedOutError,
+    #[error("cannot send message: request timed out after {0}")]
+    SendMessageTimedOutError(RetryHistory),
     #[error("cannot send message")]
     SendMessageError(#[source] mail_send::Error),
+    #[error("cannot send message after {1}")]
+    SendMessageRetryError(#[source] mail_send::Error, RetryHistory),
+    #[error("cannot build smtp tls configuration")]
+    BuildTlsConfigSmtpError(#[source] tls::Error),
     #[error("cannot connect to smtp server using tcp")]
     ConnectTcpSmtpError(#[source] mail_send::Error),
     #[error("cannot connect to smtp server using tls")]
     ConnectTlsSmtpError(#[source] mail_send::Error),
+    #[error("cannot connect to smtp server: request timed out after {0}")]
+    ConnectSmtpTimedOutError(RetryHistory),
+    #[error("cannot connect to smtp server after {1}")]
+    ConnectSmtpRetryError(#[source] Box<Error>, RetryHistory),
+    #[error("cannot get smtp server capabilities")]
+    EhloSmtpError(#[source] mail_send::Error),
+    #[error("cannot authenticate to smtp server")]
+    AuthenticateSmtpError(#[source] mail_send::Error),
+    #[error("cannot authenticate to smtp server using sasl {0} mechanism")]
+    AuthenticateSaslSmtpError(SaslMechanism, #[source] sasl::Error),
+    #[error("cannot authenticate to smtp server: sasl {0} mechanism not supported")]
+    AuthenticateSaslNotSupportedSmtpError(SaslMechanism),
+    #[error("cannot authenticate to smtp server: no supported sasl mechanism found")]
+    AuthenticateSaslMechanismNotFoundSmtpError,
     #[error("cannot get smtp password")]
     GetPasswdSmtpError(#[source] secret::Error),
     #[error("cannot get smtp password: password is empty")]
@@ -7,24 +7,33 @@
 use mail_parser::{Addr, Address, HeaderName, HeaderValue, Message, MessageParser};
 use mail_send::{
     smtp::message::{Address as SmtpAddress, IntoMessage, Message as SmtpMessage},
-    SmtpClientBuilder,
+    Credentials, SmtpClient, SmtpClientBuilder,
 };
-use tokio::{net::TcpStream, sync::Mutex};
-use tokio_rustls::client::TlsStream;
+use smtp_proto::{
+    EhloResponse, AUTH_CRAM_MD5, AUTH_EXTERNAL, AUTH_LOGIN, AUTH_PLAIN, AUTH_SCRAM_SHA_1,
+    AUTH_SCRAM_SHA_1_PLUS, AUTH_SCRAM_SHA_256, AUTH_SCRAM_SHA_256_PLUS,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::Mutex,
+};
+use tokio_rustls::{client::TlsStream, rustls::ProtocolVersion, TlsConnector};
 
 use self::config::{SmtpAuthConfig, SmtpConfig};
 #[doc(inline)]
 pub use self::error::{Error, Result};
 use crate::{
-    account::config::AccountConfig,
+    account::config::{passwd::SaslMechanism, AccountConfig},
     backend::{
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
     debug, info,
     message::send::{smtp::SendSmtpMessage, SendMessage},
-    retry::{Retry, RetryState},
-    AnyResult,
+    retry::{Retry, RetryOperation, RetryState, RetryableError},
+    sasl::{self, ChannelBinding, SaslClient},
+    warn, AnyResult,
 };
 
 /// The SMTP backend context.
@@ -38,9 +47,6 @@ pub struct SmtpContext {
     /// The SMTP configuration.
     pub smtp_config: Arc<SmtpConfig>,
 
-    /// The SMTP client builder.
-    client_builder: mail_send::SmtpClientBuilder<String>,
-
     /// The SMTP client.
     client: SmtpClientStream,
 }
@@ -70,63 +76,56 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
             }
         };
 
-        let mut retry = Retry::default();
+        let mut retry = Retry::new(self.smtp_config.retry.clone().unwrap_or_default());
+
+        // the connection may have been closed by the server while
+        // idle: since nothing has been sent yet, re-connecting is
+        // always safe
+        let alive = retry
+            .timeout(RetryOperation::Connect, self.client.noop())
+            .await;
+        if !matches!(alive, Ok(Ok(()))) {
+            debug!("smtp connection lost while idle, re-connecting…");
+            self.client = build_client_with_retry(&self.smtp_config, &mut retry).await?;
+        }
 
         loop {
             // NOTE: cannot clone the final message
             let msg = into_smtp_msg(msg.clone())?;
+            let task = self.client.send(msg);
 
-            match retry.next(retry.timeout(self.client.send(msg)).await) {
-                #[cfg(not(feature = "tracing"))]
-                RetryState::Retry => continue,
-                #[cfg(feature = "tracing")]
+            match retry.next(retry.timeout(RetryOperation::Send, task).await) {
                 RetryState::Retry => {
-                    tracing::debug!(attempt = retry.attempts, "request timed out");
+                    debug!(attempt = retry.attempts, "request timed out");
+                    retry.backoff().await;
                     continue;
                 }
                 RetryState::TimedOut => {
-                    break Err(Error::SendMessageTimedOutError);
+                    break Err(Error::SendMessageTimedOutError(retry.take_history()));
                 }
                 RetryState::Ok(Ok(res)) => {
                     break Ok(res);
                 }
                 RetryState::Ok(Err(err)) => {
-                    match err {
-                        #[cfg(not(feature = "tracing"))]
-                        mail_send::Error::Timeout => (),
-                        #[cfg(feature = "tracing")]
-                        mail_send::Error::Timeout => {
-                            tracing::warn!("connection timed out");
-                        }
-                        #[cfg(not(feature = "tracing"))]
-                        mail_send::Error::Io(_) => (),
-                        #[cfg(feature = "tracing")]
-                        mail_send::Error::Io(err) => {
-                            let reason = err.to_string();
-                            tracing::warn!(reason, "connection broke");
-                        }
-                        #[cfg(not(feature = "tracing"))]
-                        mail_send::Error::UnexpectedReply(_) => (),
-                        #[cfg(feature = "tracing")]
-                        mail_send::Error::UnexpectedReply(reply) => {
-                            let reason = reply.message;
-                            let code = reply.code;
-                            tracing::warn!(reason, "server replied with code {code}");
-                        }
-                        err => {
-                            break Err(Error::SendMessageError(err));
-                        }
+                    let Some((kind, reason)) = retryable_failure(&err) else {
+                        break Err(Error::SendMessageError(err));
                     };
 
-                    tracing::debug!("re-connecting…");
+                    if !retry.fail(kind, reason) {
+                        let history = retry.take_history();
+                        break Err(Error::SendMessageRetryError(err, history));
+                    }
+
+                    warn!(
+                        attempt = retry.attempts,
+                        "cannot send message, re-connecting…"
+                    );
+                    retry.backoff().await;
 
-                    self.client = if self.smtp_config.is_encryption_enabled() {
-                        build_tls_client(&self.client_builder).await
-                    } else {
-                        build_tcp_client(&self.client_builder).await
-                    }?;
+                    // credentials are built again, since the access
+                    // token may have expired meanwhile
+                    self.client = build_client_with_retry(&self.smtp_config, &mut retry).await?;
 
-                    retry.reset();
                     continue;
                 }
             }
@@ -185,21 +184,12 @@ fn send_message(&self) -> Option<BackendFeature<Self::Context, dyn SendMessage>>
     async fn build(self) -> AnyResult<Self::Context> {
         info!("building new smtp context");
 
-        let mut client_builder =
-            SmtpClientBuilder::new(self.smtp_config.host.clone(), self.smtp_config.port)
-                .credentials(self.smtp_config.credentials().await?)
-                .implicit_tls(!self.smtp_config.is_start_tls_encryption_enabled());
-
-        if self.smtp_config.is_encryption_disabled() {
-            client_builder = client_builder.allow_invalid_certs();
-        }
-
-        let (client_builder, client) = build_client(&self.smtp_config, client_builder).await?;
+        let mut retry = Retry::new(self.smtp_config.retry.clone().unwrap_or_default());
+        let client = build_client_with_retry(&self.smtp_config, &mut retry).await?;
 
         let ctx = SmtpContext {
             account_config: self.account_config,
             smtp_config: self.smtp_config,
-            client_builder,
             client,
         };
 
@@ -255,59 +245,356 @@ async fn check_up(&self) -> AnyResult<()> {
     }
 }
 
-pub async fn build_client(
+/// Builds the SMTP client builder from the given configuration and
+/// optional credentials.
+///
+/// Without credentials, the client does not authenticate. The TLS
+/// connector is built from the TLS configuration, if any.
+pub fn build_client_builder(
     smtp_config: &SmtpConfig,
-    #[cfg_attr(not(feature = "oauth2"), allow(unused_mut))]
-    mut client_builder: mail_send::SmtpClientBuilder<String>,
-) -> Result<(mail_send::SmtpClientBuilder<String>, SmtpClientStream)> {
-    match (&smtp_config.auth, smtp_config.is_encryption_enabled()) {
-        (SmtpAuthConfig::Passwd(_), false) => {
-            let client = build_tcp_client(&client_builder).await?;
-            Ok((client_builder, client))
-        }
-        (SmtpAuthConfig::Passwd(_), true) => {
-            let client = build_tls_client(&client_builder).await?;
-            Ok((client_builder, client))
+    credentials: Option<Credentials<String>>,
+) -> Result<SmtpClientBuilder<String>> {
+    let mut client_builder = SmtpClientBuilder::new(smtp_config.host.clone(), smtp_config.port)
+        .implicit_tls(!smtp_config.is_start_tls_encryption_enabled());
+
+    if let Some(credentials) = credentials {
+        client_builder = client_builder.credentials(credentials);
+    }
+
+    if smtp_config.is_encryption_disabled() {
+        return Ok(client_builder.allow_invalid_certs());
+    }
+
+    if let Some(tls) = &smtp_config.tls {
+        let config = tls
+            .build_rustls_config()
+            .map_err(Error::BuildTlsConfigSmtpError)?;
+        client_builder.tls_connector = TlsConnector::from(Arc::new(config));
+    }
+
+    Ok(client_builder)
+}
+
+/// Builds a new SMTP client from the given configuration.
+///
+/// The credentials are built at this moment. If the client cannot be
+/// created using the OAuth 2.0 authentication, the access token is
+/// renewed then a new client is created.
+pub async fn build_client(smtp_config: &SmtpConfig) -> Result<SmtpClientStream> {
+    match &smtp_config.auth {
+        SmtpAuthConfig::Passwd(passwd) => {
+            let mechanism = smtp_config.sasl_mechanism;
+
+            let passwd = match mechanism {
+                Some(mechanism) if !mechanism.needs_passwd() => String::new(),
+                _ => SmtpConfig::passwd(passwd).await?,
+            };
+
+            // the client authenticates by itself, since SMTP
+            // authentication from mail-send does not support SCRAM
+            // mechanisms
+            let client_builder = build_client_builder(smtp_config, None)?.say_ehlo(false);
+            let mut client = connect(smtp_config, &client_builder).await?;
+            let local_host = client_builder.local_host.as_str();
+            let login = smtp_config.login.as_str();
+
+            match &mut client {
+                SmtpClientStream::Tcp(client) => {
+                    authenticate(client, local_host, login, &passwd, mechanism, None).await?
+                }
+                SmtpClientStream::Tls(client) => {
+                    let cb = tls_channel_binding(client);
+                    authenticate(client, local_host, login, &passwd, mechanism, cb).await?
+                }
+            };
+
+            Ok(client)
         }
         #[cfg(feature = "oauth2")]
-        (SmtpAuthConfig::OAuth2(oauth2_config), false) => {
-            match Ok(build_tcp_client(&client_builder).await?) {
-                Ok(client) => Ok((client_builder, client)),
-                Err(Error::ConnectTcpSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("authentication failed, refreshing access token and retrying…");
-                    oauth2_config
-                        .refresh_access_token()
+        SmtpAuthConfig::OAuth2(oauth2_config) => {
+            let access_token = oauth2_config
+                .access_token()
+                .await
+                .map_err(|_| Error::AccessTokenWasNotAvailable)?;
+            let credentials = smtp_config.oauth2_credentials(oauth2_config, access_token.clone());
+            let client_builder = build_client_builder(smtp_config, Some(credentials))?;
+
+            match connect(smtp_config, &client_builder).await {
+                Err(Error::ConnectTcpSmtpError(mail_send::Error::AuthenticationFailed(_)))
+                | Err(Error::ConnectTlsSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
+                    warn!("authentication failed, refreshing access token and retrying…");
+                    let access_token = oauth2_config
+                        .renew_access_token(&access_token)
                         .await
                         .map_err(|_| Error::RefreshingAccessTokenFailed)?;
-                    client_builder = client_builder.credentials(smtp_config.credentials().await?);
-                    let client = build_tcp_client(&client_builder).await?;
-                    Ok((client_builder, client))
+                    let credentials = smtp_config.oauth2_credentials(oauth2_config, access_token);
+                    let client_builder = build_client_builder(smtp_config, Some(credentials))?;
+                    connect(smtp_config, &client_builder).await
                 }
-                Err(err) => Err(err),
+                res => res,
             }
         }
-        #[cfg(feature = "oauth2")]
-        (SmtpAuthConfig::OAuth2(oauth2_config), true) => {
-            match Ok(build_tls_client(&client_builder).await?) {
-                Ok(client) => Ok((client_builder, client)),
-                Err(Error::ConnectTlsSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("authentication failed, refreshing access token and retrying…");
-                    oauth2_config
-                        .refresh_access_token()
-                        .await
-                        .map_err(|_| Error::RefreshingAccessTokenFailed)?;
-                    client_builder = client_builder.credentials(smtp_config.credentials().await?);
-                    let client = build_tls_client(&client_builder).await?;
-                    Ok((client_builder, client))
+    }
+}
+
+/// Authenticates the given SMTP client using SASL, as defined in the
+/// [RFC4954](https://datatracker.ietf.org/doc/html/rfc4954).
+///
+/// An explicit mechanism is the only one tried, otherwise the most
+/// secure mechanisms supported by the server are tried first.
+async fn authenticate<T: AsyncRead + AsyncWrite + Unpin>(
+    client: &mut SmtpClient<T>,
+    local_host: &str,
+    login: &str,
+    passwd: &str,
+    mechanism: Option<SaslMechanism>,
+    channel_binding: Option<ChannelBinding>,
+) -> Result<()> {
+    let ehlo = client
+        .capabilities(local_host, false)
+        .await
+        .map_err(Error::AuthenticateSmtpError)?;
+
+    let supports = |m, plus| ehlo.auth_mechanisms & auth_mechanism(m, plus) != 0;
+
+    let mechanisms: Vec<_> = match mechanism {
+        Some(mechanism) if supports(mechanism, false) || supports(mechanism, true) => {
+            vec![mechanism]
+        }
+        Some(mechanism) => {
+            return Err(Error::AuthenticateSaslNotSupportedSmtpError(mechanism));
+        }
+        None => SaslMechanism::NEGOTIABLE
+            .into_iter()
+            .filter(|m| supports(*m, false) || supports(*m, true))
+            .collect(),
+    };
+
+    let mut last_err = Error::AuthenticateSaslMechanismNotFoundSmtpError;
+
+    for sasl_mechanism in mechanisms {
+        debug!("trying SASL {sasl_mechanism} auth mechanism…");
+
+        let sasl = match &channel_binding {
+            Some(cb) if supports(sasl_mechanism, true) => {
+                SaslClient::new(sasl_mechanism, login, passwd, Some(cb.clone()))
+            }
+            Some(_) if sasl_mechanism.supports_channel_binding() => {
+                SaslClient::new(sasl_mechanism, login, passwd, None).with_channel_binding_support()
+            }
+            _ => SaslClient::new(sasl_mechanism, login, passwd, None),
+        };
+
+        match authenticate_sasl(client, sasl_mechanism, sasl).await {
+            Ok(()) => {
+                debug!("authentication using SASL {sasl_mechanism} succeeded!");
+                return Ok(());
+            }
+            Err(err) if mechanism.is_some() => {
+                return Err(err);
+            }
+            Err(err) => {
+                warn!("authentication using SASL {sasl_mechanism} failed: {err}");
+                last_err = err;
+            }
+        }
+    }
+
+    Err(last_err)
+}
+
+/// Runs the SASL exchange of the given mechanism.
+async fn authenticate_sasl<T: AsyncRead + AsyncWrite + Unpin>(
+    client: &mut SmtpClient<T>,
+    mechanism: SaslMechanism,
+    mut sasl: SaslClient,
+) -> Result<()> {
+    let cmd = match sasl.initial_response() {
+        // an empty initial response is sent as a single equal sign
+        Some(res) if res.is_empty() => format!("AUTH {} =\r\n", sasl.name()),
+        Some(res) => format!("AUTH {} {}\r\n", sasl.name(), sasl::encode(res)),
+        None => format!("AUTH {}\r\n", sasl.name()),
+    };
+
+    let mut reply = client
+        .cmd(cmd)
+        .await
+        .map_err(Error::AuthenticateSmtpError)?;
+
+    loop {
+        match reply.code {
+            334 => {
+                let res = sasl::decode(&reply.message).and_then(|c| sasl.respond(&c));
+
+                let res = match res {
+                    Ok(res) => res,
+                    Err(err) => {
+                        // cancel the exchange, the reply does not matter
+                        let _ = client.cmd("*\r\n").await;
+                        return Err(Error::AuthenticateSaslSmtpError(mechanism, err));
+                    }
+                };
+
+                reply = client
+                    .cmd(format!("{}\r\n", sasl::encode(res)))
+                    .await
+                    .map_err(Error::AuthenticateSmtpError)?;
+            }
+            235 => {
+                return sasl
+                    .finish()
+                    .map_err(|err| Error::AuthenticateSaslSmtpError(mechanism, err));
+            }
+            _ => {
+                let err = sasl::Error::RejectedError(reply.message);
+                return Err(Error::AuthenticateSaslSmtpError(mechanism, err));
+            }
+        }
+    }
+}
+
+/// Returns the SMTP flag of the given SASL mechanism, as found in
+/// the EHLO response.
+pub(crate) fn auth_mechanism(mechanism: SaslMechanism, plus: bool) -> u64 {
+    match mechanism {
+        SaslMechanism::ScramSha256 if plus => AUTH_SCRAM_SHA_256_PLUS,
+        SaslMechanism::ScramSha256 => AUTH_SCRAM_SHA_256,
+        SaslMechanism::ScramSha1 if plus => AUTH_SCRAM_SHA_1_PLUS,
+        SaslMechanism::ScramSha1 => AUTH_SCRAM_SHA_1,
+        _ if plus => 0,
+        SaslMechanism::CramMd5 => AUTH_CRAM_MD5,
+        SaslMechanism::Plain => AUTH_PLAIN,
+        SaslMechanism::Login => AUTH_LOGIN,
+        SaslMechanism::External => AUTH_EXTERNAL,
+    }
+}
+
+/// Exports the `tls-exporter` channel binding data from the given
+/// TLS client.
+///
+/// Only TLS 1.3 connections are supported, since the `tls-exporter`
+/// channel binding is not secure with previous versions of TLS, see
+/// the [RFC9266](https://datatracker.ietf.org/doc/html/rfc9266#section-3).
+fn tls_channel_binding(client: &SmtpClient<TlsStream<TcpStream>>) -> Option<ChannelBinding> {
+    let (_, conn) = client.stream.get_ref();
+
+    if conn.protocol_version() != Some(ProtocolVersion::TLSv1_3) {
+        return None;
+    }
+
+    let data = conn
+        .export_keying_material(
+            vec![0; ChannelBinding::TLS_EXPORTER_LEN],
+            ChannelBinding::TLS_EXPORTER_LABEL,
+            None,
+        )
+        .ok()?;
+
+    Some(ChannelBinding::tls_exporter(data))
+}
+
+/// Connects to the SMTP server without authenticating, and returns
+/// the response to the `EHLO` command.
+///
+/// When STARTTLS is enabled, the command is sent once the connection
+/// is upgraded, since servers usually advertise their authentication
+/// mechanisms over encrypted connections only.
+pub async fn ehlo(smtp_config: &SmtpConfig) -> Result<EhloResponse<String>> {
+    let client_builder = build_client_builder(smtp_config, None)?.say_ehlo(false);
+    let local_host = client_builder.local_host.as_str();
+
+    let res = match connect(smtp_config, &client_builder).await? {
+        SmtpClientStream::Tcp(mut client) => client.capabilities(local_host, false).await,
+        SmtpClientStream::Tls(mut client) => client.capabilities(local_host, false).await,
+    };
+
+    res.map_err(Error::EhloSmtpError)
+}
+
+/// Builds a new SMTP client from the given configuration, retrying
+/// connection failures.
+///
+/// Connection failures are retried whatever the operation the client
+/// is built for, including sending a message: nothing has been sent
+/// yet when the connection cannot be established. Authentication
+/// failures are not retried.
+async fn build_client_with_retry(
+    smtp_config: &SmtpConfig,
+    retry: &mut Retry,
+) -> Result<SmtpClientStream> {
+    loop {
+        let task = build_client(smtp_config);
+
+        match retry.next(retry.timeout(RetryOperation::Connect, task).await) {
+            RetryState::Retry => {
+                debug!(attempt = retry.attempts, "connection timed out");
+                retry.backoff().await;
+                continue;
+            }
+            RetryState::TimedOut => {
+                break Err(Error::ConnectSmtpTimedOutError(retry.take_history()));
+            }
+            RetryState::Ok(Ok(client)) => {
+                break Ok(client);
+            }
+            RetryState::Ok(Err(err)) => {
+                let failure = match &err {
+                    Error::ConnectTcpSmtpError(err) | Error::ConnectTlsSmtpError(err) => {
+                        retryable_failure(err)
+                    }
+                    _ => None,
+                };
+
+                let Some((kind, reason)) = failure else {
+                    break Err(err);
+                };
+
+                if !retry.fail(kind, reason) {
+                    let history = retry.take_history();
+                    break Err(Error::ConnectSmtpRetryError(Box::new(err), history));
                 }
-                Err(err) => Err(err),
+
+                warn!(attempt = retry.attempts, "cannot connect, retrying…");
+                retry.backoff().await;
             }
         }
     }
 }
 
+/// Return the retryable error matching the given SMTP error, with
+/// its reason, if any.
+fn retryable_failure(err: &mail_send::Error) -> Option<(RetryableError, String)> {
+    match err {
+        mail_send::Error::Timeout => Some((
+            RetryableError::Timeout,
+            String::from("connection timed out"),
+        )),
+        mail_send::Error::Io(err) => {
+            let reason = format!("connection broke: {err}");
+            Some((RetryableError::ConnectionLost, reason))
+        }
+        mail_send::Error::UnexpectedReply(reply) => {
+            let code = reply.code;
+            let reason = format!("server replied with code {code}: {}", reply.message);
+            Some((RetryableError::UnexpectedReply, reason))
+        }
+        _ => None,
+    }
+}
+
+/// Connects to the SMTP server using TLS or TCP, depending on the
+/// given configuration.
+async fn connect(
+    smtp_config: &SmtpConfig,
+    client_builder: &SmtpClientBuilder<String>,
+) -> Result<SmtpClientStream> {
+    if smtp_config.is_encryption_enabled() {
+        build_tls_client(client_builder).await
+    } else {
+        build_tcp_client(client_builder).await
+    }
+}
+
 pub async fn build_tcp_client(
     client_builder: &mail_send::SmtpClientBuilder<String>,
 ) -> Result<SmtpClientStream> {
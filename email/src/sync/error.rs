@@ -17,6 +17,16 @@ pub enum Error {
     LockFileError(#[source] FileLockError, PathBuf),
     #[error("cannot unlock sync file at {1}")]
     UnlockFileError(#[source] FileLockError, PathBuf),
+    #[error("cannot create sync cache directory at {1}")]
+    CreateCacheDirError(#[source] io::Error, PathBuf),
+    #[error("cannot open sync journal at {1}")]
+    OpenJournalError(#[source] io::Error, PathBuf),
+    #[error("cannot read sync journal at {1}")]
+    ReadJournalError(#[source] io::Error, PathBuf),
+    #[error("cannot write sync journal at {1}")]
+    WriteJournalError(#[source] io::Error, PathBuf),
+    #[error("cannot remove sync journal at {1}")]
+    RemoveJournalError(#[source] io::Error, PathBuf),
     #[error("cannot get sync cache directory")]
     GetCacheDirectorySyncError,
     #[error("cannot sync folders")]
@@ -35,4 +45,18 @@ pub enum Error {
     RightContextNotConfiguredError(#[source] AnyBoxedError),
     #[error("cannot build sync pool context")]
     BuildSyncPoolContextError(#[source] AnyBoxedError),
+    #[error("cannot add sync {1}: left backend {2} differs from the shared left backend {0}")]
+    HubSyncLeftMismatchError(String, String, String),
+    #[error("cannot add sync {0}: a sync with the same name already exists")]
+    HubSyncDuplicateNameError(String),
+    #[error("cannot read sync plan at {1}")]
+    ReadPlanError(#[source] io::Error, PathBuf),
+    #[error("cannot write sync plan at {1}")]
+    WritePlanError(#[source] io::Error, PathBuf),
+    #[error("cannot parse sync plan: invalid record at line {0}")]
+    ParsePlanError(usize),
+    #[error("cannot apply sync plan: it was built for other backends")]
+    ApplyPlanBackendsMismatchError,
+    #[error("cannot apply sync plan: backends changed since it was built")]
+    ApplyPlanDriftedError,
 }
@@ -0,0 +1,239 @@
+//! # Hub synchronization
+//!
+//! Module dedicated to the synchronization of one shared left backend
+//! (the hub) with several right backends, for example a local Maildir
+//! with a work IMAP account plus an archive server. The main
+//! structure of this module is [`HubSyncBuilder`].
+//!
+//! This is not an N-way synchronization: there is no change
+//! detection shared by all backends. Each right backend is
+//! synchronized with the hub using its own [`SyncBuilder`], which
+//! keeps its own permissions, filters and caches. Changes coming from
+//! one right backend reach the other ones through the hub, which
+//! may take a second synchronization run.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{hash::SyncHash, report::SyncReport, Error, Result, SyncBuilder, SyncDestination};
+use crate::{backend::context::BackendContextBuilder, debug};
+
+/// The type-erased synchronization, so that right backends of
+/// different kinds can be synchronized together.
+#[async_trait]
+trait HubSyncMember: Send + Sync {
+    fn left_hash(&self) -> &str;
+    fn right_hash(&self) -> &str;
+    fn has_cache_dir(&self) -> bool;
+    fn set_cache_dir(&mut self, dir: PathBuf);
+    fn find_default_cache_dir(&self) -> Option<PathBuf>;
+    fn is_dry_run(&self) -> bool;
+    async fn sync(&self) -> Result<SyncReport>;
+}
+
+#[async_trait]
+impl<L, R> HubSyncMember for SyncBuilder<L, R>
+where
+    L: BackendContextBuilder + SyncHash + 'static,
+    R: BackendContextBuilder + SyncHash + 'static,
+{
+    fn left_hash(&self) -> &str {
+        &self.left_hash
+    }
+
+    fn right_hash(&self) -> &str {
+        &self.right_hash
+    }
+
+    fn has_cache_dir(&self) -> bool {
+        self.cache_dir.is_some()
+    }
+
+    fn set_cache_dir(&mut self, dir: PathBuf) {
+        SyncBuilder::set_cache_dir(self, dir)
+    }
+
+    fn find_default_cache_dir(&self) -> Option<PathBuf> {
+        SyncBuilder::find_default_cache_dir(self)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.get_dry_run()
+    }
+
+    async fn sync(&self) -> Result<SyncReport> {
+        self.clone().sync().await
+    }
+}
+
+/// The hub synchronization builder.
+///
+/// All the synchronizations added to the builder need to share the
+/// same left backend, and to have distinct names.
+#[derive(Default)]
+pub struct HubSyncBuilder {
+    syncs: Vec<(String, Box<dyn HubSyncMember>)>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl HubSyncBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // cache dir setters
+
+    pub fn set_some_cache_dir(&mut self, dir: Option<impl Into<PathBuf>>) {
+        self.cache_dir = dir.map(Into::into);
+    }
+
+    pub fn set_cache_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.set_some_cache_dir(Some(dir));
+    }
+
+    pub fn with_some_cache_dir(mut self, dir: Option<impl Into<PathBuf>>) -> Self {
+        self.set_some_cache_dir(dir);
+        self
+    }
+
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.set_cache_dir(dir);
+        self
+    }
+
+    // sync setters
+
+    /// Add the given synchronization under the given name.
+    ///
+    /// The left backend of the synchronization needs to be the same
+    /// as the one of synchronizations previously added, and the name
+    /// needs to be unique.
+    pub fn add_sync<L, R>(&mut self, name: impl ToString, sync: SyncBuilder<L, R>) -> Result<()>
+    where
+        L: BackendContextBuilder + SyncHash + 'static,
+        R: BackendContextBuilder + SyncHash + 'static,
+    {
+        let name = name.to_string();
+
+        if self.syncs.iter().any(|(n, _)| *n == name) {
+            return Err(Error::HubSyncDuplicateNameError(name));
+        }
+
+        if let Some((_, first)) = self.syncs.first() {
+            if first.left_hash() != sync.left_hash {
+                let left_hash = first.left_hash().to_owned();
+                let err = Error::HubSyncLeftMismatchError(left_hash, name, sync.left_hash);
+                return Err(err);
+            }
+        }
+
+        self.syncs.push((name, Box::new(sync)));
+        Ok(())
+    }
+
+    pub fn with_sync<L, R>(mut self, name: impl ToString, sync: SyncBuilder<L, R>) -> Result<Self>
+    where
+        L: BackendContextBuilder + SyncHash + 'static,
+        R: BackendContextBuilder + SyncHash + 'static,
+    {
+        self.add_sync(name, sync)?;
+        Ok(self)
+    }
+
+    /// Synchronize the shared left backend with all right backends.
+    ///
+    /// Synchronizations run one after the other, in the order they
+    /// were added. Once all of them are done, synchronizations that
+    /// ran before the last one changing the left backend run a second
+    /// time, so that they receive changes from the right backends
+    /// that followed them. This second run is skipped in dry run
+    /// mode, since the left backend did not actually change.
+    ///
+    /// A failing synchronization does not prevent the other ones from
+    /// running: its error is collected in the report.
+    ///
+    /// Right backends are never synchronized directly with each
+    /// other: a change made on one of them reaches the other ones
+    /// through the left backend only.
+    pub async fn sync(mut self) -> Result<HubSyncReport> {
+        // NOTE: the left cache reflects the left backend as seen by
+        // one right backend, so each synchronization needs its own
+        // cache directory
+        for (_, sync) in &mut self.syncs {
+            if sync.has_cache_dir() {
+                continue;
+            }
+
+            let dir = self
+                .cache_dir
+                .clone()
+                .or_else(|| sync.find_default_cache_dir())
+                .ok_or(Error::GetCacheDirectorySyncError)?
+                .join(format!("{}-{}", sync.left_hash(), sync.right_hash()));
+
+            sync.set_cache_dir(dir);
+        }
+
+        let mut report = HubSyncReport::default();
+        let mut last_left_change = None;
+
+        for (i, (name, sync)) in self.syncs.iter().enumerate() {
+            debug!("synchronizing {name}");
+            let sync_report = sync.sync().await;
+
+            match &sync_report {
+                Ok(sync_report) => {
+                    if !sync.is_dry_run() && sync_report.has_changes(&SyncDestination::Left) {
+                        last_left_change = Some(i);
+                    }
+                }
+                Err(_err) => {
+                    debug!("cannot synchronize {name}: {_err}");
+                }
+            }
+
+            report.syncs.push((name.clone(), sync_report));
+        }
+
+        if let Some(last) = last_left_change {
+            for (name, sync) in self.syncs.iter().take(last) {
+                debug!("synchronizing {name} again to propagate left changes");
+                let sync_report = sync.sync().await;
+
+                if let Err(_err) = &sync_report {
+                    debug!("cannot synchronize {name} again: {_err}");
+                }
+
+                report.propagations.push((name.clone(), sync_report));
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// The hub synchronization report.
+#[derive(Debug, Default)]
+pub struct HubSyncReport {
+    /// The result of the first run of each synchronization,
+    /// associated with the name of the synchronization, in run
+    /// order.
+    pub syncs: Vec<(String, Result<SyncReport>)>,
+
+    /// The result of the second run of synchronizations that ran
+    /// again to receive changes from the right backends that
+    /// followed them, associated with the name of the
+    /// synchronization, in run order.
+    pub propagations: Vec<(String, Result<SyncReport>)>,
+}
+
+impl HubSyncReport {
+    /// Return `true` if at least one synchronization run failed.
+    pub fn has_errors(&self) -> bool {
+        self.syncs
+            .iter()
+            .chain(&self.propagations)
+            .any(|(_, report)| report.is_err())
+    }
+}
@@ -0,0 +1,520 @@
+//! # Sync journal
+//!
+//! Module dedicated to synchronization journaling. The main structure
+//! of this module is [`SyncJournal`], which records on disk the email
+//! patch being applied as well as every hunk that completed. When a
+//! synchronization is interrupted, the journal is left behind and
+//! its pending hunks are replayed at the beginning of the next
+//! synchronization.
+//!
+//! The journal is a plain text file, where each line is a record
+//! made of tab-separated fields:
+//!
+//! ```text
+//! hunk <TAB> <index> <TAB> <hunk fields…>
+//! done <TAB> <index>
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::DateTime;
+
+use super::{Error, Result, SyncDestination};
+use crate::{
+    debug,
+    email::sync::hunk::EmailSyncHunk,
+    envelope::{Address, Envelope},
+    flag::{Flag, Flags},
+};
+
+/// The synchronization journal.
+pub struct SyncJournal {
+    /// The path of the journal file.
+    path: PathBuf,
+
+    /// The journal file, opened in append mode.
+    file: Mutex<File>,
+
+    /// The index of every hunk contained in the journal.
+    ids: HashMap<EmailSyncHunk, usize>,
+
+    /// The hunks that have not been marked as done yet.
+    pending: BTreeSet<EmailSyncHunk>,
+}
+
+impl SyncJournal {
+    /// Build the path of the journal file associated to the given
+    /// left and right backend hashes.
+    pub fn path(cache_dir: impl AsRef<Path>, left_hash: &str, right_hash: &str) -> PathBuf {
+        cache_dir
+            .as_ref()
+            .join(format!("{left_hash}-{right_hash}.journal"))
+    }
+
+    /// Create a new journal at the given path, containing the given
+    /// email hunks.
+    ///
+    /// An existing journal at the same location is overridden.
+    pub fn create<'a>(
+        path: impl Into<PathBuf>,
+        hunks: impl IntoIterator<Item = &'a EmailSyncHunk>,
+    ) -> Result<Self> {
+        let path = path.into();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| Error::CreateCacheDirError(err, dir.to_owned()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(|err| Error::OpenJournalError(err, path.clone()))?;
+
+        let mut ids = HashMap::new();
+        let mut pending = BTreeSet::new();
+        let mut records = String::new();
+
+        for (id, hunk) in hunks.into_iter().enumerate() {
+            records.push_str(&format!("hunk\t{id}\t{}\n", encode_hunk(hunk)));
+            ids.insert(hunk.clone(), id);
+            pending.insert(hunk.clone());
+        }
+
+        file.write_all(records.as_bytes())
+            .and_then(|()| file.sync_data())
+            .map_err(|err| Error::WriteJournalError(err, path.clone()))?;
+
+        debug!("created sync journal at {path:?} with {} hunks", ids.len());
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            ids,
+            pending,
+        })
+    }
+
+    /// Open the journal located at the given path.
+    ///
+    /// Returns `None` if no journal exists at this location, which
+    /// means that the previous synchronization completed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Option<Self>> {
+        let path = path.into();
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::OpenJournalError(err, path)),
+        };
+
+        let mut hunks = HashMap::new();
+        let mut done = HashSet::new();
+
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|err| Error::ReadJournalError(err, path.clone()))?;
+            let mut fields = line.split('\t');
+
+            let record = match (fields.next(), fields.next()) {
+                (Some("hunk"), Some(id)) => id
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|id| Some((id, Some(decode_hunk(fields)?)))),
+                (Some("done"), Some(id)) => id.parse::<usize>().ok().map(|id| (id, None)),
+                _ => None,
+            };
+
+            match record {
+                Some((id, Some(hunk))) => {
+                    hunks.insert(id, hunk);
+                }
+                Some((id, None)) => {
+                    done.insert(id);
+                }
+                // NOTE: the last line may be truncated if the
+                // process has been killed while writing it, in which
+                // case it is just ignored.
+                None => {
+                    debug!("skipping invalid sync journal record {line:?}");
+                }
+            }
+        }
+
+        let mut ids = HashMap::new();
+        let mut pending = BTreeSet::new();
+
+        for (id, hunk) in hunks {
+            if !done.contains(&id) {
+                pending.insert(hunk.clone());
+            }
+            ids.insert(hunk, id);
+        }
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|err| Error::OpenJournalError(err, path.clone()))?;
+
+        debug!(
+            "opened sync journal at {path:?} with {} pending hunks",
+            pending.len()
+        );
+
+        Ok(Some(Self {
+            path,
+            file: Mutex::new(file),
+            ids,
+            pending,
+        }))
+    }
+
+    /// Get the path of the journal file.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the hunks that were not marked as done when the journal
+    /// was opened.
+    pub fn pending(&self) -> &BTreeSet<EmailSyncHunk> {
+        &self.pending
+    }
+
+    /// Mark the given hunk as done.
+    ///
+    /// Hunks that are not part of the journal are ignored.
+    pub fn mark_done(&self, hunk: &EmailSyncHunk) -> Result<()> {
+        let Some(id) = self.ids.get(hunk) else {
+            return Ok(());
+        };
+
+        // NOTE: a poisoned lock only means that another task panicked
+        // while appending a record, the file itself is still usable.
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+
+        file.write_all(format!("done\t{id}\n").as_bytes())
+            .map_err(|err| Error::WriteJournalError(err, self.path.clone()))
+    }
+
+    /// Remove the journal file.
+    ///
+    /// This should be called once all the hunks have been processed.
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path).map_err(|err| Error::RemoveJournalError(err, self.path.clone()))
+    }
+}
+
+pub(crate) fn encode_field(field: &str) -> String {
+    let mut encoded = String::with_capacity(field.len());
+
+    for c in field.chars() {
+        match c {
+            '\\' => encoded.push_str("\\\\"),
+            '\t' => encoded.push_str("\\t"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            ' ' => encoded.push_str("\\s"),
+            c => encoded.push(c),
+        }
+    }
+
+    encoded
+}
+
+pub(crate) fn decode_field(field: &str) -> String {
+    let mut decoded = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => decoded.push('\\'),
+            ('\\', Some('t')) => decoded.push('\t'),
+            ('\\', Some('n')) => decoded.push('\n'),
+            ('\\', Some('r')) => decoded.push('\r'),
+            ('\\', Some('s')) => decoded.push(' '),
+            (c, _) => {
+                decoded.push(c);
+                continue;
+            }
+        }
+
+        chars.next();
+    }
+
+    decoded
+}
+
+pub(crate) fn encode_destination(dest: &SyncDestination) -> &'static str {
+    match dest {
+        SyncDestination::Left => "left",
+        SyncDestination::Right => "right",
+    }
+}
+
+pub(crate) fn decode_destination(dest: &str) -> Option<SyncDestination> {
+    match dest {
+        "left" => Some(SyncDestination::Left),
+        "right" => Some(SyncDestination::Right),
+        _ => None,
+    }
+}
+
+/// Encode the parts of the envelope needed to process a hunk: the
+/// identifier, the Message-ID, the date, the flags, the size and the
+/// Gmail message identifier, followed by the subject and the sender
+/// for review purpose.
+pub(crate) fn encode_envelope(envelope: &Envelope) -> String {
+    let flags = envelope
+        .flags
+        .iter()
+        .map(|flag| encode_field(&flag.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    [
+        encode_field(&envelope.id),
+        encode_field(&envelope.message_id),
+        envelope.date.to_rfc3339(),
+        flags,
+        envelope.size.to_string(),
+        envelope
+            .gmail_message_id
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+        encode_field(&envelope.subject),
+        encode_field(envelope.from.name.as_deref().unwrap_or_default()),
+        encode_field(&envelope.from.addr),
+    ]
+    .join("\t")
+}
+
+pub(crate) fn decode_envelope<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<Envelope> {
+    let id = decode_field(fields.next()?);
+    let message_id = decode_field(fields.next()?);
+    let date = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+    let flags = fields
+        .next()?
+        .split_whitespace()
+        .map(|flag| Flag::from(decode_field(flag).as_str()))
+        .collect::<Flags>();
+    // NOTE: the size is optional, journals written before it was
+    // recorded do not contain it
+    let size = fields
+        .next()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or_default();
+    let gmail_message_id = fields.next().and_then(|id| id.parse().ok());
+    let subject = fields.next().map(decode_field).unwrap_or_default();
+    let from_name = fields
+        .next()
+        .map(decode_field)
+        .filter(|name| !name.is_empty());
+    let from_addr = fields.next().map(decode_field).unwrap_or_default();
+
+    Some(Envelope {
+        id,
+        message_id,
+        date,
+        flags,
+        size,
+        gmail_message_id,
+        subject,
+        from: Address::new(from_name, from_addr),
+        ..Default::default()
+    })
+}
+
+pub(crate) fn encode_hunk(hunk: &EmailSyncHunk) -> String {
+    match hunk {
+        EmailSyncHunk::GetThenCache(folder, id, dest) => [
+            "get-then-cache",
+            &encode_field(folder),
+            &encode_field(id),
+            encode_destination(dest),
+        ]
+        .join("\t"),
+        EmailSyncHunk::CopyThenCache(folder, envelope, source, target, refresh) => [
+            "copy-then-cache",
+            &encode_field(folder),
+            encode_destination(source),
+            encode_destination(target),
+            if *refresh { "refresh" } else { "keep" },
+            &encode_envelope(envelope),
+        ]
+        .join("\t"),
+        EmailSyncHunk::UpdateCachedFlags(folder, envelope, dest) => [
+            "update-cached-flags",
+            &encode_field(folder),
+            encode_destination(dest),
+            &encode_envelope(envelope),
+        ]
+        .join("\t"),
+        EmailSyncHunk::UpdateFlags(folder, envelope, dest) => [
+            "update-flags",
+            &encode_field(folder),
+            encode_destination(dest),
+            &encode_envelope(envelope),
+        ]
+        .join("\t"),
+        EmailSyncHunk::Uncache(folder, id, dest) => [
+            "uncache",
+            &encode_field(folder),
+            &encode_field(id),
+            encode_destination(dest),
+        ]
+        .join("\t"),
+        EmailSyncHunk::Delete(folder, id, dest) => [
+            "delete",
+            &encode_field(folder),
+            &encode_field(id),
+            encode_destination(dest),
+        ]
+        .join("\t"),
+    }
+}
+
+pub(crate) fn decode_hunk<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<EmailSyncHunk> {
+    let kind = fields.next()?;
+    let folder = decode_field(fields.next()?);
+
+    let hunk = match kind {
+        "get-then-cache" => {
+            let id = decode_field(fields.next()?);
+            let dest = decode_destination(fields.next()?)?;
+            EmailSyncHunk::GetThenCache(folder, id, dest)
+        }
+        "copy-then-cache" => {
+            let source = decode_destination(fields.next()?)?;
+            let target = decode_destination(fields.next()?)?;
+            let refresh = match fields.next()? {
+                "refresh" => true,
+                "keep" => false,
+                _ => return None,
+            };
+            let envelope = decode_envelope(fields)?;
+            EmailSyncHunk::CopyThenCache(folder, envelope, source, target, refresh)
+        }
+        "update-cached-flags" => {
+            let dest = decode_destination(fields.next()?)?;
+            let envelope = decode_envelope(fields)?;
+            EmailSyncHunk::UpdateCachedFlags(folder, envelope, dest)
+        }
+        "update-flags" => {
+            let dest = decode_destination(fields.next()?)?;
+            let envelope = decode_envelope(fields)?;
+            EmailSyncHunk::UpdateFlags(folder, envelope, dest)
+        }
+        "uncache" => {
+            let id = decode_field(fields.next()?);
+            let dest = decode_destination(fields.next()?)?;
+            EmailSyncHunk::Uncache(folder, id, dest)
+        }
+        "delete" => {
+            let id = decode_field(fields.next()?);
+            let dest = decode_destination(fields.next()?)?;
+            EmailSyncHunk::Delete(folder, id, dest)
+        }
+        _ => return None,
+    };
+
+    Some(hunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use tempfile::tempdir;
+
+    use super::SyncJournal;
+    use crate::{
+        email::sync::hunk::EmailSyncHunk,
+        envelope::Envelope,
+        flag::{Flag, Flags},
+        sync::SyncDestination,
+    };
+
+    fn hunks() -> Vec<EmailSyncHunk> {
+        let envelope = Envelope {
+            id: "1".into(),
+            message_id: "<a@localhost>".into(),
+            date: DateTime::parse_from_rfc3339("2024-01-01T12:00:00+01:00").unwrap(),
+            flags: Flags::from_iter([Flag::Seen, Flag::custom("with space\tand tab")]),
+            gmail_message_id: Some(1337),
+            ..Default::default()
+        };
+
+        vec![
+            EmailSyncHunk::GetThenCache("INBOX".into(), "1".into(), SyncDestination::Left),
+            EmailSyncHunk::CopyThenCache(
+                "Sent\tItems".into(),
+                envelope.clone(),
+                SyncDestination::Right,
+                SyncDestination::Left,
+                true,
+            ),
+            EmailSyncHunk::UpdateFlags("INBOX".into(), envelope, SyncDestination::Right),
+            EmailSyncHunk::Delete("Trash\\".into(), "2".into(), SyncDestination::Right),
+        ]
+    }
+
+    #[test]
+    fn encode_decode_hunks() {
+        for hunk in hunks() {
+            let encoded = super::encode_hunk(&hunk);
+            let decoded = super::decode_hunk(encoded.split('\t')).unwrap();
+
+            assert_eq!(decoded, hunk);
+
+            if let (
+                EmailSyncHunk::UpdateFlags(_, decoded, _),
+                EmailSyncHunk::UpdateFlags(_, envelope, _),
+            ) = (&decoded, &hunk)
+            {
+                assert_eq!(decoded.id, envelope.id);
+                assert_eq!(decoded.date, envelope.date);
+                assert_eq!(decoded.flags, envelope.flags);
+                assert_eq!(decoded.gmail_message_id, envelope.gmail_message_id);
+            }
+        }
+    }
+
+    #[test]
+    fn resume_pending_hunks() {
+        let dir = tempdir().unwrap();
+        let path = SyncJournal::path(dir.path(), "left", "right");
+        let hunks = hunks();
+
+        assert!(SyncJournal::open(&path).unwrap().is_none());
+
+        let journal = SyncJournal::create(&path, &hunks).unwrap();
+        journal.mark_done(&hunks[0]).unwrap();
+        journal.mark_done(&hunks[2]).unwrap();
+        drop(journal);
+
+        let journal = SyncJournal::open(&path).unwrap().unwrap();
+        let pending = Vec::from_iter(journal.pending().iter().cloned());
+
+        assert_eq!(pending.len(), 2);
+        assert!(pending.contains(&hunks[1]));
+        assert!(pending.contains(&hunks[3]));
+
+        journal.mark_done(&hunks[1]).unwrap();
+        drop(journal);
+
+        let journal = SyncJournal::open(&path).unwrap().unwrap();
+        assert_eq!(
+            Vec::from_iter(journal.pending().iter().cloned()),
+            [hunks[3].clone()]
+        );
+
+        journal.remove().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -2,22 +2,28 @@
 //!
 //! Module dedicated to synchronization of folders and emails between
 //! two backends. The main structure of this module is
-//! [`SyncBuilder`].
+//! [`SyncBuilder`]. See [`hub`] for synchronizing one backend with
+//! several other backends.
 
 mod error;
 pub mod hash;
+pub mod hub;
+pub mod journal;
+pub mod plan;
 pub mod pool;
+pub mod progress;
 pub mod report;
 
 use std::{
     collections::{BTreeMap, BTreeSet},
     env, fmt,
-    fs::{self, OpenOptions},
+    fs::{self, File, OpenOptions},
     future::Future,
     hash::{DefaultHasher, Hash, Hasher},
     path::PathBuf,
     pin::Pin,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use advisory_lock::{AdvisoryFileLock, FileLockMode};
@@ -26,24 +32,33 @@
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
-use self::{hash::SyncHash, report::SyncReport};
+use self::{
+    hash::SyncHash,
+    journal::SyncJournal,
+    plan::SyncPlan,
+    progress::{SyncProgress, SyncProgressTracker},
+    report::SyncReport,
+};
 use crate::{
     backend::{context::BackendContextBuilder, BackendBuilder},
     debug,
-    email::{self, sync::hunk::EmailSyncHunk},
+    email::{
+        self,
+        sync::{hunk::EmailSyncHunk, patch::EmailSyncPatches, report::EmailSyncReport},
+    },
     envelope::sync::config::EnvelopeSyncFilters,
     flag::sync::config::FlagSyncPermissions,
     folder::{
         self,
         sync::{
-            config::{FolderSyncPermissions, FolderSyncStrategy},
+            config::{FolderSyncMapping, FolderSyncPermissions, FolderSyncStrategy},
             hunk::{FolderName, FolderSyncHunk},
-            patch::FolderSyncPatch,
+            patch::{FolderSyncPatch, FolderSyncPatches},
         },
     },
     maildir::{config::MaildirConfig, MaildirContextBuilder},
-    message::sync::config::MessageSyncPermissions,
-    sync::pool::{SyncPoolConfig, SyncPoolContextBuilder},
+    message::sync::config::{MessageSyncMode, MessageSyncPermissions, MessageSyncSizeLimit},
+    sync::pool::{SyncPoolConfig, SyncPoolContext, SyncPoolContextBuilder},
     trace,
 };
 
@@ -193,6 +208,26 @@ pub fn with_folder_filters(mut self, f: impl Into<FolderSyncStrategy>) -> Self {
         self
     }
 
+    // folder mapping setters
+
+    pub fn set_some_folder_mapping(&mut self, m: Option<impl Into<FolderSyncMapping>>) {
+        self.config.folder_mapping = m.map(Into::into);
+    }
+
+    pub fn set_folder_mapping(&mut self, m: impl Into<FolderSyncMapping>) {
+        self.set_some_folder_mapping(Some(m));
+    }
+
+    pub fn with_some_folder_mapping(mut self, m: Option<impl Into<FolderSyncMapping>>) -> Self {
+        self.set_some_folder_mapping(m);
+        self
+    }
+
+    pub fn with_folder_mapping(mut self, m: impl Into<FolderSyncMapping>) -> Self {
+        self.set_folder_mapping(m);
+        self
+    }
+
     // left folder permissions setters
 
     pub fn set_some_left_folder_permissions(
@@ -363,6 +398,75 @@ pub fn with_right_message_permissions(mut self, p: impl Into<MessageSyncPermissi
         self
     }
 
+    // left message size limit setters
+
+    pub fn set_some_left_message_size_limit(&mut self, l: Option<impl Into<MessageSyncSizeLimit>>) {
+        self.config.left_message_size_limit = l.map(Into::into);
+    }
+
+    pub fn set_left_message_size_limit(&mut self, l: impl Into<MessageSyncSizeLimit>) {
+        self.set_some_left_message_size_limit(Some(l));
+    }
+
+    pub fn with_some_left_message_size_limit(
+        mut self,
+        l: Option<impl Into<MessageSyncSizeLimit>>,
+    ) -> Self {
+        self.set_some_left_message_size_limit(l);
+        self
+    }
+
+    pub fn with_left_message_size_limit(mut self, l: impl Into<MessageSyncSizeLimit>) -> Self {
+        self.set_left_message_size_limit(l);
+        self
+    }
+
+    // right message size limit setters
+
+    pub fn set_some_right_message_size_limit(
+        &mut self,
+        l: Option<impl Into<MessageSyncSizeLimit>>,
+    ) {
+        self.config.right_message_size_limit = l.map(Into::into);
+    }
+
+    pub fn set_right_message_size_limit(&mut self, l: impl Into<MessageSyncSizeLimit>) {
+        self.set_some_right_message_size_limit(Some(l));
+    }
+
+    pub fn with_some_right_message_size_limit(
+        mut self,
+        l: Option<impl Into<MessageSyncSizeLimit>>,
+    ) -> Self {
+        self.set_some_right_message_size_limit(l);
+        self
+    }
+
+    pub fn with_right_message_size_limit(mut self, l: impl Into<MessageSyncSizeLimit>) -> Self {
+        self.set_right_message_size_limit(l);
+        self
+    }
+
+    // message mode setters
+
+    pub fn set_some_message_mode(&mut self, m: Option<impl Into<MessageSyncMode>>) {
+        self.config.message_mode = m.map(Into::into);
+    }
+
+    pub fn set_message_mode(&mut self, m: impl Into<MessageSyncMode>) {
+        self.set_some_message_mode(Some(m));
+    }
+
+    pub fn with_some_message_mode(mut self, m: Option<impl Into<MessageSyncMode>>) -> Self {
+        self.set_some_message_mode(m);
+        self
+    }
+
+    pub fn with_message_mode(mut self, m: impl Into<MessageSyncMode>) -> Self {
+        self.set_message_mode(m);
+        self
+    }
+
     // getters
 
     pub fn find_default_cache_dir(&self) -> Option<PathBuf> {
@@ -407,31 +511,81 @@ pub fn get_right_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBui
 
     // build
 
+    /// Synchronize the left and the right backends.
     pub async fn sync(self) -> Result<SyncReport> {
-        let left_lock_file_path = RUNTIME_DIR.join(format!("{}.lock", self.left_hash));
-        debug!("locking left sync file {left_lock_file_path:?}");
-        let left_lock_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&left_lock_file_path)
-            .map_err(|err| Error::OpenLockFileError(err, left_lock_file_path.clone()))?;
-        left_lock_file
-            .try_lock(FileLockMode::Exclusive)
-            .map_err(|err| Error::LockFileError(err, left_lock_file_path.clone()))?;
-
-        let right_lock_file_path = RUNTIME_DIR.join(format!("{}.lock", self.right_hash));
-        debug!("locking right sync file {right_lock_file_path:?}");
-        let right_lock_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&right_lock_file_path)
-            .map_err(|err| Error::OpenLockFileError(err, right_lock_file_path.clone()))?;
-        right_lock_file
-            .try_lock(FileLockMode::Exclusive)
-            .map_err(|err| Error::LockFileError(err, right_lock_file_path.clone()))?;
+        let locks = self.lock()?;
+        let ctx = self.build_context(self.config.clone()).await?;
+
+        let folder_patch = folder::sync::build_patch::<L, R>(ctx.clone())
+            .await
+            .map_err(Error::SyncFoldersError)?;
 
+        let report = self.apply(ctx, folder_patch, None).await?;
+
+        locks.unlock()?;
+        Ok(report)
+    }
+
+    /// Build the synchronization plan, without applying it.
+    ///
+    /// The plan can be saved, reviewed, then applied later on using
+    /// [`SyncBuilder::apply_plan`].
+    pub async fn plan(self) -> Result<SyncPlan> {
+        let locks = self.lock()?;
+        let plan = self.build_plan().await?;
+
+        locks.unlock()?;
+        Ok(plan)
+    }
+
+    /// Apply the given synchronization plan.
+    ///
+    /// The plan is built again from the current state of the
+    /// backends first. If it differs from the given plan, backends
+    /// drifted since the plan was reviewed, and nothing is applied.
+    pub async fn apply_plan(self, plan: SyncPlan) -> Result<SyncReport> {
+        if plan.left_hash != self.left_hash || plan.right_hash != self.right_hash {
+            return Err(Error::ApplyPlanBackendsMismatchError);
+        }
+
+        let locks = self.lock()?;
+
+        if !self.build_plan().await?.is_same_as(&plan) {
+            return Err(Error::ApplyPlanDriftedError);
+        }
+
+        let ctx = self.build_context(self.config.clone()).await?;
+        let report = self.apply(ctx, plan.folder, Some(plan.email)).await?;
+
+        locks.unlock()?;
+        Ok(report)
+    }
+
+    /// Lock the sync journal as well as both backends, so that
+    /// synchronizations sharing one of them cannot run concurrently.
+    fn lock(&self) -> Result<SyncLocks> {
+        let cache_dir = self.get_cache_dir()?;
+        fs::create_dir_all(&cache_dir)
+            .map_err(|err| Error::CreateCacheDirError(err, cache_dir.clone()))?;
+
+        let mut locks = SyncLocks::default();
+
+        // NOTE: the runtime directory may differ between sessions,
+        // so the journal is also protected by a lock living next to
+        // it, in the cache directory.
+        locks.lock(cache_dir.join(format!("{}-{}.lock", self.left_hash, self.right_hash)))?;
+        locks.lock(RUNTIME_DIR.join(format!("{}.lock", self.left_hash)))?;
+        locks.lock(RUNTIME_DIR.join(format!("{}.lock", self.right_hash)))?;
+
+        Ok(locks)
+    }
+
+    /// Configure backends if needed, then build the sync pool
+    /// context using the given configuration.
+    async fn build_context(
+        &self,
+        config: SyncPoolConfig,
+    ) -> Result<Arc<SyncPoolContext<L::Context, R::Context>>> {
         let mut left_cache_builder = self.get_left_cache_builder()?;
         let left_cache_check = left_cache_builder.ctx_builder.check_configuration();
 
@@ -496,39 +650,163 @@ pub async fn sync(self) -> Result<SyncReport> {
             }
         }?;
 
-        let ctx = Arc::new(
-            SyncPoolContextBuilder::new(
-                self.config,
-                left_cache_builder,
-                left_builder,
-                right_cache_builder,
-                right_builder,
-            )
-            .build()
-            .await
-            .map_err(Error::BuildSyncPoolContextError)?,
-        );
-
-        let mut report = SyncReport::default();
-
-        report.folder = folder::sync::<L, R>(ctx.clone())
+        let ctx = SyncPoolContextBuilder::new(
+            config,
+            left_cache_builder,
+            left_builder,
+            right_cache_builder,
+            right_builder,
+        )
+        .build()
+        .await
+        .map_err(Error::BuildSyncPoolContextError)?;
+
+        Ok(Arc::new(ctx))
+    }
+
+    /// Build the synchronization plan, assuming locks are held.
+    async fn build_plan(&self) -> Result<SyncPlan> {
+        // NOTE: folders are not created while planning, a dry run
+        // context lists envelopes of folders to be created as empty
+        // instead of failing. Any other listing failure fails the
+        // plan, since the missing envelopes would be planned for
+        // deletion.
+        let mut config = self.config.clone();
+        config.dry_run = Some(true);
+        let ctx = self.build_context(config).await?;
+
+        let folder = folder::sync::build_patch::<L, R>(ctx.clone())
             .await
             .map_err(Error::SyncFoldersError)?;
-        report.email = email::sync::<L, R>(ctx.clone(), &report.folder.names)
+
+        let (email, deleted_envelopes) = email::sync::build_patch::<L, R>(ctx, &folder, true)
             .await
             .map_err(Error::SyncEmailsError)?;
 
+        Ok(SyncPlan {
+            left_hash: self.left_hash.clone(),
+            right_hash: self.right_hash.clone(),
+            folder,
+            email,
+            deleted_envelopes,
+        })
+    }
+
+    /// Apply the given folder patch, then the given email patch.
+    ///
+    /// When no email patch is given, it is built once folders are
+    /// synchronized, and hunks pending from an interrupted
+    /// synchronization are resumed first.
+    async fn apply(
+        &self,
+        ctx: Arc<SyncPoolContext<L::Context, R::Context>>,
+        folder_patch: FolderSyncPatches,
+        email_patch: Option<EmailSyncPatches>,
+    ) -> Result<SyncReport> {
+        let started_at = Instant::now();
+        let mut report = SyncReport::default();
+        let folders = folder_patch.clone();
+        report.folder = folder::sync::apply_patch::<L, R>(ctx.clone(), folder_patch).await;
+
+        let cache_dir = self.get_cache_dir()?;
+        let journal_path = SyncJournal::path(&cache_dir, &self.left_hash, &self.right_hash);
+        let mut email_report = EmailSyncReport::default();
+
+        // NOTE: resumed hunks and hunks of the new patch share the
+        // same progress, so that it does not restart from zero
+        let progress = Arc::new(SyncProgressTracker::new(0, 0));
+
+        // NOTE: a plan is built from the current state of backends,
+        // which means it already covers hunks left behind by an
+        // interrupted synchronization
+        if !ctx.dry_run && email_patch.is_none() {
+            if let Some(journal) = SyncJournal::open(&journal_path)? {
+                let mut hunks = journal.pending().clone();
+                email::sync::discard_applied_hunks::<L, R>(&ctx, &mut hunks).await;
+
+                SyncEvent::ResumedEmailHunks(hunks.len())
+                    .emit(&ctx.handler)
+                    .await;
+
+                let journal = Arc::new(journal);
+                email_report = email::sync::apply_patch::<L, R>(
+                    ctx.clone(),
+                    hunks,
+                    Some(journal.clone()),
+                    progress.clone(),
+                )
+                .await;
+                journal.remove()?;
+            }
+        }
+
+        let patch = match email_patch {
+            Some(patch) => patch,
+            None => {
+                email::sync::build_patch::<L, R>(ctx.clone(), &folders, false)
+                    .await
+                    .map_err(Error::SyncEmailsError)?
+                    .0
+            }
+        };
+
+        let journal = if ctx.dry_run {
+            None
+        } else {
+            let hunks = patch.values().flatten();
+            Some(Arc::new(SyncJournal::create(&journal_path, hunks)?))
+        };
+
+        let hunks = patch.into_values().flatten();
+        let applied_report =
+            email::sync::apply_patch::<L, R>(ctx.clone(), hunks, journal.clone(), progress).await;
+        email_report.extend(applied_report);
+        report.email = email_report;
+
+        SyncEvent::ProcessedAllEmailHunks.emit(&ctx.handler).await;
+
+        if let Some(journal) = journal {
+            journal.remove()?;
+        }
+
         folder::sync::expunge::<L, R>(ctx.clone(), &report.folder.names).await;
 
+        report.duration = started_at.elapsed();
+        Ok(report)
+    }
+}
+
+/// The lock files held during a synchronization.
+#[derive(Default)]
+struct SyncLocks(Vec<(File, PathBuf)>);
+
+impl SyncLocks {
+    fn lock(&mut self, path: PathBuf) -> Result<()> {
+        debug!("locking sync file {path:?}");
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(|err| Error::OpenLockFileError(err, path.clone()))?;
+
+        file.try_lock(FileLockMode::Exclusive)
+            .map_err(|err| Error::LockFileError(err, path.clone()))?;
+
+        self.0.push((file, path));
+        Ok(())
+    }
+
+    fn unlock(self) -> Result<()> {
         debug!("unlocking sync files");
-        left_lock_file
-            .unlock()
-            .map_err(|err| Error::UnlockFileError(err, left_lock_file_path))?;
-        right_lock_file
-            .unlock()
-            .map_err(|err| Error::UnlockFileError(err, right_lock_file_path))?;
 
-        Ok(report)
+        for (file, path) in self.0 {
+            file.unlock()
+                .map_err(|err| Error::UnlockFileError(err, path))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -554,8 +832,13 @@ pub enum SyncEvent {
     ListedLeftEnvelopes(FolderName, usize),
     ListedRightCachedEnvelopes(FolderName, usize),
     ListedRightEnvelopes(FolderName, usize),
+    ResumedEmailHunks(usize),
     GeneratedEmailPatch(BTreeMap<FolderName, BTreeSet<EmailSyncHunk>>),
+    StartedEmailHunks(SyncProgress),
     ProcessedEmailHunk(EmailSyncHunk),
+    /// The hunk has been processed: comes with the amount of bytes it
+    /// transferred, the time it took and the overall progress.
+    ProgressedEmailHunk(EmailSyncHunk, usize, Duration, SyncProgress),
     ProcessedAllEmailHunks,
     ExpungedAllFolders,
 }
@@ -614,14 +897,28 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             SyncEvent::ListedRightEnvelopes(folder, n) => {
                 write!(f, "Listed {n} right envelopes from {folder}")
             }
+            SyncEvent::ResumedEmailHunks(n) => {
+                write!(f, "Resuming {n} pending hunks from previous sync")
+            }
             SyncEvent::GeneratedEmailPatch(patch) => {
                 let nf = patch.keys().count();
                 let np = patch.values().flatten().count();
                 write!(f, "Generated {np} patch for {nf} folders")
             }
+            SyncEvent::StartedEmailHunks(progress) => {
+                let n = progress.hunks_total;
+                let b = progress.bytes_total;
+                write!(f, "Processing {n} email hunks ({b} bytes)")
+            }
             SyncEvent::ProcessedEmailHunk(hunk) => {
                 write!(f, "{hunk}")
             }
+            SyncEvent::ProgressedEmailHunk(hunk, size, duration, progress) => {
+                let done = progress.hunks_done;
+                let total = progress.hunks_total;
+                let ms = duration.as_millis();
+                write!(f, "{hunk} ({size} bytes in {ms}ms, {done}/{total})")
+            }
             SyncEvent::ProcessedAllEmailHunks => {
                 write!(f, "Processed all email hunks")
             }
@@ -634,6 +931,11 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 /// The synchronization destination.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum SyncDestination {
     Left,
     Right,
@@ -0,0 +1,377 @@
+//! # Sync plan
+//!
+//! Module dedicated to synchronization plans. The main structure of
+//! this module is [`SyncPlan`], which contains the folder and email
+//! patches of a synchronization, built but not applied yet. A plan
+//! can be saved to a file, reviewed, then applied later on using
+//! [`SyncBuilder::apply_plan`](super::SyncBuilder::apply_plan).
+//!
+//! The plan file is a plain text file, where each line is a record
+//! made of tab-separated fields:
+//!
+//! ```text
+//! plan	<left hash>	<right hash>
+//! folder	<folder>
+//! folder-hunk	<kind>	<folder>	<destination>
+//! email-hunk	<hunk fields…>
+//! deleted-envelope	<folder>	<destination>	<envelope fields…>
+//! ```
+//!
+//! Envelope fields include the subject and the sender, so that
+//! plans can be reviewed. When the `derive` feature is enabled, the
+//! plan can also be (de)serialized using any serde format.
+
+use std::{
+    collections::BTreeSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    journal::{
+        decode_destination, decode_envelope, decode_field, decode_hunk, encode_destination,
+        encode_envelope, encode_field, encode_hunk,
+    },
+    Error, Result, SyncDestination,
+};
+use crate::{
+    email::sync::{
+        hunk::EmailSyncHunk,
+        patch::{EmailSyncDeletedEnvelopes, EmailSyncPatches},
+    },
+    envelope::Envelope,
+    folder::sync::{
+        hunk::{FolderName, FolderSyncHunk},
+        patch::FolderSyncPatches,
+    },
+};
+
+/// The synchronization plan.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SyncPlan {
+    /// The hash of the left backend the plan was built for.
+    pub left_hash: String,
+
+    /// The hash of the right backend the plan was built for.
+    pub right_hash: String,
+
+    /// The folder patch, indexed by folder name.
+    ///
+    /// Folders without hunks are still part of the patch, since they
+    /// are synchronized at the email level.
+    pub folder: FolderSyncPatches,
+
+    /// The email patch, indexed by folder name.
+    pub email: EmailSyncPatches,
+
+    /// The envelopes of emails the email patch deletes, indexed by
+    /// folder name.
+    ///
+    /// Email deletion hunks only contain the identifier of the email
+    /// to delete: the envelopes help reviewing deletions.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub deleted_envelopes: EmailSyncDeletedEnvelopes,
+}
+
+impl SyncPlan {
+    /// Read the plan from the given file.
+    pub fn read(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let plan = fs::read_to_string(&path).map_err(|err| Error::ReadPlanError(err, path))?;
+        Self::decode(&plan)
+    }
+
+    /// Write the plan to the given file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.encode()).map_err(|err| Error::WritePlanError(err, path.to_owned()))
+    }
+
+    /// Encode the plan using the plan file format.
+    pub fn encode(&self) -> String {
+        let mut records = format!(
+            "plan\t{}\t{}\n",
+            encode_field(&self.left_hash),
+            encode_field(&self.right_hash)
+        );
+
+        for (folder, hunks) in &self.folder {
+            records.push_str(&format!("folder\t{}\n", encode_field(folder)));
+
+            for hunk in hunks {
+                records.push_str(&format!("folder-hunk\t{}\n", encode_folder_hunk(hunk)));
+            }
+        }
+
+        for hunk in self.email.values().flatten() {
+            records.push_str(&format!("email-hunk\t{}\n", encode_hunk(hunk)));
+        }
+
+        for (folder, envelopes) in &self.deleted_envelopes {
+            for (dest, envelope) in envelopes {
+                records.push_str(&format!(
+                    "deleted-envelope\t{}\t{}\t{}\n",
+                    encode_field(folder),
+                    encode_destination(dest),
+                    encode_envelope(envelope),
+                ));
+            }
+        }
+
+        records
+    }
+
+    /// Decode the plan from the plan file format.
+    pub fn decode(plan: &str) -> Result<Self> {
+        let mut decoded = Self::default();
+
+        for (n, line) in plan.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split('\t');
+
+            let record = match fields.next() {
+                Some("plan") => fields.next().zip(fields.next()).map(|(left, right)| {
+                    decoded.left_hash = decode_field(left);
+                    decoded.right_hash = decode_field(right);
+                }),
+                Some("folder") => fields.next().map(|folder| {
+                    decoded.folder.entry(decode_field(folder)).or_default();
+                }),
+                Some("folder-hunk") => decode_folder_hunk(fields).map(|hunk| {
+                    let folder = hunk.folder().to_owned();
+                    decoded.folder.entry(folder).or_default().insert(hunk);
+                }),
+                Some("email-hunk") => decode_hunk(fields).map(|hunk| {
+                    let folder = hunk.folder().to_owned();
+                    decoded.email.entry(folder).or_default().insert(hunk);
+                }),
+                Some("deleted-envelope") => {
+                    decode_deleted_envelope(fields).map(|(folder, entry)| {
+                        decoded
+                            .deleted_envelopes
+                            .entry(folder)
+                            .or_default()
+                            .push(entry);
+                    })
+                }
+                _ => None,
+            };
+
+            if record.is_none() {
+                return Err(Error::ParsePlanError(n + 1));
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    /// Return `true` if both plans contain exactly the same changes.
+    ///
+    /// Hunks are compared on their encoded form, which includes
+    /// envelope identifiers and flags, so that a flag changed since
+    /// the plan was built is considered as a difference.
+    pub fn is_same_as(&self, other: &Self) -> bool {
+        let records = |plan: &Self| BTreeSet::from_iter(plan.encode().lines().map(String::from));
+        records(self) == records(other)
+    }
+
+    /// Return `true` if the plan contains no change at all.
+    pub fn is_empty(&self) -> bool {
+        self.folder.values().all(|hunks| hunks.is_empty())
+            && self.email.values().all(|hunks| hunks.is_empty())
+    }
+
+    /// Return the amount of folders and emails the plan deletes.
+    ///
+    /// Only deletions of backend folders and emails are counted,
+    /// cache removals are not.
+    pub fn deletions(&self) -> usize {
+        let folders = self
+            .folder
+            .values()
+            .flatten()
+            .filter(|hunk| matches!(hunk, FolderSyncHunk::Delete(..)))
+            .count();
+
+        let emails = self
+            .email
+            .values()
+            .flatten()
+            .filter(|hunk| matches!(hunk, EmailSyncHunk::Delete(..)))
+            .count();
+
+        folders + emails
+    }
+}
+
+impl fmt::Display for SyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for hunk in self.folder.values().flatten() {
+            writeln!(f, "{hunk}")?;
+        }
+
+        for (folder, hunks) in &self.email {
+            for hunk in hunks {
+                write!(f, "{hunk}")?;
+
+                if let EmailSyncHunk::Delete(_, id, dest) = hunk {
+                    let envelope = self
+                        .deleted_envelopes
+                        .get(folder)
+                        .into_iter()
+                        .flatten()
+                        .find(|(d, e)| d == dest && &e.id == id);
+
+                    if let Some((_, envelope)) = envelope {
+                        let from = envelope.from.to_string();
+                        write!(f, ": {} from {from}", envelope.subject)?;
+                    }
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn encode_folder_hunk(hunk: &FolderSyncHunk) -> String {
+    let (kind, folder, dest) = match hunk {
+        FolderSyncHunk::Create(folder, dest) => ("create", folder, dest),
+        FolderSyncHunk::Cache(folder, dest) => ("cache", folder, dest),
+        FolderSyncHunk::Delete(folder, dest) => ("delete", folder, dest),
+        FolderSyncHunk::Uncache(folder, dest) => ("uncache", folder, dest),
+    };
+
+    [kind, &encode_field(folder), encode_destination(dest)].join("\t")
+}
+
+fn decode_deleted_envelope<'a>(
+    mut fields: impl Iterator<Item = &'a str>,
+) -> Option<(FolderName, (SyncDestination, Envelope))> {
+    let folder = decode_field(fields.next()?);
+    let dest = decode_destination(fields.next()?)?;
+    let envelope = decode_envelope(fields)?;
+    Some((folder, (dest, envelope)))
+}
+
+fn decode_folder_hunk<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<FolderSyncHunk> {
+    let kind = fields.next()?;
+    let folder = decode_field(fields.next()?);
+    let dest = decode_destination(fields.next()?)?;
+
+    let hunk = match kind {
+        "create" => FolderSyncHunk::Create(folder, dest),
+        "cache" => FolderSyncHunk::Cache(folder, dest),
+        "delete" => FolderSyncHunk::Delete(folder, dest),
+        "uncache" => FolderSyncHunk::Uncache(folder, dest),
+        _ => return None,
+    };
+
+    Some(hunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use chrono::DateTime;
+
+    use super::SyncPlan;
+    use crate::{
+        email::sync::hunk::EmailSyncHunk,
+        envelope::{Address, Envelope},
+        flag::{Flag, Flags},
+        folder::sync::hunk::FolderSyncHunk,
+        sync::SyncDestination,
+    };
+
+    fn plan(flags: Flags) -> SyncPlan {
+        let envelope = Envelope {
+            id: "1".into(),
+            message_id: "<a@localhost>".into(),
+            date: DateTime::parse_from_rfc3339("2024-01-01T12:00:00+01:00").unwrap(),
+            flags,
+            size: 42,
+            ..Default::default()
+        };
+
+        let deleted = Envelope {
+            id: "2".into(),
+            message_id: "<b@localhost>".into(),
+            date: DateTime::parse_from_rfc3339("2024-01-02T12:00:00+01:00").unwrap(),
+            from: Address::new(Some("Alice"), "alice@localhost"),
+            subject: "Meeting\tnotes".into(),
+            ..Default::default()
+        };
+
+        SyncPlan {
+            left_hash: "left".into(),
+            right_hash: "right".into(),
+            folder: BTreeMap::from_iter([
+                ("INBOX".into(), BTreeSet::new()),
+                (
+                    "Old Stuff".into(),
+                    BTreeSet::from_iter([FolderSyncHunk::Delete(
+                        "Old Stuff".into(),
+                        SyncDestination::Right,
+                    )]),
+                ),
+            ]),
+            email: BTreeMap::from_iter([(
+                "INBOX".into(),
+                BTreeSet::from_iter([
+                    EmailSyncHunk::UpdateFlags("INBOX".into(), envelope, SyncDestination::Right),
+                    EmailSyncHunk::Delete("INBOX".into(), "2".into(), SyncDestination::Left),
+                ]),
+            )]),
+            deleted_envelopes: BTreeMap::from_iter([(
+                "INBOX".into(),
+                vec![(SyncDestination::Left, deleted)],
+            )]),
+        }
+    }
+
+    #[test]
+    fn encode_decode_plan() {
+        let plan = plan(Flags::from_iter([Flag::Seen]));
+        let decoded = SyncPlan::decode(&plan.encode()).unwrap();
+
+        assert_eq!(decoded, plan);
+        assert!(decoded.is_same_as(&plan));
+
+        let (_, deleted) = &decoded.deleted_envelopes["INBOX"][0];
+        assert_eq!(deleted.subject, "Meeting\tnotes");
+        assert_eq!(deleted.from.name.as_deref(), Some("Alice"));
+        assert_eq!(deleted.from.addr, "alice@localhost");
+        assert!(decoded
+            .to_string()
+            .contains(": Meeting\tnotes from Alice <alice@localhost>"));
+        assert_eq!(decoded.deletions(), 2);
+        assert!(!decoded.is_empty());
+    }
+
+    #[test]
+    fn detect_drift() {
+        let plan_a = plan(Flags::from_iter([Flag::Seen]));
+        let plan_b = plan(Flags::from_iter([Flag::Seen, Flag::Flagged]));
+
+        // NOTE: envelopes are compared on their Message-ID only
+        assert_eq!(plan_a, plan_b);
+        assert!(!plan_a.is_same_as(&plan_b));
+    }
+
+    #[test]
+    fn decode_invalid_plan() {
+        assert!(SyncPlan::decode("plan\tleft\tright\nunknown\n").is_err());
+    }
+}
@@ -8,16 +8,18 @@
         context::{BackendContext, BackendContextBuilder},
         Backend, BackendBuilder,
     },
-    email::sync::hunk::EmailSyncHunk,
+    email::sync::{hunk::EmailSyncHunk, labels::EmailSyncLabels},
     envelope::sync::config::EnvelopeSyncFilters,
     flag::sync::config::FlagSyncPermissions,
     folder::sync::{
-        config::{FolderSyncPermissions, FolderSyncStrategy},
+        config::{FolderSyncMapping, FolderSyncPermissions, FolderSyncStrategy},
         hunk::FolderSyncHunk,
         patch::FolderSyncPatches,
     },
     maildir::{MaildirContextBuilder, MaildirContextSync},
-    message::sync::config::MessageSyncPermissions,
+    message::sync::config::{
+        MessageSyncMode, MessageSyncOversizedStrategy, MessageSyncPermissions, MessageSyncSizeLimit,
+    },
     AnyResult,
 };
 
@@ -26,11 +28,15 @@ pub struct SyncPoolConfig {
     pub left_folder_permissions: Option<FolderSyncPermissions>,
     pub left_flag_permissions: Option<FlagSyncPermissions>,
     pub left_message_permissions: Option<MessageSyncPermissions>,
+    pub left_message_size_limit: Option<MessageSyncSizeLimit>,
     pub right_folder_permissions: Option<FolderSyncPermissions>,
     pub right_flag_permissions: Option<FlagSyncPermissions>,
     pub right_message_permissions: Option<MessageSyncPermissions>,
+    pub right_message_size_limit: Option<MessageSyncSizeLimit>,
+    pub message_mode: Option<MessageSyncMode>,
     pub pool_size: Option<usize>,
     pub folder_filters: Option<FolderSyncStrategy>,
+    pub folder_mapping: Option<FolderSyncMapping>,
     pub envelope_filters: Option<EnvelopeSyncFilters>,
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: Option<bool>,
@@ -113,6 +119,15 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             })
             .unwrap_or_default();
 
+        let left_message_size_limit = self.config.left_message_size_limit.clone().or_else(|| {
+            self.left_builder
+                .account_config
+                .message
+                .as_ref()
+                .and_then(|c| c.sync.as_ref())
+                .and_then(|c| c.size_limit.clone())
+        });
+
         let right_folder_permissions = self
             .config
             .right_folder_permissions
@@ -155,6 +170,32 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             })
             .unwrap_or_default();
 
+        let right_message_size_limit = self.config.right_message_size_limit.clone().or_else(|| {
+            self.right_builder
+                .account_config
+                .message
+                .as_ref()
+                .and_then(|c| c.sync.as_ref())
+                .and_then(|c| c.size_limit.clone())
+        });
+
+        let message_mode = self.config.message_mode.clone().or_else(|| {
+            [
+                &self.right_builder.account_config,
+                &self.left_builder.account_config,
+            ]
+            .into_iter()
+            .filter_map(|c| c.message.as_ref())
+            .filter_map(|c| c.sync.as_ref())
+            .map(|c| c.mode.clone())
+            .find(|mode| *mode != MessageSyncMode::Folders)
+        });
+
+        let labels = match message_mode {
+            Some(MessageSyncMode::Labels) => Some(EmailSyncLabels::default()),
+            _ => None,
+        };
+
         let folder_filters = self
             .config
             .folder_filters
@@ -169,6 +210,22 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             })
             .unwrap_or_default();
 
+        let folder_mapping = self
+            .config
+            .folder_mapping
+            .clone()
+            .or_else(|| {
+                self.right_builder
+                    .account_config
+                    .folder
+                    .as_ref()
+                    .and_then(|c| c.sync.as_ref())
+                    .map(|c| c.mapping.clone())
+            })
+            .unwrap_or_default();
+
+        folder_mapping.validate()?;
+
         let envelope_filters = self
             .config
             .envelope_filters
@@ -196,12 +253,16 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             left_folder_permissions,
             left_flag_permissions,
             left_message_permissions,
+            left_message_size_limit,
             right_cache,
             right,
             right_folder_permissions,
             right_flag_permissions,
             right_message_permissions,
+            right_message_size_limit,
+            labels,
             folder_filters,
+            folder_mapping,
             envelope_filters,
             handler: self.config.handler,
             dry_run: self.config.dry_run.unwrap_or_default(),
@@ -217,10 +278,14 @@ pub struct SyncPoolContext<L: BackendContext, R: BackendContext> {
     pub left_folder_permissions: FolderSyncPermissions,
     pub left_flag_permissions: FlagSyncPermissions,
     pub left_message_permissions: MessageSyncPermissions,
+    pub left_message_size_limit: Option<MessageSyncSizeLimit>,
     pub right_folder_permissions: FolderSyncPermissions,
     pub right_flag_permissions: FlagSyncPermissions,
     pub right_message_permissions: MessageSyncPermissions,
+    pub right_message_size_limit: Option<MessageSyncSizeLimit>,
+    pub labels: Option<EmailSyncLabels>,
     pub folder_filters: FolderSyncStrategy,
+    pub folder_mapping: FolderSyncMapping,
     pub envelope_filters: EnvelopeSyncFilters,
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: bool,
@@ -258,4 +323,31 @@ pub fn apply_flag_and_message_permissions(&self, patch: &mut BTreeSet<EmailSyncH
             Uncache(_, _, Right) | Delete(_, _, Right) => self.right_message_permissions.delete,
         });
     }
+
+    /// Discard copy hunks of oversized messages.
+    ///
+    /// Only hunks targeting a side configured to skip oversized
+    /// messages are discarded. Placeholders are built later on, when
+    /// processing hunks.
+    pub fn apply_message_size_limits(&self, patch: &mut BTreeSet<EmailSyncHunk>) {
+        use EmailSyncHunk::*;
+        use SyncDestination::*;
+
+        let skip = |limit: Option<&MessageSyncSizeLimit>, size: usize| match limit {
+            Some(limit) if limit.oversized == MessageSyncOversizedStrategy::Skip => {
+                limit.is_exceeded_by(size)
+            }
+            _ => false,
+        };
+
+        patch.retain(|hunk| match hunk {
+            CopyThenCache(_, envelope, _, Left, _) => {
+                !skip(self.left_message_size_limit.as_ref(), envelope.size)
+            }
+            CopyThenCache(_, envelope, _, Right, _) => {
+                !skip(self.right_message_size_limit.as_ref(), envelope.size)
+            }
+            _ => true,
+        });
+    }
 }
@@ -0,0 +1,194 @@
+//! # Sync progress
+//!
+//! Module dedicated to synchronization progress. The main structure
+//! of this module is [`SyncProgress`], sent along with progress
+//! [events](super::SyncEvent).
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// The synchronization progress.
+///
+/// Sizes are based on envelope sizes, which means that backends
+/// unable to compute them report 0 bytes. Hunks that do not transfer
+/// messages count as 0 bytes as well.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SyncProgress {
+    /// The amount of processed hunks.
+    pub hunks_done: usize,
+
+    /// The total amount of hunks to process.
+    pub hunks_total: usize,
+
+    /// The amount of transferred bytes.
+    pub bytes_done: usize,
+
+    /// The total amount of bytes to transfer.
+    pub bytes_total: usize,
+
+    /// The time elapsed since the beginning of the transfer.
+    pub elapsed: Duration,
+}
+
+impl SyncProgress {
+    /// Return the progress ratio, between 0 and 1.
+    ///
+    /// The ratio is based on bytes when sizes are known, otherwise
+    /// on hunks.
+    pub fn ratio(&self) -> f64 {
+        let (done, total) = if self.bytes_total > 0 {
+            (self.bytes_done, self.bytes_total)
+        } else {
+            (self.hunks_done, self.hunks_total)
+        };
+
+        if total == 0 {
+            1.0
+        } else {
+            done as f64 / total as f64
+        }
+    }
+
+    /// Return the average throughput, in bytes per second.
+    pub fn throughput(&self) -> Option<f64> {
+        throughput(self.bytes_done, self.elapsed)
+    }
+
+    /// Return the estimated remaining time, based on the average
+    /// throughput.
+    ///
+    /// Returns `None` while nothing has been transferred yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let throughput = self.throughput()?;
+
+        if throughput <= 0.0 {
+            return None;
+        }
+
+        let bytes_left = self.bytes_total.saturating_sub(self.bytes_done);
+        Some(Duration::from_secs_f64(bytes_left as f64 / throughput))
+    }
+}
+
+/// Return the throughput of the given amount of bytes over the given
+/// duration, in bytes per second.
+pub(crate) fn throughput(bytes: usize, elapsed: Duration) -> Option<f64> {
+    let secs = elapsed.as_secs_f64();
+
+    if bytes == 0 || secs <= 0.0 {
+        None
+    } else {
+        Some(bytes as f64 / secs)
+    }
+}
+
+/// The synchronization progress tracker, shared between hunks being
+/// processed in parallel.
+///
+/// The same tracker is shared between hunks resumed from an
+/// interrupted synchronization and hunks of the new patch, hence
+/// totals can grow while hunks are being processed.
+#[derive(Debug)]
+pub(crate) struct SyncProgressTracker {
+    started_at: Instant,
+    hunks_total: AtomicUsize,
+    bytes_total: AtomicUsize,
+    hunks_done: AtomicUsize,
+    bytes_done: AtomicUsize,
+}
+
+impl SyncProgressTracker {
+    pub fn new(hunks_total: usize, bytes_total: usize) -> Self {
+        Self {
+            started_at: Instant::now(),
+            hunks_total: AtomicUsize::new(hunks_total),
+            bytes_total: AtomicUsize::new(bytes_total),
+            hunks_done: AtomicUsize::new(0),
+            bytes_done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register the given amount of hunks and bytes to process.
+    pub fn extend(&self, hunks: usize, bytes: usize) {
+        self.hunks_total.fetch_add(hunks, Ordering::SeqCst);
+        self.bytes_total.fetch_add(bytes, Ordering::SeqCst);
+    }
+
+    /// Register a processed hunk of the given size, then return the
+    /// updated progress.
+    pub fn add(&self, size: usize) -> SyncProgress {
+        let hunks_done = self.hunks_done.fetch_add(1, Ordering::SeqCst) + 1;
+        let bytes_done = self.bytes_done.fetch_add(size, Ordering::SeqCst) + size;
+
+        SyncProgress {
+            hunks_done,
+            hunks_total: self.hunks_total.load(Ordering::SeqCst),
+            bytes_done,
+            bytes_total: self.bytes_total.load(Ordering::SeqCst),
+            elapsed: self.started_at.elapsed(),
+        }
+    }
+
+    /// Return the current progress.
+    pub fn get(&self) -> SyncProgress {
+        SyncProgress {
+            hunks_done: self.hunks_done.load(Ordering::SeqCst),
+            hunks_total: self.hunks_total.load(Ordering::SeqCst),
+            bytes_done: self.bytes_done.load(Ordering::SeqCst),
+            bytes_total: self.bytes_total.load(Ordering::SeqCst),
+            elapsed: self.started_at.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{SyncProgress, SyncProgressTracker};
+
+    #[test]
+    fn throughput_and_eta() {
+        let progress = SyncProgress {
+            hunks_done: 1,
+            hunks_total: 4,
+            bytes_done: 1000,
+            bytes_total: 4000,
+            elapsed: Duration::from_secs(2),
+        };
+
+        assert_eq!(progress.ratio(), 0.25);
+        assert_eq!(progress.throughput(), Some(500.0));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn unknown_sizes() {
+        let progress = SyncProgress {
+            hunks_done: 1,
+            hunks_total: 2,
+            elapsed: Duration::from_secs(2),
+            ..Default::default()
+        };
+
+        assert_eq!(progress.ratio(), 0.5);
+        assert_eq!(progress.throughput(), None);
+        assert_eq!(progress.eta(), None);
+    }
+
+    #[test]
+    fn shared_tracker() {
+        let tracker = SyncProgressTracker::new(0, 0);
+
+        tracker.extend(1, 1000);
+        let progress = tracker.add(1000);
+        assert_eq!((progress.hunks_done, progress.hunks_total), (1, 1));
+
+        tracker.extend(2, 3000);
+        let progress = tracker.add(1000);
+        assert_eq!((progress.hunks_done, progress.hunks_total), (2, 3));
+        assert_eq!((progress.bytes_done, progress.bytes_total), (2000, 4000));
+    }
+}
@@ -1,9 +1,20 @@
 //! # Sync report
 //!
 //! Module dedicated to synchronization reporting. The main structure
-//! of thi module is [`SyncReport`].
+//! of thi module is [`SyncReport`], which can be turned into a
+//! machine-readable [`SyncSummary`].
 
-use crate::{email::sync::report::EmailSyncReport, folder::sync::report::FolderSyncReport};
+use std::{collections::BTreeMap, error, fmt, time::Duration};
+
+use super::SyncDestination;
+use crate::{
+    email::sync::{hunk::EmailSyncHunk, report::EmailSyncReport},
+    folder::sync::{
+        hunk::{FolderName, FolderSyncHunk},
+        report::FolderSyncReport,
+    },
+    AnyBoxedError,
+};
 
 /// The synchronization report.
 ///
@@ -16,4 +27,273 @@ pub struct SyncReport {
 
     /// The report of email synchronization.
     pub email: EmailSyncReport,
+
+    /// The time spent synchronizing folders and emails.
+    pub duration: Duration,
+}
+
+impl SyncReport {
+    /// Return `true` if at least one hunk changed the given side.
+    ///
+    /// Only successfully processed hunks are taken into account, and
+    /// cache-only hunks are ignored.
+    pub fn has_changes(&self, side: &SyncDestination) -> bool {
+        let folder_changes = self.folder.patch.iter().any(|(hunk, err)| {
+            err.is_none()
+                && match hunk {
+                    FolderSyncHunk::Create(_, target) | FolderSyncHunk::Delete(_, target) => {
+                        target == side
+                    }
+                    _ => false,
+                }
+        });
+
+        let email_changes = self.email.patch.iter().any(|(hunk, err)| {
+            err.is_none()
+                && match hunk {
+                    EmailSyncHunk::CopyThenCache(_, _, _, target, _)
+                    | EmailSyncHunk::UpdateFlags(_, _, target)
+                    | EmailSyncHunk::Delete(_, _, target) => target == side,
+                    _ => false,
+                }
+        });
+
+        folder_changes || email_changes
+    }
+
+    /// Build the summary of the report.
+    pub fn summary(&self) -> SyncSummary {
+        let mut summary = self.folder.summary();
+        summary.extend(self.email.summary());
+        summary.duration = self.duration;
+        summary
+    }
+}
+
+#[cfg(feature = "derive")]
+impl serde::Serialize for SyncReport {
+    /// Serialize the report as its [`SyncSummary`].
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.summary().serialize(serializer)
+    }
+}
+
+/// The synchronization summary.
+///
+/// Unlike the report, the summary does not hold hunks nor errors but
+/// only counters and error messages, which makes it suitable for
+/// monitoring. It can be (de)serialized when the `derive` feature is
+/// enabled.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SyncSummary {
+    /// The summary of each synchronized folder, indexed by folder
+    /// name.
+    pub folders: BTreeMap<FolderName, SyncFolderSummary>,
+
+    /// The list of hunks that failed to be processed.
+    pub failures: Vec<SyncFailure>,
+
+    /// The amount of bytes transferred by successfully processed
+    /// hunks.
+    pub bytes: usize,
+
+    /// The time spent synchronizing folders and emails.
+    pub duration: Duration,
+}
+
+impl SyncSummary {
+    /// Return `true` if at least one hunk failed.
+    pub fn has_failures(&self) -> bool {
+        !self.failures.is_empty()
+    }
+
+    /// Return the sum of all folder summaries.
+    pub fn total(&self) -> SyncFolderSummary {
+        let mut total = SyncFolderSummary::default();
+
+        for folder in self.folders.values() {
+            total.extend(folder);
+        }
+
+        total
+    }
+
+    /// Merge the given summary into this one.
+    pub fn extend(&mut self, summary: SyncSummary) {
+        for (folder, counts) in summary.folders {
+            self.folders.entry(folder).or_default().extend(&counts);
+        }
+
+        self.failures.extend(summary.failures);
+        self.bytes += summary.bytes;
+        self.duration += summary.duration;
+    }
+}
+
+/// The synchronization summary of a folder.
+///
+/// Counters only take successfully processed hunks into account,
+/// and cache-only hunks are ignored.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SyncFolderSummary {
+    /// The amount of folders created.
+    pub folders_created: usize,
+
+    /// The amount of folders deleted.
+    pub folders_deleted: usize,
+
+    /// The amount of emails copied.
+    pub emails_copied: usize,
+
+    /// The amount of emails deleted.
+    pub emails_deleted: usize,
+
+    /// The amount of emails having their flags updated.
+    pub flags_updated: usize,
+
+    /// The amount of hunks that failed to be processed, including
+    /// cache-only hunks.
+    pub failed: usize,
+}
+
+impl SyncFolderSummary {
+    /// Merge the given folder summary into this one.
+    pub fn extend(&mut self, summary: &SyncFolderSummary) {
+        self.folders_created += summary.folders_created;
+        self.folders_deleted += summary.folders_deleted;
+        self.emails_copied += summary.emails_copied;
+        self.emails_deleted += summary.emails_deleted;
+        self.flags_updated += summary.flags_updated;
+        self.failed += summary.failed;
+    }
+}
+
+/// The synchronization failure.
+///
+/// Describes a hunk that failed to be processed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct SyncFailure {
+    /// The folder the hunk belongs to.
+    pub folder: FolderName,
+
+    /// The description of the hunk.
+    pub hunk: String,
+
+    /// The error chain, from the outermost error to its root cause.
+    pub errors: Vec<String>,
+}
+
+impl SyncFailure {
+    /// Build a failure from the given hunk and error.
+    pub fn new(folder: impl ToString, hunk: &impl fmt::Display, err: &AnyBoxedError) -> Self {
+        let mut errors = Vec::new();
+        let mut source = Some(err as &dyn error::Error);
+
+        while let Some(err) = source {
+            errors.push(err.to_string());
+            source = err.source();
+        }
+
+        Self {
+            folder: folder.to_string(),
+            hunk: hunk.to_string(),
+            errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{SyncFailure, SyncReport};
+    use crate::{
+        email::sync::hunk::EmailSyncHunk,
+        envelope::Envelope,
+        folder::{self, sync::hunk::FolderSyncHunk},
+        sync::SyncDestination::{Left, Right},
+    };
+
+    #[test]
+    fn summary() {
+        let mut report = SyncReport {
+            duration: Duration::from_secs(3),
+            ..Default::default()
+        };
+
+        report.folder.names.insert("INBOX".into());
+        report.folder.names.insert("Sent".into());
+        report.folder.patch = vec![
+            (FolderSyncHunk::Create("Sent".into(), Right), None),
+            (FolderSyncHunk::Cache("Sent".into(), Right), None),
+        ];
+
+        let envelope = Envelope {
+            id: "1".into(),
+            size: 42,
+            ..Default::default()
+        };
+
+        let err = folder::Error::ListLeftFoldersError(
+            folder::Error::ParseFolderKindError("kind".into()).into(),
+        );
+
+        report.email.patch = vec![
+            (
+                EmailSyncHunk::CopyThenCache("INBOX".into(), envelope.clone(), Left, Right, true),
+                None,
+            ),
+            (
+                EmailSyncHunk::UpdateFlags("INBOX".into(), envelope.clone(), Left),
+                None,
+            ),
+            (
+                EmailSyncHunk::UpdateCachedFlags("INBOX".into(), envelope, Left),
+                None,
+            ),
+            (
+                EmailSyncHunk::Delete("Sent".into(), "2".into(), Right),
+                Some(err.into()),
+            ),
+        ];
+
+        let summary = report.summary();
+
+        assert_eq!(summary.folders.len(), 2);
+        assert_eq!(summary.folders["Sent"].folders_created, 1);
+        assert_eq!(summary.folders["Sent"].emails_deleted, 0);
+        assert_eq!(summary.folders["Sent"].failed, 1);
+        assert_eq!(summary.folders["INBOX"].emails_copied, 1);
+        assert_eq!(summary.folders["INBOX"].flags_updated, 1);
+        assert_eq!(summary.total().failed, 1);
+        assert_eq!(summary.bytes, 42);
+        assert_eq!(summary.duration, Duration::from_secs(3));
+
+        assert_eq!(
+            summary.failures,
+            vec![SyncFailure {
+                folder: "Sent".into(),
+                hunk: "Deleting right email 2 (Sent)".into(),
+                errors: vec![
+                    "cannot sync: cannot list folders from left backend".into(),
+                    "cannot parse folder kind kind".into(),
+                ],
+            }]
+        );
+    }
 }
@@ -0,0 +1,434 @@
+//! # TLS
+//!
+//! Module dedicated to TLS customization. The main structure of
+//! this module is [`TlsConfig`], which allows backends to trust
+//! private certificate authorities, to authenticate using a client
+//! certificate, to require a minimum TLS version, to pin server
+//! certificates and to disable certificate verification.
+
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    result,
+    sync::Arc,
+};
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio_rustls::rustls::{
+    self,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{self, CryptoProvider, WebPkiSupportedAlgorithms},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+
+use crate::warn;
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot read certificate authorities from {1}")]
+    ReadCaFileError(#[source] io::Error, PathBuf),
+    #[error("cannot find any certificate authority in {0}")]
+    EmptyCaFileError(PathBuf),
+    #[error("cannot add certificate authority from {1}")]
+    AddCaError(#[source] rustls::Error, PathBuf),
+    #[error("cannot read client certificate from {1}")]
+    ReadCertFileError(#[source] io::Error, PathBuf),
+    #[error("cannot find any client certificate in {0}")]
+    EmptyCertFileError(PathBuf),
+    #[error("cannot read client certificate key from {1}")]
+    ReadKeyFileError(#[source] io::Error, PathBuf),
+    #[error("cannot find any client certificate key in {0}")]
+    EmptyKeyFileError(PathBuf),
+    #[error("cannot use client certificate: both cert-file and key-file must be defined")]
+    IncompleteClientCertError,
+    #[error("cannot use client certificate")]
+    ClientCertError(#[source] rustls::Error),
+    #[error("cannot parse certificate fingerprint {0}: expected a SHA-256 hexadecimal digest")]
+    ParseFingerprintError(String),
+    #[error("cannot set TLS versions")]
+    SetVersionsError(#[source] rustls::Error),
+    #[error("cannot find any certificate authority in the system trust store")]
+    EmptyNativeTrustStoreError,
+    #[error("cannot use ca-file together with fingerprints or insecure")]
+    CaFileConflictError,
+}
+
+/// The TLS configuration.
+///
+/// An undefined configuration means that the server certificate is
+/// verified against the trust store of the system, and that no
+/// client certificate is sent.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct TlsConfig {
+    /// The trust store used to verify server certificates.
+    ///
+    /// Defaults to the trust store of the system.
+    pub trust_store: Option<TlsTrustStore>,
+
+    /// Path to a PEM bundle of certificate authorities to trust, in
+    /// addition to the trust store.
+    ///
+    /// This is the way to trust a private certificate authority
+    /// without changing the trust store of the system. It cannot be
+    /// combined with `fingerprints` nor `insecure`, which bypass
+    /// certificate authorities.
+    pub ca_file: Option<PathBuf>,
+
+    /// Path to a PEM client certificate chain, sent to the server
+    /// during the handshake.
+    ///
+    /// Servers usually expect the SASL EXTERNAL mechanism to be
+    /// used next, see
+    /// [`crate::account::config::passwd::SaslMechanism::External`].
+    pub cert_file: Option<PathBuf>,
+
+    /// Path to the PEM private key of the client certificate.
+    pub key_file: Option<PathBuf>,
+
+    /// The minimum TLS version accepted.
+    ///
+    /// Defaults to TLS 1.2.
+    pub min_version: Option<TlsVersion>,
+
+    /// SHA-256 fingerprints of the server certificates to accept,
+    /// in hexadecimal (colons are optional).
+    ///
+    /// When defined, the server certificate is accepted if and only
+    /// if its fingerprint matches one of them: the trust store is
+    /// not used anymore, which allows pinning self-signed
+    /// certificates.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub fingerprints: Vec<String>,
+
+    /// Accept any server certificate.
+    ///
+    /// This exposes the connection to man-in-the-middle attacks, and
+    /// should only be used with local test servers.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub insecure: bool,
+}
+
+impl TlsConfig {
+    /// Build the rustls client configuration matching this TLS
+    /// configuration.
+    pub fn build_rustls_config(&self) -> Result<ClientConfig> {
+        if self.ca_file.is_some() && (self.insecure || !self.fingerprints.is_empty()) {
+            return Err(Error::CaFileConflictError);
+        }
+
+        let provider = Arc::new(crypto::ring::default_provider());
+
+        let versions: &[_] = match self.min_version {
+            Some(TlsVersion::Tls13) => &[&rustls::version::TLS13],
+            Some(TlsVersion::Tls12) | None => &[&rustls::version::TLS13, &rustls::version::TLS12],
+        };
+
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(versions)
+            .map_err(Error::SetVersionsError)?;
+
+        let builder = if self.insecure || !self.fingerprints.is_empty() {
+            let verifier = TlsVerifier::new(self, provider)?;
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+        } else {
+            builder.with_root_certificates(self.build_root_cert_store()?)
+        };
+
+        let config = match (&self.cert_file, &self.key_file) {
+            (None, None) => builder.with_no_client_auth(),
+            (Some(cert_path), Some(key_path)) => {
+                let certs = read_certs(cert_path)
+                    .map_err(|err| Error::ReadCertFileError(err, cert_path.clone()))?;
+
+                if certs.is_empty() {
+                    return Err(Error::EmptyCertFileError(cert_path.clone()));
+                }
+
+                let key = read_key(key_path)
+                    .map_err(|err| Error::ReadKeyFileError(err, key_path.clone()))?
+                    .ok_or_else(|| Error::EmptyKeyFileError(key_path.clone()))?;
+
+                builder
+                    .with_client_auth_cert(certs, key)
+                    .map_err(Error::ClientCertError)?
+            }
+            _ => return Err(Error::IncompleteClientCertError),
+        };
+
+        Ok(config)
+    }
+
+    /// Build the store of certificate authorities trusted to issue
+    /// server certificates.
+    fn build_root_cert_store(&self) -> Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+
+        match self.trust_store.unwrap_or_default() {
+            TlsTrustStore::Native => {
+                let native = rustls_native_certs::load_native_certs();
+
+                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                for err in native.errors {
+                    warn!("cannot load system certificate authority, skipping it: {err}");
+                }
+
+                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                let (added, ignored) = roots.add_parsable_certificates(native.certs);
+
+                if ignored > 0 {
+                    warn!("ignoring {ignored} invalid system certificate authorities");
+                }
+
+                if added == 0 && self.ca_file.is_none() {
+                    return Err(Error::EmptyNativeTrustStoreError);
+                }
+            }
+            TlsTrustStore::Webpki => {
+                roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            }
+        }
+
+        if let Some(path) = &self.ca_file {
+            let certs =
+                read_certs(path).map_err(|err| Error::ReadCaFileError(err, path.clone()))?;
+
+            if certs.is_empty() {
+                return Err(Error::EmptyCaFileError(path.clone()));
+            }
+
+            for cert in certs {
+                roots
+                    .add(cert)
+                    .map_err(|err| Error::AddCaError(err, path.clone()))?;
+            }
+        }
+
+        Ok(roots)
+    }
+}
+
+/// The trust store used to verify server certificates.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TlsTrustStore {
+    /// The certificate authorities trusted by the system.
+    #[default]
+    Native,
+
+    /// The Mozilla certificate authorities bundled with the library,
+    /// see [webpki-roots](https://docs.rs/webpki-roots).
+    ///
+    /// Useful on systems without a trust store, like minimal
+    /// containers.
+    Webpki,
+}
+
+/// The TLS protocol version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TlsVersion {
+    #[cfg_attr(feature = "derive", serde(rename = "1.2", alias = "tls1.2"))]
+    Tls12,
+    #[cfg_attr(feature = "derive", serde(rename = "1.3", alias = "tls1.3"))]
+    Tls13,
+}
+
+/// The server certificate verifier used for pinned certificates and
+/// for the insecure mode.
+///
+/// Handshake signatures are still verified, which makes sure that
+/// the server owns the private key of the certificate.
+#[derive(Debug)]
+struct TlsVerifier {
+    fingerprints: Vec<Vec<u8>>,
+    insecure: bool,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl TlsVerifier {
+    fn new(config: &TlsConfig, provider: Arc<CryptoProvider>) -> Result<Self> {
+        let fingerprints = config
+            .fingerprints
+            .iter()
+            .map(|fingerprint| parse_fingerprint(fingerprint))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            fingerprints,
+            insecure: config.insecure,
+            algorithms: provider.signature_verification_algorithms,
+        })
+    }
+}
+
+impl ServerCertVerifier for TlsVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> result::Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = Sha256::digest(end_entity);
+
+        if self.fingerprints.iter().any(|f| f[..] == fingerprint[..]) {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        if self.insecure {
+            warn!("accepting server certificate without verifying it");
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        let fingerprint = fingerprint
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>();
+        let err = format!("server certificate {} is not pinned", fingerprint.join(":"));
+        Err(rustls::Error::General(err))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// Parse the given SHA-256 fingerprint, in hexadecimal with optional
+/// colons.
+fn parse_fingerprint(fingerprint: &str) -> Result<Vec<u8>> {
+    let hex: String = fingerprint.chars().filter(|c| *c != ':').collect();
+    let err = || Error::ParseFingerprintError(fingerprint.to_owned());
+
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(err());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| err()))
+        .collect()
+}
+
+fn read_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::certs(&mut reader).collect()
+}
+
+fn read_key(path: &Path) -> io::Result<Option<PrivateKeyDer<'static>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fingerprint() {
+        let fingerprint = "2C:F2:4D:BA:5F:B0:A3:0E:26:E8:3B:2A:C5:B9:E2:9E:1B:16:1E:5C:1F:A7:42:5E:73:04:33:62:93:8B:98:24";
+        let bytes = super::parse_fingerprint(fingerprint).unwrap();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(bytes[0], 0x2c);
+        assert_eq!(bytes[31], 0x24);
+
+        let fingerprint = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(super::parse_fingerprint(fingerprint).unwrap(), bytes);
+
+        assert!(super::parse_fingerprint("2C:F2:4D").is_err());
+        assert!(super::parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn build_rustls_config() {
+        let config = TlsConfig::default();
+        assert!(config.build_rustls_config().is_ok());
+
+        let config = TlsConfig {
+            min_version: Some(TlsVersion::Tls13),
+            insecure: true,
+            ..Default::default()
+        };
+        assert!(config.build_rustls_config().is_ok());
+
+        let config = TlsConfig {
+            cert_file: Some(PathBuf::from("cert.pem")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.build_rustls_config(),
+            Err(Error::IncompleteClientCertError)
+        ));
+
+        let config = TlsConfig {
+            fingerprints: vec![String::from("invalid")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.build_rustls_config(),
+            Err(Error::ParseFingerprintError(_))
+        ));
+
+        let config = TlsConfig {
+            trust_store: Some(TlsTrustStore::Webpki),
+            ..Default::default()
+        };
+        assert!(config.build_rustls_config().is_ok());
+
+        let config = TlsConfig {
+            ca_file: Some(PathBuf::from("ca.pem")),
+            insecure: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.build_rustls_config(),
+            Err(Error::CaFileConflictError)
+        ));
+    }
+}
@@ -1,4 +1,7 @@
-use std::{fmt, future::Future, ops::Deref, pin::Pin, sync::Arc};
+use std::{
+    collections::BTreeMap, fmt, future::Future, ops::Deref, path::PathBuf, pin::Pin, sync::Arc,
+    time::Duration,
+};
 
 use process::Command;
 
@@ -25,6 +28,18 @@ pub struct WatchHook {
     /// [`notify_rust::Notification`]-like configuration.
     pub notify: Option<WatchNotifyConfig>,
 
+    /// Send envelope events as JSON to the given HTTP endpoint.
+    ///
+    /// Only envelope events are sent, which requires the `webhook`
+    /// cargo feature. Without it, a warning is logged instead.
+    pub webhook: Option<WatchWebhookConfig>,
+
+    /// Append envelope events as JSON lines to the given file or
+    /// FIFO.
+    ///
+    /// Only envelope events are appended.
+    pub json_lines: Option<WatchJsonLinesConfig>,
+
     /// Execute the given watch function.
     ///
     /// The watch function cannot be de/serialized. The function
@@ -40,7 +55,10 @@ impl Eq for WatchHook {
 
 impl PartialEq for WatchHook {
     fn eq(&self, other: &Self) -> bool {
-        self.cmd == other.cmd && self.notify == other.notify
+        self.cmd == other.cmd
+            && self.notify == other.notify
+            && self.webhook == other.webhook
+            && self.json_lines == other.json_lines
     }
 }
 
@@ -113,6 +131,10 @@ pub struct WatchNotifyConfig {
     ///  - "{recipient}" either the recipient name or the address
     ///  - "{recipient.name}" the recipient name or "unknown"
     ///  - "{recipient.address}" the recipient address
+    ///  - "{flags}" the flags of the envelope
+    ///  - "{event}" the kind of envelope event
+    ///  - "{flags.added}" the flags added by the event
+    ///  - "{flags.removed}" the flags removed by the event
     pub summary: String,
 
     /// The body of the notification.
@@ -126,5 +148,126 @@ pub struct WatchNotifyConfig {
     ///  - "{recipient}" either the recipient name or the address
     ///  - "{recipient.name}" the recipient name or "unknown"
     ///  - "{recipient.address}" the recipient address
+    ///  - "{flags}" the flags of the envelope
+    ///  - "{event}" the kind of envelope event
+    ///  - "{flags.added}" the flags added by the event
+    ///  - "{flags.removed}" the flags removed by the event
     pub body: String,
 }
+
+/// The watch configuration of the webhook hook variant.
+///
+/// Events are sent using a POST request, with a JSON body. When
+/// batching is enabled, the body is an array of events, otherwise it
+/// is a single event.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct WatchWebhookConfig {
+    /// The URL of the HTTP endpoint.
+    pub url: String,
+
+    /// The additional HTTP headers of the request, for example for
+    /// authentication.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub headers: BTreeMap<String, String>,
+
+    /// The retry configuration.
+    pub retry: Option<WatchRetryConfig>,
+
+    /// The batch configuration.
+    pub batch: Option<WatchBatchConfig>,
+}
+
+/// The watch configuration of the JSON lines hook variant.
+///
+/// Each event is appended to the file as a JSON object followed by a
+/// line feed. The file is created if it does not exist.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct WatchJsonLinesConfig {
+    /// The path of the file or FIFO.
+    pub path: PathBuf,
+
+    /// The retry configuration.
+    pub retry: Option<WatchRetryConfig>,
+
+    /// The batch configuration.
+    pub batch: Option<WatchBatchConfig>,
+}
+
+/// The watch retry configuration.
+///
+/// Failed deliveries are retried with an exponential backoff: the
+/// delay doubles after each attempt.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct WatchRetryConfig {
+    /// The maximum amount of retries.
+    ///
+    /// Defaults to 3.
+    pub attempts: Option<usize>,
+
+    /// The delay before the first retry, in milliseconds.
+    ///
+    /// Defaults to 1000.
+    pub delay: Option<u64>,
+}
+
+impl WatchRetryConfig {
+    /// Return the maximum amount of retries.
+    pub fn attempts(&self) -> usize {
+        self.attempts.unwrap_or(3)
+    }
+
+    /// Return the delay before the given retry, starting from 0.
+    pub fn delay(&self, retry: usize) -> Duration {
+        let delay = Duration::from_millis(self.delay.unwrap_or(1000));
+        delay.saturating_mul(2u32.saturating_pow(retry as u32))
+    }
+}
+
+/// The watch batch configuration.
+///
+/// Events are delivered as soon as possible by default. When a
+/// debounce is set, the delivery waits for other events to come,
+/// and sends them all at once.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct WatchBatchConfig {
+    /// The maximum amount of events delivered at once.
+    ///
+    /// Defaults to 100.
+    pub size: Option<usize>,
+
+    /// The time to wait for other events after receiving one, in
+    /// milliseconds.
+    pub debounce: Option<u64>,
+}
+
+impl WatchBatchConfig {
+    /// Return the maximum amount of events delivered at once.
+    pub fn size(&self) -> usize {
+        self.size.unwrap_or(100).max(1)
+    }
+
+    /// Return the debounce duration, if any.
+    pub fn debounce(&self) -> Option<Duration> {
+        self.debounce.map(Duration::from_millis)
+    }
+}
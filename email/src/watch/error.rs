@@ -0,0 +1,27 @@
+use std::{io, path::PathBuf, result};
+
+use thiserror::Error;
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot open JSON lines file at {1}")]
+    OpenJsonLinesError(#[source] io::Error, PathBuf),
+    #[error("cannot write JSON lines to {1}")]
+    WriteJsonLinesError(#[source] io::Error, PathBuf),
+    #[cfg(feature = "webhook")]
+    #[error("cannot create webhook HTTP connector")]
+    CreateHttpConnectorError(#[source] io::Error),
+    #[cfg(feature = "webhook")]
+    #[error("cannot build webhook request for {1}")]
+    BuildWebhookRequestError(#[source] hyper::http::Error, String),
+    #[cfg(feature = "webhook")]
+    #[error("cannot send webhook request to {1}")]
+    SendWebhookRequestError(#[source] hyper_util::client::legacy::Error, String),
+    #[cfg(feature = "webhook")]
+    #[error("cannot send webhook request to {0}: {1}")]
+    SendWebhookRequestStatusError(String, hyper::StatusCode),
+}
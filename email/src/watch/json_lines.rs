@@ -0,0 +1,116 @@
+//! # Watch JSON lines
+//!
+//! Module dedicated to the JSON lines watch hook, which appends
+//! events to a file or a FIFO.
+
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use super::{
+    config::{WatchBatchConfig, WatchJsonLinesConfig, WatchRetryConfig},
+    output::{WatchOutput, WatchOutputs},
+    Error, Result,
+};
+
+static OUTPUTS: Lazy<WatchOutputs<WatchJsonLinesConfig>> = Lazy::new(WatchOutputs::new);
+
+impl WatchJsonLinesConfig {
+    /// Append the given event to the file.
+    ///
+    /// The event is appended in the background, once batched.
+    pub fn send(&self, event: Value) {
+        OUTPUTS.send(self, event, || Ok(self.clone()))
+    }
+}
+
+impl WatchJsonLinesConfig {
+    /// Open the file for appending.
+    ///
+    /// Opening a FIFO for writing blocks until a reader opens it, so
+    /// the file is first opened in non-blocking mode: it fails
+    /// straight away when the FIFO has no reader, and the delivery
+    /// is retried later on. The file is then opened again in
+    /// blocking mode, which does not wait anymore since the FIFO has
+    /// a reader.
+    async fn open(&self) -> Result<File> {
+        let mut opts = OpenOptions::new();
+        opts.create(true).append(true);
+
+        #[cfg(unix)]
+        opts.clone()
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path)
+            .await
+            .map_err(|err| Error::OpenJsonLinesError(err, self.path.clone()))?;
+
+        opts.open(&self.path)
+            .await
+            .map_err(|err| Error::OpenJsonLinesError(err, self.path.clone()))
+    }
+}
+
+#[async_trait]
+impl WatchOutput for WatchJsonLinesConfig {
+    fn batch(&self) -> Option<&WatchBatchConfig> {
+        self.batch.as_ref()
+    }
+
+    fn retry(&self) -> Option<&WatchRetryConfig> {
+        self.retry.as_ref()
+    }
+
+    async fn deliver(&self, events: &[Value]) -> Result<()> {
+        let mut lines = String::new();
+
+        for event in events {
+            lines.push_str(&event.to_string());
+            lines.push('\n');
+        }
+
+        // NOTE: the file is opened for each batch, so that FIFO
+        // readers can come and go
+        let mut file = self.open().await?;
+
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(|err| Error::WriteJsonLinesError(err, self.path.clone()))?;
+
+        file.flush()
+            .await
+            .map_err(|err| Error::WriteJsonLinesError(err, self.path.clone()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{process::Command, time::Duration};
+
+    use serde_json::json;
+    use tempfile::tempdir;
+    use tokio::time::timeout;
+
+    use super::{WatchJsonLinesConfig, WatchOutput};
+
+    #[tokio::test]
+    async fn fifo_without_reader() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("events");
+        let status = Command::new("mkfifo").arg(&path).status().unwrap();
+        assert!(status.success());
+
+        let config = WatchJsonLinesConfig {
+            path,
+            ..Default::default()
+        };
+
+        let delivery = timeout(Duration::from_secs(5), config.deliver(&[json!({})])).await;
+        assert!(matches!(delivery, Ok(Err(_))));
+    }
+}
@@ -1 +1,19 @@
+//! # Watch
+//!
+//! Module dedicated to watch hooks. The main structure of this
+//! module is [`WatchHook`](config::WatchHook), which describes what
+//! should be done when a change occurs.
+
 pub mod config;
+#[cfg(feature = "watch")]
+mod error;
+#[cfg(feature = "watch")]
+pub mod json_lines;
+#[cfg(feature = "watch")]
+pub(crate) mod output;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+
+#[cfg(feature = "watch")]
+#[doc(inline)]
+pub use self::error::{Error, Result};
@@ -0,0 +1,138 @@
+//! # Watch output
+//!
+//! Module dedicated to watch outputs, which deliver JSON events
+//! outside of the process. Each output runs in its own task, which
+//! batches events then delivers them with retries.
+
+use std::{collections::HashMap, hash::Hash, sync::Mutex};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{sleep, timeout_at, Instant},
+};
+
+use super::{
+    config::{WatchBatchConfig, WatchRetryConfig},
+    Result,
+};
+use crate::debug;
+
+/// The watch output.
+#[async_trait]
+pub(crate) trait WatchOutput: Send + Sync + 'static {
+    /// Return the batch configuration of the output.
+    fn batch(&self) -> Option<&WatchBatchConfig>;
+
+    /// Return the retry configuration of the output.
+    fn retry(&self) -> Option<&WatchRetryConfig>;
+
+    /// Deliver the given events.
+    async fn deliver(&self, events: &[Value]) -> Result<()>;
+}
+
+/// The registry of watch outputs, indexed by configuration.
+///
+/// Outputs sharing the same configuration share the same task, so
+/// that their events are batched together.
+pub(crate) struct WatchOutputs<C>(Mutex<HashMap<C, UnboundedSender<Value>>>);
+
+impl<C: Clone + Eq + Hash> WatchOutputs<C> {
+    pub fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+
+    /// Send the given event to the output matching the given
+    /// configuration.
+    ///
+    /// The output is built then spawned the first time it is used,
+    /// or again if its task stopped.
+    pub fn send<O: WatchOutput>(
+        &self,
+        config: &C,
+        event: Value,
+        build: impl FnOnce() -> Result<O>,
+    ) {
+        let mut outputs = self.0.lock().unwrap_or_else(|err| err.into_inner());
+
+        let event = match outputs.get(config) {
+            Some(tx) => match tx.send(event) {
+                Ok(()) => return,
+                Err(err) => err.0,
+            },
+            None => event,
+        };
+
+        match build() {
+            Ok(output) => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                // NOTE: the receiver cannot be closed at this point
+                let _ = tx.send(event);
+                tokio::spawn(run(output, rx));
+                outputs.insert(config.clone(), tx);
+            }
+            Err(_err) => {
+                debug!("cannot build watch output, discarding event: {_err}");
+                debug!("{_err:?}");
+            }
+        }
+    }
+}
+
+/// Run the given output until the channel is closed.
+async fn run(output: impl WatchOutput, mut rx: UnboundedReceiver<Value>) {
+    // NOTE: events are delivered one by one when batching is
+    // disabled
+    let size = output.batch().map(WatchBatchConfig::size).unwrap_or(1);
+    let debounce = output.batch().and_then(WatchBatchConfig::debounce);
+    let retry = output.retry().cloned().unwrap_or_default();
+
+    while let Some(event) = rx.recv().await {
+        let mut events = vec![event];
+
+        if let Some(debounce) = debounce {
+            let deadline = Instant::now() + debounce;
+
+            while events.len() < size {
+                match timeout_at(deadline, rx.recv()).await {
+                    Ok(Some(event)) => events.push(event),
+                    _ => break,
+                }
+            }
+        }
+
+        while events.len() < size {
+            let Ok(event) = rx.try_recv() else {
+                break;
+            };
+            events.push(event);
+        }
+
+        deliver(&output, &retry, &events).await;
+    }
+}
+
+/// Deliver the given events, retrying on failure.
+async fn deliver(output: &impl WatchOutput, retry: &WatchRetryConfig, events: &[Value]) {
+    let mut attempt = 0;
+
+    loop {
+        match output.deliver(events).await {
+            Ok(()) => break,
+            Err(_err) if attempt < retry.attempts() => {
+                let delay = retry.delay(attempt);
+                debug!("cannot deliver watch events, retrying in {delay:?}: {_err}");
+                debug!("{_err:?}");
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(_err) => {
+                let _count = events.len();
+                debug!("cannot deliver watch events, discarding {_count} event(s): {_err}");
+                debug!("{_err:?}");
+                break;
+            }
+        }
+    }
+}
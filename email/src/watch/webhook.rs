@@ -0,0 +1,103 @@
+//! # Watch webhook
+//!
+//! Module dedicated to the webhook watch hook, which sends events to
+//! an HTTP endpoint.
+
+use async_trait::async_trait;
+use http_body_util::Full;
+use hyper::{body::Bytes, header::CONTENT_TYPE, Method, Request};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use super::{
+    config::{WatchBatchConfig, WatchRetryConfig, WatchWebhookConfig},
+    output::{WatchOutput, WatchOutputs},
+    Error, Result,
+};
+use crate::trace;
+
+static OUTPUTS: Lazy<WatchOutputs<WatchWebhookConfig>> = Lazy::new(WatchOutputs::new);
+
+impl WatchWebhookConfig {
+    /// Send the given event to the HTTP endpoint.
+    ///
+    /// The event is sent in the background, once batched.
+    pub fn send(&self, event: Value) {
+        OUTPUTS.send(self, event, || WatchWebhook::new(self.clone()))
+    }
+}
+
+/// The webhook watch output.
+struct WatchWebhook {
+    config: WatchWebhookConfig,
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+}
+
+impl WatchWebhook {
+    /// Create a new webhook output using a rustls connector.
+    fn new(config: WatchWebhookConfig) -> Result<Self> {
+        let conn = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .map_err(Error::CreateHttpConnectorError)?
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        let client = Client::builder(TokioExecutor::new()).build(conn);
+
+        Ok(Self { config, client })
+    }
+}
+
+#[async_trait]
+impl WatchOutput for WatchWebhook {
+    fn batch(&self) -> Option<&WatchBatchConfig> {
+        self.config.batch.as_ref()
+    }
+
+    fn retry(&self) -> Option<&WatchRetryConfig> {
+        self.config.retry.as_ref()
+    }
+
+    async fn deliver(&self, events: &[Value]) -> Result<()> {
+        let url = &self.config.url;
+
+        let body = match (&self.config.batch, events) {
+            (None, [event]) => event.to_string(),
+            _ => Value::from(events.to_vec()).to_string(),
+        };
+
+        let mut req = Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .header(CONTENT_TYPE, "application/json");
+
+        for (key, val) in &self.config.headers {
+            req = req.header(key, val);
+        }
+
+        let req = req
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|err| Error::BuildWebhookRequestError(err, url.clone()))?;
+
+        let res = self
+            .client
+            .request(req)
+            .await
+            .map_err(|err| Error::SendWebhookRequestError(err, url.clone()))?;
+
+        let status = res.status();
+
+        if !status.is_success() {
+            trace!("{res:?}");
+            return Err(Error::SendWebhookRequestStatusError(url.clone(), status));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+#![cfg(all(feature = "maildir", feature = "sync"))]
+
+use std::{collections::HashSet, path::Path, sync::Arc};
+
+use email::{
+    account::config::AccountConfig,
+    backend::{context::BackendContextBuilder, Backend, BackendBuilder},
+    envelope::list::{ListEnvelopes, ListEnvelopesOptions},
+    folder::{add::AddFolder, INBOX},
+    maildir::{config::MaildirConfig, MaildirContextBuilder, MaildirContextSync},
+    message::add::AddMessage,
+    sync::{hub::HubSyncBuilder, SyncBuilder},
+};
+use mail_builder::MessageBuilder;
+use tempfile::tempdir;
+
+async fn maildir_builder(name: &str, root_dir: &Path) -> BackendBuilder<MaildirContextBuilder> {
+    let mdir_config = Arc::new(MaildirConfig {
+        root_dir: root_dir.join(name),
+        maildirpp: false,
+    });
+
+    let account_config = Arc::new(AccountConfig {
+        name: name.into(),
+        ..Default::default()
+    });
+
+    let mut ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config);
+    ctx.configure().await.unwrap();
+
+    BackendBuilder::new(account_config, ctx)
+}
+
+async fn message_ids(backend: &Backend<MaildirContextSync>) -> HashSet<String> {
+    let opts = ListEnvelopesOptions {
+        page: 0,
+        page_size: 0,
+        query: None,
+    };
+
+    HashSet::from_iter(
+        backend
+            .list_envelopes(INBOX, opts)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.message_id),
+    )
+}
+
+fn message(id: &str) -> Vec<u8> {
+    MessageBuilder::new()
+        .message_id(id)
+        .from("alice@localhost")
+        .to("bob@localhost")
+        .subject(id)
+        .text_body(id)
+        .write_to_vec()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hub_sync() {
+    let tmp = tempdir().unwrap().path().to_owned();
+
+    let local_builder = maildir_builder("local", &tmp).await;
+    let work_builder = maildir_builder("work", &tmp).await;
+    let archive_builder = maildir_builder("archive", &tmp).await;
+
+    let local = local_builder.clone().build().await.unwrap();
+    let work = work_builder.clone().build().await.unwrap();
+    let archive = archive_builder.clone().build().await.unwrap();
+
+    work.add_folder(INBOX).await.unwrap();
+    work.add_message(INBOX, &message("a@localhost"))
+        .await
+        .unwrap();
+
+    let hub_sync = || {
+        HubSyncBuilder::new()
+            .with_cache_dir(tmp.join("cache"))
+            .with_sync(
+                "work",
+                SyncBuilder::new(local_builder.clone(), work_builder.clone()),
+            )
+            .unwrap()
+            .with_sync(
+                "archive",
+                SyncBuilder::new(local_builder.clone(), archive_builder.clone()),
+            )
+            .unwrap()
+    };
+
+    // messages from the first right backend reach the other ones
+
+    let report = hub_sync().sync().await.unwrap();
+    assert_eq!(report.syncs.len(), 2);
+
+    let expected = HashSet::from_iter(["<a@localhost>".to_owned()]);
+    assert_eq!(message_ids(&local).await, expected);
+    assert_eq!(message_ids(&archive).await, expected);
+
+    // messages from the last right backend reach the first ones
+
+    archive
+        .add_message(INBOX, &message("b@localhost"))
+        .await
+        .unwrap();
+
+    let report = hub_sync().sync().await.unwrap();
+    let names: Vec<_> = report.syncs.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["work", "archive"]);
+    let names: Vec<_> = report
+        .propagations
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(names, ["work"]);
+
+    let expected = HashSet::from_iter(["<a@localhost>".to_owned(), "<b@localhost>".to_owned()]);
+    assert_eq!(message_ids(&local).await, expected);
+    assert_eq!(message_ids(&work).await, expected);
+    assert_eq!(message_ids(&archive).await, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hub_sync_dry_run() {
+    let tmp = tempdir().unwrap().path().to_owned();
+
+    let local_builder = maildir_builder("local", &tmp).await;
+    let work_builder = maildir_builder("work", &tmp).await;
+    let archive_builder = maildir_builder("archive", &tmp).await;
+
+    let local = local_builder.clone().build().await.unwrap();
+    let archive = archive_builder.clone().build().await.unwrap();
+
+    local.add_folder(INBOX).await.unwrap();
+    archive.add_folder(INBOX).await.unwrap();
+    archive
+        .add_message(INBOX, &message("a@localhost"))
+        .await
+        .unwrap();
+
+    let report = HubSyncBuilder::new()
+        .with_cache_dir(tmp.join("cache"))
+        .with_sync(
+            "work",
+            SyncBuilder::new(local_builder.clone(), work_builder.clone()).with_dry_run(true),
+        )
+        .unwrap()
+        .with_sync(
+            "archive",
+            SyncBuilder::new(local_builder.clone(), archive_builder.clone()).with_dry_run(true),
+        )
+        .unwrap()
+        .sync()
+        .await
+        .unwrap();
+
+    // changes are not applied, so nothing needs to be propagated
+
+    let names: Vec<_> = report.syncs.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["work", "archive"]);
+    assert!(report.propagations.is_empty());
+    assert!(!report.has_errors());
+
+    assert_eq!(message_ids(&local).await, HashSet::new());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hub_sync_duplicate_name() {
+    let tmp = tempdir().unwrap().path().to_owned();
+
+    let local_builder = maildir_builder("local", &tmp).await;
+    let work_builder = maildir_builder("work", &tmp).await;
+    let archive_builder = maildir_builder("archive", &tmp).await;
+
+    let res = HubSyncBuilder::new()
+        .with_sync(
+            "work",
+            SyncBuilder::new(local_builder.clone(), work_builder),
+        )
+        .unwrap()
+        .with_sync("work", SyncBuilder::new(local_builder, archive_builder));
+
+    assert!(matches!(
+        res,
+        Err(email::sync::Error::HubSyncDuplicateNameError(name)) if name == "work"
+    ));
+}
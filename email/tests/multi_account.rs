@@ -0,0 +1,118 @@
+#![cfg(all(feature = "maildir", feature = "multi-account"))]
+
+use std::{path::Path, sync::Arc};
+
+use email::{
+    account::config::AccountConfig,
+    backend::{
+        context::BackendContextBuilder, multi::MultiAccountBackend, Backend, BackendBuilder,
+    },
+    envelope::list::{ListEnvelopes, ListEnvelopesOptions},
+    flag::{Flag, Flags},
+    folder::{add::AddFolder, expunge::ExpungeFolder, purge::PurgeFolder, INBOX},
+    maildir::{config::MaildirConfig, MaildirContextBuilder, MaildirContextSync},
+    message::add::AddMessage,
+};
+use mail_builder::MessageBuilder;
+use tempfile::tempdir;
+
+async fn maildir(name: &str, root_dir: &Path) -> Backend<MaildirContextSync> {
+    let mdir_config = Arc::new(MaildirConfig {
+        root_dir: root_dir.join(name),
+        maildirpp: false,
+    });
+
+    let account_config = Arc::new(AccountConfig {
+        name: name.into(),
+        ..Default::default()
+    });
+
+    let mut ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config);
+    ctx.configure().await.unwrap();
+
+    let backend = BackendBuilder::new(account_config, ctx)
+        .build::<Backend<MaildirContextSync>>()
+        .await
+        .unwrap();
+
+    backend.add_folder(INBOX).await.unwrap();
+    backend
+}
+
+fn message(id: &str) -> Vec<u8> {
+    MessageBuilder::new()
+        .message_id(id)
+        .from("alice@localhost")
+        .to("bob@localhost")
+        .subject(id)
+        .text_body(id)
+        .write_to_vec()
+        .unwrap()
+}
+
+async fn ids(backend: &MultiAccountBackend, folder: &str) -> Vec<String> {
+    let opts = ListEnvelopesOptions {
+        page: 0,
+        page_size: 0,
+        query: None,
+    };
+
+    let envelopes = backend.list_envelopes(folder, opts).await.unwrap();
+    let mut ids: Vec<_> = envelopes.into_iter().map(|e| e.message_id).collect();
+    ids.sort();
+    ids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_multi_account() {
+    let tmp = tempdir().unwrap().path().to_owned();
+
+    let home = maildir("home", &tmp).await;
+    let work = maildir("work", &tmp).await;
+
+    let deleted = Flags::from_iter([Flag::Deleted]);
+
+    home.add_message(INBOX, &message("a@localhost"))
+        .await
+        .unwrap();
+    home.add_message_with_flags(INBOX, &message("b@localhost"), &deleted)
+        .await
+        .unwrap();
+    work.add_message(INBOX, &message("c@localhost"))
+        .await
+        .unwrap();
+    work.add_message_with_flags(INBOX, &message("d@localhost"), &deleted)
+        .await
+        .unwrap();
+
+    let multi = MultiAccountBackend::new()
+        .with_backend("home", home)
+        .unwrap()
+        .with_backend("work", work)
+        .unwrap();
+
+    // unified folders fan out to all accounts
+
+    let all = [
+        "<a@localhost>",
+        "<b@localhost>",
+        "<c@localhost>",
+        "<d@localhost>",
+    ];
+    assert_eq!(ids(&multi, INBOX).await, all);
+
+    let work_inbox = format!("work:{INBOX}");
+    let work_ids = ["<c@localhost>", "<d@localhost>"];
+    assert_eq!(ids(&multi, &work_inbox).await, work_ids);
+
+    // destructive operations never fan out
+
+    assert!(multi.expunge_folder(INBOX).await.is_err());
+    assert!(multi.purge_folder(INBOX).await.is_err());
+    assert_eq!(ids(&multi, INBOX).await, all);
+
+    multi.expunge_folder(&work_inbox).await.unwrap();
+
+    let expected = ["<a@localhost>", "<b@localhost>", "<c@localhost>"];
+    assert_eq!(ids(&multi, INBOX).await, expected);
+}
@@ -53,7 +53,7 @@ async fn test_search_emails_query() {
         port: ports.imap,
         encryption: Some(ImapEncryptionKind::None),
         login: "alice".into(),
-        auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+        auth: ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
         ..Default::default()
     });
 
@@ -34,7 +34,7 @@ async fn test_sendmail_features() {
             port: ports.imap,
             encryption: Some(ImapEncryptionKind::None),
             login: "bob".into(),
-            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            auth: ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
             ..Default::default()
         });
 
@@ -32,7 +32,9 @@ async fn test_smtp_features() {
             port: ports.imap,
             encryption: Some(ImapEncryptionKind::None),
             login: "bob".into(),
-            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_command("echo 'password'"))),
+            auth: ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_command(
+                "echo 'password'",
+            ))),
             ..Default::default()
         });
 
@@ -41,7 +43,8 @@ async fn test_smtp_features() {
             port: ports.smtp,
             encryption: Some(SmtpEncryptionKind::None),
             login: "alice".into(),
-            auth: SmtpAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            auth: SmtpAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
+            ..Default::default()
         });
 
         let imap_ctx = ImapContextBuilder::new(account_config.clone(), imap_config);
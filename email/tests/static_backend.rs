@@ -36,7 +36,7 @@ async fn test_static_backend() {
             port: ports.imap,
             encryption: Some(ImapEncryptionKind::None),
             login: "bob".into(),
-            auth: ImapAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            auth: ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
             ..Default::default()
         });
 
@@ -45,7 +45,8 @@ async fn test_static_backend() {
             port: ports.smtp,
             encryption: Some(SmtpEncryptionKind::None),
             login: "alice".into(),
-            auth: SmtpAuthConfig::Passwd(PasswdConfig(Secret::new_raw("password"))),
+            auth: SmtpAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
+            ..Default::default()
         });
 
         // 1. define custom context made of subcontexts
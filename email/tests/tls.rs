@@ -0,0 +1,234 @@
+#![cfg(all(feature = "imap", feature = "smtp"))]
+
+use std::{
+    fs::File,
+    io::BufReader as StdBufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use email::{
+    account::config::passwd::PasswdConfig,
+    imap::{
+        config::{ImapAuthConfig, ImapConfig, ImapEncryptionKind},
+        ImapClientBuilder,
+    },
+    smtp::{
+        self,
+        config::{SmtpAuthConfig, SmtpConfig, SmtpEncryptionKind},
+    },
+    tls::TlsConfig,
+};
+use secret::Secret;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+use tokio_rustls::{
+    rustls::{crypto::ring, ServerConfig},
+    TlsAcceptor,
+};
+
+/// SHA-256 fingerprint of `tests/tls/localhost.pem`.
+const FINGERPRINT: &str =
+    "AC:B2:52:01:79:47:48:F3:17:54:9E:EF:15:77:7E:E3:A9:45:1D:DC:B3:09:7F:FD:EF:F8:46:78:3C:A6:7B:DA";
+
+fn cert_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("tls")
+        .join(name)
+}
+
+/// Build a TLS acceptor presenting the `localhost` certificate,
+/// issued by the test certificate authority.
+fn acceptor() -> TlsAcceptor {
+    let mut certs = StdBufReader::new(File::open(cert_path("localhost.pem")).unwrap());
+    let certs = rustls_pemfile::certs(&mut certs)
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let mut key = StdBufReader::new(File::open(cert_path("localhost.key")).unwrap());
+    let key = rustls_pemfile::private_key(&mut key).unwrap().unwrap();
+
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+
+    TlsAcceptor::from(Arc::new(config))
+}
+
+/// Spawn a minimal IMAP server, accepting any login.
+async fn spawn_imap(starttls: bool) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let acceptor = acceptor();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                if starttls {
+                    stream.write_all(b"* OK ready\r\n").await?;
+                    let mut reader = BufReader::new(stream);
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await?;
+                    stream = reader.into_inner();
+                    stream.write_all(b"A0 OK begin TLS\r\n").await?;
+                    serve_imap(acceptor.accept(stream).await?, false).await
+                } else {
+                    serve_imap(acceptor.accept(stream).await?, true).await
+                }
+            });
+        }
+    });
+
+    port
+}
+
+async fn serve_imap<S>(stream: S, greeting: bool) -> std::io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+
+    if greeting {
+        stream.write_all(b"* OK ready\r\n").await?;
+    }
+
+    let mut line = String::new();
+
+    while stream.read_line(&mut line).await? > 0 {
+        let mut words = line.split_whitespace();
+        let tag = words.next().unwrap_or_default();
+        let cmd = words.next().unwrap_or_default().to_uppercase();
+
+        let res = match cmd.as_str() {
+            "CAPABILITY" => format!("* CAPABILITY IMAP4rev1\r\n{tag} OK done\r\n"),
+            _ => format!("{tag} OK done\r\n"),
+        };
+
+        stream.write_all(res.as_bytes()).await?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+/// Spawn a minimal SMTP server using implicit TLS, accepting any
+/// PLAIN authentication.
+async fn spawn_smtp() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let acceptor = acceptor();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(acceptor.accept(stream).await?);
+                stream.write_all(b"220 localhost ready\r\n").await?;
+
+                let mut line = String::new();
+
+                while stream.read_line(&mut line).await? > 0 {
+                    let res: &[u8] = match line.get(..4).map(str::to_uppercase).as_deref() {
+                        Some("EHLO") => b"250-localhost\r\n250 AUTH PLAIN\r\n",
+                        Some("AUTH") => b"235 authenticated\r\n",
+                        _ => b"250 ok\r\n",
+                    };
+
+                    stream.write_all(res).await?;
+                    line.clear();
+                }
+
+                std::io::Result::Ok(())
+            });
+        }
+    });
+
+    port
+}
+
+fn imap_config(port: u16, encryption: ImapEncryptionKind, tls: TlsConfig) -> ImapConfig {
+    ImapConfig {
+        host: "localhost".into(),
+        port,
+        encryption: Some(encryption),
+        tls: Some(tls),
+        login: "alice".into(),
+        auth: ImapAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
+        ..Default::default()
+    }
+}
+
+fn smtp_config(port: u16, tls: TlsConfig) -> SmtpConfig {
+    SmtpConfig {
+        host: "localhost".into(),
+        port,
+        encryption: Some(SmtpEncryptionKind::Tls),
+        tls: Some(tls),
+        login: "alice".into(),
+        auth: SmtpAuthConfig::Passwd(PasswdConfig::from(Secret::new_raw("password"))),
+        ..Default::default()
+    }
+}
+
+fn custom_ca() -> TlsConfig {
+    TlsConfig {
+        ca_file: Some(cert_path("ca.pem")),
+        ..Default::default()
+    }
+}
+
+fn pinned_fingerprint() -> TlsConfig {
+    TlsConfig {
+        fingerprints: vec![FINGERPRINT.into()],
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_imap_custom_tls() {
+    let port = spawn_imap(false).await;
+
+    for tls in [custom_ca(), pinned_fingerprint()] {
+        let config = imap_config(port, ImapEncryptionKind::Tls, tls);
+        let mut builder = ImapClientBuilder::new(Arc::new(config), None);
+        builder.build().await.unwrap();
+    }
+
+    let config = imap_config(port, ImapEncryptionKind::Tls, TlsConfig::default());
+    let mut builder = ImapClientBuilder::new(Arc::new(config), None);
+    let res = builder.build().await;
+    assert!(matches!(
+        res,
+        Err(email::imap::Error::TlsHandshakeError(..))
+    ));
+
+    let port = spawn_imap(true).await;
+
+    for tls in [custom_ca(), pinned_fingerprint()] {
+        let config = imap_config(port, ImapEncryptionKind::StartTls, tls);
+        let mut builder = ImapClientBuilder::new(Arc::new(config), None);
+        builder.build().await.unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_smtp_custom_tls() {
+    let port = spawn_smtp().await;
+
+    for tls in [custom_ca(), pinned_fingerprint()] {
+        let config = smtp_config(port, tls);
+        smtp::build_client(&config).await.unwrap();
+    }
+
+    let config = smtp_config(port, TlsConfig::default());
+    assert!(smtp::build_client(&config).await.is_err());
+}
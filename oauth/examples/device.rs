@@ -0,0 +1,41 @@
+use oauth::v2_0::{Client, DeviceAuthorizationGrant, Tokens};
+use std::env;
+
+#[tokio::main]
+pub async fn main() {
+    let client_id = env::var("CLIENT_ID").expect("Missing the CLIENT_ID environment variable");
+    let client_secret = env::var("CLIENT_SECRET").unwrap_or_default();
+
+    let client = Client::new(
+        client_id,
+        client_secret,
+        "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+        "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+    )
+    .unwrap()
+    .with_device_auth_url("https://login.microsoftonline.com/common/oauth2/v2.0/devicecode")
+    .unwrap()
+    .build()
+    .unwrap();
+
+    let device_grant = DeviceAuthorizationGrant::new()
+        .with_scope("https://outlook.office.com/IMAP.AccessAsUser.All")
+        .with_scope("offline_access");
+
+    let details = device_grant.request_device_code(&client).await.unwrap();
+
+    println!("Go to: {}", details.verification_uri().url());
+    println!("Enter the code: {}", details.user_code().secret());
+
+    let Tokens {
+        access_token,
+        refresh_token,
+        ..
+    } = device_grant
+        .wait_for_authorization(&client, &details)
+        .await
+        .unwrap();
+
+    println!("access token: {:?}", access_token);
+    println!("refresh token: {:?}", refresh_token);
+}
@@ -0,0 +1,49 @@
+use oauth::v2_0::{AuthorizationCodeGrant, Client, ServerMetadata, Tokens};
+use std::env;
+
+#[tokio::main]
+pub async fn main() {
+    let issuer = env::var("ISSUER").expect("Missing the ISSUER environment variable");
+    let client_id = env::var("CLIENT_ID").expect("Missing the CLIENT_ID environment variable");
+    let client_secret = env::var("CLIENT_SECRET").unwrap_or_default();
+
+    let metadata = ServerMetadata::discover(issuer).await.unwrap();
+    let supports_pkce = metadata.supports_pkce();
+
+    let client = Client::new(
+        client_id,
+        client_secret,
+        metadata.authorization_endpoint.unwrap(),
+        metadata.token_endpoint.unwrap(),
+    )
+    .unwrap()
+    .with_redirect_host("127.0.0.1")
+    .with_redirect_port(9999u16)
+    .build()
+    .unwrap();
+
+    let mut auth_code_grant = AuthorizationCodeGrant::new()
+        .with_redirect_host("127.0.0.1")
+        .with_redirect_port(9999u16)
+        .with_scope("openid")
+        .with_scope("email");
+
+    if supports_pkce {
+        auth_code_grant = auth_code_grant.with_pkce();
+    }
+
+    let (redirect_url, csrf_token) = auth_code_grant.get_redirect_url(&client);
+    println!("Go to: {}", redirect_url);
+
+    let Tokens {
+        access_token,
+        refresh_token,
+        ..
+    } = auth_code_grant
+        .wait_for_redirection(&client, csrf_token)
+        .await
+        .unwrap();
+
+    println!("access token: {:?}", access_token);
+    println!("refresh token: {:?}", refresh_token);
+}
@@ -1,4 +1,4 @@
-use oauth::v2_0::{AuthorizationCodeGrant, Client, RefreshAccessToken};
+use oauth::v2_0::{AuthorizationCodeGrant, Client, RefreshAccessToken, Tokens};
 use std::env;
 
 #[tokio::main]
@@ -31,7 +31,11 @@ pub async fn main() {
 
     println!("Go to: {}", redirect_url);
 
-    let (access_token, refresh_token) = auth_code_grant
+    let Tokens {
+        access_token,
+        refresh_token,
+        ..
+    } = auth_code_grant
         .wait_for_redirection(&client, csrf_token)
         .await
         .unwrap();
@@ -40,7 +44,11 @@ pub async fn main() {
     println!("refresh token: {:?}", refresh_token);
 
     if let Some(refresh_token) = refresh_token {
-        let (access_token, refresh_token) = RefreshAccessToken::new()
+        let Tokens {
+            access_token,
+            refresh_token,
+            ..
+        } = RefreshAccessToken::new()
             .refresh_access_token(&client, refresh_token)
             .await
             .unwrap();
@@ -3,14 +3,14 @@
 
 use oauth2::{
     basic::BasicClient, url::Url, AuthorizationCode, CsrfToken, PkceCodeChallenge,
-    PkceCodeVerifier, RequestTokenError, Scope, TokenResponse,
+    PkceCodeVerifier, RequestTokenError, Scope,
 };
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
     net::TcpListener,
 };
 
-use super::{Error, Result};
+use super::{Error, Result, Tokens};
 
 /// OAuth 2.0 Authorization Code Grant flow builder.
 ///
@@ -88,7 +88,7 @@ pub async fn wait_for_redirection(
         self,
         client: &BasicClient,
         csrf_state: CsrfToken,
-    ) -> Result<(String, Option<String>)> {
+    ) -> Result<Tokens> {
         let host = self.redirect_host;
         let port = self.redirect_port;
 
@@ -162,10 +162,7 @@ pub async fn wait_for_redirection(
                 RequestTokenError::Other(err) => Error::ExchangeCodeError(err),
             })?;
 
-        let access_token = res.access_token().secret().to_owned();
-        let refresh_token = res.refresh_token().map(|t| t.secret().clone());
-
-        Ok((access_token, refresh_token))
+        Ok(Tokens::from(res))
     }
 }
 
@@ -1,7 +1,10 @@
 //! Client builder, used by other flows to send requests and build
 //! URLs.
 
-use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+use oauth2::{
+    basic::BasicClient, AuthUrl, ClientId, ClientSecret, DeviceAuthorizationUrl, RedirectUrl,
+    RevocationUrl, TokenUrl,
+};
 
 use super::{Error, Result};
 
@@ -23,6 +26,16 @@ pub struct Client {
     /// URL of the authorization server's token endpoint.
     pub token_url: TokenUrl,
 
+    /// URL of the authorization server's device authorization
+    /// endpoint, as defined in the
+    /// [RFC8628](https://datatracker.ietf.org/doc/html/rfc8628#section-3.1).
+    pub device_auth_url: Option<DeviceAuthorizationUrl>,
+
+    /// URL of the authorization server's revocation endpoint, as
+    /// defined in the
+    /// [RFC7009](https://datatracker.ietf.org/doc/html/rfc7009#section-2).
+    pub revocation_url: Option<RevocationUrl>,
+
     /// Hostname of the client's redirection endpoint.
     pub redirect_host: String,
 
@@ -42,6 +55,8 @@ pub fn new(
             client_secret: ClientSecret::new(client_secret.to_string()),
             auth_url: AuthUrl::new(auth_url.to_string()).map_err(Error::BuildAuthUrlError)?,
             token_url: TokenUrl::new(token_url.to_string()).map_err(Error::BuildTokenUrlError)?,
+            device_auth_url: None,
+            revocation_url: None,
             redirect_host: String::from("localhost"),
             redirect_port: 9999,
         })
@@ -63,21 +78,54 @@ pub fn with_redirect_port<T>(mut self, port: T) -> Self
         self
     }
 
+    pub fn with_device_auth_url<T>(mut self, url: T) -> Result<Self>
+    where
+        T: ToString,
+    {
+        let url =
+            DeviceAuthorizationUrl::new(url.to_string()).map_err(Error::BuildDeviceAuthUrlError)?;
+        self.device_auth_url = Some(url);
+        Ok(self)
+    }
+
+    pub fn with_revocation_url<T>(mut self, url: T) -> Result<Self>
+    where
+        T: ToString,
+    {
+        let url = RevocationUrl::new(url.to_string()).map_err(Error::BuildRevocationUrlError)?;
+        self.revocation_url = Some(url);
+        Ok(self)
+    }
+
     /// Build the final client.
+    ///
+    /// An empty client secret is considered as missing, which is the
+    /// case of public clients.
     pub fn build(&self) -> Result<BasicClient> {
         let host = &self.redirect_host;
         let port = self.redirect_port;
         let redirect_uri = RedirectUrl::new(format!("http://{host}:{port}"))
             .map_err(Error::BuildRedirectUrlError)?;
 
-        let client = BasicClient::new(
+        let client_secret =
+            Some(self.client_secret.clone()).filter(|secret| !secret.secret().is_empty());
+
+        let mut client = BasicClient::new(
             self.client_id.clone(),
-            Some(self.client_secret.clone()),
+            client_secret,
             self.auth_url.clone(),
             Some(self.token_url.clone()),
         )
         .set_redirect_uri(redirect_uri);
 
+        if let Some(url) = &self.device_auth_url {
+            client = client.set_device_authorization_url(url.clone());
+        }
+
+        if let Some(url) = &self.revocation_url {
+            client = client.set_revocation_uri(url.clone());
+        }
+
         Ok(client)
     }
 }
@@ -0,0 +1,121 @@
+//! Device Authorization Grant flow helper, as defined in the
+//! [RFC8628](https://datatracker.ietf.org/doc/html/rfc8628)
+
+use std::time::Duration;
+
+use oauth2::{
+    basic::BasicClient, DeviceCodeErrorResponseType, RequestTokenError, Scope,
+    StandardDeviceAuthorizationResponse,
+};
+
+use super::{Error, Result, Tokens};
+
+/// OAuth 2.0 Device Authorization Grant flow builder.
+///
+/// This flow is meant for devices that cannot spawn a browser nor a
+/// redirect server, like headless servers or SSH sessions. The
+/// client needs a device authorization URL, see
+/// [`crate::v2_0::Client::with_device_auth_url`].
+///
+/// The first step (once the builder is configured) is to request a
+/// device code by calling
+/// [`DeviceAuthorizationGrant::request_device_code`]. The response
+/// contains the verification URI and the user code the user needs
+/// to enter from any other device.
+///
+/// The last step is to poll the token endpoint until the user
+/// completes the authorization by calling
+/// [`DeviceAuthorizationGrant::wait_for_authorization`].
+#[derive(Debug, Default)]
+pub struct DeviceAuthorizationGrant {
+    pub scopes: Vec<Scope>,
+    pub timeout: Option<Duration>,
+}
+
+impl DeviceAuthorizationGrant {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_scope<T>(mut self, scope: T) -> Self
+    where
+        T: ToString,
+    {
+        self.scopes.push(Scope::new(scope.to_string()));
+        self
+    }
+
+    /// Stop polling after the given duration, even if the device
+    /// code did not expire yet.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Request a device code, together with the verification URI and
+    /// the user code to present to the user.
+    pub async fn request_device_code(
+        &self,
+        client: &BasicClient,
+    ) -> Result<StandardDeviceAuthorizationResponse> {
+        client
+            .exchange_device_code()
+            .map_err(|err| Error::MissingDeviceAuthUrlError(err.to_string()))?
+            .add_scopes(self.scopes.clone())
+            .request_async(oauth2::reqwest::async_http_client)
+            .await
+            .map_err(|err| match err {
+                RequestTokenError::Request(req) => Error::RequestDeviceCodeError(req.to_string()),
+                RequestTokenError::ServerResponse(res) => {
+                    Error::RequestDeviceCodeError(res.to_string())
+                }
+                RequestTokenError::Parse(err, _) => Error::RequestDeviceCodeError(err.to_string()),
+                RequestTokenError::Other(err) => Error::RequestDeviceCodeError(err),
+            })
+    }
+
+    /// Poll the token endpoint until the user authorizes the device
+    /// code returned by
+    /// [`DeviceAuthorizationGrant::request_device_code`], then return
+    /// the issued tokens.
+    ///
+    /// Polling respects the interval given by the authorization
+    /// server, which is increased by 5 seconds every time the server
+    /// asks to slow down. Polling stops once the device code expires
+    /// or the timeout elapses, whichever comes first, and fails with
+    /// [`Error::DeviceCodeExpiredError`].
+    pub async fn wait_for_authorization(
+        self,
+        client: &BasicClient,
+        details: &StandardDeviceAuthorizationResponse,
+    ) -> Result<Tokens> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout.min(details.expires_in()),
+            None => details.expires_in(),
+        };
+
+        let poll = client.exchange_device_access_token(details).request_async(
+            oauth2::reqwest::async_http_client,
+            tokio::time::sleep,
+            Some(timeout),
+        );
+
+        // NOTE: the timeout given to the poller is only checked
+        // between two polls, a pending poll can still outlive it
+        let res = tokio::time::timeout(timeout, poll)
+            .await
+            .map_err(|_| Error::DeviceCodeExpiredError)?
+            .map_err(|err| match err {
+                RequestTokenError::ServerResponse(res) => match res.error() {
+                    DeviceCodeErrorResponseType::ExpiredToken => Error::DeviceCodeExpiredError,
+                    DeviceCodeErrorResponseType::AccessDenied => Error::DeviceAccessDeniedError,
+                    _ => Error::ExchangeDeviceCodeError(res.to_string()),
+                },
+                RequestTokenError::Request(req) => Error::ExchangeDeviceCodeError(req.to_string()),
+                RequestTokenError::Parse(err, _) => Error::ExchangeDeviceCodeError(err.to_string()),
+                RequestTokenError::Other(err) => Error::ExchangeDeviceCodeError(err),
+            })?;
+
+        Ok(Tokens::from(res))
+    }
+}
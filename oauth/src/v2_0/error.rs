@@ -15,6 +15,8 @@ pub enum Error {
     BuildTokenUrlError(#[source] oauth2::url::ParseError),
     #[error("cannot build revocation url")]
     BuildRevocationUrlError(#[source] oauth2::url::ParseError),
+    #[error("cannot build device authorization url")]
+    BuildDeviceAuthUrlError(#[source] oauth2::url::ParseError),
     #[error("cannot build redirect url")]
     BuildRedirectUrlError(#[source] oauth2::url::ParseError),
     #[error("cannot bind redirect server")]
@@ -33,6 +35,30 @@ pub enum Error {
     FindStateInRedirectUrlError(Url),
     #[error("cannot exchange code for access and refresh tokens: {0}")]
     ExchangeCodeError(String),
+    #[error("missing device authorization url: {0}")]
+    MissingDeviceAuthUrlError(String),
+    #[error("cannot request device code: {0}")]
+    RequestDeviceCodeError(String),
+    #[error("cannot exchange device code for access and refresh tokens: {0}")]
+    ExchangeDeviceCodeError(String),
+    #[error("cannot exchange device code: code expired before authorization")]
+    DeviceCodeExpiredError,
+    #[error("cannot exchange device code: authorization denied")]
+    DeviceAccessDeniedError,
+    #[error("missing revocation url: {0}")]
+    MissingRevocationUrlError(String),
+    #[error("cannot revoke token: {0}")]
+    RevokeTokenError(String),
+    #[error("cannot parse issuer url {1}")]
+    ParseIssuerUrlError(#[source] url::ParseError, String),
+    #[error("cannot fetch authorization server metadata")]
+    FetchServerMetadataError(#[source] reqwest::Error),
+    #[error("cannot parse authorization server metadata")]
+    ParseServerMetadataError(#[source] serde_json::Error),
+    #[error("cannot discover authorization server metadata of {0}: {1}")]
+    DiscoverServerMetadataError(String, String),
+    #[error("invalid authorization server metadata issuer {0}: expected {1}")]
+    InvalidIssuerError(String, String),
 
     #[error(transparent)]
     IoError(#[from] std::io::Error),
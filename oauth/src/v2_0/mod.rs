@@ -7,13 +7,21 @@
 
 mod authorization_code_grant;
 mod client;
+mod device_authorization_grant;
 mod error;
 mod refresh_access_token;
+mod revoke_token;
+mod server_metadata;
+mod tokens;
 
 #[doc(inline)]
 pub use self::{
     authorization_code_grant::AuthorizationCodeGrant,
     client::Client,
+    device_authorization_grant::DeviceAuthorizationGrant,
     error::{Error, Result},
     refresh_access_token::RefreshAccessToken,
+    revoke_token::RevokeToken,
+    server_metadata::ServerMetadata,
+    tokens::Tokens,
 };
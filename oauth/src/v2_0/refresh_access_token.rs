@@ -1,14 +1,18 @@
 //! Refresh Access Token flow helper, as defined in the
 //! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-6)
 
-use oauth2::{basic::BasicClient, RefreshToken, TokenResponse};
+use oauth2::{basic::BasicClient, RefreshToken};
 
-use super::{Error, Result};
+use super::{Error, Result, Tokens};
 
 /// OAuth 2.0 Refresh Access Token flow builder. The builder is empty
 /// for now but scopes will be added in the future. This flow exchange
 /// a refresh token for a new pair of access token and maybe a refresh
 /// token.
+///
+/// When the returned refresh token is defined, the authorization
+/// server rotated it: the given refresh token should not be used
+/// anymore.
 #[derive(Debug, Default)]
 pub struct RefreshAccessToken;
 
@@ -21,16 +25,13 @@ pub async fn refresh_access_token(
         &self,
         client: &BasicClient,
         refresh_token: impl ToString,
-    ) -> Result<(String, Option<String>)> {
+    ) -> Result<Tokens> {
         let res = client
             .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
             .request_async(oauth2::reqwest::async_http_client)
             .await
             .map_err(Error::RefreshAccessTokenError)?;
 
-        let access_token = res.access_token().secret().to_owned();
-        let refresh_token = res.refresh_token().map(|t| t.secret().clone());
-
-        Ok((access_token, refresh_token))
+        Ok(Tokens::from(res))
     }
 }
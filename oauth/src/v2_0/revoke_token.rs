@@ -0,0 +1,58 @@
+//! Token Revocation flow helper, as defined in the
+//! [RFC7009](https://datatracker.ietf.org/doc/html/rfc7009)
+
+use oauth2::{
+    basic::BasicClient, AccessToken, RefreshToken, RequestTokenError, StandardRevocableToken,
+};
+
+use super::{Error, Result};
+
+/// OAuth 2.0 Token Revocation flow builder. This flow invalidates an
+/// access token or a refresh token, for example when an account is
+/// removed. The client needs a revocation URL, see
+/// [`crate::v2_0::Client::with_revocation_url`].
+///
+/// Revoking a refresh token should also invalidate the access tokens
+/// issued from it, depending on the authorization server policy.
+#[derive(Debug, Default)]
+pub struct RevokeToken;
+
+impl RevokeToken {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn revoke_access_token(
+        &self,
+        client: &BasicClient,
+        access_token: impl ToString,
+    ) -> Result<()> {
+        let token = AccessToken::new(access_token.to_string());
+        self.revoke(client, StandardRevocableToken::AccessToken(token))
+            .await
+    }
+
+    pub async fn revoke_refresh_token(
+        &self,
+        client: &BasicClient,
+        refresh_token: impl ToString,
+    ) -> Result<()> {
+        let token = RefreshToken::new(refresh_token.to_string());
+        self.revoke(client, StandardRevocableToken::RefreshToken(token))
+            .await
+    }
+
+    async fn revoke(&self, client: &BasicClient, token: StandardRevocableToken) -> Result<()> {
+        client
+            .revoke_token(token)
+            .map_err(|err| Error::MissingRevocationUrlError(err.to_string()))?
+            .request_async(oauth2::reqwest::async_http_client)
+            .await
+            .map_err(|err| match err {
+                RequestTokenError::Request(req) => Error::RevokeTokenError(req.to_string()),
+                RequestTokenError::ServerResponse(res) => Error::RevokeTokenError(res.to_string()),
+                RequestTokenError::Parse(err, _) => Error::RevokeTokenError(err.to_string()),
+                RequestTokenError::Other(err) => Error::RevokeTokenError(err),
+            })
+    }
+}
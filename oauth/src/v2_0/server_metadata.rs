@@ -0,0 +1,206 @@
+//! Authorization server metadata discovery helper, as defined in the
+//! [RFC8414](https://datatracker.ietf.org/doc/html/rfc8414) and in
+//! [OpenID Connect Discovery](https://openid.net/specs/openid-connect-discovery-1_0.html)
+
+use serde::Deserialize;
+use url::Url;
+
+use super::{Error, Result};
+
+/// Metadata of an OAuth 2.0 authorization server.
+///
+/// Only the metadata useful to OAuth 2.0 flows are kept, other ones
+/// are ignored. The metadata can be discovered from the issuer using
+/// [`ServerMetadata::discover`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+pub struct ServerMetadata {
+    /// The authorization server's issuer identifier.
+    pub issuer: String,
+
+    /// URL of the authorization server's authorization endpoint.
+    pub authorization_endpoint: Option<String>,
+
+    /// URL of the authorization server's token endpoint.
+    pub token_endpoint: Option<String>,
+
+    /// URL of the authorization server's device authorization
+    /// endpoint, as defined in the
+    /// [RFC8628](https://datatracker.ietf.org/doc/html/rfc8628#section-4).
+    pub device_authorization_endpoint: Option<String>,
+
+    /// URL of the authorization server's revocation endpoint, as
+    /// defined in the
+    /// [RFC7009](https://datatracker.ietf.org/doc/html/rfc7009#section-2).
+    pub revocation_endpoint: Option<String>,
+
+    /// Scopes supported by the authorization server.
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+
+    /// Grant types supported by the authorization server.
+    #[serde(default)]
+    pub grant_types_supported: Vec<String>,
+
+    /// PKCE code challenge methods supported by the authorization
+    /// server.
+    #[serde(default)]
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+impl ServerMetadata {
+    /// Discover the metadata of the authorization server identified
+    /// by the given issuer.
+    ///
+    /// The issuer is either an URL or a bare host name, in which case
+    /// HTTPS is assumed. The OpenID Connect configuration is tried
+    /// first, then the OAuth 2.0 authorization server metadata. The
+    /// discovered issuer must match the given one, otherwise the
+    /// metadata is rejected.
+    pub async fn discover(issuer: impl AsRef<str>) -> Result<Self> {
+        let issuer = parse_issuer(issuer.as_ref())?;
+        let client = reqwest::Client::new();
+        let mut errs = Vec::new();
+
+        for url in well_known_urls(&issuer) {
+            let metadata = match fetch(&client, &url).await {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    log::debug!("cannot discover server metadata at {url}: {err}");
+                    errs.push(format!("{url}: {err}"));
+                    continue;
+                }
+            };
+
+            if metadata.issuer.trim_end_matches('/') != issuer.as_str().trim_end_matches('/') {
+                return Err(Error::InvalidIssuerError(
+                    metadata.issuer,
+                    issuer.to_string(),
+                ));
+            }
+
+            return Ok(metadata);
+        }
+
+        Err(Error::DiscoverServerMetadataError(
+            issuer.to_string(),
+            errs.join(", "),
+        ))
+    }
+
+    /// Return `true` if the authorization server supports the PKCE
+    /// protection using the `S256` code challenge method.
+    pub fn supports_pkce(&self) -> bool {
+        self.code_challenge_methods_supported
+            .iter()
+            .any(|method| method == "S256")
+    }
+}
+
+/// Parse the given issuer, defaulting to HTTPS when the scheme is
+/// missing.
+fn parse_issuer(issuer: &str) -> Result<Url> {
+    let issuer = issuer.trim();
+
+    let url = if issuer.contains("://") {
+        Url::parse(issuer)
+    } else {
+        Url::parse(&format!("https://{issuer}"))
+    };
+
+    url.map_err(|err| Error::ParseIssuerUrlError(err, issuer.to_owned()))
+}
+
+/// Build the well-known URLs of the given issuer, by order of
+/// preference.
+///
+/// The OpenID Connect configuration is appended to the issuer path,
+/// whereas the OAuth 2.0 authorization server metadata is inserted
+/// between the host and the issuer path. Some servers insert the
+/// OpenID Connect configuration as well, see
+/// [Section 5](https://datatracker.ietf.org/doc/html/rfc8414#section-5).
+fn well_known_urls(issuer: &Url) -> Vec<Url> {
+    let path = issuer.path().trim_end_matches('/');
+
+    let mut paths = vec![
+        format!("{path}/.well-known/openid-configuration"),
+        format!("/.well-known/oauth-authorization-server{path}"),
+    ];
+
+    if !path.is_empty() {
+        paths.push(format!("/.well-known/openid-configuration{path}"));
+    }
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let mut url = issuer.clone();
+            url.set_path(&path);
+            url.set_query(None);
+            url.set_fragment(None);
+            url
+        })
+        .collect()
+}
+
+async fn fetch(client: &reqwest::Client, url: &Url) -> Result<ServerMetadata> {
+    let res = client
+        .get(url.clone())
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(Error::FetchServerMetadataError)?;
+
+    let body = res.text().await.map_err(Error::FetchServerMetadataError)?;
+    let metadata = serde_json::from_str(&body).map_err(Error::ParseServerMetadataError)?;
+
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_issuer, well_known_urls};
+
+    fn urls(issuer: &str) -> Vec<String> {
+        let issuer = parse_issuer(issuer).unwrap();
+        well_known_urls(&issuer)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn parse_issuer_defaults_to_https() {
+        let issuer = parse_issuer(" example.org ").unwrap();
+        assert_eq!(issuer.as_str(), "https://example.org/");
+
+        let issuer = parse_issuer("http://localhost:8080/realms/test").unwrap();
+        assert_eq!(issuer.as_str(), "http://localhost:8080/realms/test");
+
+        assert!(parse_issuer("https://").is_err());
+    }
+
+    #[test]
+    fn well_known_urls_of_bare_host() {
+        let expected = vec![
+            "https://example.org/.well-known/openid-configuration",
+            "https://example.org/.well-known/oauth-authorization-server",
+        ];
+
+        assert_eq!(urls("example.org"), expected);
+        assert_eq!(urls("https://example.org/"), expected);
+    }
+
+    #[test]
+    fn well_known_urls_of_issuer_with_path() {
+        let expected = vec![
+            "https://example.org/tenant/v2.0/.well-known/openid-configuration",
+            "https://example.org/.well-known/oauth-authorization-server/tenant/v2.0",
+            "https://example.org/.well-known/openid-configuration/tenant/v2.0",
+        ];
+
+        assert_eq!(urls("https://example.org/tenant/v2.0"), expected);
+        assert_eq!(urls("https://example.org/tenant/v2.0/"), expected);
+        assert_eq!(urls("example.org/tenant/v2.0?query#fragment"), expected);
+    }
+}
@@ -0,0 +1,51 @@
+//! Tokens returned by the token endpoint, as defined in the
+//! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-5.1)
+
+use std::{fmt, time::Duration};
+
+use oauth2::{basic::BasicTokenResponse, TokenResponse};
+
+/// Tokens returned by the token endpoint after a successful
+/// authorization or refresh.
+///
+/// Tokens are redacted from the [`fmt::Debug`] output, so that they
+/// do not leak in logs.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Tokens {
+    /// The access token issued by the authorization server.
+    pub access_token: String,
+
+    /// The refresh token, if issued by the authorization server.
+    ///
+    /// When the authorization server rotates refresh tokens, the
+    /// previous refresh token should be considered invalid as soon
+    /// as a new one is issued.
+    pub refresh_token: Option<String>,
+
+    /// The lifetime of the access token, if given by the
+    /// authorization server.
+    pub expires_in: Option<Duration>,
+}
+
+impl From<BasicTokenResponse> for Tokens {
+    fn from(res: BasicTokenResponse) -> Self {
+        Self {
+            access_token: res.access_token().secret().to_owned(),
+            refresh_token: res.refresh_token().map(|t| t.secret().clone()),
+            expires_in: res.expires_in(),
+        }
+    }
+}
+
+impl fmt::Debug for Tokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokens")
+            .field("access_token", &"<redacted>")
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}